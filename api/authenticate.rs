@@ -0,0 +1,193 @@
+/// POST /api/authenticate — Resume a routing paused on a 3-D Secure challenge.
+///
+/// Accepts an `AuthenticationRequest` naming the `challenge_id` returned by
+/// `/api/authorize` with status `ChallengeRequired`, once the cardholder
+/// has answered the challenge. The paused routing is taken from the
+/// server-side `SessionStore`, so each challenge resumes once, and put back
+/// if it cannot resume, so a failed request can be retried. The PSP that
+/// issued it reports the result; an authenticated cardholder's transaction
+/// is resent there and routing goes on across the eligible PSPs not tried
+/// yet. Returns the routing result like `/api/authorize`, with a `payment`
+/// object when funds were authorized.
+///
+/// # Responses
+///
+/// - **200** — `AuthorizationResponse` with the full attempt history.
+/// - **400** — Malformed body.
+/// - **404** — Unknown, expired or already used challenge ID.
+/// - **405** — Non-POST method used.
+/// - **500** — The PSP catalog, routing rules or FX rates are missing or
+///   invalid.
+/// - **503** — The challenging PSP left the catalog or the session store
+///   cannot be reached: the challenge is kept and the request can be
+///   retried. Also returned when the payment store cannot be reached.
+use serde_json::json;
+use vercel_runtime::{run, Body, Error, Request, Response, StatusCode};
+
+use yuno_internal_challenge::catalog::PspCatalog;
+use yuno_internal_challenge::credentials::CredentialStore;
+use yuno_internal_challenge::engine::bandit::AdaptiveRouter;
+use yuno_internal_challenge::engine::breaker::BreakerRegistry;
+use yuno_internal_challenge::engine::fees::CostModel;
+use yuno_internal_challenge::engine::recovery::RecoveryTracker;
+use yuno_internal_challenge::engine::RoutingEngine;
+use yuno_internal_challenge::fx::FxTable;
+use yuno_internal_challenge::models::authentication::AuthenticationRequest;
+use yuno_internal_challenge::models::payment::{AuthorizationResponse, Payment};
+use yuno_internal_challenge::payments::PaymentStore;
+use yuno_internal_challenge::rules::RuleSet;
+use yuno_internal_challenge::sessions::{self, ResumeError, SessionStore};
+use yuno_internal_challenge::simulator::PspSimulator;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    run(handler).await
+}
+
+pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
+    if *req.method() != http::Method::POST {
+        return json_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            &json!({
+                "error": "Method not allowed",
+                "details": "Use POST to complete a 3-D Secure challenge"
+            }),
+        );
+    }
+
+    let request: AuthenticationRequest = match serde_json::from_slice(req.body().as_ref()) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return json_response(
+                StatusCode::BAD_REQUEST,
+                &json!({
+                    "error": "Invalid request body",
+                    "details": format!("Failed to parse JSON: {e}")
+                }),
+            );
+        }
+    };
+    let catalog = match PspCatalog::from_env() {
+        Ok(c) => c,
+        Err(e) => {
+            return json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &json!({
+                    "error": "PSP catalog unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+    let rules = match RuleSet::from_env().and_then(|r| r.check_catalog(&catalog).map(|()| r)) {
+        Ok(r) => r,
+        Err(e) => {
+            return json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &json!({
+                    "error": "Routing rules unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+    let fx = match FxTable::from_env().and_then(|fx| fx.check_currencies(&catalog).map(|()| fx)) {
+        Ok(fx) => fx,
+        Err(e) => {
+            return json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &json!({
+                    "error": "FX rates unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    let payments = match PaymentStore::from_env() {
+        Ok(store) => store,
+        Err(e) => {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                &json!({
+                    "error": "Payment store unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    let sessions = match SessionStore::from_env() {
+        Ok(store) => store,
+        Err(e) => {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                &json!({
+                    "error": "Session store unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    // Resumed routings learn into the same shared state as /api/authorize
+    let engine = RoutingEngine::new(PspSimulator::new(), catalog)
+        .with_breakers(BreakerRegistry::shared())
+        .with_bandit(AdaptiveRouter::shared())
+        .with_recovery(RecoveryTracker::shared())
+        .with_credentials(CredentialStore::shared())
+        .with_rules(rules)
+        .with_costs(CostModel::new(fx));
+    match sessions::complete(&sessions, &engine, &request.challenge_id).await {
+        Ok((transaction, result)) => {
+            let payment = Payment::from_authorization(&transaction, &result).ok();
+            if let Some(payment) = &payment {
+                if let Err(e) = payments.insert(payment) {
+                    return json_response(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        &json!({
+                            "error": "Payment store unavailable",
+                            "details": e.to_string()
+                        }),
+                    );
+                }
+            }
+            json_response(
+                StatusCode::OK,
+                &AuthorizationResponse {
+                    result,
+                    payment,
+                    challenge: None,
+                },
+            )
+        }
+        Err(e) => {
+            // Only an unknown challenge is final; after the others the
+            // session is still kept
+            let (status, error) = match e {
+                ResumeError::UnknownChallenge(_) => (StatusCode::NOT_FOUND, "Unknown challenge"),
+                ResumeError::Storage(_) => (StatusCode::SERVICE_UNAVAILABLE, "Session store unavailable"),
+                ResumeError::Routing(_) => (StatusCode::SERVICE_UNAVAILABLE, "Cannot complete challenge"),
+            };
+            json_response(
+                status,
+                &json!({
+                    "error": error,
+                    "details": e.to_string()
+                }),
+            )
+        }
+    }
+}
+
+/// Build a JSON `Response` with the given status code and serializable body.
+fn json_response<T: serde::Serialize>(
+    status: StatusCode,
+    body: &T,
+) -> Result<Response<Body>, Error> {
+    let json_string = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::Text(json_string))?)
+}
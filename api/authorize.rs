@@ -2,17 +2,37 @@
 ///
 /// Accepts a JSON `AuthorizationRequest`, validates the input, builds a
 /// `Transaction`, runs it through the `RoutingEngine`, and returns the
-/// serialized `RoutingResult`.
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-
+/// serialized `RoutingResult`. Approved payments are stored for
+/// `/api/capture`, `/api/void` and `/api/refund` and returned as `payment`.
+/// Merchant routing rules from `ROUTING_RULES_PATH` apply, and the result
+/// names the rule that matched. A transaction no PSP can take (amount, currency or
+/// card scheme limits) is rejected with 422 and the reason for each PSP.
+/// A routing paused on a 3-D Secure challenge is kept server-side and
+/// returns `ChallengeRequired` with a `challenge` whose `challenge_id` is
+/// posted to `/api/authenticate` once the cardholder has answered it.
+use rand::Rng;
 use serde_json::json;
 use vercel_runtime::{run, Body, Error, Request, Response, StatusCode};
 
+use yuno_internal_challenge::card_bin::{is_valid_bin, BinTable};
+use yuno_internal_challenge::catalog::{CountryConfig, PspCatalog};
+use yuno_internal_challenge::credentials::CredentialStore;
+use yuno_internal_challenge::engine::bandit::AdaptiveRouter;
+use yuno_internal_challenge::engine::breaker::BreakerRegistry;
+use yuno_internal_challenge::engine::fees::CostModel;
+use yuno_internal_challenge::engine::recovery::RecoveryTracker;
 use yuno_internal_challenge::engine::RoutingEngine;
+use yuno_internal_challenge::fx::FxTable;
+use yuno_internal_challenge::models::payment::{AuthorizationResponse, Payment};
 use yuno_internal_challenge::models::routing::AuthorizationRequest;
-use yuno_internal_challenge::models::transaction::{Country, Currency, Transaction};
+use yuno_internal_challenge::models::transaction::{
+    Country, Currency, PaymentMethod, Transaction, MAX_INSTALLMENTS,
+};
+use yuno_internal_challenge::payments::PaymentStore;
+use yuno_internal_challenge::rules::RuleSet;
+use yuno_internal_challenge::sessions::SessionStore;
 use yuno_internal_challenge::simulator::PspSimulator;
+use yuno_internal_challenge::time::Timestamp;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -55,77 +75,203 @@ pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
     };
 
     // ------------------------------------------------------------------
-    // 3. Validate the request fields
+    // 3. Load the PSP catalog and validate the request fields against it
     // ------------------------------------------------------------------
-    if let Err(msg) = validate_request(&auth_request) {
-        return json_response(
-            StatusCode::BAD_REQUEST,
-            &json!({
-                "error": "Validation failed",
-                "details": msg
-            }),
-        );
-    }
+    let catalog = match PspCatalog::from_env() {
+        Ok(c) => c,
+        Err(e) => {
+            return json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &json!({
+                    "error": "PSP catalog unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
 
-    // ------------------------------------------------------------------
-    // 4. Map string fields to domain enums
-    // ------------------------------------------------------------------
-    let currency = match parse_currency(&auth_request.currency) {
-        Some(c) => c,
-        None => {
+    let (country, currency) = match validate_request(&auth_request, &catalog) {
+        Ok(market) => market,
+        Err(msg) => {
             return json_response(
                 StatusCode::BAD_REQUEST,
                 &json!({
                     "error": "Validation failed",
-                    "details": format!("Unsupported currency: {}", auth_request.currency)
+                    "details": msg
                 }),
             );
         }
     };
 
-    let country = match parse_country(&auth_request.country) {
-        Some(c) => c,
-        None => {
+    // ------------------------------------------------------------------
+    // 4. Resolve the amount in the market's currency
+    // ------------------------------------------------------------------
+    let amount = match auth_request.amount.to_money(currency) {
+        Ok(m) => m,
+        Err(e) => {
             return json_response(
                 StatusCode::BAD_REQUEST,
                 &json!({
                     "error": "Validation failed",
-                    "details": format!("Unsupported country: {}", auth_request.country)
+                    "details": format!("Invalid amount: {e}")
                 }),
             );
         }
     };
 
     // ------------------------------------------------------------------
-    // 5. Build the Transaction
+    // 5. Build the Transaction and enrich it from the BIN table
     // ------------------------------------------------------------------
-    let transaction_id = generate_transaction_id(&auth_request);
-    let timestamp = current_timestamp();
+    let transaction_id = generate_transaction_id();
+    let timestamp = Timestamp::now();
 
-    let transaction = Transaction {
+    let mut transaction = Transaction {
         id: transaction_id,
-        amount: auth_request.amount,
-        currency,
+        amount,
         country,
+        payment_method: auth_request.payment_method.clone(),
         card_bin: auth_request.card_bin.clone(),
         card_last4: auth_request.card_last4.clone(),
+        bin_info: None,
+        installments: auth_request.installments,
         customer_id: auth_request.customer_id.clone(),
         timestamp,
+        three_ds: None,
+        credential: None,
+    };
+
+    let bin_table = match BinTable::from_env() {
+        Ok(t) => t,
+        Err(e) => {
+            return json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &json!({
+                    "error": "BIN table unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
     };
+    bin_table.enrich(&mut transaction);
 
     // ------------------------------------------------------------------
     // 6. Route the transaction
     // ------------------------------------------------------------------
     let strategy = auth_request.routing_strategy.unwrap_or_default();
+    let policy = auth_request.retry_policy.unwrap_or_default();
 
+    let rules = match RuleSet::from_env().and_then(|r| r.check_catalog(&catalog).map(|()| r)) {
+        Ok(r) => r,
+        Err(e) => {
+            return json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &json!({
+                    "error": "Routing rules unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    let fx = match FxTable::from_env().and_then(|fx| fx.check_currencies(&catalog).map(|()| fx)) {
+        Ok(fx) => fx,
+        Err(e) => {
+            return json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &json!({
+                    "error": "FX rates unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    let payments = match PaymentStore::from_env() {
+        Ok(store) => store,
+        Err(e) => {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                &json!({
+                    "error": "Payment store unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    let sessions = match SessionStore::from_env() {
+        Ok(store) => store,
+        Err(e) => {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                &json!({
+                    "error": "Session store unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    // Breakers, learned approval rates, decline-recovery statistics and
+    // card credentials are shared by every request this instance serves.
     let simulator = PspSimulator::new();
-    let engine = RoutingEngine::new(simulator);
-    let result = engine.route(&transaction, &strategy);
+    let engine = RoutingEngine::new(simulator, catalog)
+        .with_breakers(BreakerRegistry::shared())
+        .with_bandit(AdaptiveRouter::shared())
+        .with_recovery(RecoveryTracker::shared())
+        .with_credentials(CredentialStore::shared())
+        .with_rules(rules)
+        .with_costs(CostModel::new(fx))
+        .with_explain(auth_request.explain)
+        .with_cross_border_fallback(auth_request.cross_border_fallback);
+    if let Err(e) = engine.check_eligibility(&transaction) {
+        return json_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            &json!({
+                "error": "No eligible PSP",
+                "details": e.to_string()
+            }),
+        );
+    }
+    let mut result = engine.route_async(&transaction, &strategy, &policy).await;
 
     // ------------------------------------------------------------------
-    // 7. Return the routing result
+    // 7. Store the payment to capture or void when funds were authorized,
+    //    or the routing paused on a challenge, and return it with the
+    //    routing result
     // ------------------------------------------------------------------
-    json_response(StatusCode::OK, &result)
+    let payment = Payment::from_authorization(&transaction, &result).ok();
+    if let Some(payment) = &payment {
+        if let Err(e) = payments.insert(payment) {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                &json!({
+                    "error": "Payment store unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    }
+    let challenge = match result.challenge.take().map(|session| sessions.insert(session)).transpose() {
+        Ok(challenge) => challenge,
+        Err(e) => {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                &json!({
+                    "error": "Session store unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+    json_response(
+        StatusCode::OK,
+        &AuthorizationResponse {
+            result,
+            payment,
+            challenge,
+        },
+    )
 }
 
 // ======================================================================
@@ -146,116 +292,105 @@ fn json_response<T: serde::Serialize>(
 
 /// Validate all fields of an `AuthorizationRequest`.
 ///
-/// Returns `Ok(())` when valid, or `Err(message)` describing the first
-/// validation failure encountered.
-fn validate_request(req: &AuthorizationRequest) -> Result<(), String> {
-    if req.amount <= 0.0 {
-        return Err("amount must be greater than 0".into());
+/// Returns the requested market's country and currency when valid, or
+/// `Err(message)` describing the first validation failure encountered.
+/// Countries and currencies are looked up in the catalog, and the currency
+/// must be the one the catalog settles in for the requested country.
+fn validate_request(req: &AuthorizationRequest, catalog: &PspCatalog) -> Result<(Country, Currency), String> {
+    let supported = |field: fn(&CountryConfig) -> String| {
+        catalog.markets().map(field).collect::<Vec<String>>().join(", ")
+    };
+    let currency = catalog.checkout_currency(&req.currency).cloned().ok_or_else(|| {
+        format!(
+            "Invalid currency '{}'. Supported: {}",
+            req.currency,
+            supported(|c| c.currency.to_string())
+        )
+    })?;
+
+    match req.amount.to_money(currency.clone()) {
+        Ok(amount) if amount.is_positive() => {}
+        Ok(_) => return Err("amount must be greater than 0".into()),
+        Err(e) => return Err(format!("Invalid amount: {e}")),
     }
 
-    if parse_currency(&req.currency).is_none() {
+    let market = catalog.market(&req.country).ok_or_else(|| {
+        format!(
+            "Invalid country '{}'. Supported: {}",
+            req.country,
+            supported(|c| c.country.to_string())
+        )
+    })?;
+    let country = market.country.clone();
+    if market.currency != currency {
         return Err(format!(
-            "Invalid currency '{}'. Supported: BRL, MXN, COP",
-            req.currency
+            "Currency {currency} does not match {country}; expected {}",
+            market.currency
         ));
     }
 
-    if parse_country(&req.country).is_none() {
+    if !market.payment_methods.contains(&req.payment_method) {
         return Err(format!(
-            "Invalid country '{}'. Supported: Brazil, Mexico, Colombia",
-            req.country
+            "Payment method {} is not available in {country}",
+            req.payment_method
         ));
     }
 
-    if req.card_bin.is_empty() {
-        return Err("card_bin must not be empty".into());
+    if req.payment_method == PaymentMethod::Card {
+        let card_bin = req.card_bin.as_deref().unwrap_or_default();
+        if card_bin.is_empty() {
+            return Err("card_bin must not be empty".into());
+        }
+        if !is_valid_bin(card_bin) {
+            return Err(format!("card_bin '{card_bin}' must be exactly 6 digits"));
+        }
+        if req.card_last4.as_deref().unwrap_or_default().is_empty() {
+            return Err("card_last4 must not be empty".into());
+        }
+    } else if req.card_bin.is_some() || req.card_last4.is_some() {
+        return Err(format!(
+            "card_bin and card_last4 are only accepted for Card payments, not {}",
+            req.payment_method
+        ));
     }
 
-    if req.card_last4.is_empty() {
-        return Err("card_last4 must not be empty".into());
+    if let Some(installments) = req.installments {
+        if req.payment_method != PaymentMethod::Card {
+            return Err(format!(
+                "installments are only accepted for Card payments, not {}",
+                req.payment_method
+            ));
+        }
+        if !(1..=MAX_INSTALLMENTS).contains(&installments) {
+            return Err(format!("installments must be between 1 and {MAX_INSTALLMENTS}"));
+        }
+        let offered = catalog
+            .psps_for_country(&country)
+            .iter()
+            .any(|psp| psp.supports_installments(installments));
+        if !offered {
+            return Err(format!("No PSP in {country} offers {installments} installments"));
+        }
     }
 
     if req.customer_id.is_empty() {
         return Err("customer_id must not be empty".into());
     }
 
-    Ok(())
-}
-
-/// Map a currency code string to the `Currency` enum.
-fn parse_currency(s: &str) -> Option<Currency> {
-    match s {
-        "BRL" => Some(Currency::BRL),
-        "MXN" => Some(Currency::MXN),
-        "COP" => Some(Currency::COP),
-        _ => None,
+    if let Some(policy) = &req.retry_policy {
+        policy
+            .validate()
+            .map_err(|e| format!("Invalid retry_policy: {e}"))?;
     }
-}
-
-/// Map a country name string to the `Country` enum.
-fn parse_country(s: &str) -> Option<Country> {
-    match s {
-        "Brazil" => Some(Country::Brazil),
-        "Mexico" => Some(Country::Mexico),
-        "Colombia" => Some(Country::Colombia),
-        _ => None,
-    }
-}
 
-/// Generate a deterministic transaction ID from the request fields.
-///
-/// Uses `DefaultHasher` to produce a 16-hex-digit hash, prefixed with `txn_`.
-fn generate_transaction_id(req: &AuthorizationRequest) -> String {
-    let mut hasher = DefaultHasher::new();
-    req.card_bin.hash(&mut hasher);
-    req.card_last4.hash(&mut hasher);
-    req.customer_id.hash(&mut hasher);
-    req.amount.to_bits().hash(&mut hasher);
-    let hash = hasher.finish();
-    format!("txn_{:016x}", hash)
+    Ok((country, currency))
 }
 
-/// Return the current UTC time as an ISO 8601 string.
+/// Generate a unique transaction ID: 32 random hex digits, prefixed with
+/// `txn_`.
 ///
-/// Uses a manual calculation from `SystemTime` to avoid pulling in the
-/// `chrono` crate. Falls back to a fixed epoch string if the system clock
-/// is unavailable.
-fn current_timestamp() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    let secs = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-
-    // Convert epoch seconds to a basic ISO 8601 UTC timestamp.
-    let days = secs / 86400;
-    let time_of_day = secs % 86400;
-    let hours = time_of_day / 3600;
-    let minutes = (time_of_day % 3600) / 60;
-    let seconds = time_of_day % 60;
-
-    // Gregorian calendar conversion from days since epoch (1970-01-01).
-    let (year, month, day) = epoch_days_to_date(days);
-
-    format!(
-        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
-        year, month, day, hours, minutes, seconds
-    )
-}
-
-/// Convert days since the Unix epoch (1970-01-01) to (year, month, day).
-fn epoch_days_to_date(days: u64) -> (u64, u64, u64) {
-    // Algorithm adapted from Howard Hinnant's `civil_from_days`.
-    let z = days + 719468;
-    let era = z / 146097;
-    let doe = z - era * 146097;
-    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
-    let y = yoe + era * 400;
-    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
-    let mp = (5 * doy + 2) / 153;
-    let d = doy - (153 * mp + 2) / 5 + 1;
-    let m = if mp < 10 { mp + 3 } else { mp - 9 };
-    let y = if m <= 2 { y + 1 } else { y };
-    (y, m, d)
+/// Identical purchases, say a customer buying the same item twice, get
+/// distinct IDs, so each authorization is stored as a payment of its own.
+fn generate_transaction_id() -> String {
+    format!("txn_{:032x}", rand::thread_rng().gen::<u128>())
 }
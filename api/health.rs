@@ -1,3 +1,7 @@
+/// GET /api/health — Liveness and crate version.
+///
+/// Circuit breakers are not reported: they live in the memory of the
+/// instance that routes, and this function runs in a process of its own.
 use vercel_runtime::{run, Body, Error, Request, Response, StatusCode};
 use serde_json::json;
 use yuno_internal_challenge::version;
@@ -0,0 +1,58 @@
+/// POST /api/refund — Refund all or part of a captured payment.
+///
+/// Accepts a JSON `PaymentOperationRequest` naming the `transaction_id` of
+/// a captured payment and an optional `amount` (defaults to everything not
+/// yet refunded). The refund is sent to the PSP that approved the
+/// authorization, and the stored payment is updated and returned with the
+/// PSP's answer.
+///
+/// # Responses
+///
+/// - **200** — `PaymentOperationResponse`; `operation.approved` is false
+///   when the PSP rejected the refund, leaving the payment unchanged.
+/// - **400** — Malformed body, invalid or excessive amount, or unknown PSP.
+/// - **404** — No authorized payment is stored for the transaction.
+/// - **405** — Non-POST method used.
+/// - **409** — The payment is not captured or is already fully refunded,
+///   or another operation on it is in flight.
+/// - **500** — The PSP catalog is missing or invalid.
+/// - **503** — The payment store cannot be reached; safe to retry.
+use serde_json::json;
+use vercel_runtime::{run, Body, Error, Request, Response, StatusCode};
+
+use yuno_internal_challenge::models::payment::OperationKind;
+use yuno_internal_challenge::payments::{follow_up, PaymentStore};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    run(handler).await
+}
+
+pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
+    let store = match PaymentStore::from_env() {
+        Ok(store) => store,
+        Err(e) => {
+            return json_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                &json!({
+                    "error": "Payment store unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+    let (status, body) = follow_up(&store, req.method(), req.body().as_ref(), OperationKind::Refund);
+    json_response(status, &body)
+}
+
+/// Build a JSON `Response` with the given status code and serializable body.
+fn json_response<T: serde::Serialize>(
+    status: StatusCode,
+    body: &T,
+) -> Result<Response<Body>, Error> {
+    let json_string = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::Text(json_string))?)
+}
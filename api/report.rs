@@ -1,10 +1,21 @@
+use std::sync::Arc;
+
 use serde_json::json;
 use vercel_runtime::{run, Body, Error, Request, Response, StatusCode};
+use yuno_internal_challenge::catalog::PspCatalog;
+use yuno_internal_challenge::credentials::CredentialStore;
 use yuno_internal_challenge::data;
+use yuno_internal_challenge::engine::bandit::{AdaptiveRouter, BanditConfig};
+use yuno_internal_challenge::engine::breaker::{BreakerConfig, BreakerRegistry};
+use yuno_internal_challenge::engine::fees::CostModel;
+use yuno_internal_challenge::engine::recovery::RecoveryTracker;
+use yuno_internal_challenge::engine::retry::RetryPolicy;
 use yuno_internal_challenge::engine::RoutingEngine;
+use yuno_internal_challenge::fx::FxTable;
 use yuno_internal_challenge::models::report::ReportRequest;
 use yuno_internal_challenge::models::routing::RoutingStrategy;
 use yuno_internal_challenge::report;
+use yuno_internal_challenge::rules::RuleSet;
 use yuno_internal_challenge::simulator::PspSimulator;
 
 /// Default number of transactions when none is specified.
@@ -17,24 +28,31 @@ async fn main() -> Result<(), Error> {
 
 /// POST /api/report — Generate a performance report comparing routing scenarios.
 ///
-/// Accepts an optional JSON body with `transaction_count` and `routing_strategy`
-/// fields. When the body is empty or fields are omitted, defaults to 200
-/// transactions with `OptimizeForApprovals` strategy.
+/// Accepts an optional JSON body with `transaction_count`, `routing_strategy`,
+/// `retry_policy`, `bandit` and `cross_border_fallback` fields. When the body is empty or fields are
+/// omitted, defaults to 200 transactions with `OptimizeForApprovals`
+/// strategy, the default retry policy, a Thompson-sampling bandit and no
+/// cross-border fallback.
 ///
 /// # Request Body (optional)
 ///
 /// ```json
 /// {
 ///   "transaction_count": 500,
-///   "routing_strategy": "Balanced"
+///   "routing_strategy": "Adaptive",
+///   "bandit": { "mode": "Ucb", "decay": 0.98 }
 /// }
 /// ```
 ///
 /// # Responses
 ///
 /// - **200** — JSON `PerformanceReport` with no-retry vs smart-retry comparison.
-/// - **400** — Malformed JSON in request body.
+/// - **400** — Malformed JSON in request body, or an invalid `retry_policy`
+///   or `bandit`.
 /// - **405** — Non-POST method used.
+/// - **500** — The PSP catalog named by `PSP_CATALOG_PATH`, the routing
+///   rules named by `ROUTING_RULES_PATH` or the FX rates named by
+///   `FX_RATES_PATH` are missing or invalid.
 pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
     // Reject non-POST methods.
     if *req.method() != http::Method::POST {
@@ -57,10 +75,13 @@ pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
     };
 
     // Parse request parameters or use defaults for empty body.
-    let (count, strategy) = if bytes.is_empty() {
+    let (count, strategy, policy, bandit, cross_border_fallback) = if bytes.is_empty() {
         (
             DEFAULT_TRANSACTION_COUNT,
             RoutingStrategy::OptimizeForApprovals,
+            RetryPolicy::default(),
+            BanditConfig::default(),
+            false,
         )
     } else {
         match serde_json::from_slice::<ReportRequest>(&bytes) {
@@ -68,6 +89,9 @@ pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
                 req.transaction_count.unwrap_or(DEFAULT_TRANSACTION_COUNT),
                 req.routing_strategy
                     .unwrap_or(RoutingStrategy::OptimizeForApprovals),
+                req.retry_policy.unwrap_or_default(),
+                req.bandit.unwrap_or_default(),
+                req.cross_border_fallback,
             ),
             Err(e) => {
                 let error = json!({
@@ -82,15 +106,94 @@ pub async fn handler(req: Request) -> Result<Response<Body>, Error> {
         }
     };
 
+    if let Err(e) = policy.validate() {
+        let error = json!({
+            "error": "Bad request",
+            "message": format!("Invalid retry_policy: {e}")
+        });
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header("Content-Type", "application/json")
+            .body(Body::Text(error.to_string()))?);
+    }
+
+    if let Err(e) = bandit.validate() {
+        let error = json!({
+            "error": "Bad request",
+            "message": format!("Invalid bandit: {e}")
+        });
+        return Ok(Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header("Content-Type", "application/json")
+            .body(Body::Text(error.to_string()))?);
+    }
+
     // Generate test transactions.
     let transactions = data::generate_test_data(count);
 
-    // Build the routing engine with a fresh PSP simulator.
+    // Load the PSP catalog (built-in unless PSP_CATALOG_PATH is set).
+    let catalog = match PspCatalog::from_env() {
+        Ok(c) => c,
+        Err(e) => {
+            let error = json!({
+                "error": "PSP catalog unavailable",
+                "message": e.to_string()
+            });
+            return Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .header("Content-Type", "application/json")
+                .body(Body::Text(error.to_string()))?);
+        }
+    };
+
+    // Load merchant routing rules (none unless ROUTING_RULES_PATH is set).
+    let rules = match RuleSet::from_env().and_then(|r| r.check_catalog(&catalog).map(|()| r)) {
+        Ok(r) => r,
+        Err(e) => {
+            let error = json!({
+                "error": "Routing rules unavailable",
+                "message": e.to_string()
+            });
+            return Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .header("Content-Type", "application/json")
+                .body(Body::Text(error.to_string()))?);
+        }
+    };
+
+    // Load FX rates (built-in unless FX_RATES_PATH is set) covering every
+    // catalog currency.
+    let fx = match FxTable::from_env().and_then(|fx| fx.check_currencies(&catalog).map(|()| fx)) {
+        Ok(fx) => fx,
+        Err(e) => {
+            let error = json!({
+                "error": "FX rates unavailable",
+                "message": e.to_string()
+            });
+            return Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .header("Content-Type", "application/json")
+                .body(Body::Text(error.to_string()))?);
+        }
+    };
+
+    // Build the routing engine with a fresh PSP simulator, circuit
+    // breakers, bandit and credential store, so the report replays from a
+    // clean start.
     let simulator = PspSimulator::new();
-    let engine = RoutingEngine::new(simulator);
+    let breakers = Arc::new(BreakerRegistry::new(BreakerConfig::default()));
+    let engine = RoutingEngine::new(simulator, catalog)
+        .with_breakers(breakers)
+        .with_bandit(Arc::new(AdaptiveRouter::new(bandit)))
+        .with_recovery(Arc::new(RecoveryTracker::default()))
+        .with_credentials(Arc::new(CredentialStore::default()))
+        .with_rules(rules)
+        .with_costs(CostModel::new(fx.clone()))
+        .with_cross_border_fallback(cross_border_fallback);
 
     // Run the report comparing no-retry vs smart-retry scenarios.
-    let performance_report = report::generate_report(&transactions, &engine, &strategy);
+    let performance_report =
+        report::generate_report(&transactions, &engine, &strategy, &policy, &fx);
 
     // Serialize the report to JSON.
     let body = serde_json::to_string(&performance_report)
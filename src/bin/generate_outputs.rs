@@ -3,11 +3,24 @@
 //! Produces:
 //! - `output/test_transactions.json` — 210 test transactions
 //! - `output/performance_report.json` — Full performance report (no-retry vs smart-retry)
+//!
+//! An optional first argument points to an FX rates file (`.json` or `.csv`);
+//! the built-in rates are used otherwise.
 
+use yuno_internal_challenge::catalog::PspCatalog;
+use yuno_internal_challenge::credentials::CredentialStore;
 use yuno_internal_challenge::data::get_test_dataset;
+use std::sync::Arc;
+
+use yuno_internal_challenge::engine::breaker::{BreakerConfig, BreakerRegistry};
+use yuno_internal_challenge::engine::fees::CostModel;
+use yuno_internal_challenge::engine::recovery::RecoveryTracker;
+use yuno_internal_challenge::engine::retry::RetryPolicy;
 use yuno_internal_challenge::engine::RoutingEngine;
+use yuno_internal_challenge::fx::FxTable;
 use yuno_internal_challenge::models::routing::RoutingStrategy;
 use yuno_internal_challenge::report::generate_report;
+use yuno_internal_challenge::rules::RuleSet;
 use yuno_internal_challenge::simulator::PspSimulator;
 
 fn main() {
@@ -19,9 +32,32 @@ fn main() {
     println!("Wrote output/test_transactions.json ({} transactions)", transactions.len());
 
     // Generate performance report
+    let catalog = PspCatalog::from_env().expect("Failed to load PSP catalog");
+
+    // Load FX rates for USD normalization
+    let fx = match std::env::args().nth(1) {
+        Some(path) => FxTable::load(&path).expect("Failed to load FX rates"),
+        None => FxTable::from_env().expect("Failed to load FX rates"),
+    };
+    fx.check_currencies(&catalog).expect("FX rates miss a catalog currency");
     let simulator = PspSimulator::new();
-    let engine = RoutingEngine::new(simulator);
-    let report = generate_report(&transactions, &engine, &RoutingStrategy::OptimizeForApprovals);
+    let breakers = Arc::new(BreakerRegistry::new(BreakerConfig::default()));
+    let rules = RuleSet::from_env().expect("Failed to load routing rules");
+    rules.check_catalog(&catalog).expect("Routing rules name unknown PSPs, countries or currencies");
+    let engine = RoutingEngine::new(simulator, catalog)
+        .with_breakers(breakers)
+        .with_recovery(Arc::new(RecoveryTracker::default()))
+        .with_credentials(Arc::new(CredentialStore::default()))
+        .with_rules(rules)
+        .with_costs(CostModel::new(fx.clone()))
+        .with_cross_border_fallback(true);
+    let report = generate_report(
+        &transactions,
+        &engine,
+        &RoutingStrategy::OptimizeForApprovals,
+        &RetryPolicy::default(),
+        &fx,
+    );
     let report_json = serde_json::to_string_pretty(&report).expect("Failed to serialize report");
     std::fs::write("output/performance_report.json", &report_json).expect("Failed to write report");
 
@@ -48,13 +84,19 @@ fn main() {
     println!("--- Improvement ---");
     println!("  Rate Lift:          +{:.1} percentage points", report.improvement.rate_lift_percentage);
     println!("  Extra Approvals:    {} transactions", report.improvement.additional_approvals);
-    println!("  Revenue Recovered:  ${:.2}", report.improvement.estimated_revenue_recovered_usd);
+    println!("  Revenue Recovered:  {}", report.improvement.estimated_revenue_recovered_usd);
+    println!("  Avg Ticket:         {}", report.improvement.avg_ticket_usd);
+    println!("  Fees Paid:          {}", report.improvement.total_fees_usd);
+    println!("  Cross-Border Saves: {} transactions", report.improvement.cross_border_recoveries);
+    println!("  3DS Recoveries:     {} transactions", report.improvement.three_ds_recoveries);
+    println!("  Credential Saves:   {} transactions", report.improvement.credential_recoveries);
     println!();
     println!("--- By Country ---");
     for (country, metrics) in &report.by_country {
-        println!("  {}: {:.1}% -> {:.1}% (+{:.1}pp, {} txns)",
+        println!("  {}: {:.1}% -> {:.1}% (+{:.1}pp, {} txns, {} recovered, {} fees)",
             country, metrics.no_retry_rate, metrics.smart_retry_rate,
-            metrics.improvement, metrics.total_transactions);
+            metrics.improvement, metrics.total_transactions,
+            metrics.recovered_volume, metrics.fees_paid);
     }
     println!();
     println!("--- By PSP ---");
@@ -63,4 +105,31 @@ fn main() {
             psp, metrics.total_attempts, metrics.approvals,
             metrics.approval_rate, metrics.avg_latency_ms);
     }
+    println!();
+    println!("--- By Card Scheme ---");
+    for (scheme, metrics) in &report.by_card_scheme {
+        println!("  {}: {:.1}% -> {:.1}% (+{:.1}pp, {} txns)",
+            scheme, metrics.no_retry_rate, metrics.smart_retry_rate,
+            metrics.improvement, metrics.total_transactions);
+    }
+    println!();
+    println!("--- Strategy Convergence ({} txns per window) ---", report.convergence.window_size);
+    for series in &report.convergence.strategies {
+        let windows: Vec<String> = series.window_first_attempt_rates.iter().map(|r| format!("{r:.1}")).collect();
+        println!("  {:?}: {:.1}% approved, {:.2} avg attempts, {:.1}% first-attempt [{}]",
+            series.strategy, series.authorization_rate, series.avg_attempts,
+            series.first_attempt_rate, windows.join(", "));
+    }
+    println!();
+    println!("--- Decline Recovery (reason -> next PSP) ---");
+    for pair in &report.recovery {
+        println!("  {} -> {}: {}/{} recovered ({:.1}%)",
+            pair.reason, pair.psp_name, pair.recoveries, pair.attempts, pair.recovery_rate);
+    }
+    println!();
+    println!("--- Circuit Breakers ---");
+    for (psp, breaker) in &report.circuit_breakers {
+        println!("  {}: {:?}, {} calls, {} failures, opened {} times",
+            psp, breaker.state, breaker.calls, breaker.failures, breaker.times_opened);
+    }
 }
@@ -0,0 +1,383 @@
+//! BIN intelligence — card scheme, card type and issuer lookup by BIN.
+//!
+//! A BIN (Bank Identification Number) is the first six digits of a card
+//! number. The [`BinTable`] maps inclusive BIN ranges to the card's scheme,
+//! whether it is credit, debit or prepaid, the issuing bank and the issuing
+//! country. Transactions are enriched with the matching [`BinInfo`] before
+//! routing so the simulator, strategies and report can use it.
+//!
+//! # File Formats
+//!
+//! CSV — a `range_start,range_end,scheme,card_type,issuer,issuing_country`
+//! header followed by one range per line. Blank lines and lines starting
+//! with `#` are ignored. The built-in table is compiled in from
+//! `config/bin_ranges.csv`.
+//!
+//! JSON — an array of [`BinRange`] records.
+
+use crate::models::transaction::{Country, Transaction};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Environment variable pointing at a BIN table that overrides the built-in one.
+pub const BIN_TABLE_PATH_ENV: &str = "BIN_TABLE_PATH";
+
+/// Number of leading card digits a range is keyed on.
+pub const BIN_LENGTH: usize = 6;
+
+/// Built-in BIN table source.
+const BUILTIN_BIN_TABLE: &str = include_str!("../../config/bin_ranges.csv");
+
+/// Card network.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CardScheme {
+    Visa,
+    Mastercard,
+    Amex,
+    /// Brazilian domestic scheme.
+    Elo,
+    /// Brazilian domestic scheme.
+    Hipercard,
+    /// Mexican domestic scheme.
+    Carnet,
+}
+
+impl CardScheme {
+    /// Parses a scheme name as written in BIN files (e.g., `"Visa"`).
+    pub fn from_name(name: &str) -> Option<CardScheme> {
+        match name {
+            "Visa" => Some(CardScheme::Visa),
+            "Mastercard" => Some(CardScheme::Mastercard),
+            "Amex" => Some(CardScheme::Amex),
+            "Elo" => Some(CardScheme::Elo),
+            "Hipercard" => Some(CardScheme::Hipercard),
+            "Carnet" => Some(CardScheme::Carnet),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for CardScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardScheme::Visa => write!(f, "Visa"),
+            CardScheme::Mastercard => write!(f, "Mastercard"),
+            CardScheme::Amex => write!(f, "Amex"),
+            CardScheme::Elo => write!(f, "Elo"),
+            CardScheme::Hipercard => write!(f, "Hipercard"),
+            CardScheme::Carnet => write!(f, "Carnet"),
+        }
+    }
+}
+
+/// How the card is funded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CardType {
+    Credit,
+    Debit,
+    Prepaid,
+}
+
+impl CardType {
+    /// Parses a card type as written in BIN files (e.g., `"Debit"`).
+    pub fn from_name(name: &str) -> Option<CardType> {
+        match name {
+            "Credit" => Some(CardType::Credit),
+            "Debit" => Some(CardType::Debit),
+            "Prepaid" => Some(CardType::Prepaid),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for CardType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardType::Credit => write!(f, "Credit"),
+            CardType::Debit => write!(f, "Debit"),
+            CardType::Prepaid => write!(f, "Prepaid"),
+        }
+    }
+}
+
+/// What the BIN table knows about a card.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BinInfo {
+    /// Card network.
+    pub scheme: CardScheme,
+    /// Credit, debit or prepaid.
+    pub card_type: CardType,
+    /// Issuing bank.
+    pub issuer: String,
+    /// ISO 3166-1 alpha-2 code of the issuing country (e.g., `"BR"`).
+    pub issuing_country: String,
+}
+
+impl BinInfo {
+    /// Returns true if the card was issued in `country`.
+    pub fn is_domestic(&self, country: &Country) -> bool {
+        country.iso_code().is_some_and(|code| self.issuing_country == code)
+    }
+}
+
+/// An inclusive range of six-digit BINs sharing the same card details.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BinRange {
+    /// First BIN in the range (six digits).
+    pub range_start: String,
+    /// Last BIN in the range (six digits).
+    pub range_end: String,
+    #[serde(flatten)]
+    pub info: BinInfo,
+}
+
+/// Errors produced while loading a BIN table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinError {
+    /// The BIN file could not be read.
+    Io(String),
+    /// The BIN file could not be parsed.
+    Parse(String),
+    /// The file extension is neither `.csv` nor `.json`.
+    UnsupportedFormat(String),
+    /// A range bound is not six digits, or the start is after the end.
+    InvalidRange { start: String, end: String },
+    /// Two ranges cover the same BIN.
+    OverlappingRanges { first: String, second: String },
+    /// An issuing country is not a two-letter code.
+    InvalidCountry(String),
+}
+
+impl std::fmt::Display for BinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinError::Io(msg) => write!(f, "failed to read BIN table: {msg}"),
+            BinError::Parse(msg) => write!(f, "failed to parse BIN table: {msg}"),
+            BinError::UnsupportedFormat(path) => {
+                write!(f, "unsupported BIN table format '{path}' (expected .csv or .json)")
+            }
+            BinError::InvalidRange { start, end } => write!(f, "invalid BIN range {start}-{end}"),
+            BinError::OverlappingRanges { first, second } => {
+                write!(f, "BIN ranges starting at {first} and {second} overlap")
+            }
+            BinError::InvalidCountry(code) => write!(f, "invalid issuing country '{code}'"),
+        }
+    }
+}
+
+impl std::error::Error for BinError {}
+
+/// A validated, non-overlapping set of BIN ranges.
+#[derive(Debug, Clone)]
+pub struct BinTable {
+    /// Ranges as numeric bounds, sorted by start.
+    ranges: Vec<(u32, u32, BinInfo)>,
+}
+
+impl BinTable {
+    /// Builds a table, validating every range and rejecting overlaps.
+    pub fn new(ranges: Vec<BinRange>) -> Result<Self, BinError> {
+        let mut parsed = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let invalid = || BinError::InvalidRange {
+                start: range.range_start.clone(),
+                end: range.range_end.clone(),
+            };
+            let start = parse_bin(&range.range_start).ok_or_else(invalid)?;
+            let end = parse_bin(&range.range_end).ok_or_else(invalid)?;
+            if start > end {
+                return Err(invalid());
+            }
+            let country = &range.info.issuing_country;
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(BinError::InvalidCountry(country.clone()));
+            }
+            parsed.push((start, end, range.info));
+        }
+
+        parsed.sort_by_key(|(start, _, _)| *start);
+        for pair in parsed.windows(2) {
+            if pair[1].0 <= pair[0].1 {
+                return Err(BinError::OverlappingRanges {
+                    first: format!("{:06}", pair[0].0),
+                    second: format!("{:06}", pair[1].0),
+                });
+            }
+        }
+
+        Ok(BinTable { ranges: parsed })
+    }
+
+    /// The built-in table compiled from `config/bin_ranges.csv`.
+    pub fn builtin() -> Self {
+        BinTable::from_csv_str(BUILTIN_BIN_TABLE).expect("built-in BIN table is valid")
+    }
+
+    /// Loads the table named by [`BIN_TABLE_PATH_ENV`], or the built-in one
+    /// when the variable is unset.
+    pub fn from_env() -> Result<Self, BinError> {
+        match std::env::var(BIN_TABLE_PATH_ENV) {
+            Ok(path) if !path.is_empty() => BinTable::load(path),
+            _ => Ok(BinTable::builtin()),
+        }
+    }
+
+    /// Loads a table from a `.csv` or `.json` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BinError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| BinError::Io(e.to_string()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => BinTable::from_csv_str(&contents),
+            Some("json") => BinTable::from_json_str(&contents),
+            _ => Err(BinError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+
+    /// Parses a JSON array of BIN ranges.
+    pub fn from_json_str(json: &str) -> Result<Self, BinError> {
+        let ranges: Vec<BinRange> =
+            serde_json::from_str(json).map_err(|e| BinError::Parse(e.to_string()))?;
+        BinTable::new(ranges)
+    }
+
+    /// Parses CSV with a `range_start,range_end,scheme,card_type,issuer,issuing_country` header.
+    pub fn from_csv_str(csv: &str) -> Result<Self, BinError> {
+        let mut lines = csv
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'));
+
+        let header = lines
+            .next()
+            .ok_or_else(|| BinError::Parse("empty CSV".into()))?;
+        let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+        if columns != ["range_start", "range_end", "scheme", "card_type", "issuer", "issuing_country"] {
+            return Err(BinError::Parse(format!("unexpected CSV header '{header}'")));
+        }
+
+        let mut ranges = Vec::new();
+        for line in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [start, end, scheme, card_type, issuer, country] = fields[..] else {
+                return Err(BinError::Parse(format!("expected 6 fields in '{line}'")));
+            };
+            let scheme = CardScheme::from_name(scheme)
+                .ok_or_else(|| BinError::Parse(format!("unknown scheme '{scheme}'")))?;
+            let card_type = CardType::from_name(card_type)
+                .ok_or_else(|| BinError::Parse(format!("unknown card type '{card_type}'")))?;
+            ranges.push(BinRange {
+                range_start: start.to_string(),
+                range_end: end.to_string(),
+                info: BinInfo {
+                    scheme,
+                    card_type,
+                    issuer: issuer.to_string(),
+                    issuing_country: country.to_string(),
+                },
+            });
+        }
+        BinTable::new(ranges)
+    }
+
+    /// Looks up a BIN (or any card prefix of at least six digits).
+    pub fn lookup(&self, bin: &str) -> Option<&BinInfo> {
+        let key = parse_bin(bin.get(..BIN_LENGTH)?)?;
+        let idx = self.ranges.partition_point(|(start, _, _)| *start <= key);
+        let (_, end, info) = self.ranges.get(idx.checked_sub(1)?)?;
+        (key <= *end).then_some(info)
+    }
+
+    /// Attaches the BIN details for a card transaction, if the BIN is known.
+    pub fn enrich(&self, transaction: &mut Transaction) {
+        transaction.bin_info = transaction
+            .card_bin
+            .as_deref()
+            .and_then(|bin| self.lookup(bin))
+            .cloned();
+    }
+}
+
+impl Default for BinTable {
+    fn default() -> Self {
+        BinTable::builtin()
+    }
+}
+
+/// Returns true if `bin` is exactly six ASCII digits.
+pub fn is_valid_bin(bin: &str) -> bool {
+    parse_bin(bin).is_some()
+}
+
+fn parse_bin(bin: &str) -> Option<u32> {
+    if bin.len() != BIN_LENGTH || !bin.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    bin.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_lookup() {
+        let table = BinTable::builtin();
+        let info = table.lookup("411111").unwrap();
+        assert_eq!(info.scheme, CardScheme::Visa);
+        assert_eq!(info.card_type, CardType::Credit);
+        assert_eq!(info.issuing_country, "BR");
+        assert!(info.is_domestic(&Country::new("Brazil")));
+
+        assert_eq!(table.lookup("636368").unwrap().scheme, CardScheme::Elo);
+        assert_eq!(table.lookup("506250").unwrap().card_type, CardType::Prepaid);
+    }
+
+    #[test]
+    fn test_lookup_misses() {
+        let table = BinTable::builtin();
+        assert!(table.lookup("999999").is_none());
+        assert!(table.lookup("41111").is_none());
+        assert!(table.lookup("41a111").is_none());
+        // Just past the end of the 411100-411199 range.
+        assert!(table.lookup("411200").is_none());
+    }
+
+    #[test]
+    fn test_rejects_overlapping_ranges() {
+        let csv = "range_start,range_end,scheme,card_type,issuer,issuing_country\n\
+                   400000,400099,Visa,Credit,A,BR\n\
+                   400050,400150,Visa,Debit,B,BR\n";
+        assert!(matches!(
+            BinTable::from_csv_str(csv),
+            Err(BinError::OverlappingRanges { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_bad_ranges_and_countries() {
+        let header = "range_start,range_end,scheme,card_type,issuer,issuing_country\n";
+        assert!(matches!(
+            BinTable::from_csv_str(&format!("{header}400099,400000,Visa,Credit,A,BR")),
+            Err(BinError::InvalidRange { .. })
+        ));
+        assert!(matches!(
+            BinTable::from_csv_str(&format!("{header}400000,400099,Visa,Credit,A,Brazil")),
+            Err(BinError::InvalidCountry(_))
+        ));
+        assert!(matches!(
+            BinTable::from_csv_str(&format!("{header}400000,400099,Discover,Credit,A,BR")),
+            Err(BinError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_table() {
+        let json = r#"[{
+            "range_start": "650000", "range_end": "650999",
+            "scheme": "Elo", "card_type": "Debit",
+            "issuer": "Banco Inter", "issuing_country": "BR"
+        }]"#;
+        let table = BinTable::from_json_str(json).unwrap();
+        assert_eq!(table.lookup("650123").unwrap().issuer, "Banco Inter");
+    }
+}
@@ -0,0 +1,99 @@
+//! Country and currency details known to the process.
+//!
+//! Amounts need their currency's exponent, rules their country's time zone
+//! and the simulator its ISO code wherever they are used, far from any
+//! catalog. So every validated catalog registers its currency and country
+//! tables here, and the built-in tables are known from the start. A catalog
+//! that declares an exponent, ISO code or time zone differently from one
+//! already registered is rejected rather than changing amounts and clocks
+//! under the catalogs loaded before it.
+
+use super::{CatalogError, CountryConfig, CurrencyConfig, BUILTIN_CATALOG};
+use crate::models::transaction::{Country, Currency};
+use crate::state;
+use crate::time::TimeZone;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Details of a registered country.
+#[derive(Debug, Clone, PartialEq)]
+struct CountryLocale {
+    iso_code: String,
+    time_zone: TimeZone,
+}
+
+/// Registered exponents by currency and details by country.
+#[derive(Debug, Default)]
+struct Locales {
+    currencies: HashMap<Currency, u32>,
+    countries: HashMap<Country, CountryLocale>,
+}
+
+/// The currency and country tables of a catalog file, without its PSPs.
+#[derive(Deserialize)]
+struct Tables {
+    currencies: Vec<CurrencyConfig>,
+    countries: Vec<CountryConfig>,
+}
+
+impl Locales {
+    /// Add the tables, unless one entry conflicts with a registered one.
+    fn add(&mut self, currencies: &[CurrencyConfig], countries: &[CountryConfig]) -> Result<(), CatalogError> {
+        for entry in currencies {
+            if self.currencies.get(&entry.code).is_some_and(|e| *e != entry.exponent) {
+                return Err(CatalogError::ConflictingCurrency(entry.code.clone()));
+            }
+        }
+        for entry in countries {
+            let known = self.countries.get(&entry.country);
+            if known.is_some_and(|k| k.iso_code != entry.iso_code || k.time_zone != entry.time_zone) {
+                return Err(CatalogError::ConflictingCountry(entry.country.clone()));
+            }
+        }
+        for entry in currencies {
+            self.currencies.insert(entry.code.clone(), entry.exponent);
+        }
+        for entry in countries {
+            let locale = CountryLocale {
+                iso_code: entry.iso_code.clone(),
+                time_zone: entry.time_zone.clone(),
+            };
+            self.countries.insert(entry.country.clone(), locale);
+        }
+        Ok(())
+    }
+}
+
+fn locales() -> &'static Mutex<Locales> {
+    static LOCALES: OnceLock<Mutex<Locales>> = OnceLock::new();
+    LOCALES.get_or_init(|| {
+        let tables: Tables = toml::from_str(BUILTIN_CATALOG).expect("built-in catalog tables parse");
+        let mut locales = Locales::default();
+        locales.currencies.insert(Currency::USD, 2);
+        locales
+            .add(&tables.currencies, &tables.countries)
+            .expect("built-in catalog tables are consistent");
+        Mutex::new(locales)
+    })
+}
+
+/// Register a validated catalog's currency and country tables.
+pub(crate) fn register(currencies: &[CurrencyConfig], countries: &[CountryConfig]) -> Result<(), CatalogError> {
+    state::lock(locales()).add(currencies, countries)
+}
+
+/// The registered exponent of `currency`.
+pub(crate) fn currency_exponent(currency: &Currency) -> Option<u32> {
+    state::lock(locales()).currencies.get(currency).copied()
+}
+
+/// The registered ISO code of `country`.
+pub(crate) fn iso_code(country: &Country) -> Option<String> {
+    state::lock(locales()).countries.get(country).map(|c| c.iso_code.clone())
+}
+
+/// The registered time zone of `country`.
+pub(crate) fn time_zone(country: &Country) -> Option<TimeZone> {
+    state::lock(locales()).countries.get(country).map(|c| c.time_zone.clone())
+}
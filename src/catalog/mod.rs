@@ -0,0 +1,1107 @@
+//! PSP catalog — the countries FashionForward sells in and the PSPs
+//! available in each of them, plus cross-border acquirers that can take
+//! transactions from several of them.
+//!
+//! The catalog is data, not code: it is loaded from a TOML or JSON file
+//! and validated before use, so adding a PSP or tuning its rates, fees,
+//! latency or decline distribution is a configuration change. The
+//! built-in catalog is compiled in from `config/psp_catalog.toml`.
+//!
+//! Currencies and countries are catalog data too: each currency is declared
+//! with its exponent, and each country with its ISO code, currency, time
+//! zone and the payment methods it offers. A market is added without
+//! touching code.
+//!
+//! # Validation
+//!
+//! - Currency codes are three uppercase letters, declared once, with an
+//!   exponent of at most [`MAX_CURRENCY_EXPONENT`].
+//! - Country entries and their ISO codes are unique; ISO codes are two
+//!   uppercase letters, and each country settles in a declared currency
+//!   and offers at least one payment method.
+//! - Exponents, ISO codes and time zones agree with every catalog loaded
+//!   before (see [`Currency::exponent`]).
+//! - PSP ids are unique and every PSP belongs to a declared country.
+//! - Success rates are within `0.0..=1.0` and fees are non-negative.
+//! - `latency_min_ms <= latency_max_ms`.
+//! - Decline distributions only use soft decline reasons and their weights
+//!   sum to 1.0.
+//! - Every payment method a PSP lists is offered in its country.
+//! - Response code tables do not remap the approval or PSP-unavailable codes.
+//! - Installment plans are only offered by card PSPs, count 2 to
+//!   [`MAX_INSTALLMENTS`] installments, are not repeated and have
+//!   non-negative surcharges.
+//! - Amount limits are non-negative, in the country's currency, with
+//!   `min_amount <= max_amount`; card scheme limits need card payments.
+//! - Cross-border acquirers serve at least one other declared country, none
+//!   twice, with non-negative FX markup and fee and a domestic card penalty
+//!   within `0.0..=1.0`. They take no amount limits or installments, which
+//!   are set in a market's currency and offered by local acquirers.
+
+use crate::codes::{APPROVED_CODE, PSP_UNAVAILABLE_CODE};
+use crate::models::psp::{DeclineClass, DeclineReason, PspConfig};
+use crate::models::routing::IneligiblePsp;
+use crate::models::transaction::{Country, Currency, PaymentMethod, Transaction, MAX_INSTALLMENTS};
+use crate::time::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+pub(crate) mod locale;
+
+/// Environment variable pointing at a catalog file that overrides the built-in one.
+pub const CATALOG_PATH_ENV: &str = "PSP_CATALOG_PATH";
+
+/// Largest currency exponent a catalog may declare.
+pub const MAX_CURRENCY_EXPONENT: u32 = 4;
+
+/// Tolerance when checking that decline weights sum to 1.0.
+const WEIGHT_TOLERANCE: f64 = 1e-6;
+
+/// Built-in catalog source.
+const BUILTIN_CATALOG: &str = include_str!("../../config/psp_catalog.toml");
+
+/// A currency amounts can be denominated in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrencyConfig {
+    /// ISO 4217 code.
+    pub code: Currency,
+    /// Number of decimal places amounts are kept in (0 for CLP, 2 for BRL).
+    pub exponent: u32,
+}
+
+/// A country served by the catalog and its checkout currency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CountryConfig {
+    /// The country.
+    pub country: Country,
+    /// ISO 3166-1 alpha-2 code, matched against card issuing countries.
+    pub iso_code: String,
+    /// Currency transactions in this country are denominated in.
+    pub currency: Currency,
+    /// Zone the country's transactions are reported in and its rules'
+    /// time windows are read in.
+    pub time_zone: TimeZone,
+    /// Payment methods customers in this country can pay with.
+    #[serde(default = "default_payment_methods")]
+    pub payment_methods: Vec<PaymentMethod>,
+}
+
+fn default_payment_methods() -> Vec<PaymentMethod> {
+    vec![PaymentMethod::Card]
+}
+
+/// Errors produced while loading or validating a catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogError {
+    /// The catalog file could not be read.
+    Io(String),
+    /// The catalog file could not be parsed.
+    Parse(String),
+    /// The file extension is neither `.toml` nor `.json`.
+    UnsupportedFormat(String),
+    /// A currency is declared more than once.
+    DuplicateCurrency(Currency),
+    /// A currency's code or exponent is malformed.
+    InvalidCurrency { currency: Currency, details: String },
+    /// A currency's exponent differs from the one a catalog loaded before
+    /// declared.
+    ConflictingCurrency(Currency),
+    /// A country is declared more than once.
+    DuplicateCountry(Country),
+    /// A country's ISO code, currency or payment methods are malformed.
+    InvalidCountry { country: Country, details: String },
+    /// A country's ISO code or time zone differs from the one a catalog
+    /// loaded before declared.
+    ConflictingCountry(Country),
+    /// Two PSPs share the same id.
+    DuplicatePspId(String),
+    /// A PSP references a country that is not declared.
+    UnknownCountry { psp_id: String, country: Country },
+    /// A PSP's success rate is outside `0.0..=1.0`.
+    InvalidSuccessRate { psp_id: String, rate: f64 },
+    /// A PSP's minimum latency exceeds its maximum.
+    InvalidLatency { psp_id: String, min_ms: u64, max_ms: u64 },
+    /// A PSP's fees are negative or not finite.
+    InvalidFee { psp_id: String },
+    /// A PSP's decline distribution is empty, uses a non-soft reason,
+    /// or does not sum to 1.0.
+    InvalidDeclineDistribution { psp_id: String, details: String },
+    /// A PSP lists no payment methods, or one not offered in its country.
+    InvalidPaymentMethods { psp_id: String, details: String },
+    /// A PSP's response code table remaps a reserved or empty code.
+    InvalidResponseCode { psp_id: String, code: String },
+    /// A PSP's installment plans are malformed or offered without cards.
+    InvalidInstallments { psp_id: String, details: String },
+    /// A PSP's amount, currency or card scheme limits are malformed.
+    InvalidLimits { psp_id: String, details: String },
+    /// A cross-border acquirer's countries, fees or penalty are malformed.
+    InvalidCrossBorder { psp_id: String, details: String },
+    /// A PSP's 3-D Secure rates are malformed or offered without cards.
+    InvalidThreeDs { psp_id: String, details: String },
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogError::Io(msg) => write!(f, "failed to read PSP catalog: {msg}"),
+            CatalogError::Parse(msg) => write!(f, "failed to parse PSP catalog: {msg}"),
+            CatalogError::UnsupportedFormat(path) => {
+                write!(f, "unsupported PSP catalog format '{path}' (expected .toml or .json)")
+            }
+            CatalogError::DuplicateCurrency(currency) => {
+                write!(f, "currency {currency} is declared more than once")
+            }
+            CatalogError::InvalidCurrency { currency, details } => {
+                write!(f, "currency {currency} is invalid: {details}")
+            }
+            CatalogError::ConflictingCurrency(currency) => {
+                write!(f, "currency {currency} has another exponent in a catalog already loaded")
+            }
+            CatalogError::DuplicateCountry(country) => {
+                write!(f, "country {country} is declared more than once")
+            }
+            CatalogError::InvalidCountry { country, details } => {
+                write!(f, "country {country} is invalid: {details}")
+            }
+            CatalogError::ConflictingCountry(country) => write!(
+                f,
+                "country {country} has another ISO code or time zone in a catalog already loaded"
+            ),
+            CatalogError::DuplicatePspId(id) => write!(f, "duplicate PSP id '{id}'"),
+            CatalogError::UnknownCountry { psp_id, country } => {
+                write!(f, "PSP '{psp_id}' references undeclared country {country}")
+            }
+            CatalogError::InvalidSuccessRate { psp_id, rate } => {
+                write!(f, "PSP '{psp_id}' has success rate {rate} outside 0.0..=1.0")
+            }
+            CatalogError::InvalidLatency {
+                psp_id,
+                min_ms,
+                max_ms,
+            } => write!(
+                f,
+                "PSP '{psp_id}' has latency_min_ms {min_ms} > latency_max_ms {max_ms}"
+            ),
+            CatalogError::InvalidFee { psp_id } => write!(f, "PSP '{psp_id}' has an invalid fee"),
+            CatalogError::InvalidDeclineDistribution { psp_id, details } => {
+                write!(f, "PSP '{psp_id}' has an invalid decline distribution: {details}")
+            }
+            CatalogError::InvalidPaymentMethods { psp_id, details } => {
+                write!(f, "PSP '{psp_id}' has invalid payment methods: {details}")
+            }
+            CatalogError::InvalidResponseCode { psp_id, code } => {
+                write!(f, "PSP '{psp_id}' cannot remap response code '{code}'")
+            }
+            CatalogError::InvalidInstallments { psp_id, details } => {
+                write!(f, "PSP '{psp_id}' has invalid installment plans: {details}")
+            }
+            CatalogError::InvalidLimits { psp_id, details } => {
+                write!(f, "PSP '{psp_id}' has invalid eligibility limits: {details}")
+            }
+            CatalogError::InvalidCrossBorder { psp_id, details } => {
+                write!(f, "PSP '{psp_id}' has invalid cross-border terms: {details}")
+            }
+            CatalogError::InvalidThreeDs { psp_id, details } => {
+                write!(f, "PSP '{psp_id}' has invalid 3-D Secure settings: {details}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// The PSPs of a transaction's country, split by whether they can take it.
+#[derive(Debug, Clone, Default)]
+pub struct Eligibility {
+    /// PSPs that can take the transaction, in catalog order.
+    pub eligible: Vec<PspConfig>,
+    /// PSPs that cannot, with the reason.
+    pub ineligible: Vec<IneligiblePsp>,
+}
+
+/// A validated set of currencies, countries and PSP configurations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "CatalogFile")]
+pub struct PspCatalog {
+    currencies: Vec<CurrencyConfig>,
+    countries: Vec<CountryConfig>,
+    psps: Vec<PspConfig>,
+}
+
+/// On-disk shape of a catalog, before validation.
+#[derive(Deserialize)]
+struct CatalogFile {
+    currencies: Vec<CurrencyConfig>,
+    countries: Vec<CountryConfig>,
+    psps: Vec<PspConfig>,
+}
+
+impl TryFrom<CatalogFile> for PspCatalog {
+    type Error = CatalogError;
+
+    fn try_from(file: CatalogFile) -> Result<Self, Self::Error> {
+        PspCatalog::new(file.currencies, file.countries, file.psps)
+    }
+}
+
+impl PspCatalog {
+    /// Builds a catalog, validating every currency, country and PSP entry,
+    /// and registers its currencies and countries (see [`Currency::exponent`]).
+    pub fn new(
+        currencies: Vec<CurrencyConfig>,
+        countries: Vec<CountryConfig>,
+        psps: Vec<PspConfig>,
+    ) -> Result<Self, CatalogError> {
+        let mut seen_currencies = HashSet::new();
+        for entry in &currencies {
+            validate_currency(entry)?;
+            if !seen_currencies.insert(entry.code.clone()) {
+                return Err(CatalogError::DuplicateCurrency(entry.code.clone()));
+            }
+        }
+
+        let mut seen_countries = HashSet::new();
+        let mut seen_iso_codes = HashSet::new();
+        for entry in &countries {
+            if !seen_countries.insert(entry.country.clone()) {
+                return Err(CatalogError::DuplicateCountry(entry.country.clone()));
+            }
+            validate_country(entry, &seen_currencies)?;
+            if !seen_iso_codes.insert(entry.iso_code.as_str()) {
+                return Err(CatalogError::InvalidCountry {
+                    country: entry.country.clone(),
+                    details: format!("ISO code {} is declared twice", entry.iso_code),
+                });
+            }
+        }
+
+        let mut seen_ids = HashSet::new();
+        for psp in &psps {
+            if !seen_ids.insert(psp.id.as_str()) {
+                return Err(CatalogError::DuplicatePspId(psp.id.clone()));
+            }
+            if !seen_countries.contains(&psp.country) {
+                return Err(CatalogError::UnknownCountry {
+                    psp_id: psp.id.clone(),
+                    country: psp.country.clone(),
+                });
+            }
+            let country = countries
+                .iter()
+                .find(|c| c.country == psp.country)
+                .expect("PSP country was checked above");
+            validate_psp(psp, country)?;
+            validate_limits(psp, &country.currency)?;
+            validate_cross_border(psp, &seen_countries)?;
+            validate_three_ds(psp)?;
+        }
+
+        locale::register(&currencies, &countries)?;
+        Ok(PspCatalog {
+            currencies,
+            countries,
+            psps,
+        })
+    }
+
+    /// The built-in catalog compiled from `config/psp_catalog.toml`.
+    pub fn builtin() -> Self {
+        PspCatalog::from_toml_str(BUILTIN_CATALOG).expect("built-in PSP catalog is valid")
+    }
+
+    /// Loads the catalog named by [`CATALOG_PATH_ENV`], or the built-in one
+    /// when the variable is unset.
+    pub fn from_env() -> Result<Self, CatalogError> {
+        match std::env::var(CATALOG_PATH_ENV) {
+            Ok(path) if !path.is_empty() => PspCatalog::load(path),
+            _ => Ok(PspCatalog::builtin()),
+        }
+    }
+
+    /// Loads a catalog from a `.toml` or `.json` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CatalogError> {
+        let path = path.as_ref();
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| CatalogError::Io(e.to_string()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => PspCatalog::from_toml_str(&contents),
+            Some("json") => PspCatalog::from_json_str(&contents),
+            _ => Err(CatalogError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+
+    /// Parses and validates a TOML catalog.
+    pub fn from_toml_str(source: &str) -> Result<Self, CatalogError> {
+        let file: CatalogFile =
+            toml::from_str(source).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        PspCatalog::try_from(file)
+    }
+
+    /// Parses and validates a JSON catalog.
+    pub fn from_json_str(source: &str) -> Result<Self, CatalogError> {
+        let file: CatalogFile =
+            serde_json::from_str(source).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        PspCatalog::try_from(file)
+    }
+
+    /// All currencies in the catalog.
+    pub fn currencies(&self) -> &[CurrencyConfig] {
+        &self.currencies
+    }
+
+    /// All countries in the catalog.
+    pub fn countries(&self) -> &[CountryConfig] {
+        &self.countries
+    }
+
+    /// The countries that are markets: those with a local PSP. A country
+    /// declared only as the home of cross-border acquirers is not one.
+    pub fn markets(&self) -> impl Iterator<Item = &CountryConfig> {
+        self.countries.iter().filter(|c| {
+            self.psps
+                .iter()
+                .any(|p| p.country == c.country && !p.is_cross_border())
+        })
+    }
+
+    /// The market named `name` (e.g. `"Brazil"`), if the catalog serves it.
+    /// API requests name their country through this lookup, so a market
+    /// added to the catalog is accepted without touching the handlers.
+    pub fn market(&self, name: &str) -> Option<&CountryConfig> {
+        self.markets().find(|c| c.country.name() == name)
+    }
+
+    /// The currency with ISO code `code`, if some market checks out in it.
+    pub fn checkout_currency(&self, code: &str) -> Option<&Currency> {
+        self.markets().map(|c| &c.currency).find(|c| c.code() == code)
+    }
+
+    /// The checkout currency for a country, if the catalog serves it.
+    pub fn currency_for(&self, country: &Country) -> Option<&Currency> {
+        self.countries
+            .iter()
+            .find(|c| c.country == *country)
+            .map(|c| &c.currency)
+    }
+
+    /// All PSP configurations, in catalog order.
+    pub fn psps(&self) -> &[PspConfig] {
+        &self.psps
+    }
+
+    /// PSPs serving a country, in catalog order.
+    pub fn psps_for_country(&self, country: &Country) -> Vec<PspConfig> {
+        self.psps
+            .iter()
+            .filter(|p| p.country == *country)
+            .cloned()
+            .collect()
+    }
+
+    /// PSPs serving a country that can process `method`, in catalog order.
+    pub fn psps_supporting(&self, country: &Country, method: &PaymentMethod) -> Vec<PspConfig> {
+        self.psps
+            .iter()
+            .filter(|p| p.country == *country && p.supports(method))
+            .cloned()
+            .collect()
+    }
+
+    /// Cross-border acquirers that can take transactions from `country`,
+    /// in catalog order.
+    pub fn cross_border_psps(&self, country: &Country) -> Vec<PspConfig> {
+        self.psps
+            .iter()
+            .filter(|p| p.serves_cross_border(country))
+            .cloned()
+            .collect()
+    }
+
+    /// PSPs that can process `transaction`, in catalog order: see
+    /// [`eligibility`](PspCatalog::eligibility).
+    pub fn eligible_psps(&self, transaction: &Transaction) -> Vec<PspConfig> {
+        self.eligibility(transaction).eligible
+    }
+
+    /// Split the PSPs of the transaction's country by whether they can take
+    /// it: payment method, installments, currency, amount limits and card
+    /// scheme (see [`PspConfig::check_eligibility`]).
+    pub fn eligibility(&self, transaction: &Transaction) -> Eligibility {
+        let mut eligibility = Eligibility::default();
+        for psp in self.psps.iter().filter(|p| p.country == transaction.country) {
+            match psp.check_eligibility(transaction) {
+                Ok(()) => eligibility.eligible.push(psp.clone()),
+                Err(reason) => eligibility.ineligible.push(IneligiblePsp {
+                    psp_id: psp.id.clone(),
+                    psp_name: psp.name.clone(),
+                    reason,
+                }),
+            }
+        }
+        eligibility
+    }
+
+    /// Split the cross-border acquirers serving the transaction's country
+    /// by whether they can take it, as [`eligibility`](PspCatalog::eligibility)
+    /// does for local PSPs.
+    pub fn cross_border_eligibility(&self, transaction: &Transaction) -> Eligibility {
+        let mut eligibility = Eligibility::default();
+        for psp in self.psps.iter().filter(|p| p.serves_cross_border(&transaction.country)) {
+            match psp.check_eligibility(transaction) {
+                Ok(()) => eligibility.eligible.push(psp.clone()),
+                Err(reason) => eligibility.ineligible.push(IneligiblePsp {
+                    psp_id: psp.id.clone(),
+                    psp_name: psp.name.clone(),
+                    reason,
+                }),
+            }
+        }
+        eligibility
+    }
+
+    /// Looks up a PSP by id.
+    pub fn psp(&self, id: &str) -> Option<&PspConfig> {
+        self.psps.iter().find(|p| p.id == id)
+    }
+}
+
+impl Default for PspCatalog {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Validates a currency's code and exponent.
+fn validate_currency(currency: &CurrencyConfig) -> Result<(), CatalogError> {
+    let invalid = |details: String| CatalogError::InvalidCurrency {
+        currency: currency.code.clone(),
+        details,
+    };
+    let code = currency.code.code();
+    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+        return Err(invalid("code is not three uppercase letters".into()));
+    }
+    if currency.exponent > MAX_CURRENCY_EXPONENT {
+        return Err(invalid(format!(
+            "exponent {} is above {MAX_CURRENCY_EXPONENT}",
+            currency.exponent
+        )));
+    }
+    Ok(())
+}
+
+/// Validates a country's ISO code, currency and payment methods.
+fn validate_country(country: &CountryConfig, currencies: &HashSet<Currency>) -> Result<(), CatalogError> {
+    let invalid = |details: String| CatalogError::InvalidCountry {
+        country: country.country.clone(),
+        details,
+    };
+    if country.country.name().is_empty() {
+        return Err(invalid("empty name".into()));
+    }
+    let iso_code = &country.iso_code;
+    if iso_code.len() != 2 || !iso_code.bytes().all(|b| b.is_ascii_uppercase()) {
+        return Err(invalid(format!("ISO code '{iso_code}' is not two uppercase letters")));
+    }
+    if !currencies.contains(&country.currency) {
+        return Err(invalid(format!("currency {} is not declared", country.currency)));
+    }
+    if country.payment_methods.is_empty() {
+        return Err(invalid("no payment methods".into()));
+    }
+    Ok(())
+}
+
+/// Validates a single PSP's numeric fields and decline distribution.
+fn validate_psp(psp: &PspConfig, country: &CountryConfig) -> Result<(), CatalogError> {
+    if !(0.0..=1.0).contains(&psp.base_success_rate) {
+        return Err(CatalogError::InvalidSuccessRate {
+            psp_id: psp.id.clone(),
+            rate: psp.base_success_rate,
+        });
+    }
+
+    if psp.latency_min_ms > psp.latency_max_ms {
+        return Err(CatalogError::InvalidLatency {
+            psp_id: psp.id.clone(),
+            min_ms: psp.latency_min_ms,
+            max_ms: psp.latency_max_ms,
+        });
+    }
+
+    if !psp.fee_percentage.is_finite() || psp.fee_percentage < 0.0 || psp.fee_fixed.minor_units < 0 {
+        return Err(CatalogError::InvalidFee {
+            psp_id: psp.id.clone(),
+        });
+    }
+
+    let invalid_methods = |details: String| CatalogError::InvalidPaymentMethods {
+        psp_id: psp.id.clone(),
+        details,
+    };
+    if psp.payment_methods.is_empty() {
+        return Err(invalid_methods("no payment methods".into()));
+    }
+    for method in &psp.payment_methods {
+        if !country.payment_methods.contains(method) {
+            return Err(invalid_methods(format!("{method} is not offered in {}", psp.country)));
+        }
+    }
+
+    let invalid_distribution = |details: String| CatalogError::InvalidDeclineDistribution {
+        psp_id: psp.id.clone(),
+        details,
+    };
+
+    if psp.decline_distribution.is_empty() {
+        return Err(invalid_distribution("no decline reasons".into()));
+    }
+    for dw in &psp.decline_distribution {
+        if !matches!(dw.reason.class(), DeclineClass::Soft)
+            || matches!(dw.reason, DeclineReason::Unrecognized(_))
+        {
+            return Err(invalid_distribution(format!("{} is not a soft decline", dw.reason)));
+        }
+        if !dw.weight.is_finite() || dw.weight < 0.0 {
+            return Err(invalid_distribution(format!("{} has weight {}", dw.reason, dw.weight)));
+        }
+    }
+    let total: f64 = psp.decline_distribution.iter().map(|d| d.weight).sum();
+    if (total - 1.0).abs() > WEIGHT_TOLERANCE {
+        return Err(invalid_distribution(format!("weights sum to {total}, expected 1.0")));
+    }
+
+    for code in psp.response_codes.codes.keys() {
+        if code.is_empty() || code == APPROVED_CODE || code == PSP_UNAVAILABLE_CODE {
+            return Err(CatalogError::InvalidResponseCode {
+                psp_id: psp.id.clone(),
+                code: code.clone(),
+            });
+        }
+    }
+
+    let invalid_installments = |details: String| CatalogError::InvalidInstallments {
+        psp_id: psp.id.clone(),
+        details,
+    };
+    if !psp.installments.is_empty() && !psp.supports(&PaymentMethod::Card) {
+        return Err(invalid_installments("installments require card payments".into()));
+    }
+    for (i, plan) in psp.installments.iter().enumerate() {
+        if !(2..=MAX_INSTALLMENTS).contains(&plan.count) {
+            return Err(invalid_installments(format!(
+                "{} installments is outside 2..={MAX_INSTALLMENTS}",
+                plan.count
+            )));
+        }
+        if psp.installments[..i].iter().any(|p| p.count == plan.count) {
+            return Err(invalid_installments(format!("{} installments listed twice", plan.count)));
+        }
+        if !plan.extra_fee_percentage.is_finite() || plan.extra_fee_percentage < 0.0 {
+            return Err(invalid_installments(format!(
+                "{} installments has fee {}",
+                plan.count, plan.extra_fee_percentage
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates a PSP's eligibility limits against its country's currency.
+fn validate_limits(psp: &PspConfig, currency: &Currency) -> Result<(), CatalogError> {
+    let invalid = |details: String| CatalogError::InvalidLimits {
+        psp_id: psp.id.clone(),
+        details,
+    };
+    for (field, limit) in [("min_amount", &psp.min_amount), ("max_amount", &psp.max_amount)] {
+        let Some(limit) = limit else { continue };
+        if limit.currency != *currency {
+            return Err(invalid(format!("{field} is in {}, expected {currency}", limit.currency)));
+        }
+        if limit.minor_units < 0 {
+            return Err(invalid(format!("{field} is negative")));
+        }
+    }
+    if let (Some(min), Some(max)) = (&psp.min_amount, &psp.max_amount) {
+        if min.minor_units > max.minor_units {
+            return Err(invalid(format!("min_amount {min} is above max_amount {max}")));
+        }
+    }
+    if !psp.card_schemes.is_empty() && !psp.supports(&PaymentMethod::Card) {
+        return Err(invalid("card_schemes require card payments".into()));
+    }
+    Ok(())
+}
+
+/// Validates a cross-border acquirer's countries, fees and penalty.
+fn validate_cross_border(psp: &PspConfig, countries: &HashSet<Country>) -> Result<(), CatalogError> {
+    let Some(cross_border) = &psp.cross_border else {
+        return Ok(());
+    };
+    let invalid = |details: String| CatalogError::InvalidCrossBorder {
+        psp_id: psp.id.clone(),
+        details,
+    };
+    if cross_border.countries.is_empty() {
+        return Err(invalid("no countries".into()));
+    }
+    for (i, country) in cross_border.countries.iter().enumerate() {
+        if *country == psp.country {
+            return Err(invalid(format!("{country} is the PSP's own country")));
+        }
+        if !countries.contains(country) {
+            return Err(invalid(format!("{country} is not declared")));
+        }
+        if cross_border.countries[..i].contains(country) {
+            return Err(invalid(format!("{country} listed twice")));
+        }
+    }
+    for (field, value) in [
+        ("fx_markup_percentage", cross_border.fx_markup_percentage),
+        ("fee_percentage", cross_border.fee_percentage),
+    ] {
+        if !value.is_finite() || value < 0.0 {
+            return Err(invalid(format!("{field} is {value}")));
+        }
+    }
+    if !(0.0..=1.0).contains(&cross_border.domestic_card_penalty) {
+        return Err(invalid(format!(
+            "domestic_card_penalty {} is outside 0.0..=1.0",
+            cross_border.domestic_card_penalty
+        )));
+    }
+    if psp.min_amount.is_some() || psp.max_amount.is_some() {
+        return Err(invalid("amount limits are not supported".into()));
+    }
+    if !psp.installments.is_empty() {
+        return Err(invalid("installments are not supported".into()));
+    }
+    Ok(())
+}
+
+/// Validates a PSP's 3-D Secure rates.
+fn validate_three_ds(psp: &PspConfig) -> Result<(), CatalogError> {
+    let Some(three_ds) = &psp.three_ds else {
+        return Ok(());
+    };
+    let invalid = |details: String| CatalogError::InvalidThreeDs {
+        psp_id: psp.id.clone(),
+        details,
+    };
+    if !psp.supports(&PaymentMethod::Card) {
+        return Err(invalid("3-D Secure requires card payments".into()));
+    }
+    for (field, value) in [
+        ("frictionless_rate", three_ds.frictionless_rate),
+        ("challenge_success_rate", three_ds.challenge_success_rate),
+        ("approval_uplift", three_ds.approval_uplift),
+    ] {
+        if !(0.0..=1.0).contains(&value) {
+            return Err(invalid(format!("{field} {value} is outside 0.0..=1.0")));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::psp::IneligibilityReason;
+
+    /// Serializes the built-in catalog, applies `modify`, and reloads it.
+    fn load_modified(modify: impl FnOnce(&mut serde_json::Value)) -> Result<PspCatalog, CatalogError> {
+        let mut value = serde_json::to_value(PspCatalog::builtin()).unwrap();
+        modify(&mut value);
+        PspCatalog::from_json_str(&value.to_string())
+    }
+
+    #[test]
+    fn test_each_country_has_three_psps() {
+        let catalog = PspCatalog::builtin();
+        for country in [
+            Country::new("Brazil"),
+            Country::new("Mexico"),
+            Country::new("Colombia"),
+            Country::new("Chile"),
+            Country::new("Peru"),
+            Country::new("Argentina"),
+        ] {
+            assert_eq!(catalog.psps_for_country(&country).len(), 3, "{country}");
+        }
+    }
+
+    #[test]
+    fn test_builtin_has_eighteen_local_psps_and_one_cross_border() {
+        let catalog = PspCatalog::builtin();
+        assert_eq!(catalog.psps().len(), 19);
+        assert_eq!(catalog.psps().iter().filter(|p| p.is_cross_border()).count(), 1);
+    }
+
+    #[test]
+    fn test_builtin_country_currencies() {
+        let catalog = PspCatalog::builtin();
+        assert_eq!(catalog.currency_for(&Country::new("Chile")), Some(&Currency::new("CLP")));
+        assert_eq!(catalog.currency_for(&Country::new("Peru")), Some(&Currency::new("PEN")));
+        assert_eq!(catalog.currency_for(&Country::new("Argentina")), Some(&Currency::new("ARS")));
+    }
+
+    #[test]
+    fn test_requests_name_markets_and_currencies_through_the_catalog() {
+        let catalog = PspCatalog::builtin();
+        let markets: Vec<String> = catalog.markets().map(|c| c.country.to_string()).collect();
+        assert_eq!(markets, ["Brazil", "Mexico", "Colombia", "Chile", "Peru", "Argentina"]);
+        assert_eq!(catalog.market("Chile").map(|c| &c.currency), Some(&Currency::new("CLP")));
+        assert!(catalog.market("UnitedStates").is_none());
+        assert!(catalog.market("Atlantis").is_none());
+        assert_eq!(catalog.checkout_currency("PEN"), Some(&Currency::new("PEN")));
+        // USD prices fees and reports, but no market checks out in it
+        assert!(catalog.checkout_currency("USD").is_none());
+    }
+
+    #[test]
+    fn test_success_rates_are_valid() {
+        for psp in PspCatalog::builtin().psps() {
+            assert!(psp.base_success_rate > 0.0 && psp.base_success_rate < 1.0,
+                "PSP {} has invalid success rate: {}", psp.name, psp.base_success_rate);
+        }
+    }
+
+    #[test]
+    fn test_decline_distributions_sum_to_one() {
+        for psp in PspCatalog::builtin().psps() {
+            let total: f64 = psp.decline_distribution.iter().map(|d| d.weight).sum();
+            assert!((total - 1.0).abs() < 0.01,
+                "PSP {} decline weights sum to {}, expected 1.0", psp.id, total);
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip_matches_builtin() {
+        let catalog = load_modified(|_| {}).unwrap();
+        assert_eq!(catalog.psps().len(), 19);
+        assert_eq!(catalog.currency_for(&Country::new("Colombia")), Some(&Currency::new("COP")));
+        assert_eq!(catalog.psp("psp_br_2").unwrap().name, "Cielo");
+    }
+
+    #[test]
+    fn test_rejects_duplicate_psp_ids() {
+        let result = load_modified(|v| v["psps"][1]["id"] = "psp_br_1".into());
+        assert_eq!(result.unwrap_err(), CatalogError::DuplicatePspId("psp_br_1".into()));
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_success_rate() {
+        let result = load_modified(|v| v["psps"][0]["base_success_rate"] = 1.2.into());
+        assert!(matches!(result, Err(CatalogError::InvalidSuccessRate { .. })));
+    }
+
+    #[test]
+    fn test_rejects_inverted_latency() {
+        let result = load_modified(|v| v["psps"][0]["latency_min_ms"] = 900.into());
+        assert!(matches!(result, Err(CatalogError::InvalidLatency { .. })));
+    }
+
+    #[test]
+    fn test_rejects_weights_not_summing_to_one() {
+        let result = load_modified(|v| v["psps"][0]["decline_distribution"][0]["weight"] = 0.9.into());
+        assert!(matches!(result, Err(CatalogError::InvalidDeclineDistribution { .. })));
+    }
+
+    #[test]
+    fn test_rejects_hard_decline_in_distribution() {
+        let result = load_modified(|v| {
+            v["psps"][0]["decline_distribution"][0]["reason"] = "CardExpired".into()
+        });
+        assert!(matches!(result, Err(CatalogError::InvalidDeclineDistribution { .. })));
+    }
+
+    #[test]
+    fn test_builtin_proprietary_response_codes() {
+        let catalog = PspCatalog::builtin();
+        let mercado_pago = &catalog.psp("psp_ar_1").unwrap().response_codes;
+        assert_eq!(
+            mercado_pago.normalize("cc_rejected_high_risk", None),
+            Some(DeclineReason::SuspectedFraud)
+        );
+        assert_eq!(mercado_pago.unknown_code_class, DeclineClass::Hard);
+
+        let conekta = &catalog.psp("psp_mx_1").unwrap().response_codes;
+        assert_eq!(conekta.unknown_code_class, DeclineClass::Soft);
+        assert!(conekta.normalize("unheard_of", None).unwrap().is_soft_decline());
+    }
+
+    #[test]
+    fn test_local_payment_methods_have_capable_psps() {
+        let catalog = PspCatalog::builtin();
+        for (country, method) in [
+            (Country::new("Brazil"), PaymentMethod::Pix),
+            (Country::new("Brazil"), PaymentMethod::Boleto),
+            (Country::new("Mexico"), PaymentMethod::Oxxo),
+            (Country::new("Colombia"), PaymentMethod::Pse),
+        ] {
+            let psps = catalog.psps_supporting(&country, &method);
+            assert!(!psps.is_empty(), "no PSP supports {method} in {country}");
+            assert!(psps.len() < catalog.psps_for_country(&country).len());
+        }
+        assert!(catalog.psps_supporting(&Country::new("Chile"), &PaymentMethod::Pix).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_payment_method_outside_psp_country() {
+        let result = load_modified(|v| v["psps"][0]["payment_methods"] = serde_json::json!(["Oxxo"]));
+        assert!(matches!(result, Err(CatalogError::InvalidPaymentMethods { .. })));
+    }
+
+    #[test]
+    fn test_rejects_remapping_the_approval_code() {
+        let result = load_modified(|v| {
+            v["psps"][0]["response_codes"]["codes"]["00"] = "DoNotHonor".into()
+        });
+        assert!(matches!(result, Err(CatalogError::InvalidResponseCode { .. })));
+    }
+
+    #[test]
+    fn test_builtin_installment_plans() {
+        let catalog = PspCatalog::builtin();
+        let stone = catalog.psp("psp_br_3").unwrap();
+        assert!(stone.supports_installments(1));
+        assert!(stone.supports_installments(12));
+        assert!(!stone.supports_installments(10));
+        assert_eq!(stone.fee_percentage_for(1), 2.5);
+        assert!((stone.fee_percentage_for(12) - 6.5).abs() < 1e-9);
+
+        for country in [Country::new("Colombia"), Country::new("Chile"), Country::new("Peru"), Country::new("Argentina")] {
+            assert!(catalog.psps_for_country(&country).iter().all(|p| p.installments.is_empty()));
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_installment_plans() {
+        for plans in [
+            serde_json::json!([{ "count": 1, "extra_fee_percentage": 1.0 }]),
+            serde_json::json!([{ "count": 25, "extra_fee_percentage": 1.0 }]),
+            serde_json::json!([{ "count": 3, "extra_fee_percentage": -1.0 }]),
+            serde_json::json!([
+                { "count": 3, "extra_fee_percentage": 1.0 },
+                { "count": 3, "extra_fee_percentage": 2.0 }
+            ]),
+        ] {
+            let result = load_modified(|v| v["psps"][0]["installments"] = plans.clone());
+            assert!(matches!(result, Err(CatalogError::InvalidInstallments { .. })), "{plans}");
+        }
+    }
+
+    #[test]
+    fn test_cross_border_acquirer_serves_other_countries_only_as_fallback() {
+        let catalog = PspCatalog::builtin();
+        assert!(catalog.psps_for_country(&Country::new("Brazil")).iter().all(|p| !p.is_cross_border()));
+        let cross_border = catalog.cross_border_psps(&Country::new("Brazil"));
+        assert_eq!(cross_border.len(), 1);
+        assert_eq!(cross_border[0].id, "psp_us_1");
+        // 2.9% plus a 2.0% FX markup and a 1.5% cross-border fee
+        assert!((cross_border[0].fee_percentage_for(1) - 6.4).abs() < 1e-9);
+        assert!(catalog.cross_border_psps(&Country::new("UnitedStates")).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_invalid_cross_border_terms() {
+        let last = PspCatalog::builtin().psps().len() - 1;
+        for (field, value) in [
+            ("countries", serde_json::json!([])),
+            ("countries", serde_json::json!(["UnitedStates"])),
+            ("countries", serde_json::json!(["Brazil", "Brazil"])),
+            ("fx_markup_percentage", serde_json::json!(-1.0)),
+            ("fee_percentage", serde_json::json!(-0.5)),
+            ("domestic_card_penalty", serde_json::json!(1.5)),
+        ] {
+            let result = load_modified(|v| v["psps"][last]["cross_border"][field] = value.clone());
+            assert!(matches!(result, Err(CatalogError::InvalidCrossBorder { .. })), "{field} = {value}");
+        }
+
+        let result = load_modified(|v| {
+            v["psps"][last]["max_amount"] = serde_json::json!({ "minor_units": 100_000, "currency": "USD" })
+        });
+        assert!(matches!(result, Err(CatalogError::InvalidCrossBorder { .. })));
+    }
+
+    #[test]
+    fn test_rejects_invalid_three_ds_rates() {
+        for (field, value) in [
+            ("frictionless_rate", -0.1),
+            ("challenge_success_rate", 1.2),
+            ("approval_uplift", 1.5),
+        ] {
+            let result = load_modified(|v| {
+                v["psps"][0]["three_ds"] = serde_json::json!({
+                    "frictionless_rate": 0.7,
+                    "challenge_success_rate": 0.8,
+                    "approval_uplift": 0.2
+                });
+                v["psps"][0]["three_ds"][field] = value.into();
+            });
+            assert!(matches!(result, Err(CatalogError::InvalidThreeDs { .. })), "{field} = {value}");
+        }
+    }
+
+    #[test]
+    fn test_builtin_currencies_and_countries() {
+        let catalog = PspCatalog::builtin();
+        assert_eq!(catalog.currencies().len(), 7);
+        assert_eq!(Currency::new("CLP").exponent(), 0);
+        assert_eq!(Currency::new("BRL").exponent(), 2);
+        assert_eq!(Country::new("Colombia").iso_code().as_deref(), Some("CO"));
+        assert_eq!(TimeZone::for_country(&Country::new("Peru")), TimeZone::Lima);
+        let chile = catalog.market("Chile").unwrap();
+        assert_eq!(chile.payment_methods, [PaymentMethod::Card]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_currencies_and_countries() {
+        for (path, value) in [
+            ("/currencies/0/code", serde_json::json!("brl")),
+            ("/currencies/0/exponent", serde_json::json!(MAX_CURRENCY_EXPONENT + 1)),
+        ] {
+            let result = load_modified(|v| *v.pointer_mut(path).unwrap() = value.clone());
+            assert!(matches!(result, Err(CatalogError::InvalidCurrency { .. })), "{path}");
+        }
+        let result = load_modified(|v| {
+            let brl = v["currencies"][0].clone();
+            v["currencies"].as_array_mut().unwrap().push(brl);
+        });
+        assert_eq!(result.unwrap_err(), CatalogError::DuplicateCurrency(Currency::new("BRL")));
+
+        for (path, value) in [
+            ("/countries/0/iso_code", serde_json::json!("BRA")),
+            ("/countries/0/iso_code", serde_json::json!("MX")),
+            ("/countries/0/currency", serde_json::json!("XXX")),
+            ("/countries/0/payment_methods", serde_json::json!([])),
+        ] {
+            let result = load_modified(|v| *v.pointer_mut(path).unwrap() = value.clone());
+            assert!(matches!(result, Err(CatalogError::InvalidCountry { .. })), "{path} = {value}");
+        }
+    }
+
+    #[test]
+    fn test_rejects_redefining_a_loaded_currency_or_country() {
+        let result = load_modified(|v| v["currencies"][2]["exponent"] = serde_json::json!(2));
+        assert_eq!(result.unwrap_err(), CatalogError::ConflictingCurrency(Currency::new("COP")));
+        let result = load_modified(|v| v["countries"][0]["time_zone"] = serde_json::json!("Utc"));
+        assert_eq!(result.unwrap_err(), CatalogError::ConflictingCountry(Country::new("Brazil")));
+        assert_eq!(TimeZone::for_country(&Country::new("Brazil")), TimeZone::SaoPaulo);
+        // Payment methods are each catalog's own, so offering another is no
+        // conflict
+        let catalog = load_modified(|v| v["countries"][3]["payment_methods"] = serde_json::json!(["Card", "Pix"]));
+        assert_eq!(catalog.unwrap().market("Chile").unwrap().payment_methods.len(), 2);
+    }
+
+    #[test]
+    fn test_new_market_is_added_by_configuration() {
+        let uruguay = r#"
+            [[currencies]]
+            code = "UYU"
+            exponent = 2
+
+            [[countries]]
+            country = "Uruguay"
+            iso_code = "UY"
+            currency = "UYU"
+            time_zone = "BuenosAires"
+
+            [[psps]]
+            id = "psp_uy_1"
+            name = "dLocal"
+            country = "Uruguay"
+            base_success_rate = 0.80
+            latency_min_ms = 150
+            latency_max_ms = 300
+            fee_percentage = 3.1
+            fee_fixed = { minor_units = 25, currency = "USD" }
+            decline_distribution = [{ reason = "DoNotHonor", weight = 1.0 }]
+        "#;
+        let catalog = PspCatalog::from_toml_str(&format!("{BUILTIN_CATALOG}\n{uruguay}")).unwrap();
+        let market = catalog.market("Uruguay").unwrap();
+        assert_eq!(market.currency, Currency::new("UYU"));
+        assert_eq!(catalog.checkout_currency("UYU"), Some(&Currency::new("UYU")));
+        assert_eq!(Country::new("Uruguay").iso_code().as_deref(), Some("UY"));
+        assert_eq!(catalog.psps_for_country(&market.country).len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_psp_in_undeclared_country() {
+        let result = load_modified(|v| {
+            v["countries"].as_array_mut().unwrap().retain(|c| c["country"] != "Colombia")
+        });
+        assert!(matches!(result, Err(CatalogError::UnknownCountry { .. })));
+    }
+
+    #[test]
+    fn test_rejects_invalid_limits() {
+        for (field, limit) in [
+            ("min_amount", serde_json::json!({ "amount": "10.00", "currency": "MXN" })),
+            ("min_amount", serde_json::json!({ "amount": "-10.00", "currency": "BRL" })),
+            ("max_amount", serde_json::json!({ "amount": "1.00", "currency": "BRL" })),
+        ] {
+            let result = load_modified(|v| {
+                v["psps"][0]["min_amount"] = serde_json::json!({ "amount": "5.00", "currency": "BRL" });
+                v["psps"][0][field] = limit.clone();
+            });
+            assert!(matches!(result, Err(CatalogError::InvalidLimits { .. })), "{field} {limit}");
+        }
+    }
+
+    #[test]
+    fn test_eligibility_lists_reasons() {
+        let catalog = PspCatalog::builtin();
+        let mut txn = Transaction {
+            id: "txn_elig".to_string(),
+            amount: crate::models::money::Money::new(900_000, Currency::new("MXN")),
+            country: Country::new("Mexico"),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some("411111".to_string()),
+            card_last4: Some("1111".to_string()),
+            bin_info: None,
+            installments: None,
+            customer_id: "cust_elig".to_string(),
+            timestamp: crate::time::Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
+        };
+        let mexico = catalog.eligibility(&txn);
+        assert_eq!(mexico.eligible.len(), 2);
+        assert_eq!(mexico.ineligible.len(), 1);
+        assert_eq!(mexico.ineligible[0].psp_id, "psp_mx_3");
+        assert!(matches!(mexico.ineligible[0].reason, IneligibilityReason::AboveMaxAmount(_)));
+
+        txn.country = Country::new("Brazil");
+        txn.amount = crate::models::money::Money::new(15_000, Currency::new("BRL"));
+        txn.card_bin = Some("376400".to_string());
+        crate::card_bin::BinTable::builtin().enrich(&mut txn);
+        let brazil = catalog.eligibility(&txn);
+        assert_eq!(brazil.ineligible.len(), 1);
+        assert_eq!(brazil.ineligible[0].psp_id, "psp_br_3");
+        assert_eq!(
+            brazil.ineligible[0].reason,
+            IneligibilityReason::CardSchemeNotSupported(crate::card_bin::CardScheme::Amex)
+        );
+    }
+
+    #[test]
+    fn test_new_psp_is_added_by_configuration() {
+        let extra = r#"
+            [[psps]]
+            id = "psp_br_4"
+            name = "Rede"
+            country = "Brazil"
+            base_success_rate = 0.80
+            latency_min_ms = 150
+            latency_max_ms = 300
+            fee_percentage = 2.7
+            fee_fixed = { minor_units = 25, currency = "USD" }
+            decline_distribution = [{ reason = "DoNotHonor", weight = 1.0 }]
+        "#;
+        let catalog = PspCatalog::from_toml_str(&format!("{BUILTIN_CATALOG}\n{extra}")).unwrap();
+        assert_eq!(catalog.psps_for_country(&Country::new("Brazil")).len(), 4);
+    }
+}
@@ -0,0 +1,260 @@
+//! PSP response codes — normalizing what a processor actually returned
+//! into the engine's [`DeclineReason`] taxonomy.
+//!
+//! PSPs answer with raw codes: mostly ISO 8583 field 39 values (`05`,
+//! `51`, `54`, `91`, ...), sometimes with a Mastercard merchant advice
+//! code (MAC) attached, and sometimes with proprietary codes of their own.
+//! Each PSP carries a [`ResponseCodeTable`] in the catalog; normalization
+//! resolves a raw response in this order:
+//!
+//! 1. A merchant advice code that changes retry behaviour (`01`, `02`,
+//!    `03`, `21`) wins over the response code.
+//! 2. The PSP's own code table.
+//! 3. The shared ISO 8583 table.
+//! 4. Anything else becomes [`DeclineReason::Unrecognized`] with the
+//!    table's configured `unknown_code_class`.
+
+use crate::models::psp::{DeclineClass, DeclineReason};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// ISO 8583 response code for an approval.
+pub const APPROVED_CODE: &str = "00";
+
+/// Synthetic response code recorded when the PSP could not be reached at all.
+pub const PSP_UNAVAILABLE_CODE: &str = "UNAVAILABLE";
+
+/// ISO 8583 response codes, their normalized reason and standard message.
+///
+/// The first entry for a reason is its canonical code.
+const ISO_8583_CODES: &[(&str, DeclineReason, &str)] = &[
+    ("04", DeclineReason::PickUpCard, "Pick up card"),
+    ("05", DeclineReason::DoNotHonor, "Do not honor"),
+    ("06", DeclineReason::ProcessorDeclined, "Error"),
+    ("07", DeclineReason::PickUpCard, "Pick up card, special condition"),
+    ("12", DeclineReason::TransactionNotPermitted, "Invalid transaction"),
+    ("14", DeclineReason::InvalidCard, "Invalid card number"),
+    ("15", DeclineReason::InvalidCard, "No such issuer"),
+    ("19", DeclineReason::TryAgainLater, "Re-enter transaction"),
+    ("1A", DeclineReason::AuthenticationRequired, "Additional customer authentication required"),
+    ("41", DeclineReason::LostCard, "Lost card, pick up"),
+    ("43", DeclineReason::StolenCard, "Stolen card, pick up"),
+    ("51", DeclineReason::InsufficientFunds, "Insufficient funds"),
+    ("54", DeclineReason::CardExpired, "Expired card"),
+    ("57", DeclineReason::TransactionNotPermitted, "Transaction not permitted to cardholder"),
+    ("58", DeclineReason::TransactionNotPermitted, "Transaction not permitted to terminal"),
+    ("59", DeclineReason::SuspectedFraud, "Suspected fraud"),
+    ("61", DeclineReason::ExceedsLimit, "Exceeds withdrawal amount limit"),
+    ("62", DeclineReason::RestrictedCard, "Restricted card"),
+    ("65", DeclineReason::ExceedsLimit, "Exceeds withdrawal frequency limit"),
+    ("82", DeclineReason::InvalidCvv, "Negative CAM, dCVV, iCVV or CVV results"),
+    ("N7", DeclineReason::InvalidCvv, "Decline for CVV2 failure"),
+    ("91", DeclineReason::IssuerUnavailable, "Issuer or switch inoperative"),
+    ("92", DeclineReason::IssuerUnavailable, "Unable to route transaction"),
+    ("96", DeclineReason::SystemError, "System malfunction"),
+];
+
+/// Mastercard merchant advice codes that override the response code.
+const MERCHANT_ADVICE_CODES: &[(&str, DeclineReason)] = &[
+    ("01", DeclineReason::AccountUpdateRequired),
+    ("02", DeclineReason::TryAgainLater),
+    ("03", DeclineReason::DoNotRetry),
+    ("21", DeclineReason::DoNotRetry),
+];
+
+/// A raw PSP response rendered from a normalized reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawResponse {
+    pub code: String,
+    pub message: String,
+    pub merchant_advice_code: Option<String>,
+}
+
+/// Per-PSP normalization table for raw response codes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseCodeTable {
+    /// PSP-specific codes, consulted before the ISO 8583 table.
+    #[serde(default)]
+    pub codes: BTreeMap<String, DeclineReason>,
+    /// Classification given to codes that no table recognises.
+    #[serde(default)]
+    pub unknown_code_class: DeclineClass,
+}
+
+impl ResponseCodeTable {
+    /// Normalize a raw response into a [`DeclineReason`].
+    ///
+    /// Returns `None` for the approval code.
+    pub fn normalize(&self, code: &str, merchant_advice_code: Option<&str>) -> Option<DeclineReason> {
+        if code == APPROVED_CODE {
+            return None;
+        }
+        if code == PSP_UNAVAILABLE_CODE {
+            return Some(DeclineReason::PspUnavailable);
+        }
+        if let Some(reason) = merchant_advice_code.and_then(advice_code_reason) {
+            return Some(reason);
+        }
+        let reason = self
+            .codes
+            .get(code)
+            .cloned()
+            .or_else(|| iso_8583_reason(code))
+            .unwrap_or(DeclineReason::Unrecognized(self.unknown_code_class));
+        Some(reason)
+    }
+
+    /// Render a normalized reason as the raw response this PSP would send.
+    ///
+    /// Prefers the PSP's own code for the reason, then the canonical
+    /// ISO 8583 code. Reasons that ISO 8583 expresses through a merchant
+    /// advice code are sent as `05` with the advice code attached.
+    pub fn encode(&self, reason: &DeclineReason) -> RawResponse {
+        if let Some((code, _)) = self.codes.iter().find(|(_, r)| *r == reason) {
+            return RawResponse {
+                code: code.clone(),
+                message: reason.to_string(),
+                merchant_advice_code: None,
+            };
+        }
+        if let Some((code, _, message)) = ISO_8583_CODES.iter().find(|(_, r, _)| r == reason) {
+            return RawResponse {
+                code: code.to_string(),
+                message: message.to_string(),
+                merchant_advice_code: None,
+            };
+        }
+        if let Some((advice, _)) = MERCHANT_ADVICE_CODES.iter().find(|(_, r)| r == reason) {
+            return RawResponse {
+                code: "05".to_string(),
+                message: "Do not honor".to_string(),
+                merchant_advice_code: Some(advice.to_string()),
+            };
+        }
+        match reason {
+            DeclineReason::PspUnavailable => RawResponse {
+                code: PSP_UNAVAILABLE_CODE.to_string(),
+                message: "PSP unavailable".to_string(),
+                merchant_advice_code: None,
+            },
+            _ => RawResponse {
+                code: "06".to_string(),
+                message: "Error".to_string(),
+                merchant_advice_code: None,
+            },
+        }
+    }
+}
+
+/// Look up an ISO 8583 response code.
+pub fn iso_8583_reason(code: &str) -> Option<DeclineReason> {
+    ISO_8583_CODES
+        .iter()
+        .find(|(c, _, _)| *c == code)
+        .map(|(_, reason, _)| reason.clone())
+}
+
+/// Look up a Mastercard merchant advice code that changes retry behaviour.
+pub fn advice_code_reason(code: &str) -> Option<DeclineReason> {
+    MERCHANT_ADVICE_CODES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, reason)| reason.clone())
+}
+
+/// The raw response for an approval.
+pub fn approved() -> RawResponse {
+    RawResponse {
+        code: APPROVED_CODE.to_string(),
+        message: "Approved".to_string(),
+        merchant_advice_code: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iso_codes_normalize() {
+        let table = ResponseCodeTable::default();
+        assert_eq!(table.normalize("00", None), None);
+        assert_eq!(table.normalize("05", None), Some(DeclineReason::DoNotHonor));
+        assert_eq!(table.normalize("51", None), Some(DeclineReason::InsufficientFunds));
+        assert_eq!(table.normalize("54", None), Some(DeclineReason::CardExpired));
+        assert_eq!(table.normalize("91", None), Some(DeclineReason::IssuerUnavailable));
+        assert_eq!(
+            table.normalize(PSP_UNAVAILABLE_CODE, None),
+            Some(DeclineReason::PspUnavailable)
+        );
+    }
+
+    #[test]
+    fn test_merchant_advice_code_overrides_response_code() {
+        let table = ResponseCodeTable::default();
+        // "Do not honor" is soft on its own, but MAC 03 forbids retrying.
+        let reason = table.normalize("05", Some("03")).unwrap();
+        assert_eq!(reason, DeclineReason::DoNotRetry);
+        assert!(reason.is_hard_decline());
+
+        assert_eq!(table.normalize("51", Some("02")), Some(DeclineReason::TryAgainLater));
+        // Advice codes without retry guidance leave the response code alone.
+        assert_eq!(table.normalize("05", Some("99")), Some(DeclineReason::DoNotHonor));
+    }
+
+    #[test]
+    fn test_psp_codes_take_precedence_over_iso() {
+        let mut table = ResponseCodeTable::default();
+        table.codes.insert("05".into(), DeclineReason::SuspectedFraud);
+        table.codes.insert("P-117".into(), DeclineReason::IssuerUnavailable);
+
+        assert_eq!(table.normalize("05", None), Some(DeclineReason::SuspectedFraud));
+        assert_eq!(table.normalize("P-117", None), Some(DeclineReason::IssuerUnavailable));
+    }
+
+    #[test]
+    fn test_unknown_codes_use_configured_class() {
+        let mut table = ResponseCodeTable::default();
+        let reason = table.normalize("ZZ", None).unwrap();
+        assert_eq!(reason, DeclineReason::Unrecognized(DeclineClass::Hard));
+        assert!(reason.is_hard_decline());
+
+        table.unknown_code_class = DeclineClass::Soft;
+        let reason = table.normalize("ZZ", None).unwrap();
+        assert!(reason.is_soft_decline());
+    }
+
+    #[test]
+    fn test_encode_round_trips_through_normalize() {
+        let mut table = ResponseCodeTable::default();
+        table.codes.insert("P-117".into(), DeclineReason::IssuerUnavailable);
+
+        for reason in [
+            DeclineReason::InsufficientFunds,
+            DeclineReason::CardExpired,
+            DeclineReason::InvalidCard,
+            DeclineReason::StolenCard,
+            DeclineReason::LostCard,
+            DeclineReason::PickUpCard,
+            DeclineReason::RestrictedCard,
+            DeclineReason::TransactionNotPermitted,
+            DeclineReason::ExceedsLimit,
+            DeclineReason::InvalidCvv,
+            DeclineReason::AccountUpdateRequired,
+            DeclineReason::DoNotRetry,
+            DeclineReason::IssuerUnavailable,
+            DeclineReason::SuspectedFraud,
+            DeclineReason::DoNotHonor,
+            DeclineReason::ProcessorDeclined,
+            DeclineReason::TryAgainLater,
+            DeclineReason::SystemError,
+            DeclineReason::AuthenticationRequired,
+            DeclineReason::PspUnavailable,
+        ] {
+            let raw = table.encode(&reason);
+            let normalized = table.normalize(&raw.code, raw.merchant_advice_code.as_deref());
+            assert_eq!(normalized, Some(reason.clone()), "{reason} encoded as {raw:?}");
+        }
+        assert_eq!(table.encode(&DeclineReason::IssuerUnavailable).code, "P-117");
+    }
+}
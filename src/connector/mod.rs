@@ -0,0 +1,507 @@
+//! PSP connectors — how the routing engine talks to a processor.
+//!
+//! A [`PspConnector`] sends an authorization, or a capture, void or refund
+//! of an authorized payment, to one PSP and returns its response. The
+//! [`PspSimulator`](crate::simulator::PspSimulator) is one implementation;
+//! sandbox HTTP clients and scripted test doubles are others.
+//!
+//! Connectors to PSPs with a 3-D Secure server also authenticate
+//! cardholders and complete their challenges; the others keep the default,
+//! which answers as unavailable.
+//!
+//! [`AsyncPspConnector`] is the async flavour for connectors that do real
+//! I/O. Every synchronous connector is also an async one whose futures are
+//! ready immediately.
+//!
+//! A [`ConnectorRegistry`] maps `PspConfig.id` to a connector, with an
+//! optional fallback for PSPs not registered individually, so connectors
+//! of different kinds can serve one catalog side by side. The registry is
+//! itself a connector and can be handed to the engine directly.
+
+use crate::catalog::PspCatalog;
+use crate::models::authentication::{ThreeDsFlow, ThreeDsResponse};
+use crate::models::payment::{OperationKind, Payment};
+use crate::models::psp::{DeclineReason, PspConfig, PspResponse};
+use crate::models::transaction::Transaction;
+use crate::simulator;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Future returned by [`AsyncPspConnector`] calls.
+pub type ConnectorFuture<'a> = Pin<Box<dyn Future<Output = PspResponse> + Send + 'a>>;
+
+/// Future returned by [`AsyncPspConnector::authenticate_async`] and
+/// [`AsyncPspConnector::complete_challenge_async`].
+pub type AuthenticationFuture<'a> = Pin<Box<dyn Future<Output = ThreeDsResponse> + Send + 'a>>;
+
+/// A blocking connection to one or more PSPs.
+///
+/// Connectors report transport failures (timeouts, refused connections)
+/// as a response with [`DeclineReason::PspUnavailable`] rather than an
+/// error, so the engine cascades past them like any other outage.
+pub trait PspConnector: Send + Sync {
+    /// Send `transaction` to `psp` for authorization.
+    fn authorize(&self, transaction: &Transaction, psp: &PspConfig) -> PspResponse;
+
+    /// Send `transaction` to `psp` again after `retry` earlier attempts on
+    /// it declined. Connectors that cannot tell a resend from a first
+    /// attempt keep the default, which calls [`authorize`](Self::authorize).
+    fn resend(&self, transaction: &Transaction, psp: &PspConfig, retry: u32) -> PspResponse {
+        let _ = retry;
+        self.authorize(transaction, psp)
+    }
+
+    /// Send a capture, void or refund of `payment` to `psp`, the PSP that
+    /// authorized it.
+    fn execute(&self, payment: &Payment, psp: &PspConfig, kind: OperationKind) -> PspResponse;
+
+    /// Ask `psp`'s 3-D Secure server to authenticate the cardholder of
+    /// `transaction`, which may take a challenge.
+    fn authenticate(&self, transaction: &Transaction, psp: &PspConfig) -> ThreeDsResponse {
+        let _ = transaction;
+        ThreeDsResponse::unavailable(psp, ThreeDsFlow::Frictionless)
+    }
+
+    /// Fetch the result of the challenge `session_id` that `psp` issued
+    /// for `transaction`.
+    fn complete_challenge(
+        &self,
+        transaction: &Transaction,
+        psp: &PspConfig,
+        session_id: &str,
+    ) -> ThreeDsResponse {
+        let _ = (transaction, session_id);
+        ThreeDsResponse::unavailable(psp, ThreeDsFlow::Challenge)
+    }
+}
+
+/// A non-blocking connection to one or more PSPs.
+///
+/// Same contract as [`PspConnector`]; implement this one for connectors
+/// that wait on the network.
+pub trait AsyncPspConnector: Send + Sync {
+    /// Send `transaction` to `psp` for authorization.
+    fn authorize_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+    ) -> ConnectorFuture<'a>;
+
+    /// Send `transaction` to `psp` again after `retry` earlier attempts on
+    /// it declined; defaults to [`authorize_async`](Self::authorize_async).
+    fn resend_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+        retry: u32,
+    ) -> ConnectorFuture<'a> {
+        let _ = retry;
+        self.authorize_async(transaction, psp)
+    }
+
+    /// Send a capture, void or refund of `payment` to `psp`.
+    fn execute_async<'a>(
+        &'a self,
+        payment: &'a Payment,
+        psp: &'a PspConfig,
+        kind: OperationKind,
+    ) -> ConnectorFuture<'a>;
+
+    /// Ask `psp`'s 3-D Secure server to authenticate the cardholder of
+    /// `transaction`; defaults to unavailable.
+    fn authenticate_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+    ) -> AuthenticationFuture<'a> {
+        let _ = transaction;
+        Box::pin(std::future::ready(ThreeDsResponse::unavailable(psp, ThreeDsFlow::Frictionless)))
+    }
+
+    /// Fetch the result of the challenge `session_id` that `psp` issued
+    /// for `transaction`; defaults to unavailable.
+    fn complete_challenge_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+        session_id: &'a str,
+    ) -> AuthenticationFuture<'a> {
+        let _ = (transaction, session_id);
+        Box::pin(std::future::ready(ThreeDsResponse::unavailable(psp, ThreeDsFlow::Challenge)))
+    }
+}
+
+impl<T: PspConnector + ?Sized> AsyncPspConnector for T {
+    fn authorize_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+    ) -> ConnectorFuture<'a> {
+        Box::pin(std::future::ready(self.authorize(transaction, psp)))
+    }
+
+    fn resend_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+        retry: u32,
+    ) -> ConnectorFuture<'a> {
+        Box::pin(std::future::ready(self.resend(transaction, psp, retry)))
+    }
+
+    fn execute_async<'a>(
+        &'a self,
+        payment: &'a Payment,
+        psp: &'a PspConfig,
+        kind: OperationKind,
+    ) -> ConnectorFuture<'a> {
+        Box::pin(std::future::ready(self.execute(payment, psp, kind)))
+    }
+
+    fn authenticate_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+    ) -> AuthenticationFuture<'a> {
+        Box::pin(std::future::ready(self.authenticate(transaction, psp)))
+    }
+
+    fn complete_challenge_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+        session_id: &'a str,
+    ) -> AuthenticationFuture<'a> {
+        Box::pin(std::future::ready(self.complete_challenge(transaction, psp, session_id)))
+    }
+}
+
+impl<T: PspConnector + ?Sized> PspConnector for Arc<T> {
+    fn authorize(&self, transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+        (**self).authorize(transaction, psp)
+    }
+
+    fn resend(&self, transaction: &Transaction, psp: &PspConfig, retry: u32) -> PspResponse {
+        (**self).resend(transaction, psp, retry)
+    }
+
+    fn execute(&self, payment: &Payment, psp: &PspConfig, kind: OperationKind) -> PspResponse {
+        (**self).execute(payment, psp, kind)
+    }
+
+    fn authenticate(&self, transaction: &Transaction, psp: &PspConfig) -> ThreeDsResponse {
+        (**self).authenticate(transaction, psp)
+    }
+
+    fn complete_challenge(
+        &self,
+        transaction: &Transaction,
+        psp: &PspConfig,
+        session_id: &str,
+    ) -> ThreeDsResponse {
+        (**self).complete_challenge(transaction, psp, session_id)
+    }
+}
+
+/// Errors produced while checking a registry against a catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectorError {
+    /// A catalog PSP has no connector and the registry has no fallback.
+    MissingConnector(String),
+    /// A connector is registered for a PSP the catalog does not have.
+    UnknownPsp(String),
+}
+
+impl std::fmt::Display for ConnectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectorError::MissingConnector(psp_id) => {
+                write!(f, "no connector is registered for PSP '{psp_id}'")
+            }
+            ConnectorError::UnknownPsp(psp_id) => {
+                write!(f, "a connector is registered for unknown PSP '{psp_id}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConnectorError {}
+
+/// Connectors keyed by `PspConfig.id`, with an optional fallback.
+///
+/// `C` is `dyn PspConnector` for blocking connectors or
+/// `dyn AsyncPspConnector` for async ones; either way the registry
+/// implements the same trait and dispatches each call on `psp.id`. A PSP
+/// with no connector answers as unavailable.
+pub struct ConnectorRegistry<C: ?Sized = dyn PspConnector> {
+    connectors: HashMap<String, Arc<C>>,
+    fallback: Option<Arc<C>>,
+}
+
+impl<C: ?Sized> ConnectorRegistry<C> {
+    /// An empty registry: every PSP answers as unavailable.
+    pub fn new() -> Self {
+        ConnectorRegistry {
+            connectors: HashMap::new(),
+            fallback: None,
+        }
+    }
+
+    /// Serve PSPs without a connector of their own with `connector`.
+    pub fn with_fallback(mut self, connector: Arc<C>) -> Self {
+        self.fallback = Some(connector);
+        self
+    }
+
+    /// Serve `psp_id` with `connector`, replacing any connector it had.
+    pub fn with_connector(mut self, psp_id: impl Into<String>, connector: Arc<C>) -> Self {
+        self.connectors.insert(psp_id.into(), connector);
+        self
+    }
+
+    /// The connector serving `psp_id`, falling back to the default one.
+    pub fn get(&self, psp_id: &str) -> Option<&Arc<C>> {
+        self.connectors.get(psp_id).or(self.fallback.as_ref())
+    }
+
+    /// Check that every PSP in `catalog` has a connector and that every
+    /// registered connector serves a PSP in `catalog`.
+    pub fn check_psps(&self, catalog: &PspCatalog) -> Result<(), ConnectorError> {
+        if let Some(psp_id) = self.connectors.keys().find(|id| catalog.psp(id).is_none()) {
+            return Err(ConnectorError::UnknownPsp(psp_id.clone()));
+        }
+        match catalog.psps().iter().find(|psp| self.get(&psp.id).is_none()) {
+            Some(psp) => Err(ConnectorError::MissingConnector(psp.id.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<C: ?Sized> Default for ConnectorRegistry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: ?Sized> Clone for ConnectorRegistry<C> {
+    fn clone(&self) -> Self {
+        ConnectorRegistry {
+            connectors: self.connectors.clone(),
+            fallback: self.fallback.clone(),
+        }
+    }
+}
+
+impl<C: ?Sized> std::fmt::Debug for ConnectorRegistry<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut psp_ids: Vec<&String> = self.connectors.keys().collect();
+        psp_ids.sort();
+        f.debug_struct("ConnectorRegistry")
+            .field("psp_ids", &psp_ids)
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+impl PspConnector for ConnectorRegistry<dyn PspConnector> {
+    fn authorize(&self, transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+        match self.get(&psp.id) {
+            Some(connector) => connector.authorize(transaction, psp),
+            None => unavailable(psp, 0),
+        }
+    }
+
+    fn resend(&self, transaction: &Transaction, psp: &PspConfig, retry: u32) -> PspResponse {
+        match self.get(&psp.id) {
+            Some(connector) => connector.resend(transaction, psp, retry),
+            None => unavailable(psp, 0),
+        }
+    }
+
+    fn execute(&self, payment: &Payment, psp: &PspConfig, kind: OperationKind) -> PspResponse {
+        match self.get(&psp.id) {
+            Some(connector) => connector.execute(payment, psp, kind),
+            None => unavailable(psp, 0),
+        }
+    }
+
+    fn authenticate(&self, transaction: &Transaction, psp: &PspConfig) -> ThreeDsResponse {
+        match self.get(&psp.id) {
+            Some(connector) => connector.authenticate(transaction, psp),
+            None => ThreeDsResponse::unavailable(psp, ThreeDsFlow::Frictionless),
+        }
+    }
+
+    fn complete_challenge(
+        &self,
+        transaction: &Transaction,
+        psp: &PspConfig,
+        session_id: &str,
+    ) -> ThreeDsResponse {
+        match self.get(&psp.id) {
+            Some(connector) => connector.complete_challenge(transaction, psp, session_id),
+            None => ThreeDsResponse::unavailable(psp, ThreeDsFlow::Challenge),
+        }
+    }
+}
+
+impl AsyncPspConnector for ConnectorRegistry<dyn AsyncPspConnector> {
+    fn authorize_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+    ) -> ConnectorFuture<'a> {
+        match self.get(&psp.id) {
+            Some(connector) => connector.authorize_async(transaction, psp),
+            None => Box::pin(std::future::ready(unavailable(psp, 0))),
+        }
+    }
+
+    fn resend_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+        retry: u32,
+    ) -> ConnectorFuture<'a> {
+        match self.get(&psp.id) {
+            Some(connector) => connector.resend_async(transaction, psp, retry),
+            None => Box::pin(std::future::ready(unavailable(psp, 0))),
+        }
+    }
+
+    fn execute_async<'a>(
+        &'a self,
+        payment: &'a Payment,
+        psp: &'a PspConfig,
+        kind: OperationKind,
+    ) -> ConnectorFuture<'a> {
+        match self.get(&psp.id) {
+            Some(connector) => connector.execute_async(payment, psp, kind),
+            None => Box::pin(std::future::ready(unavailable(psp, 0))),
+        }
+    }
+
+    fn authenticate_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+    ) -> AuthenticationFuture<'a> {
+        match self.get(&psp.id) {
+            Some(connector) => connector.authenticate_async(transaction, psp),
+            None => Box::pin(std::future::ready(ThreeDsResponse::unavailable(psp, ThreeDsFlow::Frictionless))),
+        }
+    }
+
+    fn complete_challenge_async<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        psp: &'a PspConfig,
+        session_id: &'a str,
+    ) -> AuthenticationFuture<'a> {
+        match self.get(&psp.id) {
+            Some(connector) => connector.complete_challenge_async(transaction, psp, session_id),
+            None => Box::pin(std::future::ready(ThreeDsResponse::unavailable(psp, ThreeDsFlow::Challenge))),
+        }
+    }
+}
+
+/// The response of a PSP nobody could reach, given up on after
+/// `latency_ms`: what a connector returns on a transport failure and the
+/// engine records when an attempt times out.
+pub fn unavailable(psp: &PspConfig, latency_ms: u64) -> PspResponse {
+    simulator::respond(psp, latency_ms, Some(DeclineReason::PspUnavailable))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::money::Money;
+    use crate::models::transaction::{Country, Currency, PaymentMethod};
+    use crate::simulator::PspSimulator;
+    use crate::time::Timestamp;
+
+    /// Declines everything with the same reason.
+    struct AlwaysDecline(DeclineReason);
+
+    impl PspConnector for AlwaysDecline {
+        fn authorize(&self, _transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+            simulator::respond(psp, 10, Some(self.0.clone()))
+        }
+
+        fn execute(&self, _payment: &Payment, psp: &PspConfig, _kind: OperationKind) -> PspResponse {
+            simulator::respond(psp, 10, Some(self.0.clone()))
+        }
+    }
+
+    fn make_txn() -> Transaction {
+        Transaction {
+            id: "txn_connector".to_string(),
+            amount: Money::new(15_000, Currency::new("BRL")),
+            country: Country::new("Brazil"),
+            payment_method: PaymentMethod::Pix,
+            card_bin: None,
+            card_last4: None,
+            bin_info: None,
+            installments: None,
+            customer_id: "cust_connector".to_string(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
+        }
+    }
+
+    #[test]
+    fn test_registry_dispatches_by_psp_id() {
+        let catalog = PspCatalog::builtin();
+        let registry = ConnectorRegistry::<dyn PspConnector>::new()
+            .with_fallback(Arc::new(PspSimulator::new()))
+            .with_connector("psp_br_2", Arc::new(AlwaysDecline(DeclineReason::SuspectedFraud)));
+        let txn = make_txn();
+
+        let cielo = registry.authorize(&txn, catalog.psp("psp_br_2").unwrap());
+        assert_eq!(cielo.decline_reason, Some(DeclineReason::SuspectedFraud));
+        let pagseguro = catalog.psp("psp_br_1").unwrap();
+        assert_eq!(
+            registry.authorize(&txn, pagseguro).response_code,
+            PspSimulator::new().process(&txn, pagseguro).response_code
+        );
+    }
+
+    #[test]
+    fn test_psp_without_connector_is_unavailable() {
+        let catalog = PspCatalog::builtin();
+        let registry: ConnectorRegistry = ConnectorRegistry::new();
+        let response = registry.authorize(&make_txn(), catalog.psp("psp_br_1").unwrap());
+        assert!(!response.approved);
+        assert_eq!(response.decline_reason, Some(DeclineReason::PspUnavailable));
+    }
+
+    #[test]
+    fn test_check_psps() {
+        let catalog = PspCatalog::builtin();
+        let simulator: Arc<dyn PspConnector> = Arc::new(PspSimulator::new());
+        let empty: ConnectorRegistry = ConnectorRegistry::new();
+        assert!(matches!(empty.check_psps(&catalog), Err(ConnectorError::MissingConnector(_))));
+
+        let stray = ConnectorRegistry::new()
+            .with_fallback(simulator.clone())
+            .with_connector("psp_xx_9", simulator.clone());
+        assert_eq!(stray.check_psps(&catalog), Err(ConnectorError::UnknownPsp("psp_xx_9".into())));
+
+        let full = ConnectorRegistry::new().with_fallback(simulator);
+        assert_eq!(full.check_psps(&catalog), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_sync_connectors_serve_async_registries() {
+        let catalog = PspCatalog::builtin();
+        let registry = ConnectorRegistry::<dyn AsyncPspConnector>::new()
+            .with_fallback(Arc::new(AlwaysDecline(DeclineReason::DoNotHonor)));
+        let response = registry
+            .authorize_async(&make_txn(), catalog.psp("psp_br_1").unwrap())
+            .await;
+        assert_eq!(response.decline_reason, Some(DeclineReason::DoNotHonor));
+    }
+}
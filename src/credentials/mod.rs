@@ -0,0 +1,254 @@
+//! Card credential store — network tokens and account updates for cards on file.
+//!
+//! An expired or reissued card is hard-declined by every PSP, yet for a
+//! returning customer the card scheme usually knows the card's new
+//! details: the network token provisioned for it keeps working across
+//! reissues, and the scheme's account updater reports the new expiry. The
+//! [`CredentialStore`] keeps those credentials keyed by customer and card
+//! (BIN and last four digits), so the routing engine can resend such a
+//! decline once with them (see
+//! [`RetryAction::RefreshCredential`](crate::engine::retry::RetryAction::RefreshCredential)).
+//!
+//! A network token is provisioned for a card when a payment with it is
+//! approved. Cards without one are looked up with the [`AccountUpdater`],
+//! a local stand-in for the schemes' updater services, and what it reports
+//! is kept for the customer's next payment.
+
+use crate::models::credential::{CardCredential, CredentialSource};
+use crate::models::transaction::{PaymentMethod, Transaction};
+use crate::state;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Default share of cards the account updater has new details for.
+pub const DEFAULT_UPDATER_COVERAGE: f64 = 0.75;
+
+/// A customer's card, as the store keys it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CardKey {
+    pub customer_id: String,
+    /// First 6 digits of the card.
+    pub card_bin: String,
+    /// Last 4 digits of the card.
+    pub card_last4: String,
+}
+
+impl CardKey {
+    /// The card `transaction` is paid with; `None` for alternative payment
+    /// methods and cards without a BIN or last four digits.
+    pub fn of(transaction: &Transaction) -> Option<CardKey> {
+        if transaction.payment_method != PaymentMethod::Card {
+            return None;
+        }
+        match (&transaction.card_bin, &transaction.card_last4) {
+            (Some(card_bin), Some(card_last4)) => Some(CardKey {
+                customer_id: transaction.customer_id.clone(),
+                card_bin: card_bin.clone(),
+                card_last4: card_last4.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// A deterministic seed for one `purpose` on this card.
+    fn seed(&self, purpose: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        purpose.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Local stand-in for the card schemes' account updater services.
+///
+/// Whether a card's issuer has reported new details is seeded on the card,
+/// so lookups are reproducible: a share `coverage` of cards has them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccountUpdater {
+    coverage: f64,
+}
+
+impl Default for AccountUpdater {
+    fn default() -> Self {
+        AccountUpdater::new(DEFAULT_UPDATER_COVERAGE)
+    }
+}
+
+impl AccountUpdater {
+    /// An updater with new details for a share `coverage` of cards
+    /// (clamped to 0–1).
+    pub fn new(coverage: f64) -> Self {
+        AccountUpdater {
+            coverage: coverage.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Share of cards the updater has new details for.
+    pub fn coverage(&self) -> f64 {
+        self.coverage
+    }
+
+    /// The new details of the card `key`, if its issuer reported any, as
+    /// of the transaction's date.
+    pub fn lookup(&self, key: &CardKey, transaction: &Transaction) -> Option<CardCredential> {
+        let mut rng = StdRng::seed_from_u64(key.seed("account_updater"));
+        if rng.gen::<f64>() >= self.coverage {
+            return None;
+        }
+        Some(CardCredential {
+            source: CredentialSource::AccountUpdater,
+            reference: format!("au_{:016x}", key.seed("account_updater_reference")),
+            expiry: renewed_expiry(transaction, &mut rng),
+        })
+    }
+}
+
+/// The network token or account-updater details of each card on file that
+/// has one, keyed by [`CardKey`].
+///
+/// A credential is kept for as long as its store: the process's own (see
+/// [`shared`](CredentialStore::shared)) starts empty on a cold start, and a
+/// token provisioned by one instance is unknown to the others, which fall
+/// back on the account updater for that card.
+#[derive(Debug, Default)]
+pub struct CredentialStore {
+    updater: AccountUpdater,
+    credentials: Mutex<HashMap<CardKey, CardCredential>>,
+}
+
+impl CredentialStore {
+    /// An empty store that looks cards without a credential up with
+    /// `updater`.
+    pub fn new(updater: AccountUpdater) -> Self {
+        CredentialStore {
+            updater,
+            credentials: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The store of this process, used by the API handlers, so credentials
+    /// persist across the requests one warm instance serves.
+    pub fn shared() -> Arc<CredentialStore> {
+        state::per_process(CredentialStore::default)
+    }
+
+    /// The account updater cards without a credential are looked up with.
+    pub fn updater(&self) -> &AccountUpdater {
+        &self.updater
+    }
+
+    /// Provision a network token for the card `transaction` is paid with,
+    /// unless the card already has a credential, and return the card's
+    /// credential. `None` if the payment is not by card.
+    pub fn provision_token(&self, transaction: &Transaction) -> Option<CardCredential> {
+        let key = CardKey::of(transaction)?;
+        let mut rng = StdRng::seed_from_u64(key.seed("network_token"));
+        let credential = self
+            .lock()
+            .entry(key.clone())
+            .or_insert_with(|| CardCredential {
+                source: CredentialSource::NetworkToken,
+                reference: format!("ntk_{:016x}", key.seed("network_token_reference")),
+                expiry: renewed_expiry(transaction, &mut rng),
+            })
+            .clone();
+        Some(credential)
+    }
+
+    /// The stored credential for the card `key`, if any.
+    pub fn credential(&self, key: &CardKey) -> Option<CardCredential> {
+        self.lock().get(key).cloned()
+    }
+
+    /// An up-to-date credential for the card `transaction` is paid with:
+    /// the stored one, or else what the account updater reports, which is
+    /// then stored. `None` if neither knows the card.
+    pub fn refresh(&self, transaction: &Transaction) -> Option<CardCredential> {
+        let key = CardKey::of(transaction)?;
+        if let Some(credential) = self.credential(&key) {
+            return Some(credential);
+        }
+        let credential = self.updater.lookup(&key, transaction)?;
+        self.lock().insert(key, credential.clone());
+        Some(credential)
+    }
+
+    /// Number of cards with a stored credential.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if no card has a stored credential.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<CardKey, CardCredential>> {
+        state::lock(&self.credentials)
+    }
+}
+
+/// Expiry of a credential issued on the transaction's date: one to four
+/// years later, as `MM/YY`.
+fn renewed_expiry(transaction: &Transaction, rng: &mut StdRng) -> String {
+    let year = transaction.timestamp.to_utc().year + rng.gen_range(1..=4);
+    let month: u32 = rng.gen_range(1..=12);
+    format!("{month:02}/{:02}", year.rem_euclid(100))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::generate_test_data;
+
+    fn card_transaction() -> Transaction {
+        generate_test_data(20)
+            .into_iter()
+            .find(|t| t.payment_method == PaymentMethod::Card)
+            .expect("test data has card payments")
+    }
+
+    #[test]
+    fn test_refresh_prefers_the_network_token() {
+        let store = CredentialStore::new(AccountUpdater::new(1.0));
+        let txn = card_transaction();
+        let token = store.provision_token(&txn).unwrap();
+        assert_eq!(token.source, CredentialSource::NetworkToken);
+        assert!(token.reference.starts_with("ntk_"));
+
+        assert_eq!(store.refresh(&txn), Some(token));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_refresh_falls_back_on_the_account_updater_and_keeps_its_answer() {
+        let txn = card_transaction();
+        assert_eq!(CredentialStore::new(AccountUpdater::new(0.0)).refresh(&txn), None);
+
+        let store = CredentialStore::new(AccountUpdater::new(1.0));
+        let update = store.refresh(&txn).unwrap();
+        assert_eq!(update.source, CredentialSource::AccountUpdater);
+        assert_eq!(update.expiry.len(), 5);
+        assert_eq!(store.credential(&CardKey::of(&txn).unwrap()), Some(update.clone()));
+        // A later token provisioning keeps the updated details
+        assert_eq!(store.provision_token(&txn), Some(update));
+    }
+
+    #[test]
+    fn test_only_cards_have_credentials() {
+        let store = CredentialStore::new(AccountUpdater::new(1.0));
+        let pix = Transaction {
+            payment_method: PaymentMethod::Pix,
+            card_bin: None,
+            card_last4: None,
+            ..card_transaction()
+        };
+        assert_eq!(store.provision_token(&pix), None);
+        assert_eq!(store.refresh(&pix), None);
+        assert!(store.is_empty());
+    }
+}
@@ -1,45 +1,62 @@
 //! Test data generation for the routing engine.
 //!
 //! Generates realistic transaction data for FashionForward's
-//! Brazil, Mexico, and Colombia operations. Uses seeded RNG
-//! for reproducible datasets across runs.
+//! Brazil, Mexico, Colombia, Chile, Peru, and Argentina operations.
+//! Uses seeded RNG for reproducible datasets across runs.
 //!
 //! # Data Distribution
-//! - 210 transactions (~70 per country)
+//! - 210 transactions (~35 per country)
 //! - 15 unique customers (some with many transactions)
-//! - Amount range: $10–$500 USD equivalent
-//! - Realistic fake BINs per country
+//! - Amount range: $10–$500 USD equivalent, converted to local currency
+//! - Realistic fake BINs per country, enriched from the built-in BIN table
 //! - Timestamps spread across a business day
+//! - One in three Brazilian and Mexican card payments in installments
 
-use crate::models::transaction::{Country, Currency, Transaction};
+use crate::card_bin::BinTable;
+use crate::fx::FxTable;
+use crate::models::money::Money;
+use crate::models::transaction::{Country, Currency, PaymentMethod, Transaction};
+use crate::time::Timestamp;
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 
 /// BINs per country — realistic but fake card prefixes.
-const BRAZIL_BINS: [&str; 3] = ["411111", "510510", "376411"];
-const MEXICO_BINS: [&str; 3] = ["424242", "551234", "371449"];
+const BRAZIL_BINS: [&str; 5] = ["411111", "510510", "376411", "636368", "606282"];
+const MEXICO_BINS: [&str; 4] = ["424242", "551234", "371449", "506250"];
 const COLOMBIA_BINS: [&str; 3] = ["431940", "520082", "378282"];
+const CHILE_BINS: [&str; 3] = ["455638", "526424", "377790"];
+const PERU_BINS: [&str; 3] = ["421355", "512345", "376650"];
+const ARGENTINA_BINS: [&str; 3] = ["450799", "530210", "377800"];
+
+/// Installment counts shoppers pick, per market that offers them.
+const BRAZIL_INSTALLMENTS: [u8; 5] = [3, 6, 10, 12, 2];
+const MEXICO_INSTALLMENTS: [u8; 4] = [3, 6, 9, 12];
 
 /// Data seed for reproducible generation.
 const DATA_SEED: u64 = 42;
 
 /// Generate a batch of test transactions with realistic distribution.
 ///
-/// Transactions are split roughly equally across Brazil, Mexico, and Colombia,
+/// Transactions are split roughly equally across all six markets,
 /// with varied amounts, multiple customers, and timestamps across a business day.
 pub fn generate_test_data(count: usize) -> Vec<Transaction> {
     let mut rng = StdRng::seed_from_u64(DATA_SEED);
+    let fx = FxTable::builtin();
+    let bin_table = BinTable::builtin();
     let mut transactions = Vec::with_capacity(count);
 
-    // Country distribution: ~equal thirds
+    // Country distribution: ~equal sixths
     let countries = [
-        (Country::Brazil, Currency::BRL, &BRAZIL_BINS),
-        (Country::Mexico, Currency::MXN, &MEXICO_BINS),
-        (Country::Colombia, Currency::COP, &COLOMBIA_BINS),
+        (Country::new("Brazil"), Currency::new("BRL"), &BRAZIL_BINS[..]),
+        (Country::new("Mexico"), Currency::new("MXN"), &MEXICO_BINS[..]),
+        (Country::new("Colombia"), Currency::new("COP"), &COLOMBIA_BINS[..]),
+        (Country::new("Chile"), Currency::new("CLP"), &CHILE_BINS[..]),
+        (Country::new("Peru"), Currency::new("PEN"), &PERU_BINS[..]),
+        (Country::new("Argentina"), Currency::new("ARS"), &ARGENTINA_BINS[..]),
     ];
 
     for i in 0..count {
-        let country_idx = i % 3;
+        let country_idx = i % countries.len();
         let (country, currency, bins) = &countries[country_idx];
 
         // Pick a BIN (rotate through available BINs)
@@ -51,31 +68,57 @@ pub fn generate_test_data(count: usize) -> Vec<Transaction> {
         // Customer ID: 15 unique customers, some heavy users
         let customer_id = format!("cust_{:03}", select_customer(&mut rng));
 
-        // Amount: weighted distribution
+        // Amount: weighted USD distribution, converted to local currency
         // 40% small ($10-100), 35% medium ($100-300), 25% large ($300-500)
-        let amount = generate_amount(&mut rng);
+        let amount_usd = generate_amount_usd(&mut rng);
 
         // Timestamp: spread across 2025-01-15 08:00-20:00 UTC
-        let hour = 8 + (i * 12 / count);
+        let hour = 8 + (i * 12 / count) as u32;
         let minute = rng.gen_range(0..60);
         let second = rng.gen_range(0..60);
-        let timestamp = format!("2025-01-15T{:02}:{:02}:{:02}Z", hour, minute, second);
+        let timestamp = Timestamp::from_utc(2025, 1, 15, hour, minute, second)
+            .expect("generated time fields are in range");
+
+        let amount = fx
+            .from_usd(&amount_usd, currency, &timestamp.date_string())
+            .expect("built-in FX rates cover every checkout currency");
 
         transactions.push(Transaction {
             id: format!("txn_{:04}", i + 1),
-            amount: round_to_cents(amount),
-            currency: currency.clone(),
+            amount,
             country: country.clone(),
-            card_bin: bin.to_string(),
-            card_last4: last4,
+            payment_method: PaymentMethod::Card,
+            card_bin: Some(bin.to_string()),
+            card_last4: Some(last4),
+            bin_info: bin_table.lookup(bin).cloned(),
+            installments: installment_plan(country, i / countries.len()),
             customer_id,
             timestamp,
+            three_ds: None,
+            credential: None,
         });
     }
 
     transactions
 }
 
+/// Installments for a country's `nth` transaction: every third payment in
+/// a country that offers installments, cycling through the common plans.
+///
+/// Derived from the index rather than the RNG so the rest of the dataset
+/// is unaffected.
+fn installment_plan(country: &Country, nth_in_country: usize) -> Option<u8> {
+    let plans: &[u8] = match country.name() {
+        "Brazil" => &BRAZIL_INSTALLMENTS,
+        "Mexico" => &MEXICO_INSTALLMENTS,
+        _ => return None,
+    };
+    if !nth_in_country.is_multiple_of(3) {
+        return None;
+    }
+    Some(plans[(nth_in_country / 3) % plans.len()])
+}
+
 /// Get the standard test dataset of 210 transactions.
 ///
 /// This is the canonical dataset used for performance reports
@@ -102,25 +145,23 @@ fn select_customer(rng: &mut StdRng) -> u32 {
     }
 }
 
-/// Generate a transaction amount with weighted distribution.
+/// Generate a USD transaction amount with weighted distribution.
+///
+/// Drawn directly as whole cents so no rounding is needed afterwards.
 ///
 /// - 40% small: $10–$100
 /// - 35% medium: $100–$300
 /// - 25% large: $300–$500
-fn generate_amount(rng: &mut StdRng) -> f64 {
+fn generate_amount_usd(rng: &mut StdRng) -> Money {
     let roll: f64 = rng.gen();
-    if roll < 0.40 {
-        rng.gen_range(10.0..100.0)
+    let cents = if roll < 0.40 {
+        rng.gen_range(1_000..10_000)
     } else if roll < 0.75 {
-        rng.gen_range(100.0..300.0)
+        rng.gen_range(10_000..30_000)
     } else {
-        rng.gen_range(300.0..500.0)
-    }
-}
-
-/// Round a float to 2 decimal places (cents).
-fn round_to_cents(amount: f64) -> f64 {
-    (amount * 100.0).round() / 100.0
+        rng.gen_range(30_000..50_000)
+    };
+    Money::new(cents, Currency::USD)
 }
 
 #[cfg(test)]
@@ -154,25 +195,50 @@ mod tests {
     #[test]
     fn test_country_distribution() {
         let data = get_test_dataset();
-        let brazil = data.iter().filter(|t| t.country == Country::Brazil).count();
-        let mexico = data.iter().filter(|t| t.country == Country::Mexico).count();
-        let colombia = data.iter().filter(|t| t.country == Country::Colombia).count();
-
-        // Each should be ~70 (210/3)
-        assert_eq!(brazil, 70);
-        assert_eq!(mexico, 70);
-        assert_eq!(colombia, 70);
+
+        // Each should be 35 (210/6)
+        for country in [
+            Country::new("Brazil"),
+            Country::new("Mexico"),
+            Country::new("Colombia"),
+            Country::new("Chile"),
+            Country::new("Peru"),
+            Country::new("Argentina"),
+        ] {
+            let count = data.iter().filter(|t| t.country == country).count();
+            assert_eq!(count, 35, "Unexpected count for {country}");
+        }
     }
 
     #[test]
     fn test_amount_range() {
         let data = get_test_dataset();
+        let fx = FxTable::builtin();
         for tx in &data {
-            assert!(tx.amount >= 10.0 && tx.amount <= 500.0,
+            // Allow one cent of slack for the round trip through local currency.
+            let usd = fx.to_usd(&tx.amount, &tx.timestamp.date_string()).unwrap().minor_units;
+            assert!((999..=50_001).contains(&usd),
                 "Amount {} out of range for txn {}", tx.amount, tx.id);
         }
     }
 
+    #[test]
+    fn test_amounts_match_country_currency() {
+        let data = get_test_dataset();
+        for tx in &data {
+            let expected = match tx.country.name() {
+                "Brazil" => Currency::new("BRL"),
+                "Mexico" => Currency::new("MXN"),
+                "Colombia" => Currency::new("COP"),
+                "Chile" => Currency::new("CLP"),
+                "Peru" => Currency::new("PEN"),
+                "Argentina" => Currency::new("ARS"),
+                other => unreachable!("{other} is not a market"),
+            };
+            assert_eq!(tx.amount.currency, expected, "Wrong currency for txn {}", tx.id);
+        }
+    }
+
     #[test]
     fn test_unique_customer_count() {
         let data = get_test_dataset();
@@ -187,13 +253,57 @@ mod tests {
     fn test_valid_bins_per_country() {
         let data = get_test_dataset();
         for tx in &data {
-            let valid_bins = match tx.country {
-                Country::Brazil => &BRAZIL_BINS[..],
-                Country::Mexico => &MEXICO_BINS[..],
-                Country::Colombia => &COLOMBIA_BINS[..],
+            let valid_bins = match tx.country.name() {
+                "Brazil" => &BRAZIL_BINS[..],
+                "Mexico" => &MEXICO_BINS[..],
+                "Colombia" => &COLOMBIA_BINS[..],
+                "Chile" => &CHILE_BINS[..],
+                "Peru" => &PERU_BINS[..],
+                "Argentina" => &ARGENTINA_BINS[..],
+                other => unreachable!("{other} is not a market"),
             };
-            assert!(valid_bins.contains(&tx.card_bin.as_str()),
-                "Invalid BIN {} for country {:?}", tx.card_bin, tx.country);
+            let bin = tx.card_bin.as_deref().unwrap();
+            assert!(valid_bins.contains(&bin),
+                "Invalid BIN {} for country {:?}", bin, tx.country);
+        }
+    }
+
+    #[test]
+    fn test_cards_are_enriched_with_domestic_bin_info() {
+        for tx in &get_test_dataset() {
+            let info = tx.bin_info.as_ref().expect("every generated BIN is in the table");
+            assert!(info.is_domestic(&tx.country), "{} issued in {}", tx.id, info.issuing_country);
+        }
+    }
+
+    #[test]
+    fn test_timestamps_span_the_business_day() {
+        let data = get_test_dataset();
+        let start = Timestamp::parse("2025-01-15T08:00:00Z").unwrap();
+        let end = Timestamp::parse("2025-01-15T20:00:00Z").unwrap();
+        for tx in &data {
+            assert!(tx.timestamp >= start && tx.timestamp < end, "{} at {}", tx.id, tx.timestamp);
+        }
+        assert!(data.windows(2).all(|w| w[0].timestamp.truncate_to_hour() <= w[1].timestamp));
+    }
+
+    #[test]
+    fn test_installments_only_in_brazil_and_mexico() {
+        let data = get_test_dataset();
+        let catalog = crate::catalog::PspCatalog::builtin();
+        let with_installments: Vec<_> = data.iter().filter(|tx| tx.installments.is_some()).collect();
+        assert!(!with_installments.is_empty());
+        for tx in with_installments {
+            assert!(matches!(tx.country.name(), "Brazil" | "Mexico"), "{}", tx.id);
+            assert!(
+                catalog
+                    .psps_for_country(&tx.country)
+                    .iter()
+                    .any(|psp| psp.supports_installments(tx.installment_count())),
+                "no PSP offers {} installments for {}",
+                tx.installment_count(),
+                tx.id
+            );
         }
     }
 
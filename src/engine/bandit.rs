@@ -0,0 +1,577 @@
+/// Adaptive PSP ranking with a multi-armed bandit.
+///
+/// The static strategies rank PSPs by `base_success_rate`, but real
+/// approval rates drift by hour, issuer and card brand. The
+/// [`AdaptiveRouter`] keeps a Beta posterior over the approval rate of every
+/// (country, PSP, card brand) arm, updated from each routing attempt, and
+/// ranks PSPs for [`RoutingStrategy::Adaptive`](crate::models::routing::RoutingStrategy::Adaptive):
+///
+/// - **Thompson** (default): draw a rate from each arm's posterior and
+///   sort by the draws, so uncertain arms still get explored.
+/// - **Ucb**: sort by posterior mean plus an exploration bonus that shrinks
+///   as an arm gathers observations.
+///
+/// Each arm starts from a prior centred on the PSP's configured
+/// `base_success_rate`, worth `prior_weight` observations. Before each new
+/// observation an arm's evidence is multiplied by `decay`, so old outcomes
+/// fade and the posterior follows drift. Unavailable PSPs are left to the
+/// circuit breakers and teach the bandit nothing.
+use crate::models::psp::PspConfig;
+use crate::models::transaction::{Country, PaymentMethod, Transaction};
+use crate::state;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Default pseudo-observations behind each arm's prior.
+pub const DEFAULT_PRIOR_WEIGHT: f64 = 10.0;
+
+/// Default factor applied to an arm's evidence before each observation.
+pub const DEFAULT_DECAY: f64 = 0.99;
+
+/// Default UCB exploration coefficient.
+pub const DEFAULT_EXPLORATION: f64 = 1.0;
+
+/// Brand recorded for cards without BIN enrichment.
+pub const UNKNOWN_CARD_BRAND: &str = "Unknown";
+
+/// How the bandit turns posteriors into a ranking.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum BanditMode {
+    /// Thompson sampling from each arm's Beta posterior.
+    #[default]
+    Thompson,
+    /// Upper confidence bound on each arm's posterior mean.
+    Ucb,
+}
+
+/// Priors, decay and exploration for an [`AdaptiveRouter`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BanditConfig {
+    /// Ranking rule.
+    pub mode: BanditMode,
+    /// Observations the prior (the PSP's `base_success_rate`) is worth;
+    /// 0 starts every arm from a uniform prior.
+    pub prior_weight: f64,
+    /// Factor in `(0, 1]` applied to an arm's evidence before each new
+    /// observation; 1 never forgets.
+    pub decay: f64,
+    /// Weight of the UCB exploration bonus (ignored by Thompson sampling).
+    pub exploration: f64,
+    /// Seed for Thompson draws, so a replay ranks PSPs the same way.
+    pub seed: u64,
+}
+
+impl Default for BanditConfig {
+    fn default() -> Self {
+        BanditConfig {
+            mode: BanditMode::default(),
+            prior_weight: DEFAULT_PRIOR_WEIGHT,
+            decay: DEFAULT_DECAY,
+            exploration: DEFAULT_EXPLORATION,
+            seed: 0,
+        }
+    }
+}
+
+/// Why a bandit configuration or snapshot was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BanditError {
+    /// `prior_weight` is negative or not finite.
+    InvalidPriorWeight(f64),
+    /// `decay` is outside `(0, 1]`.
+    InvalidDecay(f64),
+    /// `exploration` is negative or not finite.
+    InvalidExploration(f64),
+    /// A snapshot arm has negative or non-finite evidence.
+    InvalidArm {
+        country: Country,
+        psp_id: String,
+        card_brand: String,
+    },
+}
+
+impl std::fmt::Display for BanditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BanditError::InvalidPriorWeight(w) => {
+                write!(f, "prior_weight must be a non-negative number, got {w}")
+            }
+            BanditError::InvalidDecay(d) => write!(f, "decay must be in (0, 1], got {d}"),
+            BanditError::InvalidExploration(c) => {
+                write!(f, "exploration must be a non-negative number, got {c}")
+            }
+            BanditError::InvalidArm {
+                country,
+                psp_id,
+                card_brand,
+            } => write!(
+                f,
+                "arm {country}/{psp_id}/{card_brand} has negative or non-finite evidence"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BanditError {}
+
+impl BanditConfig {
+    /// Check that the priors, decay and exploration are usable.
+    pub fn validate(&self) -> Result<(), BanditError> {
+        if !self.prior_weight.is_finite() || self.prior_weight < 0.0 {
+            return Err(BanditError::InvalidPriorWeight(self.prior_weight));
+        }
+        if !(self.decay > 0.0 && self.decay <= 1.0) {
+            return Err(BanditError::InvalidDecay(self.decay));
+        }
+        if !self.exploration.is_finite() || self.exploration < 0.0 {
+            return Err(BanditError::InvalidExploration(self.exploration));
+        }
+        Ok(())
+    }
+
+    /// Beta prior for a PSP: uniform plus `prior_weight` observations at
+    /// its configured success rate.
+    fn prior(&self, psp: &PspConfig) -> (f64, f64) {
+        let rate = psp.base_success_rate.clamp(0.0, 1.0);
+        (
+            1.0 + self.prior_weight * rate,
+            1.0 + self.prior_weight * (1.0 - rate),
+        )
+    }
+}
+
+/// One arm: a PSP for a card brand in a country.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ArmKey {
+    country: Country,
+    psp_id: String,
+    card_brand: String,
+}
+
+/// Decayed evidence gathered for an arm, on top of its prior.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ArmStats {
+    successes: f64,
+    failures: f64,
+    observations: u64,
+}
+
+/// A saved arm, as found in a [`BanditSnapshot`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArmSnapshot {
+    pub country: Country,
+    pub psp_id: String,
+    pub card_brand: String,
+    /// Decayed approvals observed.
+    pub successes: f64,
+    /// Decayed declines observed.
+    pub failures: f64,
+    /// Attempts observed, without decay.
+    pub observations: u64,
+}
+
+/// Everything an [`AdaptiveRouter`] has learned, for saving and restoring.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BanditSnapshot {
+    pub config: BanditConfig,
+    /// Arms sorted by country, PSP and card brand.
+    pub arms: Vec<ArmSnapshot>,
+}
+
+struct BanditState {
+    arms: HashMap<ArmKey, ArmStats>,
+    rng: StdRng,
+}
+
+/// Approval posteriors per (country, PSP, card brand), learned from the
+/// attempts this router has observed.
+///
+/// What it learns lasts as long as the router, unless saved with
+/// [`snapshot`](AdaptiveRouter::snapshot). The process's own (see
+/// [`shared`](AdaptiveRouter::shared)) starts from the priors on every
+/// cold start.
+pub struct AdaptiveRouter {
+    config: BanditConfig,
+    state: Mutex<BanditState>,
+}
+
+impl AdaptiveRouter {
+    /// A router that has observed nothing yet.
+    pub fn new(config: BanditConfig) -> Self {
+        AdaptiveRouter {
+            config,
+            state: Mutex::new(BanditState {
+                arms: HashMap::new(),
+                rng: StdRng::seed_from_u64(config.seed),
+            }),
+        }
+    }
+
+    /// The router of this process, used by the API handlers, so what one
+    /// request learns ranks PSPs for the next one the instance serves.
+    pub fn shared() -> Arc<AdaptiveRouter> {
+        state::per_process(|| AdaptiveRouter::new(BanditConfig::default()))
+    }
+
+    /// Rebuild a router from a [`snapshot`](AdaptiveRouter::snapshot).
+    pub fn from_snapshot(snapshot: BanditSnapshot) -> Result<Self, BanditError> {
+        snapshot.config.validate()?;
+        let router = AdaptiveRouter::new(snapshot.config);
+        {
+            let mut state = router.lock();
+            for arm in snapshot.arms {
+                let valid = [arm.successes, arm.failures]
+                    .iter()
+                    .all(|v| v.is_finite() && *v >= 0.0);
+                if !valid {
+                    return Err(BanditError::InvalidArm {
+                        country: arm.country,
+                        psp_id: arm.psp_id,
+                        card_brand: arm.card_brand,
+                    });
+                }
+                let key = ArmKey {
+                    country: arm.country,
+                    psp_id: arm.psp_id,
+                    card_brand: arm.card_brand,
+                };
+                let stats = ArmStats {
+                    successes: arm.successes,
+                    failures: arm.failures,
+                    observations: arm.observations,
+                };
+                state.arms.insert(key, stats);
+            }
+        }
+        Ok(router)
+    }
+
+    /// The configuration this router applies.
+    pub fn config(&self) -> &BanditConfig {
+        &self.config
+    }
+
+    /// Score each of `psps` for the transaction; a higher score ranks first.
+    /// Scores explore: Thompson sampling draws them at random from the
+    /// posteriors, and UCB adds an exploration bonus to the mean.
+    pub fn scores(&self, transaction: &Transaction, psps: &[PspConfig]) -> HashMap<String, f64> {
+        let mut state = self.lock();
+        let posteriors = self.posteriors(&state, transaction, psps);
+
+        match self.config.mode {
+            BanditMode::Thompson => posteriors
+                .into_iter()
+                .map(|(psp, alpha, beta, _)| (psp.id.clone(), sample_beta(&mut state.rng, alpha, beta)))
+                .collect(),
+            BanditMode::Ucb => {
+                let total: u64 = posteriors.iter().map(|(_, _, _, n)| n).sum();
+                let log_total = ((total + 1) as f64).ln();
+                posteriors
+                    .into_iter()
+                    .map(|(psp, alpha, beta, n)| {
+                        let mean = alpha / (alpha + beta);
+                        let bonus = self.config.exploration * (log_total / (n + 1) as f64).sqrt();
+                        (psp.id.clone(), mean + bonus)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The learned approval rate of each of `psps` for the transaction: its
+    /// posterior mean, `alpha / (alpha + beta)`. Unlike
+    /// [`scores`](AdaptiveRouter::scores) it does not explore, so it is the
+    /// estimate to weigh costs against.
+    pub fn posterior_means(&self, transaction: &Transaction, psps: &[PspConfig]) -> HashMap<String, f64> {
+        let state = self.lock();
+        self.posteriors(&state, transaction, psps)
+            .into_iter()
+            .map(|(psp, alpha, beta, _)| (psp.id.clone(), alpha / (alpha + beta)))
+            .collect()
+    }
+
+    /// Each PSP's posterior `alpha` and `beta` and its observation count.
+    fn posteriors<'a>(
+        &self,
+        state: &BanditState,
+        transaction: &Transaction,
+        psps: &'a [PspConfig],
+    ) -> Vec<(&'a PspConfig, f64, f64, u64)> {
+        psps.iter()
+            .map(|psp| {
+                let stats = state
+                    .arms
+                    .get(&arm_key(transaction, &psp.id))
+                    .copied()
+                    .unwrap_or_default();
+                let (alpha, beta) = self.config.prior(psp);
+                (psp, alpha + stats.successes, beta + stats.failures, stats.observations)
+            })
+            .collect()
+    }
+
+    /// Record whether the PSP approved the transaction.
+    pub fn observe(&self, transaction: &Transaction, psp_id: &str, approved: bool) {
+        let decay = self.config.decay;
+        let mut state = self.lock();
+        let stats = state.arms.entry(arm_key(transaction, psp_id)).or_default();
+        stats.successes *= decay;
+        stats.failures *= decay;
+        if approved {
+            stats.successes += 1.0;
+        } else {
+            stats.failures += 1.0;
+        }
+        stats.observations += 1;
+    }
+
+    /// Everything learned so far. Restoring it with
+    /// [`from_snapshot`](AdaptiveRouter::from_snapshot) gives the same
+    /// posteriors (Thompson draws restart from the configured seed).
+    pub fn snapshot(&self) -> BanditSnapshot {
+        let state = self.lock();
+        let mut arms: Vec<ArmSnapshot> = state
+            .arms
+            .iter()
+            .map(|(key, stats)| ArmSnapshot {
+                country: key.country.clone(),
+                psp_id: key.psp_id.clone(),
+                card_brand: key.card_brand.clone(),
+                successes: stats.successes,
+                failures: stats.failures,
+                observations: stats.observations,
+            })
+            .collect();
+        arms.sort_by(|a, b| {
+            (a.country.to_string(), &a.psp_id, &a.card_brand)
+                .cmp(&(b.country.to_string(), &b.psp_id, &b.card_brand))
+        });
+        BanditSnapshot {
+            config: self.config,
+            arms,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BanditState> {
+        state::lock(&self.state)
+    }
+}
+
+/// The arm a transaction's attempt at a PSP belongs to. Cards are keyed by
+/// scheme; other payment methods by the method itself.
+fn arm_key(transaction: &Transaction, psp_id: &str) -> ArmKey {
+    let card_brand = match (&transaction.payment_method, &transaction.bin_info) {
+        (PaymentMethod::Card, Some(info)) => info.scheme.to_string(),
+        (PaymentMethod::Card, None) => UNKNOWN_CARD_BRAND.to_string(),
+        (method, _) => method.to_string(),
+    };
+    ArmKey {
+        country: transaction.country.clone(),
+        psp_id: psp_id.to_string(),
+        card_brand,
+    }
+}
+
+/// Draw from Beta(alpha, beta) as the ratio of two gamma draws.
+fn sample_beta(rng: &mut StdRng, alpha: f64, beta: f64) -> f64 {
+    let x = sample_gamma(rng, alpha);
+    let y = sample_gamma(rng, beta);
+    if x + y > 0.0 {
+        x / (x + y)
+    } else {
+        0.5
+    }
+}
+
+/// Draw from Gamma(shape, 1) with Marsaglia and Tsang's method.
+fn sample_gamma(rng: &mut StdRng, shape: f64) -> f64 {
+    if shape < 1.0 {
+        // Boost the shape above 1 and scale back down
+        let u: f64 = rng.gen();
+        return sample_gamma(rng, shape + 1.0) * u.powf(1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = sample_standard_normal(rng);
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u: f64 = rng.gen();
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+/// Draw from N(0, 1) with the Box-Muller transform.
+fn sample_standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>(); // (0, 1], keeps ln finite
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::PspCatalog;
+    use crate::models::money::Money;
+    use crate::models::transaction::Currency;
+    use crate::time::Timestamp;
+
+    fn make_transaction() -> Transaction {
+        Transaction {
+            id: "txn_bandit".to_string(),
+            amount: Money::new(15000, Currency::new("BRL")),
+            country: Country::new("Brazil"),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some("411111".to_string()),
+            card_last4: Some("1234".to_string()),
+            bin_info: None,
+            installments: None,
+            customer_id: "cust_001".to_string(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
+        }
+    }
+
+    fn brazil_psps() -> Vec<PspConfig> {
+        PspCatalog::builtin().psps_for_country(&Country::new("Brazil"))
+    }
+
+    #[test]
+    fn test_beta_samples_center_on_the_mean() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let n = 4000;
+        let mean = (0..n).map(|_| sample_beta(&mut rng, 8.0, 2.0)).sum::<f64>() / n as f64;
+        assert!((mean - 0.8).abs() < 0.02, "mean was {mean}");
+        let small = (0..n).map(|_| sample_beta(&mut rng, 0.5, 0.5)).sum::<f64>() / n as f64;
+        assert!((small - 0.5).abs() < 0.03, "mean was {small}");
+    }
+
+    #[test]
+    fn test_observations_overturn_the_prior() {
+        let txn = make_transaction();
+        let psps = brazil_psps();
+        let best_prior = psps
+            .iter()
+            .max_by(|a, b| a.base_success_rate.total_cmp(&b.base_success_rate))
+            .unwrap()
+            .id
+            .clone();
+        let underdog = psps.iter().find(|p| p.id != best_prior).unwrap().id.clone();
+
+        for mode in [BanditMode::Thompson, BanditMode::Ucb] {
+            let router = AdaptiveRouter::new(BanditConfig {
+                mode,
+                ..BanditConfig::default()
+            });
+            for _ in 0..200 {
+                router.observe(&txn, &best_prior, false);
+                router.observe(&txn, &underdog, true);
+            }
+            let scores = router.scores(&txn, &psps);
+            assert!(scores[&underdog] > scores[&best_prior], "{mode:?}: {scores:?}");
+        }
+    }
+
+    #[test]
+    fn test_posterior_means_do_not_explore() {
+        let txn = make_transaction();
+        let psps = brazil_psps();
+        let router = AdaptiveRouter::new(BanditConfig {
+            decay: 1.0,
+            ..BanditConfig::default()
+        });
+        for _ in 0..30 {
+            router.observe(&txn, &psps[0].id, true);
+        }
+        router.observe(&txn, &psps[0].id, false);
+
+        let means = router.posterior_means(&txn, &psps);
+        assert_eq!(means, router.posterior_means(&txn, &psps));
+        let (alpha, beta) = router.config().prior(&psps[0]);
+        let (alpha, beta) = (alpha + 30.0, beta + 1.0);
+        assert_eq!(means[&psps[0].id], alpha / (alpha + beta));
+        let (alpha, beta) = router.config().prior(&psps[1]);
+        assert_eq!(means[&psps[1].id], alpha / (alpha + beta));
+        // Thompson draws differ from one ranking to the next
+        assert_ne!(router.scores(&txn, &psps), router.scores(&txn, &psps));
+    }
+
+    #[test]
+    fn test_arms_are_split_by_card_brand() {
+        let router = AdaptiveRouter::new(BanditConfig::default());
+        let txn = make_transaction();
+        let mut voucher = make_transaction();
+        voucher.payment_method = PaymentMethod::Boleto;
+        router.observe(&txn, "psp_br_1", true);
+        router.observe(&voucher, "psp_br_1", false);
+
+        let brands: Vec<String> = router.snapshot().arms.into_iter().map(|a| a.card_brand).collect();
+        assert_eq!(brands, [PaymentMethod::Boleto.to_string(), UNKNOWN_CARD_BRAND.to_string()]);
+    }
+
+    #[test]
+    fn test_decay_forgets_old_outcomes() {
+        let router = AdaptiveRouter::new(BanditConfig {
+            decay: 0.5,
+            ..BanditConfig::default()
+        });
+        let txn = make_transaction();
+        for _ in 0..10 {
+            router.observe(&txn, "psp_br_1", false);
+        }
+        router.observe(&txn, "psp_br_1", true);
+
+        let arm = &router.snapshot().arms[0];
+        assert_eq!(arm.observations, 11);
+        assert!(arm.failures < 1.0 && arm.successes == 1.0, "{arm:?}");
+    }
+
+    #[test]
+    fn test_snapshot_round_trips() {
+        let config = BanditConfig {
+            mode: BanditMode::Ucb,
+            ..BanditConfig::default()
+        };
+        let router = AdaptiveRouter::new(config);
+        let txn = make_transaction();
+        router.observe(&txn, "psp_br_1", true);
+        router.observe(&txn, "psp_br_2", false);
+
+        let json = serde_json::to_string(&router.snapshot()).unwrap();
+        let restored = AdaptiveRouter::from_snapshot(serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(restored.snapshot(), router.snapshot());
+        assert_eq!(restored.scores(&txn, &brazil_psps()), router.scores(&txn, &brazil_psps()));
+    }
+
+    #[test]
+    fn test_invalid_config_and_snapshot_are_rejected() {
+        let bad_decay = BanditConfig {
+            decay: 0.0,
+            ..BanditConfig::default()
+        };
+        assert_eq!(bad_decay.validate(), Err(BanditError::InvalidDecay(0.0)));
+
+        let mut snapshot = AdaptiveRouter::new(BanditConfig::default()).snapshot();
+        snapshot.arms.push(ArmSnapshot {
+            country: Country::new("Mexico"),
+            psp_id: "psp_mx_1".to_string(),
+            card_brand: "Visa".to_string(),
+            successes: -1.0,
+            failures: 0.0,
+            observations: 1,
+        });
+        assert!(matches!(
+            AdaptiveRouter::from_snapshot(snapshot),
+            Err(BanditError::InvalidArm { .. })
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+/// Per-PSP circuit breakers fed by PSP-unavailable outcomes.
+///
+/// A PSP that keeps timing out or refusing connections still costs every
+/// transaction its latency before the engine cascades. A breaker counts
+/// consecutive failures per PSP:
+///
+/// - **Closed**: normal routing.
+/// - **Open**: `failure_threshold` consecutive failures were seen; the PSP is
+///   skipped until `open_duration_secs` have passed.
+/// - **Half-open**: the cool-down is over; the PSP is tried again but only
+///   after the healthy ones. One success closes the breaker, one failure
+///   reopens it.
+///
+/// Time comes from the caller (the transaction's timestamp), so a report
+/// replaying a day of transactions sees breakers open and recover exactly
+/// as they would have live.
+use crate::state;
+use crate::time::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Default consecutive failures that open a breaker.
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// Default seconds a breaker stays open before allowing a probe.
+pub const DEFAULT_OPEN_DURATION_SECS: u64 = 30;
+
+/// Where a PSP's breaker stands.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum BreakerState {
+    /// The PSP is routed to normally.
+    #[default]
+    Closed,
+    /// The PSP is skipped.
+    Open,
+    /// The PSP is tried last, as a probe.
+    HalfOpen,
+}
+
+/// Thresholds shared by every breaker in a registry.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct BreakerConfig {
+    /// Consecutive failures that open the breaker.
+    pub failure_threshold: u32,
+    /// Seconds the breaker stays open before a probe is allowed.
+    pub open_duration_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            open_duration_secs: DEFAULT_OPEN_DURATION_SECS,
+        }
+    }
+}
+
+/// One PSP's breaker and its counters.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CircuitBreaker {
+    /// State as of the last call that touched the breaker.
+    pub state: BreakerState,
+    /// Failures since the last success.
+    pub consecutive_failures: u32,
+    /// When the breaker last opened.
+    pub opened_at: Option<Timestamp>,
+    /// Calls recorded.
+    pub calls: u64,
+    /// Calls that failed (PSP unavailable or timed out).
+    pub failures: u64,
+    /// Times the breaker has opened.
+    pub times_opened: u64,
+}
+
+impl CircuitBreaker {
+    /// Move an open breaker to half-open once its cool-down has passed.
+    fn refresh(&mut self, now: Timestamp, config: &BreakerConfig) {
+        if self.state != BreakerState::Open {
+            return;
+        }
+        let cooled_down = self
+            .opened_at
+            .and_then(|opened| now.duration_since(&opened))
+            .is_some_and(|elapsed| elapsed >= Duration::from_secs(config.open_duration_secs));
+        if cooled_down {
+            self.state = BreakerState::HalfOpen;
+        }
+    }
+
+    fn record(&mut self, success: bool, now: Timestamp, config: &BreakerConfig) {
+        self.refresh(now, config);
+        self.calls += 1;
+        if success {
+            self.consecutive_failures = 0;
+            self.state = BreakerState::Closed;
+            return;
+        }
+
+        self.failures += 1;
+        self.consecutive_failures += 1;
+        let trips = self.state == BreakerState::HalfOpen
+            || (self.state == BreakerState::Closed
+                && self.consecutive_failures >= config.failure_threshold);
+        if trips {
+            self.state = BreakerState::Open;
+            self.opened_at = Some(now);
+            self.times_opened += 1;
+        }
+    }
+}
+
+/// Circuit breakers keyed by `PspConfig.id`; PSPs never recorded are
+/// closed.
+///
+/// Breakers live as long as their registry. The report builds one per
+/// replay; the API handlers use the process's own (see
+/// [`shared`](BreakerRegistry::shared)), whose breakers all start closed
+/// on a cold start.
+#[derive(Debug, Default)]
+pub struct BreakerRegistry {
+    config: BreakerConfig,
+    breakers: Mutex<HashMap<String, CircuitBreaker>>,
+}
+
+impl BreakerRegistry {
+    /// An empty registry with the given thresholds.
+    pub fn new(config: BreakerConfig) -> Self {
+        BreakerRegistry {
+            config,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The registry of this process, used by the API handlers, so breakers
+    /// persist across the requests one warm instance serves. Other
+    /// instances and functions have their own (see [`crate::state`]).
+    pub fn shared() -> Arc<BreakerRegistry> {
+        state::per_process(BreakerRegistry::default)
+    }
+
+    /// The thresholds this registry applies.
+    pub fn config(&self) -> &BreakerConfig {
+        &self.config
+    }
+
+    /// State of a PSP's breaker at `now`.
+    pub fn state(&self, psp_id: &str, now: Timestamp) -> BreakerState {
+        let mut breakers = self.lock();
+        match breakers.get_mut(psp_id) {
+            Some(breaker) => {
+                breaker.refresh(now, &self.config);
+                breaker.state
+            }
+            None => BreakerState::Closed,
+        }
+    }
+
+    /// Record that a call to the PSP completed (`success`) or found it
+    /// unavailable or timed out.
+    pub fn record(&self, psp_id: &str, success: bool, now: Timestamp) {
+        self.lock()
+            .entry(psp_id.to_string())
+            .or_default()
+            .record(success, now, &self.config);
+    }
+
+    /// Every breaker that has seen a call, as of the last call to each.
+    pub fn snapshot(&self) -> HashMap<String, CircuitBreaker> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, CircuitBreaker>> {
+        state::lock(&self.breakers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> Timestamp {
+        Timestamp::from_unix_seconds(1_736_935_200 + seconds)
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let registry = BreakerRegistry::new(BreakerConfig::default());
+        registry.record("psp_br_1", false, at(0));
+        registry.record("psp_br_1", false, at(1));
+        assert_eq!(registry.state("psp_br_1", at(1)), BreakerState::Closed);
+
+        registry.record("psp_br_1", false, at(2));
+        assert_eq!(registry.state("psp_br_1", at(2)), BreakerState::Open);
+        assert_eq!(registry.state("psp_br_2", at(2)), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_success_resets_the_failure_count() {
+        let registry = BreakerRegistry::new(BreakerConfig::default());
+        for (i, success) in [false, false, true, false, false].into_iter().enumerate() {
+            registry.record("psp_mx_1", success, at(i as i64));
+        }
+        assert_eq!(registry.state("psp_mx_1", at(5)), BreakerState::Closed);
+        assert_eq!(registry.snapshot()["psp_mx_1"].consecutive_failures, 2);
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_or_reopens() {
+        let config = BreakerConfig {
+            failure_threshold: 1,
+            open_duration_secs: 30,
+        };
+        let registry = BreakerRegistry::new(config);
+        registry.record("psp_co_1", false, at(0));
+        assert_eq!(registry.state("psp_co_1", at(29)), BreakerState::Open);
+        assert_eq!(registry.state("psp_co_1", at(30)), BreakerState::HalfOpen);
+
+        // A failed probe reopens for another full cool-down.
+        registry.record("psp_co_1", false, at(31));
+        assert_eq!(registry.state("psp_co_1", at(60)), BreakerState::Open);
+        assert_eq!(registry.state("psp_co_1", at(61)), BreakerState::HalfOpen);
+
+        registry.record("psp_co_1", true, at(62));
+        assert_eq!(registry.state("psp_co_1", at(62)), BreakerState::Closed);
+
+        let breaker = &registry.snapshot()["psp_co_1"];
+        assert_eq!((breaker.calls, breaker.failures, breaker.times_opened), (3, 2, 2));
+    }
+}
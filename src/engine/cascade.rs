@@ -0,0 +1,663 @@
+/// The retry loop of one routing call, as a state machine.
+///
+/// [`RoutingEngine::route`] and [`RoutingEngine::route_async`] differ only
+/// in how they wait on PSPs (and whether they hedge); both ask a
+/// [`Cascade`] which PSP to try next, feed it each response in the order
+/// it arrived and let it apply the [`RetryPolicy`]. When a decline calls
+/// for 3-D Secure, the cascade asks for the cardholder to be authenticated
+/// before the next PSP call, and pauses on a challenge. When a decline
+/// calls for the card's refreshed credential, the cascade fetches it from
+/// the engine's credential store and sends it with every later attempt.
+use super::retry::{self, RetryAction, RetryPolicy};
+use super::{accepted_status, build_attempt, BreakerState, RoutingEngine};
+use crate::connector;
+use crate::models::authentication::{
+    AuthenticationSession, ThreeDsAuthentication, ThreeDsFlow, ThreeDsResponse, ThreeDsStatus,
+};
+use crate::models::credential::CardCredential;
+use crate::models::psp::{DeclineClass, DeclineReason, PspConfig, PspResponse};
+use crate::models::routing::{
+    AuthorizationStatus, CandidateScore, DecisionTrace, ExcludedPsp, ExclusionReason, IneligiblePsp,
+    RetryDecision, RetryStep, RetryType, RoutingAttempt, RoutingResult, RoutingStrategy,
+};
+use crate::models::transaction::{PaymentMethod, Transaction};
+use crate::rules::RoutingRule;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Whether routing goes on after a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Step {
+    /// Try the next PSP.
+    Continue,
+    /// Approved, or the policy ended routing.
+    Done,
+}
+
+/// How an attempt was sent and answered, beyond the PSP's response.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct AttemptFlags {
+    pub hedged: bool,
+    pub timed_out: bool,
+}
+
+/// A PSP call the cascade has decided to make.
+#[derive(Debug, Clone)]
+pub(super) struct Dispatch {
+    pub psp: PspConfig,
+    pub retry_type: RetryType,
+    /// Resends of the transaction to this PSP so far, this one included
+    /// (0 unless `retry_type` is `SamePsp`; also 0 for a resend with the
+    /// cardholder's authentication, which is a new authorization).
+    pub retry: u32,
+    /// Time to wait before sending, already counted in the latency.
+    pub backoff_ms: u64,
+    /// The cardholder's 3-D Secure authentication, sent along.
+    pub authentication: Option<ThreeDsAuthentication>,
+    /// The card's refreshed credential, sent in place of the card details.
+    pub credential: Option<CardCredential>,
+}
+
+impl Dispatch {
+    /// The transaction as sent to the PSP: with the cardholder's
+    /// authentication and the card's refreshed credential, if there are.
+    ///
+    /// Only a credential this routing fetched from the engine's credential
+    /// store is sent; one the transaction came with is dropped.
+    pub fn transaction<'t>(&self, transaction: &'t Transaction) -> Cow<'t, Transaction> {
+        if self.authentication.is_none() && self.credential == transaction.credential {
+            return Cow::Borrowed(transaction);
+        }
+        let mut sent = transaction.clone();
+        if let Some(authentication) = &self.authentication {
+            sent.three_ds = Some(authentication.clone());
+        }
+        sent.credential = self.credential.clone();
+        Cow::Owned(sent)
+    }
+}
+
+/// State of one routing call between PSP responses.
+pub(super) struct Cascade<'e, C> {
+    engine: &'e RoutingEngine<C>,
+    transaction: &'e Transaction,
+    /// The strategy routing was asked for, kept to rank PSPs again when a
+    /// paused routing resumes.
+    strategy: RoutingStrategy,
+    policy: &'e RetryPolicy,
+    /// Whether acquirers from abroad are among `psps`.
+    cross_border_fallback: bool,
+    rule: Option<&'e RoutingRule>,
+    circuits: HashMap<String, BreakerState>,
+    psps: Vec<PspConfig>,
+    next_psp: usize,
+    /// The PSP to resend to under [`RetryAction::RetrySamePsp`].
+    retry_psp: Option<PspConfig>,
+    same_psp_retries: usize,
+    /// The PSP to authenticate the cardholder at under
+    /// [`RetryAction::Authenticate`], and the decline that called for it.
+    authentication_due: Option<(PspConfig, DeclineReason)>,
+    /// The PSP to resend to with the cardholder's authentication.
+    authenticated_psp: Option<(PspConfig, ThreeDsFlow)>,
+    /// The cardholder goes through 3-D Secure at most once per routing.
+    authentication_tried: bool,
+    /// The PSP and session of a challenge routing is paused on.
+    challenge: Option<(PspConfig, String)>,
+    authentications: Vec<ThreeDsResponse>,
+    /// The PSP to resend to with the card's refreshed credential.
+    refreshed_psp: Option<PspConfig>,
+    /// The card's refreshed credential, once fetched.
+    credential: Option<CardCredential>,
+    /// The credential store is asked at most once per routing.
+    credential_tried: bool,
+    dispatched: usize,
+    attempts: Vec<RoutingAttempt>,
+    total_latency_ms: u64,
+    attempt_number: usize,
+    unavailable_cascades: usize,
+    budget_exhausted: bool,
+    previous_decline: Option<DeclineReason>,
+    approved_by: Option<String>,
+    matched_rule: Option<String>,
+    ineligible: Vec<IneligiblePsp>,
+    trace: Option<DecisionTrace>,
+}
+
+impl<'e, C> Cascade<'e, C> {
+    pub(super) fn new(
+        engine: &'e RoutingEngine<C>,
+        transaction: &'e Transaction,
+        policy: &'e RetryPolicy,
+        rule: Option<&'e RoutingRule>,
+        circuits: HashMap<String, BreakerState>,
+        psps: Vec<PspConfig>,
+        ineligible: Vec<IneligiblePsp>,
+    ) -> Self {
+        Cascade {
+            engine,
+            transaction,
+            strategy: RoutingStrategy::default(),
+            policy,
+            cross_border_fallback: false,
+            rule,
+            circuits,
+            psps,
+            next_psp: 0,
+            retry_psp: None,
+            same_psp_retries: 0,
+            authentication_due: None,
+            authenticated_psp: None,
+            authentication_tried: false,
+            challenge: None,
+            authentications: Vec::new(),
+            refreshed_psp: None,
+            credential: None,
+            credential_tried: false,
+            dispatched: 0,
+            attempts: Vec::new(),
+            total_latency_ms: 0,
+            attempt_number: 0,
+            unavailable_cascades: 0,
+            budget_exhausted: false,
+            previous_decline: None,
+            approved_by: None,
+            matched_rule: rule.map(|r| r.name.clone()),
+            ineligible,
+            trace: None,
+        }
+    }
+
+    /// Remember that routing was asked for under `strategy`.
+    pub(super) fn requested(mut self, strategy: &RoutingStrategy) -> Self {
+        self.strategy = strategy.clone();
+        self
+    }
+
+    /// Remember whether acquirers from abroad were made eligible, so a
+    /// routing paused on a challenge resumes with them.
+    pub(super) fn cross_border(mut self, enabled: bool) -> Self {
+        self.cross_border_fallback = enabled;
+        self
+    }
+
+    /// Record the retry policy's verdict on each decline in `trace`, and
+    /// return it with the result.
+    pub(super) fn explain(mut self, trace: DecisionTrace) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Pick up the routing `session` paused: its attempts, latency and
+    /// trace so far, and the card's refreshed credential if one was
+    /// fetched. The cardholder has already been asked to authenticate.
+    ///
+    /// The cascade comes freshly ranked from the catalog, rules and
+    /// breakers; PSPs the session already sent the transaction to are not
+    /// tried again.
+    pub(super) fn resume(mut self, session: &AuthenticationSession) -> Self {
+        let tried: HashSet<&str> = session.attempts.iter().map(|a| a.psp_id.as_str()).collect();
+        self.psps.retain(|psp| !tried.contains(psp.id.as_str()));
+        let answered = session.attempts.iter().filter(|a| !a.cancelled);
+        self.unavailable_cascades = answered
+            .clone()
+            .filter(|a| a.decline_reason.as_ref().is_some_and(retry::is_psp_unavailable))
+            .count();
+        self.previous_decline = answered
+            .filter_map(|a| a.decline_reason.clone())
+            .rfind(|reason| !retry::is_psp_unavailable(reason));
+        self.attempts = session.attempts.clone();
+        self.dispatched = session.attempts.len();
+        self.attempt_number = session.total_attempts;
+        self.total_latency_ms = session.total_latency_ms;
+        self.authentications = session.authentications.clone();
+        self.authentication_tried = true;
+        self.credential = session.transaction.credential.clone();
+        self.credential_tried = self.credential.is_some();
+        self.trace = session.trace.clone();
+        self
+    }
+
+    /// The next PSP to send the transaction to, if any, were it sent
+    /// `in_flight_ms` after the latency spent so far (a hedge starts while
+    /// the primary is still pending).
+    ///
+    /// A resend to the same PSP comes first: with the cardholder's
+    /// authentication or the card's refreshed credential, or after the
+    /// policy's backoff. PSPs not expected to answer within what is left of
+    /// the latency budget (backoff included) are skipped.
+    pub(super) fn next_psp(&mut self, in_flight_ms: u64) -> Option<Dispatch> {
+        let spent = self.total_latency_ms.saturating_add(in_flight_ms);
+        if let Some((psp, flow)) = self.authenticated_psp.take() {
+            if self.policy.fits_budget(spent, &psp) {
+                let mut dispatch = self.dispatch(psp, RetryType::SamePsp, 0, 0);
+                dispatch.authentication = Some(ThreeDsAuthentication {
+                    psp_id: dispatch.psp.id.clone(),
+                    flow,
+                });
+                return Some(dispatch);
+            }
+            self.budget_exhausted = true;
+        }
+        if let Some(psp) = self.refreshed_psp.take() {
+            if self.policy.fits_budget(spent, &psp) {
+                return Some(self.dispatch(psp, RetryType::SamePsp, 0, 0));
+            }
+            self.budget_exhausted = true;
+        }
+        if let Some(psp) = self.retry_psp.take() {
+            let retry = self.same_psp_retries as u32;
+            let backoff_ms = self.policy.backoff.delay_ms(retry, &self.transaction.id, &psp.id);
+            if self.policy.fits_budget(spent.saturating_add(backoff_ms), &psp) {
+                self.total_latency_ms += backoff_ms;
+                return Some(self.dispatch(psp, RetryType::SamePsp, retry, backoff_ms));
+            }
+            self.budget_exhausted = true;
+        }
+        self.same_psp_retries = 0;
+        while let Some(psp) = self.psps.get(self.next_psp).cloned() {
+            self.next_psp += 1;
+            if self.policy.fits_budget(spent, &psp) {
+                let retry_type = if self.dispatched == 0 {
+                    RetryType::Initial
+                } else {
+                    RetryType::NextPsp
+                };
+                return Some(self.dispatch(psp, retry_type, 0, 0));
+            }
+            self.budget_exhausted = true;
+        }
+        None
+    }
+
+    fn dispatch(&mut self, psp: PspConfig, retry_type: RetryType, retry: u32, backoff_ms: u64) -> Dispatch {
+        self.dispatched += 1;
+        Dispatch {
+            psp,
+            retry_type,
+            retry,
+            backoff_ms,
+            authentication: None,
+            credential: self.credential.clone(),
+        }
+    }
+
+    /// Record the response to `dispatch`, which arrived `elapsed_ms` after
+    /// the previous one, and decide whether routing goes on.
+    pub(super) fn record(
+        &mut self,
+        dispatch: &Dispatch,
+        response: PspResponse,
+        elapsed_ms: u64,
+        flags: AttemptFlags,
+    ) -> Step {
+        let psp = &dispatch.psp;
+        // A response from another PSP supersedes a pending resend
+        if self.retry_psp.as_ref().is_some_and(|p| p.id != psp.id) {
+            self.retry_psp = None;
+        }
+        self.total_latency_ms += elapsed_ms;
+        self.engine.record_outcome(
+            &psp.id,
+            &response,
+            self.transaction,
+            self.previous_decline.as_ref(),
+        );
+
+        if response.approved {
+            self.attempt_number += 1;
+            self.push(&response, self.attempt_number, dispatch, flags);
+            self.approved_by = Some(response.psp_name);
+            return Step::Done;
+        }
+
+        // A decline always carries a normalized reason; treat a missing one
+        // like an unrecognized soft decline.
+        let reason = response
+            .decline_reason
+            .clone()
+            .unwrap_or(DeclineReason::Unrecognized(DeclineClass::Soft));
+
+        // PSP unavailable — recorded, but not counted as an attempt
+        let unavailable = retry::is_psp_unavailable(&reason);
+        if unavailable {
+            self.unavailable_cascades += 1;
+            self.push(&response, self.attempt_number + 1, dispatch, flags);
+        } else {
+            self.attempt_number += 1;
+            self.push(&response, self.attempt_number, dispatch, flags);
+        }
+
+        let action = self.policy.action_for(&reason);
+        // The attempt limit is on local PSPs: once they are all declined,
+        // the cross-border fallback is still tried
+        let fallback = !unavailable
+            && self.attempt_number >= self.policy.max_attempts
+            && self.only_cross_border_left();
+        let out_of_budget = if unavailable {
+            self.policy
+                .max_unavailable_cascades
+                .is_some_and(|cap| self.unavailable_cascades > cap)
+        } else {
+            self.attempt_number >= self.policy.max_attempts && !fallback
+        };
+        if action == RetryAction::RefreshCredential && !unavailable && !fallback && !out_of_budget {
+            if let Some(credential) = self.refresh_credential() {
+                self.credential = Some(credential);
+                self.refreshed_psp = Some(psp.clone());
+                self.explain_decision(psp, &reason, action, RetryStep::CredentialRefreshed);
+                self.previous_decline = Some(reason);
+                return Step::Continue;
+            }
+        }
+        // Without a refreshed credential, the decline's class decides
+        let stop = match action {
+            RetryAction::Stop => true,
+            RetryAction::RefreshCredential => retry::class_action(&reason) == RetryAction::Stop,
+            _ => false,
+        };
+        if stop || out_of_budget {
+            let next = if stop {
+                RetryStep::Stopped
+            } else if unavailable {
+                RetryStep::MaxCascadesReached
+            } else {
+                RetryStep::MaxAttemptsReached
+            };
+            self.explain_decision(psp, &reason, action, next);
+            // The policy ended routing, not the latency budget
+            self.budget_exhausted = false;
+            return Step::Done;
+        }
+
+        if action == RetryAction::Authenticate && !unavailable && !fallback && self.can_authenticate(psp) {
+            self.authentication_tried = true;
+            self.authentication_due = Some((psp.clone(), reason.clone()));
+            self.previous_decline = Some(reason);
+            return Step::Continue;
+        }
+        if action == RetryAction::RetrySamePsp
+            && !fallback
+            && self.same_psp_retries < self.policy.max_same_psp_retries
+        {
+            self.same_psp_retries += 1;
+            self.retry_psp = Some(psp.clone());
+            self.explain_decision(psp, &reason, action, RetryStep::RetriedSamePsp);
+            if !unavailable {
+                self.previous_decline = Some(reason);
+            }
+            return Step::Continue;
+        }
+        if !unavailable {
+            self.engine.rerank_remaining(
+                &reason,
+                &mut self.psps[self.next_psp..],
+                self.rule,
+                &self.circuits,
+            );
+        }
+        self.explain_decision(psp, &reason, action, RetryStep::MovedOn);
+        if !unavailable {
+            self.previous_decline = Some(reason);
+        }
+        Step::Continue
+    }
+
+    /// The PSP to authenticate the cardholder at before anything else, if
+    /// the latest decline called for it, and that decline.
+    pub(super) fn authentication_due(&mut self) -> Option<(PspConfig, DeclineReason)> {
+        self.authentication_due.take()
+    }
+
+    /// Record `psp`'s answer to a 3-D Secure step and decide whether
+    /// routing goes on: an authenticated cardholder is resent to `psp`, a
+    /// challenge pauses routing, a refusal ends it and an unreachable 3-D
+    /// Secure server moves on to the next PSP.
+    ///
+    /// `decline` is the decline that called for authentication, whose
+    /// verdict goes into the trace; `None` when completing a challenge,
+    /// whose decline was explained when it was issued.
+    pub(super) fn authenticated(
+        &mut self,
+        psp: &PspConfig,
+        response: ThreeDsResponse,
+        decline: Option<&DeclineReason>,
+    ) -> Step {
+        self.total_latency_ms += response.latency_ms;
+        let (next, step) = match (response.status, &response.session_id) {
+            (ThreeDsStatus::Authenticated, _) => {
+                self.authenticated_psp = Some((psp.clone(), response.flow));
+                (RetryStep::Authenticated, Step::Continue)
+            }
+            (ThreeDsStatus::ChallengeRequired, Some(session_id)) => {
+                self.challenge = Some((psp.clone(), session_id.clone()));
+                (RetryStep::ChallengeRequired, Step::Done)
+            }
+            (ThreeDsStatus::ChallengeRequired, None) | (ThreeDsStatus::Failed, _) => {
+                (RetryStep::AuthenticationFailed, Step::Done)
+            }
+            (ThreeDsStatus::Unavailable, _) => {
+                if let Some(reason) = self.previous_decline.clone() {
+                    self.engine.rerank_remaining(
+                        &reason,
+                        &mut self.psps[self.next_psp..],
+                        self.rule,
+                        &self.circuits,
+                    );
+                }
+                (RetryStep::MovedOn, Step::Continue)
+            }
+        };
+        self.authentications.push(response);
+        if let Some(reason) = decline {
+            self.explain_decision(psp, reason, RetryAction::Authenticate, next);
+        }
+        if step == Step::Done {
+            // Authentication ended routing, not the latency budget
+            self.budget_exhausted = false;
+        }
+        step
+    }
+
+    /// Record a response that arrived after another attempt had been
+    /// approved. It still tells the breaker and bandit how the PSP did, but
+    /// does not count as an attempt or add to the latency.
+    pub(super) fn cancel(&mut self, dispatch: &Dispatch, response: PspResponse, flags: AttemptFlags) -> RoutingAttempt {
+        self.engine.record_outcome(
+            &dispatch.psp.id,
+            &response,
+            self.transaction,
+            self.previous_decline.as_ref(),
+        );
+        self.push(&response, self.attempt_number + 1, dispatch, flags);
+        let attempt = self.attempts.last_mut().expect("just pushed");
+        attempt.cancelled = true;
+        attempt.clone()
+    }
+
+    /// Whether a PSP has approved the transaction.
+    pub(super) fn approved(&self) -> bool {
+        self.approved_by.is_some()
+    }
+
+    /// The routing result: the approval if there was one, the session to
+    /// resume if routing is paused on a challenge, otherwise a decline with
+    /// the full attempt history.
+    pub(super) fn finish(self) -> RoutingResult {
+        let (status, budget_exhausted) = match (&self.approved_by, &self.challenge) {
+            (Some(_), _) => (accepted_status(self.transaction), false),
+            (None, Some(_)) => (AuthorizationStatus::ChallengeRequired, false),
+            (None, None) => (AuthorizationStatus::Declined, self.budget_exhausted),
+        };
+        let challenge = match &self.challenge {
+            Some((psp, session_id)) if self.approved_by.is_none() => Some(AuthenticationSession {
+                session_id: session_id.clone(),
+                psp_id: psp.id.clone(),
+                psp_name: psp.name.clone(),
+                transaction: Transaction {
+                    credential: self.credential.clone(),
+                    ..self.transaction.clone()
+                },
+                strategy: self.strategy.clone(),
+                policy: self.policy.clone(),
+                cross_border_fallback: self.cross_border_fallback,
+                attempts: self.attempts.clone(),
+                total_attempts: self.attempt_number,
+                total_latency_ms: self.total_latency_ms,
+                authentications: self.authentications.clone(),
+                trace: self.trace.clone(),
+            }),
+            _ => None,
+        };
+        RoutingResult {
+            transaction_id: self.transaction.id.clone(),
+            approved: status == AuthorizationStatus::Approved,
+            status,
+            final_psp: self.approved_by,
+            attempts: self.attempts,
+            total_attempts: self.attempt_number,
+            total_latency_ms: self.total_latency_ms,
+            budget_exhausted,
+            matched_rule: self.matched_rule,
+            ineligible_psps: self.ineligible,
+            trace: self.trace,
+            authentications: self.authentications,
+            challenge,
+        }
+    }
+
+    /// Whether the cardholder can be authenticated at `psp` after a
+    /// decline: once per routing, for cards, at PSPs with 3-D Secure.
+    fn can_authenticate(&self, psp: &PspConfig) -> bool {
+        !self.authentication_tried
+            && psp.supports_three_ds()
+            && self.transaction.payment_method == PaymentMethod::Card
+    }
+
+    /// The card's refreshed credential from the engine's credential store:
+    /// asked once per routing, for card payments.
+    fn refresh_credential(&mut self) -> Option<CardCredential> {
+        if self.credential_tried {
+            return None;
+        }
+        self.credential_tried = true;
+        self.engine.credentials.as_ref()?.refresh(self.transaction)
+    }
+
+    /// Whether the PSPs left to try are all cross-border acquirers.
+    fn only_cross_border_left(&self) -> bool {
+        let remaining = &self.psps[self.next_psp..];
+        !remaining.is_empty() && remaining.iter().all(PspConfig::is_cross_border)
+    }
+
+    /// Add the decision on the latest attempt to the trace, if explaining.
+    fn explain_decision(
+        &mut self,
+        psp: &PspConfig,
+        reason: &DeclineReason,
+        action: RetryAction,
+        next: RetryStep,
+    ) {
+        let Some(trace) = &mut self.trace else {
+            return;
+        };
+        let remaining = match next {
+            RetryStep::RetriedSamePsp
+            | RetryStep::MovedOn
+            | RetryStep::Authenticated
+            | RetryStep::ChallengeRequired
+            | RetryStep::CredentialRefreshed => self
+                .retry_psp
+                .iter()
+                .chain(self.authenticated_psp.iter().map(|(psp, _)| psp))
+                .chain(&self.refreshed_psp)
+                .chain(&self.psps[self.next_psp..])
+                .map(|p| p.id.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        trace.decisions.push(RetryDecision {
+            attempt_number: self.attempts.last().map_or(0, |a| a.attempt_number),
+            psp_id: psp.id.clone(),
+            decline_reason: reason.clone(),
+            class: retry::classify(reason),
+            action,
+            from_rule: self.policy.rules.iter().any(|r| r.reason == *reason),
+            next,
+            remaining,
+        });
+    }
+
+    fn push(&mut self, response: &PspResponse, attempt_number: usize, dispatch: &Dispatch, flags: AttemptFlags) {
+        let mut attempt = build_attempt(response, attempt_number);
+        attempt.retry_type = dispatch.retry_type;
+        attempt.backoff_ms = dispatch.backoff_ms;
+        attempt.hedged = flags.hedged;
+        attempt.timed_out = flags.timed_out;
+        attempt.cross_border = dispatch.psp.is_cross_border();
+        attempt.three_ds = dispatch.authentication.as_ref().map(|a| a.flow);
+        attempt.credential = dispatch.credential.as_ref().map(|c| c.source);
+        self.attempts.push(attempt);
+    }
+}
+
+/// Treat a response slower than the policy's attempt timeout as the PSP
+/// being unavailable, given up on at the timeout. Also returns whether it
+/// timed out.
+pub(super) fn apply_timeout(policy: &RetryPolicy, psp: &PspConfig, response: PspResponse) -> (PspResponse, bool) {
+    match policy.attempt_timeout_ms {
+        Some(timeout) if response.latency_ms > timeout => (connector::unavailable(psp, timeout), true),
+        _ => (response, false),
+    }
+}
+
+/// The start of a decision trace: how the PSPs were ranked and which ones
+/// never were. `eligible` is every PSP that could take the transaction,
+/// `kept` those the matched rule left in, `scores` the ranking of the kept
+/// PSPs whose circuit is not open and `ordered` the final initial order.
+pub(super) fn decision_trace(
+    strategy: &RoutingStrategy,
+    rule: Option<&RoutingRule>,
+    ineligible: &[IneligiblePsp],
+    eligible: &[PspConfig],
+    kept: &[PspConfig],
+    scores: Vec<CandidateScore>,
+    ordered: &[PspConfig],
+) -> DecisionTrace {
+    let mut excluded: Vec<ExcludedPsp> = ineligible
+        .iter()
+        .map(|p| ExcludedPsp {
+            psp_id: p.psp_id.clone(),
+            psp_name: p.psp_name.clone(),
+            reason: ExclusionReason::Ineligible(p.reason.clone()),
+        })
+        .collect();
+    for psp in eligible {
+        let reason = if !kept.iter().any(|p| p.id == psp.id) {
+            ExclusionReason::ExcludedByRule(rule.map(|r| r.name.clone()).unwrap_or_default())
+        } else if !scores.iter().any(|s| s.psp_id == psp.id) {
+            ExclusionReason::CircuitOpen
+        } else {
+            continue;
+        };
+        excluded.push(ExcludedPsp {
+            psp_id: psp.id.clone(),
+            psp_name: psp.name.clone(),
+            reason,
+        });
+    }
+
+    // A rule's psp_order may have moved PSPs ahead of the strategy's order
+    let mut candidates: Vec<CandidateScore> = ordered
+        .iter()
+        .filter_map(|psp| scores.iter().find(|s| s.psp_id == psp.id).cloned())
+        .collect();
+    for (i, candidate) in candidates.iter_mut().enumerate() {
+        candidate.rank = i + 1;
+    }
+    DecisionTrace {
+        strategy: strategy.clone(),
+        candidates,
+        excluded,
+        decisions: Vec::new(),
+    }
+}
@@ -0,0 +1,180 @@
+/// What routing a transaction to a PSP costs and is worth.
+///
+/// A PSP charges a percentage of the amount (plus any installment
+/// surcharge) and a fixed fee quoted in USD, so which PSP is cheaper depends
+/// on the amount: a low percentage with a high fixed fee wins on large
+/// orders and loses on small ones. [`transaction_fee`] prices one
+/// transaction in its own currency, converting the fixed fee at the
+/// transaction date.
+///
+/// The expected value of trying a PSP weighs that fee against the chance of
+/// approval, what a decline costs (network fees, a customer who may not
+/// retry) and the time the customer waits:
+///
+/// ```text
+/// EV = p × (amount − fee) − (1 − p) × decline_cost − latency_cost × expected_latency
+/// ```
+use crate::fx::FxTable;
+use crate::models::money::Money;
+use crate::models::psp::PspConfig;
+use crate::models::transaction::{Currency, Transaction};
+
+/// Default cost of a declined attempt, in US cents.
+pub const DEFAULT_DECLINE_COST_USD_CENTS: i64 = 10;
+
+/// Default cost of each second a customer waits on a PSP, in US cents.
+pub const DEFAULT_LATENCY_COST_USD_CENTS_PER_SEC: i64 = 2;
+
+/// The fee `psp` charges on `transaction`, in the transaction's currency.
+///
+/// Applies the percentage fee for the transaction's installment plan to the
+/// amount, rounded half away from zero to the minor unit, and adds the USD
+/// fixed fee converted at the transaction date.
+///
+/// # Panics
+///
+/// If `fx` cannot convert into the transaction's currency (every validated
+/// table covers the checkout currencies) or the fee overflows.
+pub fn transaction_fee(psp: &PspConfig, transaction: &Transaction, fx: &FxTable) -> Money {
+    let fee_bps = (psp.fee_percentage_for(transaction.installment_count()) * 100.0).round() as i64;
+    let percentage_fee = transaction
+        .amount
+        .checked_apply_bps(fee_bps)
+        .expect("percentage fee overflowed");
+    let fixed_fee = usd_in(fx, &psp.fee_fixed, transaction);
+    percentage_fee
+        .checked_add(&fixed_fee)
+        .expect("fee overflowed")
+}
+
+/// Prices PSPs for one transaction: fees, decline and latency costs.
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    fx: FxTable,
+    decline_cost: Money,
+    latency_cost_per_second: Money,
+}
+
+impl CostModel {
+    /// A cost model converting USD fees with `fx`, with the default decline
+    /// and latency costs.
+    pub fn new(fx: FxTable) -> Self {
+        CostModel {
+            fx,
+            decline_cost: Money::new(DEFAULT_DECLINE_COST_USD_CENTS, Currency::USD),
+            latency_cost_per_second: Money::new(DEFAULT_LATENCY_COST_USD_CENTS_PER_SEC, Currency::USD),
+        }
+    }
+
+    /// Cost of a declined attempt, in USD.
+    pub fn with_decline_cost(mut self, usd: Money) -> Self {
+        self.decline_cost = usd;
+        self
+    }
+
+    /// Cost of each second spent waiting on a PSP, in USD.
+    pub fn with_latency_cost(mut self, usd_per_second: Money) -> Self {
+        self.latency_cost_per_second = usd_per_second;
+        self
+    }
+
+    /// The FX rates fixed fees are converted with.
+    pub fn fx(&self) -> &FxTable {
+        &self.fx
+    }
+
+    /// The fee `psp` charges on `transaction` (see [`transaction_fee`]).
+    pub fn fee(&self, psp: &PspConfig, transaction: &Transaction) -> Money {
+        transaction_fee(psp, transaction, &self.fx)
+    }
+
+    /// Expected value of trying `psp` for `transaction`, in major units of
+    /// the transaction's currency, given its approval probability.
+    pub fn expected_value(&self, psp: &PspConfig, transaction: &Transaction, approval_rate: f64) -> f64 {
+        let amount = transaction.amount.to_major_f64();
+        let fee = self.fee(psp, transaction).to_major_f64();
+        let decline_cost = usd_in(&self.fx, &self.decline_cost, transaction).to_major_f64();
+        let latency_cost = usd_in(&self.fx, &self.latency_cost_per_second, transaction).to_major_f64()
+            * psp.expected_latency_ms() as f64
+            / 1000.0;
+        approval_rate * (amount - fee) - (1.0 - approval_rate) * decline_cost - latency_cost
+    }
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel::new(FxTable::builtin())
+    }
+}
+
+/// Converts a USD amount into the transaction's currency at its date.
+fn usd_in(fx: &FxTable, usd: &Money, transaction: &Transaction) -> Money {
+    fx.from_usd(usd, &transaction.amount.currency, &transaction.timestamp.date_string())
+        .expect("FX table covers every checkout currency")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::PspCatalog;
+    use crate::models::transaction::{Country, PaymentMethod};
+    use crate::time::Timestamp;
+
+    fn make_txn(minor_units: i64) -> Transaction {
+        Transaction {
+            id: "txn_fee".to_string(),
+            amount: Money::new(minor_units, Currency::new("BRL")),
+            country: Country::new("Brazil"),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some("411111".to_string()),
+            card_last4: Some("1111".to_string()),
+            bin_info: None,
+            installments: None,
+            customer_id: "cust_fee".to_string(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
+        }
+    }
+
+    #[test]
+    fn test_fee_scales_with_amount() {
+        let catalog = PspCatalog::builtin();
+        let pagseguro = catalog.psp("psp_br_1").unwrap(); // 2.9% + USD 0.30
+        let fx = FxTable::builtin();
+
+        // 2.9% of 100.00 = 2.90, USD 0.30 at 6.18 = 1.85
+        assert_eq!(transaction_fee(pagseguro, &make_txn(10_000), &fx), Money::new(475, Currency::new("BRL")));
+        // 2.9% of 5000.00 = 145.00
+        assert_eq!(transaction_fee(pagseguro, &make_txn(500_000), &fx), Money::new(14_685, Currency::new("BRL")));
+    }
+
+    #[test]
+    fn test_fee_includes_installment_surcharge() {
+        let catalog = PspCatalog::builtin();
+        let stone = catalog.psp("psp_br_3").unwrap();
+        let mut txn = make_txn(10_000);
+        let single = transaction_fee(stone, &txn, &FxTable::builtin());
+        txn.installments = Some(12);
+        let twelve = transaction_fee(stone, &txn, &FxTable::builtin());
+        assert_eq!(twelve.minor_units - single.minor_units, 400); // +4.0% of 100.00
+    }
+
+    #[test]
+    fn test_expected_value_weighs_approval_against_costs() {
+        let catalog = PspCatalog::builtin();
+        let psp = catalog.psp("psp_br_2").unwrap();
+        let model = CostModel::default();
+        let txn = make_txn(10_000);
+
+        let certain = model.expected_value(psp, &txn, 1.0);
+        let fee = model.fee(psp, &txn).to_major_f64();
+        assert!(certain < 100.0 - fee, "latency still costs something");
+        assert!(model.expected_value(psp, &txn, 0.0) < 0.0);
+
+        let free = CostModel::default()
+            .with_decline_cost(Money::zero(Currency::USD))
+            .with_latency_cost(Money::zero(Currency::USD));
+        assert!((free.expected_value(psp, &txn, 0.5) - 0.5 * (100.0 - fee)).abs() < 1e-9);
+    }
+}
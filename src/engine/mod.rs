@@ -2,114 +2,502 @@
 ///
 /// Routes transactions through multiple PSPs, retrying on soft declines
 /// and failing fast on hard declines. Supports real-time cascading when
-/// a PSP is unavailable.
+/// a PSP is unavailable. Declines that doubt the cardholder are recovered
+/// with 3-D Secure where the PSP offers it, and an expired card with its
+/// refreshed credential when one is on file. Captures, voids and refunds of
+/// an approved payment go to the PSP that authorized it, with no routing.
+pub mod bandit;
+pub mod breaker;
+mod cascade;
+pub mod fees;
+pub mod recovery;
 pub mod retry;
 pub mod strategy;
 
-use crate::models::psp::PspResponse;
-use crate::models::routing::{RoutingAttempt, RoutingResult, RoutingStrategy};
-use crate::models::transaction::Transaction;
-use crate::simulator::config::get_psps_for_country;
+use crate::catalog::{Eligibility, PspCatalog};
+use crate::connector::{self, AsyncPspConnector, PspConnector};
+use crate::credentials::CredentialStore;
+use crate::models::authentication::AuthenticationSession;
+use crate::models::money::Money;
+use crate::models::payment::{LifecycleError, OperationKind, Payment, PaymentOperation};
+use crate::models::psp::{DeclineReason, PspConfig, PspResponse};
+use crate::models::routing::{
+    AuthorizationStatus, CandidateScore, IneligiblePsp, RetryType, RoutingAttempt, RoutingResult,
+    RoutingStrategy,
+};
+use crate::models::transaction::{Country, Transaction};
+use crate::rules::{RoutingRule, RuleSet};
 use crate::simulator::PspSimulator;
+use bandit::AdaptiveRouter;
+use breaker::{BreakerRegistry, BreakerState};
+use cascade::{AttemptFlags, Cascade, Dispatch, Step};
+use fees::CostModel;
+use recovery::RecoveryTracker;
+use retry::RetryPolicy;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::{JoinError, JoinHandle};
 
-/// Maximum number of PSP decline attempts before giving up.
-///
-/// PSP-unavailable cascades do NOT count toward this limit.
-const MAX_ATTEMPTS: usize = 3;
+/// Why a transaction cannot be routed at all.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoutingError {
+    /// No PSP in the country can take the transaction.
+    NoEligiblePsp {
+        country: Country,
+        ineligible: Vec<IneligiblePsp>,
+    },
+    /// An authentication session names a PSP the catalog does not have.
+    UnknownPsp(String),
+}
+
+impl std::fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoutingError::NoEligiblePsp { country, ineligible } if ineligible.is_empty() => {
+                write!(f, "no PSP is configured for {country}")
+            }
+            RoutingError::NoEligiblePsp { country, ineligible } => {
+                let reasons: Vec<String> = ineligible
+                    .iter()
+                    .map(|p| format!("{} {}", p.psp_name, p.reason))
+                    .collect();
+                write!(f, "no PSP in {country} can take this transaction: {}", reasons.join("; "))
+            }
+            RoutingError::UnknownPsp(psp_id) => {
+                write!(f, "the authentication session names unknown PSP '{psp_id}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
 
 /// The core routing engine that orchestrates PSP selection and retry logic.
 ///
-/// The engine is stateless — each call to [`route`](RoutingEngine::route) is
-/// independent, making it safe for concurrent use in a serverless environment.
-pub struct RoutingEngine {
-    simulator: PspSimulator,
+/// PSPs are reached through `C`, any [`PspConnector`]: the
+/// [`PspSimulator`] by default, or a [`ConnectorRegistry`](crate::connector::ConnectorRegistry)
+/// mixing sandbox clients, test doubles and the simulator per PSP.
+///
+/// Each call to [`route`](RoutingEngine::route) is independent unless the
+/// engine is given a [`BreakerRegistry`], an [`AdaptiveRouter`], a
+/// [`RecoveryTracker`] or a [`CredentialStore`]; the only state it then
+/// shares is the per-PSP circuit breakers, the learned approval rates, the
+/// decline-recovery counts and the customers' card credentials, all safe
+/// for concurrent use.
+pub struct RoutingEngine<C = PspSimulator> {
+    connector: Arc<C>,
+    catalog: PspCatalog,
+    breakers: Option<Arc<BreakerRegistry>>,
+    bandit: Option<Arc<AdaptiveRouter>>,
+    recovery: Option<Arc<RecoveryTracker>>,
+    credentials: Option<Arc<CredentialStore>>,
+    rules: RuleSet,
+    costs: CostModel,
+    explain: bool,
+    cross_border_fallback: bool,
 }
 
-impl RoutingEngine {
-    /// Creates a new routing engine backed by the given PSP simulator.
-    pub fn new(simulator: PspSimulator) -> Self {
-        RoutingEngine { simulator }
+impl<C> RoutingEngine<C> {
+    /// Creates a new routing engine that reaches PSPs through `connector`,
+    /// routing across the PSPs in `catalog`.
+    pub fn new(connector: C, catalog: PspCatalog) -> Self {
+        RoutingEngine {
+            connector: Arc::new(connector),
+            catalog,
+            breakers: None,
+            bandit: None,
+            recovery: None,
+            credentials: None,
+            rules: RuleSet::default(),
+            costs: CostModel::default(),
+            explain: false,
+            cross_border_fallback: false,
+        }
+    }
+
+    /// Feed PSP outcomes into `registry` and skip or demote PSPs whose
+    /// circuit breaker is open or half-open when routing.
+    pub fn with_breakers(mut self, registry: Arc<BreakerRegistry>) -> Self {
+        self.breakers = Some(registry);
+        self
+    }
+
+    /// Teach `router` from every attempt and let it rank PSPs for
+    /// [`RoutingStrategy::Adaptive`].
+    pub fn with_bandit(mut self, router: Arc<AdaptiveRouter>) -> Self {
+        self.bandit = Some(router);
+        self
+    }
+
+    /// After each soft decline, re-rank the PSPs not tried yet by how often
+    /// `tracker` has seen them recover that decline reason, and teach it
+    /// from every attempt that follows a decline.
+    pub fn with_recovery(mut self, tracker: Arc<RecoveryTracker>) -> Self {
+        self.recovery = Some(tracker);
+        self
+    }
+
+    /// Resend declines that call for it (see
+    /// [`RetryAction::RefreshCredential`](retry::RetryAction::RefreshCredential))
+    /// once with the card's credential from `store`, and provision a
+    /// network token there for every card payment approved.
+    pub fn with_credentials(mut self, store: Arc<CredentialStore>) -> Self {
+        self.credentials = Some(store);
+        self
+    }
+
+    /// Apply the merchant's routing `rules` before ordering PSPs.
+    pub fn with_rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Price PSP fees and expected values with `costs` (built-in FX rates
+    /// and default decline and latency costs otherwise).
+    pub fn with_costs(mut self, costs: CostModel) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    /// Attach a [`DecisionTrace`](crate::models::routing::DecisionTrace)
+    /// to every routed result: how each PSP scored, which were left out and
+    /// why, and what the retry policy made of each decline.
+    pub fn with_explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+
+    /// When every local PSP has declined, fall back on the catalog's
+    /// cross-border acquirers serving the transaction's country. They are
+    /// tried after all local PSPs, at their own fees and approval rates.
+    /// A routing resumed after a challenge keeps the setting it was
+    /// started with.
+    pub fn with_cross_border_fallback(mut self, enabled: bool) -> Self {
+        self.cross_border_fallback = enabled;
+        self
+    }
+
+    /// The connector this engine sends PSP calls through.
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+
+    /// The PSP catalog this engine routes across.
+    pub fn catalog(&self) -> &PspCatalog {
+        &self.catalog
+    }
+
+    /// The circuit breakers this engine consults, if any.
+    pub fn breakers(&self) -> Option<&BreakerRegistry> {
+        self.breakers.as_deref()
+    }
+
+    /// The bandit this engine learns into, if any.
+    pub fn bandit(&self) -> Option<&AdaptiveRouter> {
+        self.bandit.as_deref()
+    }
+
+    /// The decline-recovery statistics this engine re-ranks with, if any.
+    pub fn recovery(&self) -> Option<&RecoveryTracker> {
+        self.recovery.as_deref()
+    }
+
+    /// The card credentials this engine refreshes declines with, if any.
+    pub fn credentials(&self) -> Option<&CredentialStore> {
+        self.credentials.as_deref()
+    }
+
+    /// The cost model fees and expected values are priced with.
+    pub fn costs(&self) -> &CostModel {
+        &self.costs
+    }
+
+    /// The PSPs that can take `transaction`, or an error listing why none
+    /// can. Lets callers reject a transaction before routing it.
+    pub fn check_eligibility(&self, transaction: &Transaction) -> Result<Eligibility, RoutingError> {
+        let eligibility = self.eligibility(transaction, self.cross_border_fallback);
+        if eligibility.eligible.is_empty() {
+            return Err(RoutingError::NoEligiblePsp {
+                country: transaction.country.clone(),
+                ineligible: eligibility.ineligible,
+            });
+        }
+        Ok(eligibility)
+    }
+
+    /// The catalog's split of the transaction's country PSPs and, with the
+    /// `cross_border` fallback, of the acquirers serving it from abroad.
+    fn eligibility(&self, transaction: &Transaction, cross_border: bool) -> Eligibility {
+        let mut eligibility = self.catalog.eligibility(transaction);
+        if cross_border {
+            let cross_border = self.catalog.cross_border_eligibility(transaction);
+            eligibility.eligible.extend(cross_border.eligible);
+            eligibility.ineligible.extend(cross_border.ineligible);
+        }
+        eligibility
+    }
+
+    /// Steps 1–3 of [`route`](RoutingEngine::route): the eligible PSPs,
+    /// filtered by the matching rule and ordered by strategy, ready to be
+    /// tried, with acquirers from abroad among them if `cross_border`.
+    fn cascade<'e>(
+        &'e self,
+        transaction: &'e Transaction,
+        strategy: &RoutingStrategy,
+        policy: &'e RetryPolicy,
+        cross_border: bool,
+    ) -> Cascade<'e, C> {
+        let requested = strategy;
+        let rule = self.rules.evaluate(transaction);
+        let Eligibility {
+            eligible: mut psps,
+            ineligible,
+        } = self.eligibility(transaction, cross_border);
+        let eligible = self.explain.then(|| psps.clone());
+        let strategy = match rule {
+            Some(rule) => {
+                rule.then.filter(&mut psps);
+                rule.then.strategy.as_ref().unwrap_or(strategy)
+            }
+            None => strategy,
+        };
+        let circuits = self.circuit_states(&psps, transaction);
+        let adaptive_scores = match (strategy, &self.bandit) {
+            (RoutingStrategy::Adaptive, Some(bandit)) => bandit.scores(transaction, &psps),
+            (RoutingStrategy::ExpectedValue, Some(bandit)) => {
+                bandit.posterior_means(transaction, &psps)
+            }
+            _ => HashMap::new(),
+        };
+        let (mut ordered_psps, scores): (Vec<PspConfig>, Vec<CandidateScore>) = strategy::rank_psps(
+            &psps,
+            strategy,
+            transaction,
+            &self.costs,
+            &circuits,
+            &adaptive_scores,
+        )
+        .into_iter()
+        .unzip();
+        if let Some(rule) = rule {
+            rule.then.reorder(&mut ordered_psps);
+        }
+
+        let trace = eligible.map(|eligible| {
+            cascade::decision_trace(strategy, rule, &ineligible, &eligible, &psps, scores, &ordered_psps)
+        });
+        let cascade = Cascade::new(self, transaction, policy, rule, circuits, ordered_psps, ineligible)
+            .requested(requested)
+            .cross_border(cross_border);
+        match trace {
+            Some(trace) => cascade.explain(trace),
+            None => cascade,
+        }
+    }
+
+    /// The routing `session` paused on a challenge, ready to go on with
+    /// the PSPs still eligible that it has not tried yet. Acquirers from
+    /// abroad are among them if the routing was started with the
+    /// cross-border fallback, whatever this engine's setting.
+    fn resume<'e>(&'e self, session: &'e AuthenticationSession) -> Cascade<'e, C> {
+        let cross_border = session.cross_border_fallback;
+        self.cascade(&session.transaction, &session.strategy, &session.policy, cross_border)
+            .resume(session)
     }
 
+    /// Breaker state of each PSP at the transaction's time (empty without
+    /// a registry, i.e. all closed).
+    fn circuit_states(
+        &self,
+        psps: &[PspConfig],
+        transaction: &Transaction,
+    ) -> HashMap<String, BreakerState> {
+        let Some(breakers) = &self.breakers else {
+            return HashMap::new();
+        };
+        psps.iter()
+            .map(|psp| (psp.id.clone(), breakers.state(&psp.id, transaction.timestamp)))
+            .collect()
+    }
+
+    /// After a decline with `reason`, re-rank the PSPs not tried yet by
+    /// recovery rate. As in the initial order, PSPs pinned by the matched
+    /// rule stay first, then local PSPs, half-open ones last, then
+    /// cross-border acquirers.
+    fn rerank_remaining(
+        &self,
+        reason: &DeclineReason,
+        remaining: &mut [PspConfig],
+        rule: Option<&RoutingRule>,
+        circuits: &HashMap<String, BreakerState>,
+    ) {
+        let Some(recovery) = &self.recovery else {
+            return;
+        };
+        recovery.rerank(reason, remaining);
+        remaining.sort_by_key(|psp| {
+            let half_open = circuits.get(&psp.id) == Some(&BreakerState::HalfOpen);
+            (psp.is_cross_border(), half_open)
+        });
+        if let Some(rule) = rule {
+            rule.then.reorder(remaining);
+        }
+    }
+
+    /// Tell the PSP's breaker whether it answered and, when it did, tell
+    /// the bandit whether it approved and the recovery tracker whether it
+    /// recovered the `previous_decline`. Any answer, approval or decline,
+    /// counts as a breaker success; only an unavailable PSP is a failure.
+    /// An approved card gets a network token in the credential store.
+    fn record_outcome(
+        &self,
+        psp_id: &str,
+        response: &PspResponse,
+        transaction: &Transaction,
+        previous_decline: Option<&DeclineReason>,
+    ) {
+        let unavailable = response
+            .decline_reason
+            .as_ref()
+            .is_some_and(retry::is_psp_unavailable);
+        if let Some(breakers) = &self.breakers {
+            breakers.record(psp_id, !unavailable, transaction.timestamp);
+        }
+        if let (Some(bandit), false) = (&self.bandit, unavailable) {
+            bandit.observe(transaction, psp_id, response.approved);
+        }
+        if let (Some(recovery), Some(reason), false) = (&self.recovery, previous_decline, unavailable) {
+            recovery.observe(reason, psp_id, response.approved);
+        }
+        if let (Some(credentials), true) = (&self.credentials, response.approved) {
+            credentials.provision_token(transaction);
+        }
+    }
+}
+
+impl<C: PspConnector> RoutingEngine<C> {
     /// Route a transaction through PSPs with smart retry logic.
     ///
     /// # Algorithm
     ///
-    /// 1. Retrieve available PSPs for the transaction's country.
-    /// 2. Order them according to the chosen [`RoutingStrategy`].
-    /// 3. Iterate through the ordered PSPs:
-    ///    - **Approved** → return success immediately.
-    ///    - **Hard decline** → return failure immediately (no retry).
-    ///    - **Soft decline** → record attempt, try next PSP.
-    ///    - **PSP unavailable** → cascade to next PSP without counting as an attempt.
-    /// 4. If all PSPs are exhausted → return declined with full attempt history.
-    pub fn route(&self, transaction: &Transaction, strategy: &RoutingStrategy) -> RoutingResult {
-        let psps = get_psps_for_country(&transaction.country);
-        let ordered_psps = strategy::select_psp_order(&psps, strategy);
-
-        let mut attempts: Vec<RoutingAttempt> = Vec::new();
-        let mut total_latency_ms: u64 = 0;
-        let mut attempt_number: usize = 0;
-
-        for psp in &ordered_psps {
-            let response: PspResponse = self.simulator.process(transaction, psp);
-            total_latency_ms += response.latency_ms;
-
-            // PSP unavailable — cascade immediately, don't count as an attempt
-            if let Some(ref reason) = response.decline_reason {
-                if retry::is_psp_unavailable(reason) {
-                    attempts.push(build_attempt(&response, attempt_number + 1));
-                    continue;
-                }
-            }
-
-            attempt_number += 1;
-
-            // Approved — return success
-            if response.approved {
-                attempts.push(build_attempt(&response, attempt_number));
-                return RoutingResult {
-                    transaction_id: transaction.id.clone(),
-                    approved: true,
-                    final_psp: Some(response.psp_name),
-                    attempts,
-                    total_attempts: attempt_number,
-                    total_latency_ms,
-                };
-            }
+    /// 1. Retrieve the catalog's PSPs for the transaction's country that
+    ///    can take it (payment method, installments, currency, amount limits
+    ///    and card scheme); the others are listed in `ineligible_psps`.
+    ///    With [`with_cross_border_fallback`](RoutingEngine::with_cross_border_fallback),
+    ///    the cross-border acquirers serving the country are added too.
+    /// 2. Apply the first merchant rule that matches (see [`crate::rules`]):
+    ///    drop the PSPs it excludes and take its strategy, if it sets one.
+    /// 3. Order them according to the chosen [`RoutingStrategy`], leaving
+    ///    out PSPs whose circuit breaker is open and trying half-open ones
+    ///    last, then cross-border acquirers. Fees are priced on the
+    ///    transaction's amount. `Adaptive`
+    ///    ranks by the bandit's approval estimates, which `ExpectedValue`
+    ///    also uses as approval probabilities. The rule's `psp_order` then
+    ///    goes first, ahead of any demotion.
+    /// 4. Iterate through the ordered PSPs, acting on each outcome as the
+    ///    [`RetryPolicy`] says:
+    ///    - **Approved** → return success immediately (pending for
+    ///      voucher and bank-transfer methods).
+    ///    - **Stop** (hard declines by default) → return failure immediately.
+    ///    - **Retry next PSP** (soft declines by default) → record attempt,
+    ///      try next PSP, up to `max_attempts` declines; when only
+    ///      cross-border acquirers are left, they are tried regardless. With a
+    ///      [`RecoveryTracker`], the PSPs not tried yet are first re-ranked
+    ///      by how well each recovers the reason just received.
+    ///    - **Retry same PSP** → record attempt, wait out the policy's
+    ///      [`Backoff`](retry::Backoff) and resend to the same PSP (a
+    ///      `SamePsp` attempt) up to `max_same_psp_retries` times, then move
+    ///      on. The wait counts toward the latency.
+    ///    - **Authenticate** (`SuspectedFraud`, `DoNotHonor` and
+    ///      `AuthenticationRequired` by default) → record attempt and, once
+    ///      per routing, authenticate the cardholder with 3-D Secure at the
+    ///      same PSP. Frictionless authentication resends the transaction
+    ///      there with it (a `SamePsp` attempt marked `three_ds`); a
+    ///      challenge pauses routing with status `ChallengeRequired` until
+    ///      [`complete_challenge`](RoutingEngine::complete_challenge); a
+    ///      refusal stops. Card payments at PSPs without 3-D Secure move on
+    ///      as for a soft decline.
+    ///    - **Refresh credential** (`CardExpired` and `AccountUpdateRequired`
+    ///      by default) → record attempt and, once per routing, look the
+    ///      card up in the [`CredentialStore`]: its network token, or else
+    ///      the account updater's new details. With one, resend to the same
+    ///      PSP (a `SamePsp` attempt marked `credential`) and send every
+    ///      later attempt with it too; without one, the decline's class
+    ///      decides (a hard decline stops).
+    ///    - **PSP unavailable** → cascade without counting as an attempt, up
+    ///      to `max_unavailable_cascades`.
+    ///
+    ///    With a `latency_budget_ms`, a PSP whose expected latency does not
+    ///    fit in what is left of the budget is skipped, and the result is
+    ///    flagged `budget_exhausted` if routing ends without an approval.
+    ///    A PSP that takes longer than `attempt_timeout_ms` to answer is
+    ///    recorded as `timed_out` and treated as unavailable.
+    /// 5. If all PSPs are exhausted → return declined with full attempt history.
+    pub fn route(
+        &self,
+        transaction: &Transaction,
+        strategy: &RoutingStrategy,
+        policy: &RetryPolicy,
+    ) -> RoutingResult {
+        let mut cascade = self.cascade(transaction, strategy, policy, self.cross_border_fallback);
+        self.drive(&mut cascade, transaction, policy);
+        cascade.finish()
+    }
 
-            // Declined — classify
-            attempts.push(build_attempt(&response, attempt_number));
-
-            if let Some(ref reason) = response.decline_reason {
-                // Hard decline — fail fast, no retry
-                if retry::is_hard_decline(reason) {
-                    return RoutingResult {
-                        transaction_id: transaction.id.clone(),
-                        approved: false,
-                        final_psp: None,
-                        attempts,
-                        total_attempts: attempt_number,
-                        total_latency_ms,
-                    };
-                }
+    /// Resume a routing paused on a 3-D Secure challenge, once the
+    /// cardholder has answered it.
+    ///
+    /// The PSP that issued the challenge reports its result. An
+    /// authenticated cardholder's transaction is resent there with the
+    /// authentication and routing goes on as in [`route`](RoutingEngine::route),
+    /// across the eligible PSPs the session has not tried yet, ranked again
+    /// from the catalog, rules and breakers; a failed challenge ends routing
+    /// declined, and an unreachable 3-D Secure server moves on to the next
+    /// PSP.
+    pub fn complete_challenge(&self, session: &AuthenticationSession) -> Result<RoutingResult, RoutingError> {
+        let psp = self
+            .catalog
+            .psp(&session.psp_id)
+            .ok_or_else(|| RoutingError::UnknownPsp(session.psp_id.clone()))?;
+        let mut cascade = self.resume(session);
+        let response = self
+            .connector
+            .complete_challenge(&session.transaction, psp, &session.session_id);
+        if cascade.authenticated(psp, response, None) == Step::Continue {
+            self.drive(&mut cascade, &session.transaction, &session.policy);
+        }
+        Ok(cascade.finish())
+    }
 
-                // Soft decline — retry if we haven't exhausted attempts
-                if attempt_number >= MAX_ATTEMPTS {
-                    break;
+    /// Step 4 of [`route`](RoutingEngine::route): send the transaction to
+    /// each PSP the cascade picks, authenticating the cardholder when a
+    /// decline calls for it, until the cascade is done.
+    fn drive(&self, cascade: &mut Cascade<'_, C>, transaction: &Transaction, policy: &RetryPolicy) {
+        loop {
+            if let Some((psp, reason)) = cascade.authentication_due() {
+                let response = self.connector.authenticate(transaction, &psp);
+                if cascade.authenticated(&psp, response, Some(&reason)) == Step::Done {
+                    return;
                 }
-                // Otherwise, continue to next PSP
+                continue;
+            }
+            let Some(dispatch) = cascade.next_psp(0) else {
+                return;
+            };
+            // Backoff is simulated: it counts toward the latency, but the
+            // synchronous engine does not sleep
+            let sent = dispatch.transaction(transaction);
+            let response = match dispatch.retry {
+                0 => self.connector.authorize(&sent, &dispatch.psp),
+                retry => self.connector.resend(&sent, &dispatch.psp, retry),
+            };
+            let (response, timed_out) = cascade::apply_timeout(policy, &dispatch.psp, response);
+            let latency = response.latency_ms;
+            let flags = AttemptFlags { hedged: false, timed_out };
+            if cascade.record(&dispatch, response, latency, flags) == Step::Done {
+                return;
             }
-        }
-
-        // All PSPs exhausted — return declined
-        RoutingResult {
-            transaction_id: transaction.id.clone(),
-            approved: false,
-            final_psp: None,
-            attempts,
-            total_attempts: attempt_number,
-            total_latency_ms,
         }
     }
 
@@ -119,28 +507,41 @@ impl RoutingEngine {
     /// PSP and return the result regardless of the outcome. Used as the
     /// baseline for performance comparison in reports.
     pub fn route_no_retry(&self, transaction: &Transaction) -> RoutingResult {
-        let psps = get_psps_for_country(&transaction.country);
-        let psp = match psps.first() {
+        let Eligibility { eligible, ineligible } = self.catalog.eligibility(transaction);
+        let psp = match eligible.first() {
             Some(p) => p,
             None => {
                 return RoutingResult {
                     transaction_id: transaction.id.clone(),
                     approved: false,
+                    status: AuthorizationStatus::Declined,
                     final_psp: None,
                     attempts: vec![],
                     total_attempts: 0,
                     total_latency_ms: 0,
+                    budget_exhausted: false,
+                    matched_rule: None,
+                    ineligible_psps: ineligible,
+                    trace: None,
+                    authentications: Vec::new(),
+                    challenge: None,
                 };
             }
         };
 
-        let response = self.simulator.process(transaction, psp);
+        let response = self.connector.authorize(transaction, psp);
         let attempt = build_attempt(&response, 1);
         let latency = response.latency_ms;
+        let status = if response.approved {
+            accepted_status(transaction)
+        } else {
+            AuthorizationStatus::Declined
+        };
 
         RoutingResult {
             transaction_id: transaction.id.clone(),
-            approved: response.approved,
+            approved: status == AuthorizationStatus::Approved,
+            status,
             final_psp: if response.approved {
                 Some(response.psp_name)
             } else {
@@ -149,8 +550,335 @@ impl RoutingEngine {
             attempts: vec![attempt],
             total_attempts: 1,
             total_latency_ms: latency,
+            budget_exhausted: false,
+            matched_rule: None,
+            ineligible_psps: ineligible,
+            trace: None,
+            authentications: Vec::new(),
+            challenge: None,
+        }
+    }
+
+    /// Capture an authorized payment; `amount` defaults to the full
+    /// authorization and may be less (the remainder is released).
+    pub fn capture(
+        &self,
+        payment: &mut Payment,
+        amount: Option<Money>,
+    ) -> Result<PaymentOperation, LifecycleError> {
+        self.execute(payment, OperationKind::Capture, amount)
+    }
+
+    /// Release an authorized payment without collecting funds.
+    pub fn void(&self, payment: &mut Payment) -> Result<PaymentOperation, LifecycleError> {
+        self.execute(payment, OperationKind::Void, None)
+    }
+
+    /// Refund a captured payment; `amount` defaults to everything not yet
+    /// refunded.
+    pub fn refund(
+        &self,
+        payment: &mut Payment,
+        amount: Option<Money>,
+    ) -> Result<PaymentOperation, LifecycleError> {
+        self.execute(payment, OperationKind::Refund, amount)
+    }
+
+    /// Validate a follow-up operation, send it to the payment's PSP and
+    /// record the response.
+    ///
+    /// Invalid transitions and amounts are rejected before any PSP call. A
+    /// PSP rejection is not an error: it is recorded on the payment and
+    /// returned with `approved: false`, leaving the state unchanged.
+    pub fn execute(
+        &self,
+        payment: &mut Payment,
+        kind: OperationKind,
+        amount: Option<Money>,
+    ) -> Result<PaymentOperation, LifecycleError> {
+        let amount = payment.prepare(kind, amount)?;
+        let psp = self
+            .catalog
+            .psp(&payment.psp_id)
+            .ok_or_else(|| LifecycleError::UnknownPsp(payment.psp_id.clone()))?;
+
+        let response = self.connector.execute(payment, psp, kind);
+        let operation = PaymentOperation {
+            kind,
+            amount,
+            approved: response.approved,
+            response_code: response.response_code,
+            decline_reason: response.decline_reason,
+            latency_ms: response.latency_ms,
+        };
+        payment.record(operation.clone());
+        Ok(operation)
+    }
+}
+
+impl<C: AsyncPspConnector + 'static> RoutingEngine<C> {
+    /// Route a transaction like [`route`](RoutingEngine::route), waiting
+    /// on PSPs without blocking.
+    ///
+    /// Each PSP call is cut off after the policy's `attempt_timeout_ms`
+    /// and recorded as a `timed_out` unavailable attempt. With
+    /// `hedge_after_ms`, a PSP that has not answered by then is raced
+    /// against the next one in the order: the responses are taken in the
+    /// order they arrive and the first approval wins at once. A response
+    /// already in hand by then is recorded as `cancelled`; a PSP still
+    /// working is left to answer in the background. Either way a late
+    /// approval is voided in the background. The hedge is marked `hedged`
+    /// and counts toward `max_attempts` like any other attempt.
+    ///
+    /// Latency is measured on the PSPs' reported latency, so simulated
+    /// PSPs, which answer at once, time out and get hedged exactly as live
+    /// ones would. 3-D Secure authentication is not hedged.
+    pub async fn route_async(
+        &self,
+        transaction: &Transaction,
+        strategy: &RoutingStrategy,
+        policy: &RetryPolicy,
+    ) -> RoutingResult {
+        let mut cascade = self.cascade(transaction, strategy, policy, self.cross_border_fallback);
+        self.drive_async(&mut cascade, transaction, policy).await;
+        cascade.finish()
+    }
+
+    /// Resume a routing paused on a 3-D Secure challenge like
+    /// [`complete_challenge`](RoutingEngine::complete_challenge), going on
+    /// as in [`route_async`](RoutingEngine::route_async).
+    pub async fn complete_challenge_async(
+        &self,
+        session: &AuthenticationSession,
+    ) -> Result<RoutingResult, RoutingError> {
+        let psp = self
+            .catalog
+            .psp(&session.psp_id)
+            .ok_or_else(|| RoutingError::UnknownPsp(session.psp_id.clone()))?;
+        let mut cascade = self.resume(session);
+        let response = self
+            .connector
+            .complete_challenge_async(&session.transaction, psp, &session.session_id)
+            .await;
+        if cascade.authenticated(psp, response, None) == Step::Continue {
+            self.drive_async(&mut cascade, &session.transaction, &session.policy)
+                .await;
+        }
+        Ok(cascade.finish())
+    }
+
+    /// The retry loop of [`route_async`](RoutingEngine::route_async):
+    /// send the transaction to each PSP the cascade picks, hedging and
+    /// authenticating the cardholder as called for, until the cascade is
+    /// done.
+    async fn drive_async(&self, cascade: &mut Cascade<'_, C>, transaction: &Transaction, policy: &RetryPolicy) {
+        loop {
+            if let Some((psp, reason)) = cascade.authentication_due() {
+                let response = self.connector.authenticate_async(transaction, &psp).await;
+                if cascade.authenticated(&psp, response, Some(&reason)) == Step::Done {
+                    return;
+                }
+                continue;
+            }
+            let Some(dispatch) = cascade.next_psp(0) else {
+                return;
+            };
+            if dispatch.backoff_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(dispatch.backoff_ms)).await;
+            }
+            let step = match policy.hedge_after_ms {
+                Some(hedge_after_ms) => {
+                    self.hedged_attempt(cascade, transaction, policy, &dispatch, hedge_after_ms)
+                        .await
+                }
+                None => {
+                    let (response, timed_out) = call(&*self.connector, transaction, &dispatch, policy).await;
+                    let latency = response.latency_ms;
+                    cascade.record(&dispatch, response, latency, AttemptFlags { hedged: false, timed_out })
+                }
+            };
+            if step == Step::Done {
+                return;
+            }
+        }
+    }
+
+    /// Send `transaction` to `primary`, and to the next PSP as well if
+    /// `primary` takes longer than `hedge_after_ms`.
+    async fn hedged_attempt(
+        &self,
+        cascade: &mut Cascade<'_, C>,
+        transaction: &Transaction,
+        policy: &RetryPolicy,
+        primary: &Dispatch,
+        hedge_after_ms: u64,
+    ) -> Step {
+        let mut primary_call = self.spawn_call(transaction, primary, policy);
+        let early = tokio::select! {
+            answer = &mut primary_call => Some(answered(answer)),
+            _ = tokio::time::sleep(Duration::from_millis(hedge_after_ms)) => None,
+        };
+        if let Some((response, timed_out)) = &early {
+            if response.latency_ms <= hedge_after_ms {
+                let latency = response.latency_ms;
+                let flags = AttemptFlags { hedged: false, timed_out: *timed_out };
+                return cascade.record(primary, response.clone(), latency, flags);
+            }
+        }
+
+        let Some(hedge) = cascade.next_psp(hedge_after_ms) else {
+            let (response, timed_out) = match early {
+                Some(answer) => answer,
+                None => answered(primary_call.await),
+            };
+            let latency = response.latency_ms;
+            return cascade.record(primary, response, latency, AttemptFlags { hedged: false, timed_out });
+        };
+        let mut hedge_call = self.spawn_call(transaction, &hedge, policy);
+        let primary_leg = Leg { dispatch: primary, hedged: false, sent_ms: 0 };
+        let hedge_leg = Leg { dispatch: &hedge, hedged: true, sent_ms: hedge_after_ms };
+
+        // Take the answer that arrives first; the other may still be on its way
+        let (first, (response, timed_out), second, late) = match early {
+            Some(primary_answer) => {
+                // The primary answered at once, reporting a latency past the
+                // hedge, so order the two by their reported latencies
+                let hedge_answer = answered(hedge_call.await);
+                if hedge_leg.done(&hedge_answer.0) < primary_leg.done(&primary_answer.0) {
+                    (hedge_leg, hedge_answer, primary_leg, Late::Answered(primary_answer))
+                } else {
+                    (primary_leg, primary_answer, hedge_leg, Late::Answered(hedge_answer))
+                }
+            }
+            None => tokio::select! {
+                answer = &mut primary_call => {
+                    (primary_leg, answered(answer), hedge_leg, Late::Pending(hedge_call))
+                }
+                answer = &mut hedge_call => {
+                    (hedge_leg, answered(answer), primary_leg, Late::Pending(primary_call))
+                }
+            },
+        };
+
+        let first_done = first.done(&response);
+        let step = cascade.record(first.dispatch, response, first_done, first.flags(timed_out));
+        if cascade.approved() {
+            if let Late::Answered((response, timed_out)) = &late {
+                cascade.cancel(second.dispatch, response.clone(), second.flags(*timed_out));
+            }
+            self.void_late_approval(transaction, &second.dispatch.psp, late);
+            return Step::Done;
+        }
+        // The second answer was already in flight, so it is recorded even
+        // if the first ended routing
+        let (response, timed_out) = match late {
+            Late::Answered(answer) => answer,
+            Late::Pending(call) => answered(call.await),
+        };
+        let after_first = second.done(&response).saturating_sub(first_done);
+        let second_step = cascade.record(second.dispatch, response, after_first, second.flags(timed_out));
+        if step == Step::Done {
+            Step::Done
+        } else {
+            second_step
         }
     }
+
+    /// Start one PSP call, cut off at the policy's attempt timeout, as a
+    /// task of its own, so it can be left to finish in the background.
+    fn spawn_call(
+        &self,
+        transaction: &Transaction,
+        dispatch: &Dispatch,
+        policy: &RetryPolicy,
+    ) -> JoinHandle<(PspResponse, bool)> {
+        let connector = Arc::clone(&self.connector);
+        let (transaction, dispatch, policy) = (transaction.clone(), dispatch.clone(), policy.clone());
+        tokio::spawn(async move { call(&*connector, &transaction, &dispatch, &policy).await })
+    }
+
+    /// Release in the background the funds a late answer put on hold, once
+    /// it has arrived, if it was an approval.
+    fn void_late_approval(&self, transaction: &Transaction, psp: &PspConfig, late: Late) {
+        let connector = Arc::clone(&self.connector);
+        let (transaction, psp) = (transaction.clone(), psp.clone());
+        tokio::spawn(async move {
+            let (response, _) = match late {
+                Late::Answered(answer) => answer,
+                Late::Pending(call) => answered(call.await),
+            };
+            if response.approved {
+                let payment = Payment::authorized_by(&transaction, &build_attempt(&response, 0));
+                connector.execute_async(&payment, &psp, OperationKind::Void).await;
+            }
+        });
+    }
+}
+
+/// One PSP call, cut off at the policy's attempt timeout. Also returns
+/// whether it timed out.
+async fn call<C: AsyncPspConnector + ?Sized>(
+    connector: &C,
+    transaction: &Transaction,
+    dispatch: &Dispatch,
+    policy: &RetryPolicy,
+) -> (PspResponse, bool) {
+    let psp = &dispatch.psp;
+    let sent = dispatch.transaction(transaction);
+    let call = match dispatch.retry {
+        0 => connector.authorize_async(&sent, psp),
+        retry => connector.resend_async(&sent, psp, retry),
+    };
+    let Some(timeout_ms) = policy.attempt_timeout_ms else {
+        return (call.await, false);
+    };
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), call).await {
+        Ok(response) => cascade::apply_timeout(policy, psp, response),
+        Err(_) => (connector::unavailable(psp, timeout_ms), true),
+    }
+}
+
+/// The answer of a spawned PSP call, passing on a panic in the connector.
+fn answered(joined: Result<(PspResponse, bool), JoinError>) -> (PspResponse, bool) {
+    joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// One of the two calls of a hedged attempt.
+struct Leg<'a> {
+    dispatch: &'a Dispatch,
+    hedged: bool,
+    /// When the call was sent, in milliseconds from the start of the attempt.
+    sent_ms: u64,
+}
+
+impl Leg<'_> {
+    /// When `response` arrived, in milliseconds from the start of the
+    /// attempt.
+    fn done(&self, response: &PspResponse) -> u64 {
+        self.sent_ms + response.latency_ms
+    }
+
+    fn flags(&self, timed_out: bool) -> AttemptFlags {
+        AttemptFlags { hedged: self.hedged, timed_out }
+    }
+}
+
+/// The call of a hedged attempt that did not answer first.
+enum Late {
+    /// Its answer was already in hand.
+    Answered((PspResponse, bool)),
+    /// It is still on its way.
+    Pending(JoinHandle<(PspResponse, bool)>),
+}
+
+/// Outcome of a PSP accepting the transaction: vouchers and bank
+/// transfers stay pending until the customer pays.
+fn accepted_status(transaction: &Transaction) -> AuthorizationStatus {
+    if transaction.payment_method.settles_asynchronously() {
+        AuthorizationStatus::Pending
+    } else {
+        AuthorizationStatus::Approved
+    }
 }
 
 /// Build a [`RoutingAttempt`] from a PSP response.
@@ -159,42 +887,72 @@ fn build_attempt(response: &PspResponse, attempt_number: usize) -> RoutingAttemp
         psp_id: response.psp_id.clone(),
         psp_name: response.psp_name.clone(),
         approved: response.approved,
+        response_code: response.response_code.clone(),
         decline_reason: response.decline_reason.clone(),
         latency_ms: response.latency_ms,
         attempt_number,
+        retry_type: RetryType::Initial,
+        backoff_ms: 0,
+        hedged: false,
+        timed_out: false,
+        cancelled: false,
+        cross_border: false,
+        three_ds: None,
+        credential: None,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::transaction::{Country, Currency, Transaction};
+    use crate::time::Timestamp;
+    use crate::models::money::Money;
+    use crate::engine::retry::{RetryAction, RetryRule};
+    use crate::models::psp::DeclineClass;
+    use crate::models::payment::PaymentState;
+    use crate::models::authentication::{ThreeDsFlow, ThreeDsResponse, ThreeDsStatus};
+    use crate::models::transaction::{Country, Currency, PaymentMethod, Transaction};
 
     fn make_transaction(country: Country) -> Transaction {
-        let currency = match country {
-            Country::Brazil => Currency::BRL,
-            Country::Mexico => Currency::MXN,
-            Country::Colombia => Currency::COP,
+        let amount = match country.name() {
+            "Brazil" => Money::new(15000, Currency::new("BRL")),
+            "Mexico" => Money::new(15000, Currency::new("MXN")),
+            "Colombia" => Money::new(150, Currency::new("COP")),
+            "Chile" => Money::new(150, Currency::new("CLP")),
+            "Peru" => Money::new(15000, Currency::new("PEN")),
+            "Argentina" => Money::new(15000, Currency::new("ARS")),
+            _ => Money::new(15000, Currency::USD),
         };
         Transaction {
             id: "txn_test_001".to_string(),
-            amount: 150.0,
-            currency,
+            amount,
             country,
-            card_bin: "411111".to_string(),
-            card_last4: "1234".to_string(),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some("411111".to_string()),
+            card_last4: Some("1234".to_string()),
+            bin_info: None,
+            installments: None,
             customer_id: "cust_001".to_string(),
-            timestamp: "2025-01-15T10:00:00Z".to_string(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
         }
     }
 
     #[test]
     fn test_route_returns_result_for_each_country() {
-        let engine = RoutingEngine::new(PspSimulator::new());
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
 
-        for country in [Country::Brazil, Country::Mexico, Country::Colombia] {
+        for country in [
+            Country::new("Brazil"),
+            Country::new("Mexico"),
+            Country::new("Colombia"),
+            Country::new("Chile"),
+            Country::new("Peru"),
+            Country::new("Argentina"),
+        ] {
             let txn = make_transaction(country.clone());
-            let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals);
+            let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
 
             assert_eq!(result.transaction_id, "txn_test_001");
             assert!(result.total_attempts >= 1, "Should have at least 1 attempt");
@@ -205,10 +963,1047 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_route_only_uses_psps_supporting_the_payment_method() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let mut txn = make_transaction(Country::new("Mexico"));
+        txn.payment_method = PaymentMethod::Oxxo;
+        txn.card_bin = None;
+        txn.card_last4 = None;
+
+        for strategy in [
+            RoutingStrategy::OptimizeForApprovals,
+            RoutingStrategy::OptimizeForCost,
+            RoutingStrategy::Balanced,
+        ] {
+            let result = engine.route(&txn, &strategy, &RetryPolicy::default());
+            for attempt in &result.attempts {
+                let psp = engine.catalog().psp(&attempt.psp_id).unwrap();
+                assert!(psp.supports(&PaymentMethod::Oxxo), "{} cannot take OXXO", psp.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_voucher_payments_end_pending_not_approved() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+
+        let mut saw_pending = false;
+        for i in 0..20 {
+            let mut txn = make_transaction(Country::new("Brazil"));
+            txn.id = format!("txn_boleto_{i}");
+            txn.customer_id = format!("cust_{i}");
+            txn.payment_method = PaymentMethod::Boleto;
+            txn.card_bin = None;
+            txn.card_last4 = None;
+
+            let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+            assert!(!result.approved, "boleto must never be approved synchronously");
+            if result.status == AuthorizationStatus::Pending {
+                assert!(result.final_psp.is_some());
+                saw_pending = true;
+            }
+        }
+        assert!(saw_pending, "some boleto payments should be accepted as pending");
+    }
+
+    #[test]
+    fn test_route_without_capable_psp_declines_without_attempts() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let mut txn = make_transaction(Country::new("Chile"));
+        txn.payment_method = PaymentMethod::Pix;
+
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+        assert_eq!(result.status, AuthorizationStatus::Declined);
+        assert!(result.attempts.is_empty());
+    }
+
+    #[test]
+    fn test_route_skips_and_lists_ineligible_psps() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let mut txn = make_transaction(Country::new("Mexico"));
+        txn.amount = Money::new(900_000, Currency::new("MXN"));
+        for strategy in [RoutingStrategy::OptimizeForApprovals, RoutingStrategy::OptimizeForCost] {
+            let result = engine.route(&txn, &strategy, &RetryPolicy::default());
+            assert!(result.attempts.iter().all(|a| a.psp_id != "psp_mx_3"));
+            assert_eq!(result.ineligible_psps.len(), 1);
+            assert_eq!(result.ineligible_psps[0].psp_id, "psp_mx_3");
+        }
+        let single = engine.route_no_retry(&txn);
+        assert_eq!(single.ineligible_psps.len(), 1);
+        assert!(engine.check_eligibility(&txn).is_ok());
+    }
+
+    #[test]
+    fn test_check_eligibility_errors_when_no_psp_can_take_it() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let mut txn = make_transaction(Country::new("Mexico"));
+        txn.payment_method = PaymentMethod::Pix;
+        let Err(RoutingError::NoEligiblePsp { ineligible, .. }) = engine.check_eligibility(&txn) else {
+            panic!("Pix should not be routable in Mexico");
+        };
+        assert_eq!(ineligible.len(), 3);
+        let message = engine.check_eligibility(&txn).unwrap_err().to_string();
+        assert!(message.contains("SR Pago does not process PIX"), "{message}");
+    }
+
+    #[test]
+    fn test_route_only_uses_psps_offering_the_installment_plan() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+
+        for i in 0..50 {
+            let mut txn = make_transaction(Country::new("Brazil"));
+            txn.id = format!("txn_installments_{i}");
+            txn.card_last4 = Some(format!("{i:04}"));
+            txn.installments = Some(10);
+            let result = engine.route(&txn, &RoutingStrategy::OptimizeForCost, &RetryPolicy::default());
+            assert!(result.attempts.iter().all(|a| a.psp_id != "psp_br_3"));
+        }
+
+        let mut txn = make_transaction(Country::new("Colombia"));
+        txn.installments = Some(6);
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+        assert!(result.attempts.is_empty());
+        assert_eq!(result.status, AuthorizationStatus::Declined);
+    }
+
+    /// Route cards until one ends with the given decline under the default
+    /// policy, returning the transaction.
+    fn declined_with(engine: &RoutingEngine, reason: DeclineReason) -> Transaction {
+        (0..2000)
+            .map(|i| {
+                let mut txn = make_transaction(Country::new("Brazil"));
+                txn.id = format!("txn_policy_{i}");
+                txn.card_last4 = Some(format!("{i:04}"));
+                txn
+            })
+            .find(|txn| {
+                let result = engine.route(txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+                result.attempts.first().and_then(|a| a.decline_reason.as_ref()) == Some(&reason)
+                    && result.attempts.len() > 1
+            })
+            .expect("some card should be declined with the reason")
+    }
+
+    #[test]
+    fn test_policy_rule_stops_on_reason() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = declined_with(&engine, DeclineReason::SuspectedFraud);
+        let policy = RetryPolicy {
+            rules: vec![RetryRule {
+                reason: DeclineReason::SuspectedFraud,
+                action: RetryAction::Stop,
+            }],
+            ..RetryPolicy::default()
+        };
+
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy);
+        assert!(!result.approved);
+        assert_eq!(result.attempts.len(), 1);
+        assert_eq!(result.attempts[0].decline_reason, Some(DeclineReason::SuspectedFraud));
+    }
+
+    #[test]
+    fn test_policy_retry_same_psp_resends_before_moving_on() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = declined_with(&engine, DeclineReason::DoNotHonor);
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            max_same_psp_retries: 2,
+            rules: vec![RetryRule {
+                reason: DeclineReason::DoNotHonor,
+                action: RetryAction::RetrySamePsp,
+            }],
+            ..RetryPolicy::default()
+        };
+
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy);
+        let first_psp = &result.attempts[0].psp_id;
+        let on_first = result.attempts.iter().take_while(|a| &a.psp_id == first_psp).count();
+        assert!(on_first > 1 && on_first <= 3, "{on_first} attempts on {first_psp}");
+    }
+
+    #[test]
+    fn test_same_psp_resends_back_off_until_transient_decline_clears() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let policy = RetryPolicy {
+            max_same_psp_retries: 3,
+            backoff: retry::Backoff { jitter: 0.0, ..retry::Backoff::default() },
+            rules: vec![RetryRule {
+                reason: DeclineReason::IssuerUnavailable,
+                action: RetryAction::RetrySamePsp,
+            }],
+            ..RetryPolicy::default()
+        };
+
+        let result = (0..2000)
+            .map(|i| {
+                let mut txn = make_transaction(Country::new("Brazil"));
+                txn.id = format!("txn_backoff_{i}");
+                txn.card_last4 = Some(format!("{i:04}"));
+                engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy)
+            })
+            .find(|result| {
+                result.attempts[0].decline_reason == Some(DeclineReason::IssuerUnavailable)
+                    && result.attempts.iter().any(|a| a.retry_type == RetryType::SamePsp && a.approved)
+            })
+            .expect("some issuer outage should clear on a resend");
+
+        let first = &result.attempts[0];
+        assert_eq!((first.retry_type, first.backoff_ms), (RetryType::Initial, 0));
+        let resends = &result.attempts[1..];
+        assert!(resends.iter().all(|a| a.psp_id == first.psp_id && a.retry_type == RetryType::SamePsp));
+        let backoffs: Vec<u64> = resends.iter().map(|a| a.backoff_ms).collect();
+        assert_eq!(backoffs, [100, 200, 400][..resends.len()]);
+        assert_eq!(result.final_psp.as_ref(), Some(&first.psp_name));
+
+        let waited: u64 = result.attempts.iter().map(|a| a.latency_ms + a.backoff_ms).sum();
+        assert_eq!(result.total_latency_ms, waited);
+    }
+
+    #[test]
+    fn test_policy_max_attempts_bounds_declines() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let policy = RetryPolicy { max_attempts: 1, ..RetryPolicy::default() };
+
+        for i in 0..100 {
+            let mut txn = make_transaction(Country::new("Mexico"));
+            txn.id = format!("txn_budget_{i}");
+            txn.card_last4 = Some(format!("{i:04}"));
+            let result = engine.route(&txn, &RoutingStrategy::Balanced, &policy);
+            assert!(result.total_attempts <= 1);
+        }
+    }
+
+    #[test]
+    fn test_policy_caps_unavailable_cascades() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let policy = RetryPolicy {
+            max_unavailable_cascades: Some(0),
+            ..RetryPolicy::default()
+        };
+
+        let mut capped = 0;
+        for i in 0..300 {
+            let mut txn = make_transaction(Country::new("Colombia"));
+            txn.id = format!("txn_cascade_{i}");
+            txn.card_last4 = Some(format!("{i:04}"));
+            let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy);
+            let unavailable = result
+                .attempts
+                .iter()
+                .filter(|a| a.decline_reason == Some(DeclineReason::PspUnavailable))
+                .count();
+            assert!(unavailable <= 1);
+            if unavailable == 1 {
+                assert_eq!(
+                    result.attempts.last().unwrap().decline_reason,
+                    Some(DeclineReason::PspUnavailable)
+                );
+                capped += 1;
+            }
+        }
+        assert!(capped > 0, "some PSP should have been unavailable");
+    }
+
+    #[test]
+    fn test_latency_budget_skips_psps_that_do_not_fit() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        // Brazil's expected latencies: Cielo 200ms, PagSeguro 300ms, Stone 450ms.
+        let policy = RetryPolicy {
+            latency_budget_ms: Some(450),
+            ..RetryPolicy::default()
+        };
+
+        let mut exhausted = 0;
+        for i in 0..200 {
+            let mut txn = make_transaction(Country::new("Brazil"));
+            txn.id = format!("txn_budget_{i}");
+            txn.card_last4 = Some(format!("{i:04}"));
+            let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy);
+            assert!(result.attempts.iter().all(|a| a.psp_id != "psp_br_3"));
+            if result.budget_exhausted {
+                assert!(!result.approved);
+                exhausted += 1;
+            }
+        }
+        assert!(exhausted > 0, "some cascades should run out of budget");
+    }
+
+    #[test]
+    fn test_latency_budget_below_every_psp_makes_no_attempts() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let policy = RetryPolicy {
+            latency_budget_ms: Some(100),
+            ..RetryPolicy::default()
+        };
+        let txn = make_transaction(Country::new("Mexico"));
+
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy);
+        assert!(result.attempts.is_empty());
+        assert!(result.budget_exhausted);
+        assert_eq!(result.status, AuthorizationStatus::Declined);
+
+        let unlimited = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+        assert!(!unlimited.budget_exhausted);
+    }
+
+    #[test]
+    fn test_matching_rule_shapes_psp_order() {
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[rules]]
+            name = "stone-only"
+            when = { customer_ids = ["cust_001"] }
+            then = { psp_order = ["psp_br_3"], exclusive = true }
+
+            [[rules]]
+            name = "cielo-first"
+            when = { countries = ["Brazil"] }
+            then = { psp_order = ["psp_br_2"] }
+            "#,
+        )
+        .unwrap();
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin()).with_rules(rules);
+        let policy = RetryPolicy::default();
+
+        let txn = make_transaction(Country::new("Brazil"));
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy);
+        assert_eq!(result.matched_rule.as_deref(), Some("stone-only"));
+        assert!(result.attempts.iter().all(|a| a.psp_id == "psp_br_3"));
+
+        let mut other = txn.clone();
+        other.customer_id = "cust_002".to_string();
+        let result = engine.route(&other, &RoutingStrategy::OptimizeForApprovals, &policy);
+        assert_eq!(result.matched_rule.as_deref(), Some("cielo-first"));
+        assert_eq!(result.attempts[0].psp_id, "psp_br_2");
+
+        let mut mexico = make_transaction(Country::new("Mexico"));
+        mexico.customer_id = "cust_002".to_string();
+        let result = engine.route(&mexico, &RoutingStrategy::OptimizeForApprovals, &policy);
+        assert_eq!(result.matched_rule, None);
+    }
+
+    #[test]
+    fn test_adaptive_routing_learns_from_attempts() {
+        let bandit = Arc::new(AdaptiveRouter::new(bandit::BanditConfig::default()));
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin())
+            .with_bandit(bandit.clone());
+        let policy = RetryPolicy::default();
+
+        let mut attempts = 0;
+        for i in 0..100 {
+            let mut txn = make_transaction(Country::new("Mexico"));
+            txn.id = format!("txn_adaptive_{i}");
+            txn.card_last4 = Some(format!("{i:04}"));
+            let result = engine.route(&txn, &RoutingStrategy::Adaptive, &policy);
+            attempts += result
+                .attempts
+                .iter()
+                .filter(|a| a.decline_reason != Some(DeclineReason::PspUnavailable))
+                .count() as u64;
+        }
+
+        let observed: u64 = bandit.snapshot().arms.iter().map(|a| a.observations).sum();
+        assert_eq!(observed, attempts);
+    }
+
+    #[test]
+    fn test_open_breaker_keeps_psp_out_of_routing() {
+        let registry = Arc::new(BreakerRegistry::new(breaker::BreakerConfig::default()));
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin())
+            .with_breakers(registry.clone());
+        let txn = make_transaction(Country::new("Brazil"));
+        for _ in 0..breaker::DEFAULT_FAILURE_THRESHOLD {
+            registry.record("psp_br_1", false, txn.timestamp);
+        }
+
+        for i in 0..50 {
+            let mut txn = txn.clone();
+            txn.id = format!("txn_breaker_{i}");
+            let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+            assert!(result.attempts.iter().all(|a| a.psp_id != "psp_br_1"));
+        }
+
+        // Every PSP that was called has a breaker with its outcomes
+        let snapshot = registry.snapshot();
+        assert!(snapshot["psp_br_2"].calls > 0);
+        assert_eq!(snapshot["psp_br_1"].state, BreakerState::Open);
+    }
+
+    #[test]
+    fn test_recovery_reranks_remaining_psps_after_a_decline() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin())
+            .with_recovery(Arc::new(RecoveryTracker::default()));
+        let catalog = PspCatalog::builtin();
+        let remaining = || vec![catalog.psp("psp_br_1").unwrap().clone(), catalog.psp("psp_br_3").unwrap().clone()];
+        let ids = |psps: &[PspConfig]| psps.iter().map(|p| p.id.clone()).collect::<Vec<_>>();
+
+        // PagSeguro's own declines are mostly issuer outages, Stone's are not
+        let mut psps = remaining();
+        engine.rerank_remaining(&DeclineReason::IssuerUnavailable, &mut psps, None, &HashMap::new());
+        assert_eq!(ids(&psps), ["psp_br_3", "psp_br_1"]);
+
+        // A half-open PSP stays last whatever its recovery rate
+        let circuits = HashMap::from([("psp_br_3".to_string(), BreakerState::HalfOpen)]);
+        let mut psps = remaining();
+        engine.rerank_remaining(&DeclineReason::IssuerUnavailable, &mut psps, None, &circuits);
+        assert_eq!(ids(&psps), ["psp_br_1", "psp_br_3"]);
+    }
+
+    #[test]
+    fn test_recovery_learns_from_attempts_after_declines() {
+        let tracker = Arc::new(RecoveryTracker::default());
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin())
+            .with_recovery(tracker.clone());
+        let mut retried = 0;
+        for i in 0..60 {
+            let mut txn = make_transaction(Country::new("Brazil"));
+            txn.id = format!("txn_recovery_{i}");
+            txn.card_last4 = Some(format!("{:04}", 1000 + i));
+            let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+            // Every answered attempt after the first follows a decline
+            let answered = result
+                .attempts
+                .iter()
+                .filter(|a| a.decline_reason != Some(DeclineReason::PspUnavailable))
+                .count();
+            retried += answered.saturating_sub(1);
+        }
+
+        let snapshot = tracker.snapshot();
+        assert!(!snapshot.is_empty());
+        assert!(snapshot.iter().all(|r| r.recoveries <= r.attempts));
+        assert_eq!(snapshot.iter().map(|r| r.attempts as usize).sum::<usize>(), retried);
+    }
+
+    /// Answers every call with the same outcome.
+    struct Scripted(Option<DeclineReason>);
+
+    impl PspConnector for Scripted {
+        fn authorize(&self, _transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+            crate::simulator::respond(psp, 50, self.0.clone())
+        }
+
+        fn execute(&self, _payment: &Payment, psp: &PspConfig, _kind: OperationKind) -> PspResponse {
+            crate::simulator::respond(psp, 50, self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_engine_routes_through_registered_connectors() {
+        let registry = crate::connector::ConnectorRegistry::<dyn PspConnector>::new()
+            .with_fallback(Arc::new(Scripted(Some(DeclineReason::DoNotHonor))))
+            .with_connector("psp_br_3", Arc::new(Scripted(None)));
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin());
+
+        let result = engine.route(
+            &make_transaction(Country::new("Brazil")),
+            &RoutingStrategy::OptimizeForApprovals,
+            &RetryPolicy::default(),
+        );
+        let ids: Vec<&str> = result.attempts.iter().map(|a| a.psp_id.as_str()).collect();
+        assert_eq!(ids, ["psp_br_2", "psp_br_1", "psp_br_3"]);
+        assert_eq!(result.final_psp.as_deref(), Some("Stone"));
+        assert_eq!(result.total_latency_ms, 150);
+    }
+
+    #[test]
+    fn test_cross_border_fallback_is_tried_last_and_only_when_opted_in() {
+        let registry = || {
+            crate::connector::ConnectorRegistry::<dyn PspConnector>::new()
+                .with_fallback(Arc::new(Scripted(Some(DeclineReason::DoNotHonor))))
+                .with_connector("psp_us_1", Arc::new(Scripted(None)))
+        };
+        let txn = make_transaction(Country::new("Brazil"));
+        let strategy = RoutingStrategy::OptimizeForApprovals;
+        let policy = RetryPolicy::default();
+
+        let local_only = RoutingEngine::new(registry(), PspCatalog::builtin()).route(&txn, &strategy, &policy);
+        assert!(!local_only.approved);
+        assert!(local_only.attempts.iter().all(|a| !a.cross_border));
+
+        // Worldpay's catalog rate would rank it second, and the default
+        // policy stops after three declines
+        let engine = RoutingEngine::new(registry(), PspCatalog::builtin()).with_cross_border_fallback(true);
+        let result = engine.route(&txn, &strategy, &policy);
+        let ids: Vec<&str> = result.attempts.iter().map(|a| a.psp_id.as_str()).collect();
+        assert_eq!(ids, ["psp_br_2", "psp_br_1", "psp_br_3", "psp_us_1"]);
+        assert!(result.approved);
+        assert_eq!(result.final_psp.as_deref(), Some("Worldpay"));
+        assert!(result.attempts[3].cross_border);
+        assert!(engine.check_eligibility(&txn).unwrap().eligible.iter().any(|p| p.id == "psp_us_1"));
+    }
+
+    #[test]
+    fn test_cross_border_fallback_respects_hard_declines() {
+        let registry = crate::connector::ConnectorRegistry::<dyn PspConnector>::new()
+            .with_fallback(Arc::new(Scripted(Some(DeclineReason::StolenCard))))
+            .with_connector("psp_us_1", Arc::new(Scripted(None)));
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin()).with_cross_border_fallback(true);
+
+        let result = engine.route(
+            &make_transaction(Country::new("Brazil")),
+            &RoutingStrategy::OptimizeForApprovals,
+            &RetryPolicy::default(),
+        );
+        assert!(!result.approved);
+        assert_eq!(result.attempts.len(), 1);
+    }
+
+    /// Declines cards with `SuspectedFraud` unless the PSP authenticated
+    /// the cardholder, and answers 3-D Secure as scripted.
+    struct ThreeDsScripted {
+        authentication: ThreeDsStatus,
+        challenge: ThreeDsStatus,
+    }
+
+    impl PspConnector for ThreeDsScripted {
+        fn authorize(&self, transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+            let outcome = match &transaction.three_ds {
+                Some(authentication) if authentication.psp_id == psp.id => None,
+                _ => Some(DeclineReason::SuspectedFraud),
+            };
+            crate::simulator::respond(psp, 50, outcome)
+        }
+
+        fn execute(&self, _payment: &Payment, psp: &PspConfig, _kind: OperationKind) -> PspResponse {
+            crate::simulator::respond(psp, 50, None)
+        }
+
+        fn authenticate(&self, _transaction: &Transaction, psp: &PspConfig) -> ThreeDsResponse {
+            ThreeDsResponse {
+                psp_id: psp.id.clone(),
+                flow: ThreeDsFlow::Frictionless,
+                status: self.authentication,
+                session_id: (self.authentication == ThreeDsStatus::ChallengeRequired).then(|| "sess_1".into()),
+                latency_ms: 30,
+            }
+        }
+
+        fn complete_challenge(&self, _transaction: &Transaction, psp: &PspConfig, session_id: &str) -> ThreeDsResponse {
+            ThreeDsResponse {
+                psp_id: psp.id.clone(),
+                flow: ThreeDsFlow::Challenge,
+                status: self.challenge,
+                session_id: Some(session_id.to_string()),
+                latency_ms: 20,
+            }
+        }
+    }
+
+    fn three_ds_engine(authentication: ThreeDsStatus, challenge: ThreeDsStatus) -> RoutingEngine<ThreeDsScripted> {
+        RoutingEngine::new(ThreeDsScripted { authentication, challenge }, PspCatalog::builtin())
+    }
+
+    #[test]
+    fn test_frictionless_authentication_resends_to_the_same_psp() {
+        let engine = three_ds_engine(ThreeDsStatus::Authenticated, ThreeDsStatus::Failed).with_explain(true);
+        let result = engine.route(
+            &make_transaction(Country::new("Brazil")),
+            &RoutingStrategy::OptimizeForApprovals,
+            &RetryPolicy::default(),
+        );
+
+        assert_eq!(result.status, AuthorizationStatus::Approved);
+        let attempts: Vec<(&str, RetryType, Option<ThreeDsFlow>)> = result
+            .attempts
+            .iter()
+            .map(|a| (a.psp_id.as_str(), a.retry_type, a.three_ds))
+            .collect();
+        assert_eq!(
+            attempts,
+            [
+                ("psp_br_2", RetryType::Initial, None),
+                ("psp_br_2", RetryType::SamePsp, Some(ThreeDsFlow::Frictionless)),
+            ]
+        );
+        assert_eq!(result.total_attempts, 2);
+        assert_eq!(result.total_latency_ms, 50 + 30 + 50);
+        assert_eq!(result.authentications.len(), 1);
+        let decision = &result.trace.unwrap().decisions[0];
+        assert_eq!(decision.action, RetryAction::Authenticate);
+        assert_eq!(decision.next, crate::models::routing::RetryStep::Authenticated);
+    }
+
+    #[test]
+    fn test_challenge_pauses_routing_until_completed() {
+        let engine = three_ds_engine(ThreeDsStatus::ChallengeRequired, ThreeDsStatus::Authenticated);
+        let txn = make_transaction(Country::new("Brazil"));
+        let paused = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+
+        assert_eq!(paused.status, AuthorizationStatus::ChallengeRequired);
+        assert!(!paused.approved);
+        assert!(Payment::from_authorization(&txn, &paused).is_err());
+        let session = paused.challenge.expect("paused on a challenge");
+        assert_eq!((session.psp_id.as_str(), session.session_id.as_str()), ("psp_br_2", "sess_1"));
+        assert_eq!(session.strategy, RoutingStrategy::OptimizeForApprovals);
+
+        let result = engine.complete_challenge(&session).unwrap();
+        assert!(result.approved);
+        assert_eq!(result.final_psp.as_deref(), Some("Cielo"));
+        assert_eq!(result.attempts.len(), 2);
+        assert_eq!(result.attempts[1].three_ds, Some(ThreeDsFlow::Challenge));
+        assert_eq!(result.total_attempts, 2);
+        assert_eq!(result.total_latency_ms, 50 + 30 + 20 + 50);
+        assert_eq!(result.authentications.len(), 2);
+        assert!(result.challenge.is_none());
+
+        let failed = three_ds_engine(ThreeDsStatus::ChallengeRequired, ThreeDsStatus::Failed)
+            .complete_challenge(&session)
+            .unwrap();
+        assert_eq!(failed.status, AuthorizationStatus::Declined);
+        assert_eq!(failed.attempts.len(), 1);
+
+        // Resuming ranks the PSPs again: rules in force now apply, and the
+        // PSP already tried is not sent the transaction twice
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[rules]]
+            name = "no-pagseguro"
+            when = { countries = ["Brazil"] }
+            then = { exclude = ["psp_br_1"] }
+            "#,
+        )
+        .unwrap();
+        let unavailable = three_ds_engine(ThreeDsStatus::ChallengeRequired, ThreeDsStatus::Unavailable)
+            .with_rules(rules)
+            .complete_challenge(&session)
+            .unwrap();
+        let tried: Vec<&str> = unavailable.attempts.iter().map(|a| a.psp_id.as_str()).collect();
+        assert_eq!(tried, ["psp_br_2", "psp_br_3"]);
+        assert_eq!(unavailable.matched_rule.as_deref(), Some("no-pagseguro"));
+
+        let unknown = AuthenticationSession { psp_id: "psp_xx_9".into(), ..session };
+        assert_eq!(engine.complete_challenge(&unknown).unwrap_err(), RoutingError::UnknownPsp("psp_xx_9".into()));
+    }
+
+    #[tokio::test]
+    async fn test_async_challenge_completes_like_the_sync_one() {
+        let engine = three_ds_engine(ThreeDsStatus::ChallengeRequired, ThreeDsStatus::Authenticated);
+        let txn = make_transaction(Country::new("Brazil"));
+        let paused = engine
+            .route_async(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default())
+            .await;
+        let session = paused.challenge.expect("paused on a challenge");
+
+        let result = engine.complete_challenge_async(&session).await.unwrap();
+        assert!(result.approved);
+        assert_eq!(result.final_psp.as_deref(), Some("Cielo"));
+        assert_eq!(result.attempts[1].three_ds, Some(ThreeDsFlow::Challenge));
+        assert_eq!(result.total_latency_ms, 50 + 30 + 20 + 50);
+    }
+
+    #[test]
+    fn test_psps_without_three_ds_move_on() {
+        let engine = three_ds_engine(ThreeDsStatus::Authenticated, ThreeDsStatus::Authenticated);
+        let result = engine.route(
+            &make_transaction(Country::new("Colombia")),
+            &RoutingStrategy::OptimizeForApprovals,
+            &RetryPolicy::default(),
+        );
+        assert!(!result.approved);
+        assert_eq!(result.attempts.len(), 3);
+        assert!(result.authentications.is_empty());
+    }
+
+    /// Declines cards as expired unless sent with a refreshed credential.
+    struct Reissued;
+
+    impl PspConnector for Reissued {
+        fn authorize(&self, transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+            let outcome = match transaction.credential {
+                Some(_) => None,
+                None => Some(DeclineReason::CardExpired),
+            };
+            crate::simulator::respond(psp, 50, outcome)
+        }
+
+        fn execute(&self, _payment: &Payment, psp: &PspConfig, _kind: OperationKind) -> PspResponse {
+            crate::simulator::respond(psp, 50, None)
+        }
+    }
+
+    #[test]
+    fn test_expired_card_is_resent_with_its_refreshed_credential() {
+        use crate::credentials::AccountUpdater;
+        use crate::models::credential::CredentialSource;
+        use crate::models::routing::RetryStep;
+
+        let txn = make_transaction(Country::new("Brazil"));
+        let strategy = RoutingStrategy::OptimizeForApprovals;
+        let policy = RetryPolicy::default();
+
+        // Without a credential store the hard decline still fails fast
+        let result = RoutingEngine::new(Reissued, PspCatalog::builtin()).route(&txn, &strategy, &policy);
+        assert!(!result.approved);
+        assert_eq!(result.attempts.len(), 1);
+
+        // A credential the transaction arrives with is not the store's,
+        // so it is not sent
+        let forged = Transaction {
+            credential: Some(crate::models::credential::CardCredential {
+                source: CredentialSource::NetworkToken,
+                reference: "ntk_forged".into(),
+                expiry: "12/99".into(),
+            }),
+            ..txn.clone()
+        };
+        let result = RoutingEngine::new(Reissued, PspCatalog::builtin()).route(&forged, &strategy, &policy);
+        assert!(!result.approved);
+        assert_eq!(result.attempts.len(), 1);
+        assert_eq!(result.attempts[0].credential, None);
+
+        let store = Arc::new(CredentialStore::new(AccountUpdater::new(1.0)));
+        let engine = RoutingEngine::new(Reissued, PspCatalog::builtin())
+            .with_credentials(store.clone())
+            .with_explain(true);
+        let result = engine.route(&txn, &strategy, &policy);
+        assert!(result.approved);
+        let attempts: Vec<(&str, RetryType, Option<CredentialSource>)> = result
+            .attempts
+            .iter()
+            .map(|a| (a.psp_id.as_str(), a.retry_type, a.credential))
+            .collect();
+        assert_eq!(
+            attempts,
+            [
+                ("psp_br_2", RetryType::Initial, None),
+                ("psp_br_2", RetryType::SamePsp, Some(CredentialSource::AccountUpdater)),
+            ]
+        );
+        let decision = &result.trace.unwrap().decisions[0];
+        assert_eq!(decision.action, RetryAction::RefreshCredential);
+        assert_eq!(decision.next, RetryStep::CredentialRefreshed);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_network_token_rescues_a_returning_customer() {
+        use crate::credentials::AccountUpdater;
+        use crate::models::credential::CredentialSource;
+        use crate::models::routing::RetryStep;
+
+        let txn = make_transaction(Country::new("Mexico"));
+        let store = Arc::new(CredentialStore::new(AccountUpdater::new(0.0)));
+        let engine = RoutingEngine::new(Reissued, PspCatalog::builtin())
+            .with_credentials(store.clone())
+            .with_explain(true);
+
+        // Unknown to the account updater: only one attempt, stopped
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+        assert_eq!(result.attempts.len(), 1);
+        assert_eq!(result.trace.unwrap().decisions[0].next, RetryStep::Stopped);
+
+        store.provision_token(&txn);
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+        assert!(result.approved);
+        assert_eq!(result.attempts[1].credential, Some(CredentialSource::NetworkToken));
+    }
+
+    #[test]
+    fn test_approved_cards_get_a_network_token() {
+        let store = Arc::new(CredentialStore::default());
+        let engine = RoutingEngine::new(Scripted(None), PspCatalog::builtin()).with_credentials(store.clone());
+        let txn = make_transaction(Country::new("Brazil"));
+        engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+
+        let key = crate::credentials::CardKey::of(&txn).unwrap();
+        assert_eq!(
+            store.credential(&key).map(|c| c.source),
+            Some(crate::models::credential::CredentialSource::NetworkToken)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_async_route_authenticates_like_sync() {
+        let engine = three_ds_engine(ThreeDsStatus::Authenticated, ThreeDsStatus::Failed);
+        let txn = make_transaction(Country::new("Mexico"));
+        let sync = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+        let result = engine
+            .route_async(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default())
+            .await;
+        assert!(result.approved);
+        assert_eq!(result.attempts.len(), sync.attempts.len());
+        assert_eq!(result.attempts[1].three_ds, Some(ThreeDsFlow::Frictionless));
+    }
+
+    /// Answers every call with the same outcome and reported latency, and
+    /// counts the voids it is sent.
+    struct Delayed {
+        latency_ms: u64,
+        outcome: Option<DeclineReason>,
+        voids: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Delayed {
+        fn new(latency_ms: u64, outcome: Option<DeclineReason>) -> Arc<Delayed> {
+            Arc::new(Delayed { latency_ms, outcome, voids: Default::default() })
+        }
+
+        fn voids(&self) -> usize {
+            self.voids.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl PspConnector for Delayed {
+        fn authorize(&self, _transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+            crate::simulator::respond(psp, self.latency_ms, self.outcome.clone())
+        }
+
+        fn execute(&self, _payment: &Payment, psp: &PspConfig, kind: OperationKind) -> PspResponse {
+            if kind == OperationKind::Void {
+                self.voids.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            crate::simulator::respond(psp, self.latency_ms, None)
+        }
+    }
+
+    /// Approves every call after really waiting its latency, and counts
+    /// the voids it is sent.
+    struct Sleeping {
+        latency_ms: u64,
+        voids: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Sleeping {
+        fn new(latency_ms: u64) -> Arc<Sleeping> {
+            Arc::new(Sleeping { latency_ms, voids: Default::default() })
+        }
+
+        fn voids(&self) -> usize {
+            self.voids.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl AsyncPspConnector for Sleeping {
+        fn authorize_async<'a>(
+            &'a self,
+            _transaction: &'a Transaction,
+            psp: &'a PspConfig,
+        ) -> crate::connector::ConnectorFuture<'a> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(self.latency_ms)).await;
+                crate::simulator::respond(psp, self.latency_ms, None)
+            })
+        }
+
+        fn execute_async<'a>(
+            &'a self,
+            _payment: &'a Payment,
+            psp: &'a PspConfig,
+            kind: OperationKind,
+        ) -> crate::connector::ConnectorFuture<'a> {
+            if kind == OperationKind::Void {
+                self.voids.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            Box::pin(async move { crate::simulator::respond(psp, self.latency_ms, None) })
+        }
+    }
+
+    /// Wait, up to five seconds, for the background void of a late approval
+    /// to be counted by `voids`.
+    async fn voided(voids: impl Fn() -> usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while voids() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the late approval was not voided");
+        assert_eq!(voids(), 1);
+    }
+
+    /// Never answers within a test's lifetime.
+    struct Hung;
+
+    impl AsyncPspConnector for Hung {
+        fn authorize_async<'a>(
+            &'a self,
+            _transaction: &'a Transaction,
+            psp: &'a PspConfig,
+        ) -> crate::connector::ConnectorFuture<'a> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                crate::simulator::respond(psp, 60_000, None)
+            })
+        }
+
+        fn execute_async<'a>(
+            &'a self,
+            _payment: &'a Payment,
+            psp: &'a PspConfig,
+            _kind: OperationKind,
+        ) -> crate::connector::ConnectorFuture<'a> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                crate::simulator::respond(psp, 60_000, None)
+            })
+        }
+    }
+
+    #[test]
+    fn test_slow_psp_times_out_and_cascades() {
+        let registry = crate::connector::ConnectorRegistry::<dyn PspConnector>::new()
+            .with_fallback(Delayed::new(50, None))
+            .with_connector("psp_br_2", Delayed::new(500, None));
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin());
+        let policy = RetryPolicy { attempt_timeout_ms: Some(300), ..RetryPolicy::default() };
+        let txn = make_transaction(Country::new("Brazil"));
+
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &policy);
+        let first = &result.attempts[0];
+        assert_eq!(first.psp_id, "psp_br_2");
+        assert!(first.timed_out && !first.approved);
+        assert_eq!(first.decline_reason, Some(DeclineReason::PspUnavailable));
+        assert_eq!(first.latency_ms, 300);
+        assert_eq!(result.final_psp.as_deref(), Some("PagSeguro"));
+        assert_eq!((result.total_attempts, result.total_latency_ms), (1, 350));
+    }
+
+    #[tokio::test]
+    async fn test_async_route_cuts_off_hung_psps() {
+        let registry = crate::connector::ConnectorRegistry::<dyn AsyncPspConnector>::new()
+            .with_fallback(Arc::new(Hung))
+            .with_connector("psp_br_1", Delayed::new(10, None));
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin());
+        let policy = RetryPolicy { attempt_timeout_ms: Some(20), ..RetryPolicy::default() };
+
+        let result = engine
+            .route_async(&make_transaction(Country::new("Brazil")), &RoutingStrategy::OptimizeForApprovals, &policy)
+            .await;
+        let ids: Vec<&str> = result.attempts.iter().map(|a| a.psp_id.as_str()).collect();
+        assert_eq!(ids, ["psp_br_2", "psp_br_1"]);
+        assert!(result.attempts[0].timed_out);
+        assert_eq!(result.attempts[0].latency_ms, 20);
+        assert_eq!(result.final_psp.as_deref(), Some("PagSeguro"));
+    }
+
+    #[tokio::test]
+    async fn test_hedge_keeps_first_approval_and_voids_the_late_one() {
+        let slow = Delayed::new(400, None);
+        let fast = Delayed::new(100, None);
+        let registry = crate::connector::ConnectorRegistry::<dyn PspConnector>::new()
+            .with_connector("psp_br_2", slow.clone())
+            .with_connector("psp_br_1", fast.clone());
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin());
+        let policy = RetryPolicy { hedge_after_ms: Some(150), ..RetryPolicy::default() };
+        let txn = make_transaction(Country::new("Brazil"));
+
+        let result = engine
+            .route_async(&txn, &RoutingStrategy::OptimizeForApprovals, &policy)
+            .await;
+        // The hedge answered at 150 + 100 ms, before the primary's 400 ms
+        let winner = &result.attempts[0];
+        assert_eq!((winner.psp_id.as_str(), winner.hedged, winner.cancelled), ("psp_br_1", true, false));
+        let late = &result.attempts[1];
+        assert_eq!((late.psp_id.as_str(), late.hedged, late.cancelled), ("psp_br_2", false, true));
+        assert!(late.approved);
+        assert_eq!((result.total_attempts, result.total_latency_ms), (1, 250));
+        assert_eq!(result.final_psp.as_deref(), Some("PagSeguro"));
+        voided(|| slow.voids()).await;
+        assert_eq!(fast.voids(), 0);
+
+        let payment = Payment::from_authorization(&txn, &result).unwrap();
+        assert_eq!(payment.psp_id, "psp_br_1");
+    }
+
+    #[tokio::test]
+    async fn test_hedge_approval_does_not_wait_for_a_slow_primary() {
+        let slow = Sleeping::new(2_000);
+        let fast = Sleeping::new(50);
+        let registry = crate::connector::ConnectorRegistry::<dyn AsyncPspConnector>::new()
+            .with_connector("psp_br_2", slow.clone())
+            .with_connector("psp_br_1", fast.clone());
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin());
+        let policy = RetryPolicy { hedge_after_ms: Some(100), ..RetryPolicy::default() };
+
+        let started = std::time::Instant::now();
+        let result = engine
+            .route_async(&make_transaction(Country::new("Brazil")), &RoutingStrategy::OptimizeForApprovals, &policy)
+            .await;
+        let elapsed = started.elapsed();
+        // The hedge's own 100 + 50 ms, not the primary's 2 s
+        assert!(elapsed >= Duration::from_millis(150), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1_000), "{elapsed:?}");
+        assert_eq!(result.final_psp.as_deref(), Some("PagSeguro"));
+        let ids: Vec<(&str, bool)> = result.attempts.iter().map(|a| (a.psp_id.as_str(), a.hedged)).collect();
+        assert_eq!(ids, [("psp_br_1", true)]);
+        assert_eq!(result.total_latency_ms, 150);
+
+        // The primary's approval arrives later and is voided then
+        voided(|| slow.voids()).await;
+        assert_eq!(fast.voids(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_hedge_is_not_sent_when_primary_answers_in_time() {
+        let registry = crate::connector::ConnectorRegistry::<dyn PspConnector>::new()
+            .with_fallback(Delayed::new(100, Some(DeclineReason::DoNotHonor)));
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin());
+        let policy = RetryPolicy { hedge_after_ms: Some(150), ..RetryPolicy::default() };
+
+        let result = engine
+            .route_async(&make_transaction(Country::new("Brazil")), &RoutingStrategy::OptimizeForApprovals, &policy)
+            .await;
+        assert_eq!(result.attempts.len(), 3);
+        assert!(result.attempts.iter().all(|a| !a.hedged && !a.cancelled));
+        assert_eq!(result.total_latency_ms, 300);
+    }
+
+    #[test]
+    fn test_explain_traces_ranking_exclusions_and_retry_decisions() {
+        use crate::models::routing::{ExclusionReason, RetryStep};
+
+        let rules = RuleSet::from_toml_str(
+            r#"
+            [[rules]]
+            name = "no-pagseguro"
+            when = { countries = ["Brazil"] }
+            then = { exclude = ["psp_br_1"] }
+            "#,
+        )
+        .unwrap();
+        let registry = crate::connector::ConnectorRegistry::<dyn PspConnector>::new()
+            .with_fallback(Delayed::new(50, Some(DeclineReason::DoNotHonor)))
+            .with_connector("psp_br_3", Delayed::new(50, None));
+        let engine = RoutingEngine::new(registry, PspCatalog::builtin()).with_rules(rules);
+        let txn = make_transaction(Country::new("Brazil"));
+        let strategy = RoutingStrategy::OptimizeForApprovals;
+
+        let plain = engine.route(&txn, &strategy, &RetryPolicy::default());
+        assert!(plain.trace.is_none());
+
+        let engine = engine.with_explain(true);
+        let result = engine.route(&txn, &strategy, &RetryPolicy::default());
+        let trace = result.trace.expect("explained");
+        assert_eq!(trace.strategy, strategy);
+        let ranked: Vec<(&str, usize)> =
+            trace.candidates.iter().map(|c| (c.psp_id.as_str(), c.rank)).collect();
+        assert_eq!(ranked, [("psp_br_2", 1), ("psp_br_3", 2)]);
+        assert!(trace.candidates[0].success_rate > trace.candidates[1].success_rate);
+        assert!(trace.candidates.iter().all(|c| c.fee.minor_units > 0));
+
+        assert_eq!(trace.excluded.len(), 1);
+        assert_eq!(trace.excluded[0].psp_id, "psp_br_1");
+        assert_eq!(trace.excluded[0].reason, ExclusionReason::ExcludedByRule("no-pagseguro".into()));
+
+        assert_eq!(trace.decisions.len(), 1);
+        let decision = &trace.decisions[0];
+        assert_eq!((decision.attempt_number, decision.psp_id.as_str()), (1, "psp_br_2"));
+        assert_eq!(decision.class, DeclineClass::Soft);
+        // The test connector has no 3-D Secure server, so the engine moves on
+        assert_eq!(decision.action, RetryAction::Authenticate);
+        assert!(!decision.from_rule);
+        assert_eq!(decision.next, RetryStep::MovedOn);
+        assert_eq!(decision.remaining, ["psp_br_3"]);
+        assert_eq!(result.authentications.len(), 1);
+    }
+
     #[test]
     fn test_route_no_retry_makes_single_attempt() {
-        let engine = RoutingEngine::new(PspSimulator::new());
-        let txn = make_transaction(Country::Brazil);
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = make_transaction(Country::new("Brazil"));
         let result = engine.route_no_retry(&txn);
 
         assert_eq!(result.total_attempts, 1);
@@ -218,9 +2013,9 @@ mod tests {
 
     #[test]
     fn test_route_respects_max_attempts() {
-        let engine = RoutingEngine::new(PspSimulator::new());
-        let txn = make_transaction(Country::Brazil);
-        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals);
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = make_transaction(Country::new("Brazil"));
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
 
         // Decline attempts (excluding PSP-unavailable cascades) should not exceed MAX_ATTEMPTS
         let decline_attempts = result
@@ -231,20 +2026,21 @@ mod tests {
                     && !a
                         .decline_reason
                         .as_ref()
-                        .map_or(false, retry::is_psp_unavailable)
+                        .is_some_and(retry::is_psp_unavailable)
             })
             .count();
         assert!(
-            decline_attempts <= MAX_ATTEMPTS,
-            "Decline attempts ({decline_attempts}) should not exceed MAX_ATTEMPTS ({MAX_ATTEMPTS})"
+            decline_attempts <= retry::DEFAULT_MAX_ATTEMPTS,
+            "Decline attempts ({decline_attempts}) should not exceed the default max_attempts ({})",
+            retry::DEFAULT_MAX_ATTEMPTS
         );
     }
 
     #[test]
     fn test_route_approved_has_final_psp() {
-        let engine = RoutingEngine::new(PspSimulator::new());
-        let txn = make_transaction(Country::Brazil);
-        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals);
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = make_transaction(Country::new("Brazil"));
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
 
         if result.approved {
             assert!(
@@ -256,9 +2052,9 @@ mod tests {
 
     #[test]
     fn test_route_declined_has_no_final_psp() {
-        let engine = RoutingEngine::new(PspSimulator::new());
-        let txn = make_transaction(Country::Brazil);
-        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals);
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = make_transaction(Country::new("Brazil"));
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
 
         if !result.approved {
             assert!(
@@ -270,9 +2066,9 @@ mod tests {
 
     #[test]
     fn test_latency_is_sum_of_attempts() {
-        let engine = RoutingEngine::new(PspSimulator::new());
-        let txn = make_transaction(Country::Mexico);
-        let result = engine.route(&txn, &RoutingStrategy::Balanced);
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = make_transaction(Country::new("Mexico"));
+        let result = engine.route(&txn, &RoutingStrategy::Balanced, &RetryPolicy::default());
 
         let sum: u64 = result.attempts.iter().map(|a| a.latency_ms).sum();
         assert_eq!(
@@ -283,9 +2079,9 @@ mod tests {
 
     #[test]
     fn test_attempt_numbers_are_sequential() {
-        let engine = RoutingEngine::new(PspSimulator::new());
-        let txn = make_transaction(Country::Colombia);
-        let result = engine.route(&txn, &RoutingStrategy::OptimizeForCost);
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = make_transaction(Country::new("Colombia"));
+        let result = engine.route(&txn, &RoutingStrategy::OptimizeForCost, &RetryPolicy::default());
 
         // Attempt numbers should be sequential (though PSP-unavailable ones
         // get the next number without incrementing the main counter)
@@ -299,19 +2095,80 @@ mod tests {
 
     #[test]
     fn test_all_strategies_produce_results() {
-        let engine = RoutingEngine::new(PspSimulator::new());
-        let txn = make_transaction(Country::Brazil);
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let txn = make_transaction(Country::new("Brazil"));
 
         for strategy in [
             RoutingStrategy::OptimizeForApprovals,
             RoutingStrategy::OptimizeForCost,
             RoutingStrategy::Balanced,
         ] {
-            let result = engine.route(&txn, &strategy);
+            let result = engine.route(&txn, &strategy, &RetryPolicy::default());
             assert!(
                 !result.attempts.is_empty(),
                 "Strategy {strategy:?} should produce attempts"
             );
         }
     }
+
+    /// Route Brazilian cards until one is approved and start its lifecycle.
+    fn approved_payment(engine: &RoutingEngine) -> Payment {
+        (0..100)
+            .find_map(|i| {
+                let mut txn = make_transaction(Country::new("Brazil"));
+                txn.id = format!("txn_lifecycle_{i}");
+                txn.card_last4 = Some(format!("{i:04}"));
+                let result = engine.route(&txn, &RoutingStrategy::OptimizeForApprovals, &RetryPolicy::default());
+                Payment::from_authorization(&txn, &result).ok()
+            })
+            .expect("some card should be approved")
+    }
+
+    #[test]
+    fn test_capture_and_refund_go_to_the_authorizing_psp() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let mut payment = approved_payment(&engine);
+        let psp_id = payment.psp_id.clone();
+
+        // Operations are seeded on the transaction, and neither of this
+        // one's meets a briefly unavailable PSP
+        assert!(engine.capture(&mut payment, None).unwrap().approved);
+        assert_eq!(payment.state, PaymentState::Captured);
+        assert_eq!(payment.captured_amount, payment.authorized_amount);
+
+        assert!(engine.refund(&mut payment, None).unwrap().approved);
+        assert_eq!(payment.state, PaymentState::Refunded);
+        assert_eq!(payment.psp_id, psp_id);
+        assert_eq!(payment.operations.len(), 2);
+        assert!(payment.operations.iter().all(|op| op.response_code == "00"));
+    }
+
+    #[test]
+    fn test_invalid_transition_never_reaches_the_psp() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let mut payment = approved_payment(&engine);
+
+        let err = engine.refund(&mut payment, None).unwrap_err();
+        assert_eq!(
+            err,
+            LifecycleError::InvalidTransition {
+                state: PaymentState::Authorized,
+                operation: OperationKind::Refund
+            }
+        );
+        assert!(payment.operations.is_empty());
+    }
+
+    #[test]
+    fn test_follow_up_to_unknown_psp_is_rejected() {
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let mut payment = approved_payment(&engine);
+        payment.psp_id = "psp_gone".into();
+
+        assert_eq!(
+            engine.void(&mut payment).unwrap_err(),
+            LifecycleError::UnknownPsp("psp_gone".into())
+        );
+        assert_eq!(payment.state, PaymentState::Authorized);
+    }
 }
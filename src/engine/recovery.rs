@@ -0,0 +1,186 @@
+/// Decline-reason aware ranking of the PSPs left after a soft decline.
+///
+/// PSPs differ in what they decline for: one whose own declines are mostly
+/// `SuspectedFraud` is a poor second choice after another PSP flagged the
+/// transaction as fraud, while one that rarely flags fraud may well approve
+/// it. The tracker keeps, for each (decline reason, PSP) pair, how often an
+/// attempt on that PSP following a decline with that reason was approved,
+/// and ranks the remaining PSPs by that recovery rate.
+///
+/// Until a pair has been observed, its rate comes from the catalog: the
+/// PSP's success rate discounted by the share of its declines that carry the
+/// same reason. Observations then pull the estimate toward what actually
+/// happened, with the prior counting as `prior_weight` attempts.
+use crate::models::psp::{DeclineReason, PspConfig};
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Default number of attempts the catalog prior is worth.
+pub const DEFAULT_PRIOR_WEIGHT: f64 = 5.0;
+
+/// Attempts following a decline with `reason` that went to `psp_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecoveryRecord {
+    /// Reason of the decline that preceded the attempt.
+    pub reason: DeclineReason,
+    /// PSP tried next.
+    pub psp_id: String,
+    /// Attempts observed.
+    pub attempts: u64,
+    /// Attempts that were approved.
+    pub recoveries: u64,
+}
+
+/// Attempts and approvals after a decline, counted per (decline reason,
+/// `PspConfig.id`) pair.
+///
+/// The counts are only those the tracker has observed: a fresh tracker
+/// ranks on the catalog priors alone, and the process's own (see
+/// [`shared`](RecoveryTracker::shared)) starts fresh on every cold start.
+#[derive(Debug)]
+pub struct RecoveryTracker {
+    prior_weight: f64,
+    stats: Mutex<HashMap<(DeclineReason, String), (u64, u64)>>,
+}
+
+impl Default for RecoveryTracker {
+    fn default() -> Self {
+        RecoveryTracker::new(DEFAULT_PRIOR_WEIGHT)
+    }
+}
+
+impl RecoveryTracker {
+    /// An empty tracker whose catalog prior counts as `prior_weight`
+    /// attempts (clamped to be non-negative).
+    pub fn new(prior_weight: f64) -> Self {
+        RecoveryTracker {
+            prior_weight: prior_weight.max(0.0),
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The tracker of this process, used by the API handlers, so recovery
+    /// counts build up across the requests one warm instance serves.
+    pub fn shared() -> Arc<RecoveryTracker> {
+        state::per_process(RecoveryTracker::default)
+    }
+
+    /// Catalog estimate of `psp` approving after a decline with `reason`:
+    /// its success rate times the share of its declines for other reasons.
+    pub fn prior(psp: &PspConfig, reason: &DeclineReason) -> f64 {
+        let same_reason: f64 = psp
+            .decline_distribution
+            .iter()
+            .filter(|w| w.reason == *reason)
+            .map(|w| w.weight)
+            .sum();
+        psp.base_success_rate * (1.0 - same_reason).clamp(0.0, 1.0)
+    }
+
+    /// Estimated chance that `psp` approves after a decline with `reason`.
+    pub fn recovery_rate(&self, psp: &PspConfig, reason: &DeclineReason) -> f64 {
+        let (attempts, recoveries) = self
+            .lock()
+            .get(&(reason.clone(), psp.id.clone()))
+            .copied()
+            .unwrap_or_default();
+        let weight = self.prior_weight + attempts as f64;
+        if weight == 0.0 {
+            return Self::prior(psp, reason);
+        }
+        (Self::prior(psp, reason) * self.prior_weight + recoveries as f64) / weight
+    }
+
+    /// Record whether an attempt on `psp_id` that followed a decline with
+    /// `reason` was approved.
+    pub fn observe(&self, reason: &DeclineReason, psp_id: &str, approved: bool) {
+        let mut stats = self.lock();
+        let entry = stats.entry((reason.clone(), psp_id.to_string())).or_default();
+        entry.0 += 1;
+        if approved {
+            entry.1 += 1;
+        }
+    }
+
+    /// Order `remaining` by recovery rate after a decline with `reason`,
+    /// highest first. The sort is stable, so ties keep their order.
+    pub fn rerank(&self, reason: &DeclineReason, remaining: &mut [PspConfig]) {
+        let rates: HashMap<String, f64> = remaining
+            .iter()
+            .map(|psp| (psp.id.clone(), self.recovery_rate(psp, reason)))
+            .collect();
+        remaining.sort_by(|a, b| {
+            rates[&b.id]
+                .partial_cmp(&rates[&a.id])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    /// Every pair observed so far, sorted by reason then PSP.
+    pub fn snapshot(&self) -> Vec<RecoveryRecord> {
+        let mut records: Vec<RecoveryRecord> = self
+            .lock()
+            .iter()
+            .map(|((reason, psp_id), &(attempts, recoveries))| RecoveryRecord {
+                reason: reason.clone(),
+                psp_id: psp_id.clone(),
+                attempts,
+                recoveries,
+            })
+            .collect();
+        records.sort_by(|a, b| {
+            (a.reason.to_string(), &a.psp_id).cmp(&(b.reason.to_string(), &b.psp_id))
+        });
+        records
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<(DeclineReason, String), (u64, u64)>> {
+        state::lock(&self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::PspCatalog;
+    use crate::models::transaction::Country;
+
+    #[test]
+    fn test_prior_avoids_psps_heavy_in_the_same_reason() {
+        let catalog = PspCatalog::builtin();
+        let tracker = RecoveryTracker::default();
+        // Cielo declines mostly for fraud, PagSeguro for issuer outages
+        let mut remaining = catalog
+            .psps_for_country(&Country::new("Brazil"))
+            .into_iter()
+            .filter(|p| p.id != "psp_br_3")
+            .collect::<Vec<_>>();
+
+        tracker.rerank(&DeclineReason::SuspectedFraud, &mut remaining);
+        assert_eq!(remaining[0].id, "psp_br_1");
+        tracker.rerank(&DeclineReason::IssuerUnavailable, &mut remaining);
+        assert_eq!(remaining[0].id, "psp_br_2");
+    }
+
+    #[test]
+    fn test_observations_override_the_prior() {
+        let catalog = PspCatalog::builtin();
+        let cielo = catalog.psp("psp_br_2").unwrap();
+        let tracker = RecoveryTracker::new(1.0);
+        let reason = DeclineReason::SuspectedFraud;
+        let prior = tracker.recovery_rate(cielo, &reason);
+        assert!((prior - RecoveryTracker::prior(cielo, &reason)).abs() < 1e-12);
+
+        for _ in 0..9 {
+            tracker.observe(&reason, "psp_br_2", true);
+        }
+        let learned = tracker.recovery_rate(cielo, &reason);
+        assert!((learned - (prior + 9.0) / 10.0).abs() < 1e-12);
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!((snapshot[0].attempts, snapshot[0].recoveries), (9, 9));
+    }
+}
@@ -1,24 +1,355 @@
-/// Decline classification for the routing engine's retry logic.
+/// Decline classification and retry policy for the routing engine.
 ///
 /// Classifies PSP decline reasons into three categories:
 /// - **Hard declines**: Permanent failures — retrying will not help.
 /// - **Soft declines**: Temporary failures — a different PSP may succeed.
 /// - **PSP unavailable**: The PSP itself is down — cascade immediately.
-use crate::models::psp::DeclineReason;
+///
+/// Classification always runs on the normalized [`DeclineReason`] (see
+/// [`crate::codes`]), never on a PSP's raw response code. Codes no table
+/// recognises arrive as [`DeclineReason::Unrecognized`] carrying the PSP's
+/// configured default class.
+///
+/// A [`RetryPolicy`] turns the classification into an action and bounds
+/// how far the engine cascades. Its defaults reproduce the classes above;
+/// per-reason rules override them (e.g. "never retry `SuspectedFraud`").
+/// Soft declines that doubt the cardholder rather than the card default to
+/// 3-D Secure authentication where the PSP offers it, and hard declines
+/// for an out-of-date card to a resend with its refreshed credential.
+use crate::models::psp::{DeclineClass, DeclineReason, PspConfig};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Default maximum number of PSP decline attempts before giving up.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 3;
+
+/// Default number of times a PSP is retried when a rule asks for it.
+pub const DEFAULT_MAX_SAME_PSP_RETRIES: usize = 1;
+
+/// Most times a client-supplied policy may have one PSP retried.
+pub const MAX_SAME_PSP_RETRIES: usize = 5;
+
+/// Default wait before the first resend to the same PSP, in milliseconds.
+pub const DEFAULT_BACKOFF_INITIAL_MS: u64 = 100;
+
+/// Default growth of the wait between successive resends.
+pub const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
+
+/// Default longest wait between resends, in milliseconds.
+pub const DEFAULT_BACKOFF_MAX_MS: u64 = 1_000;
+
+/// Longest wait between resends a client-supplied policy may ask for, in
+/// milliseconds.
+pub const MAX_BACKOFF_MS: u64 = 10_000;
+
+/// Default fraction of each wait that is randomised away.
+pub const DEFAULT_BACKOFF_JITTER: f64 = 0.5;
+
+/// What the engine does after a declined attempt.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RetryAction {
+    /// Move on to the next PSP in the order.
+    RetryNextPsp,
+    /// Send the transaction to the same PSP again, then move on.
+    RetrySamePsp,
+    /// Authenticate the cardholder with 3-D Secure at the same PSP and
+    /// resend the transaction with the authentication; move on when the PSP
+    /// cannot authenticate.
+    Authenticate,
+    /// Resend the transaction to the same PSP with the card's refreshed
+    /// credential from the engine's credential store; without one, act as
+    /// the decline's class says.
+    RefreshCredential,
+    /// Give up and return the decline.
+    Stop,
+}
+
+/// A merchant override of the action for one decline reason.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryRule {
+    pub reason: DeclineReason,
+    pub action: RetryAction,
+}
+
+/// How the engine retries after declines.
+///
+/// Every field has a default, so `{}` or a partial object is a valid
+/// policy:
+///
+/// ```json
+/// {
+///   "max_attempts": 2,
+///   "rules": [{ "reason": "SuspectedFraud", "action": "Stop" }]
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Maximum PSP decline attempts. PSP-unavailable outcomes do not count.
+    pub max_attempts: usize,
+    /// Maximum PSP-unavailable outcomes to cascade past; `None` is unlimited.
+    pub max_unavailable_cascades: Option<usize>,
+    /// How many times one PSP is retried under [`RetryAction::RetrySamePsp`].
+    pub max_same_psp_retries: usize,
+    /// How long to wait before each resend to the same PSP.
+    pub backoff: Backoff,
+    /// Per-reason actions, overriding the class defaults.
+    pub rules: Vec<RetryRule>,
+    /// Total PSP latency the request may spend, in milliseconds (e.g. a
+    /// checkout SLA); `None` is unlimited.
+    pub latency_budget_ms: Option<u64>,
+    /// Longest one PSP call may take, in milliseconds; a PSP that has not
+    /// answered by then is treated as unavailable. `None` waits for it.
+    pub attempt_timeout_ms: Option<u64>,
+    /// With [`RoutingEngine::route_async`](super::RoutingEngine::route_async),
+    /// how long to wait on a PSP before sending the transaction to the next
+    /// one in parallel; `None` never hedges.
+    pub hedge_after_ms: Option<u64>,
+}
+
+/// Errors in a client-supplied [`RetryPolicy`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryPolicyError {
+    /// `max_attempts` is zero, so no PSP would ever be tried.
+    ZeroMaxAttempts,
+    /// `latency_budget_ms` is zero, so no PSP would ever be tried.
+    ZeroLatencyBudget,
+    /// `attempt_timeout_ms` is zero, so every PSP would time out.
+    ZeroAttemptTimeout,
+    /// `hedge_after_ms` is not below `attempt_timeout_ms`, so the hedge
+    /// would only fire after the primary had already timed out.
+    HedgeAfterTimeout { hedge_after_ms: u64, attempt_timeout_ms: u64 },
+    /// `max_same_psp_retries` is above [`MAX_SAME_PSP_RETRIES`].
+    TooManySamePspRetries(usize),
+    /// The backoff can shrink between resends, jitters by more than the
+    /// whole wait or waits longer than [`MAX_BACKOFF_MS`].
+    InvalidBackoff(String),
+    /// The backoff's `max_ms` is above `latency_budget_ms`, so one wait
+    /// could outlast the whole budget.
+    BackoffExceedsBudget { max_ms: u64, latency_budget_ms: u64 },
+    /// Two rules name the same decline reason.
+    DuplicateRule(DeclineReason),
+}
+
+impl std::fmt::Display for RetryPolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetryPolicyError::ZeroMaxAttempts => write!(f, "max_attempts must be at least 1"),
+            RetryPolicyError::ZeroLatencyBudget => {
+                write!(f, "latency_budget_ms must be greater than 0")
+            }
+            RetryPolicyError::ZeroAttemptTimeout => {
+                write!(f, "attempt_timeout_ms must be greater than 0")
+            }
+            RetryPolicyError::HedgeAfterTimeout {
+                hedge_after_ms,
+                attempt_timeout_ms,
+            } => write!(
+                f,
+                "hedge_after_ms ({hedge_after_ms}) must be less than attempt_timeout_ms ({attempt_timeout_ms})"
+            ),
+            RetryPolicyError::TooManySamePspRetries(retries) => write!(
+                f,
+                "max_same_psp_retries must be at most {MAX_SAME_PSP_RETRIES}, got {retries}"
+            ),
+            RetryPolicyError::InvalidBackoff(message) => write!(f, "invalid backoff: {message}"),
+            RetryPolicyError::BackoffExceedsBudget {
+                max_ms,
+                latency_budget_ms,
+            } => write!(
+                f,
+                "backoff max_ms ({max_ms}) must not exceed latency_budget_ms ({latency_budget_ms})"
+            ),
+            RetryPolicyError::DuplicateRule(reason) => {
+                write!(f, "more than one retry rule for {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RetryPolicyError {}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            max_unavailable_cascades: None,
+            max_same_psp_retries: DEFAULT_MAX_SAME_PSP_RETRIES,
+            backoff: Backoff::default(),
+            rules: Vec::new(),
+            latency_budget_ms: None,
+            attempt_timeout_ms: None,
+            hedge_after_ms: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Check that the policy can route at all, stays within the hard caps
+    /// on resends and waits, and its rules are unambiguous.
+    pub fn validate(&self) -> Result<(), RetryPolicyError> {
+        if self.max_attempts == 0 {
+            return Err(RetryPolicyError::ZeroMaxAttempts);
+        }
+        if self.latency_budget_ms == Some(0) {
+            return Err(RetryPolicyError::ZeroLatencyBudget);
+        }
+        if self.attempt_timeout_ms == Some(0) {
+            return Err(RetryPolicyError::ZeroAttemptTimeout);
+        }
+        if let (Some(hedge_after_ms), Some(attempt_timeout_ms)) =
+            (self.hedge_after_ms, self.attempt_timeout_ms)
+        {
+            if hedge_after_ms >= attempt_timeout_ms {
+                return Err(RetryPolicyError::HedgeAfterTimeout {
+                    hedge_after_ms,
+                    attempt_timeout_ms,
+                });
+            }
+        }
+        if self.max_same_psp_retries > MAX_SAME_PSP_RETRIES {
+            return Err(RetryPolicyError::TooManySamePspRetries(self.max_same_psp_retries));
+        }
+        self.backoff.validate().map_err(RetryPolicyError::InvalidBackoff)?;
+        if let Some(latency_budget_ms) = self.latency_budget_ms {
+            if self.backoff.max_ms > latency_budget_ms {
+                return Err(RetryPolicyError::BackoffExceedsBudget {
+                    max_ms: self.backoff.max_ms,
+                    latency_budget_ms,
+                });
+            }
+        }
+        for (i, rule) in self.rules.iter().enumerate() {
+            if self.rules[..i].iter().any(|r| r.reason == rule.reason) {
+                return Err(RetryPolicyError::DuplicateRule(rule.reason.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if `psp` is expected to answer within what is left of
+    /// the latency budget after `spent_ms`.
+    pub fn fits_budget(&self, spent_ms: u64, psp: &PspConfig) -> bool {
+        match self.latency_budget_ms {
+            Some(budget) => spent_ms.saturating_add(psp.expected_latency_ms()) <= budget,
+            None => true,
+        }
+    }
+
+    /// The action for a decline: the matching rule, or the class default
+    /// (hard declines stop, soft declines and unavailable PSPs move on).
+    /// Declines that call for authentication (see
+    /// [`DeclineReason::calls_for_authentication`]) default to
+    /// [`RetryAction::Authenticate`], and those a refreshed card credential
+    /// may recover (see [`DeclineReason::calls_for_credential_refresh`]) to
+    /// [`RetryAction::RefreshCredential`].
+    pub fn action_for(&self, reason: &DeclineReason) -> RetryAction {
+        if let Some(rule) = self.rules.iter().find(|r| r.reason == *reason) {
+            return rule.action;
+        }
+        if reason.calls_for_authentication() {
+            return RetryAction::Authenticate;
+        }
+        if reason.calls_for_credential_refresh() {
+            return RetryAction::RefreshCredential;
+        }
+        class_action(reason)
+    }
+}
+
+/// Exponential backoff with jitter between resends to the same PSP.
+///
+/// The `n`th resend waits `initial_ms × multiplierⁿ⁻¹`, capped at `max_ms`,
+/// less a random share of up to `jitter` of that wait so that resends from
+/// many checkouts do not hit a recovering issuer at the same instant. The
+/// randomness is seeded on the transaction and PSP, so a simulated run is
+/// reproducible.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Backoff {
+    /// Wait before the first resend, in milliseconds.
+    pub initial_ms: u64,
+    /// Growth of the wait between successive resends (at least 1).
+    pub multiplier: f64,
+    /// Longest wait, in milliseconds.
+    pub max_ms: u64,
+    /// Share of each wait randomised away, from 0 (none) to 1 (full jitter).
+    pub jitter: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial_ms: DEFAULT_BACKOFF_INITIAL_MS,
+            multiplier: DEFAULT_BACKOFF_MULTIPLIER,
+            max_ms: DEFAULT_BACKOFF_MAX_MS,
+            jitter: DEFAULT_BACKOFF_JITTER,
+        }
+    }
+}
+
+impl Backoff {
+    /// Check that waits never shrink, stay within [`MAX_BACKOFF_MS`] and
+    /// jitter is a share of the wait.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.multiplier >= 1.0 && self.multiplier.is_finite()) {
+            return Err(format!("multiplier must be at least 1, got {}", self.multiplier));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(format!("jitter must be between 0 and 1, got {}", self.jitter));
+        }
+        if self.max_ms < self.initial_ms {
+            return Err(format!(
+                "max_ms ({}) must be at least initial_ms ({})",
+                self.max_ms, self.initial_ms
+            ));
+        }
+        if self.max_ms > MAX_BACKOFF_MS {
+            return Err(format!("max_ms must be at most {MAX_BACKOFF_MS}, got {}", self.max_ms));
+        }
+        Ok(())
+    }
+
+    /// The wait before resend number `retry` (1-indexed) of
+    /// `transaction_id` to `psp_id`, in milliseconds.
+    pub fn delay_ms(&self, retry: u32, transaction_id: &str, psp_id: &str) -> u64 {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let ceiling = (self.initial_ms as f64 * self.multiplier.powi(exponent)).min(self.max_ms as f64);
+
+        let mut hasher = DefaultHasher::new();
+        transaction_id.hash(&mut hasher);
+        psp_id.hash(&mut hasher);
+        retry.hash(&mut hasher);
+        let mut rng = StdRng::seed_from_u64(hasher.finish());
+        let jitter = self.jitter * rng.gen::<f64>();
+        (ceiling * (1.0 - jitter)).round() as u64
+    }
+}
+
+/// Classify a normalized decline reason.
+pub fn classify(reason: &DeclineReason) -> DeclineClass {
+    reason.class()
+}
+
+/// The action for a decline by its class alone: hard declines stop, soft
+/// declines and unavailable PSPs move on.
+pub fn class_action(reason: &DeclineReason) -> RetryAction {
+    match classify(reason) {
+        DeclineClass::Hard => RetryAction::Stop,
+        DeclineClass::Soft | DeclineClass::PspUnavailable => RetryAction::RetryNextPsp,
+    }
+}
 
 /// Returns true if the decline is permanent and should NOT be retried.
 ///
 /// Hard declines indicate a fundamental issue with the payment instrument
-/// that no PSP can resolve: insufficient funds, expired card, invalid card,
-/// or a stolen card flagged by the network.
+/// that no PSP can resolve: insufficient funds, an expired, lost, stolen or
+/// restricted card, a failed CVV, or a network "do not try again" advice.
 pub fn is_hard_decline(reason: &DeclineReason) -> bool {
-    matches!(
-        reason,
-        DeclineReason::InsufficientFunds
-            | DeclineReason::CardExpired
-            | DeclineReason::InvalidCard
-            | DeclineReason::StolenCard
-    )
+    classify(reason) == DeclineClass::Hard
 }
 
 /// Returns true if the decline is temporary and should be retried with the next PSP.
@@ -28,13 +359,7 @@ pub fn is_hard_decline(reason: &DeclineReason) -> bool {
 /// the processor may have a momentary issue. Trying a different PSP frequently
 /// resolves these.
 pub fn is_soft_decline(reason: &DeclineReason) -> bool {
-    matches!(
-        reason,
-        DeclineReason::IssuerUnavailable
-            | DeclineReason::SuspectedFraud
-            | DeclineReason::DoNotHonor
-            | DeclineReason::ProcessorDeclined
-    )
+    classify(reason) == DeclineClass::Soft
 }
 
 /// Returns true if the PSP itself is unavailable (timeout, downtime, etc.).
@@ -42,7 +367,7 @@ pub fn is_soft_decline(reason: &DeclineReason) -> bool {
 /// When a PSP is unavailable, the attempt should not count as a decline.
 /// The engine cascades immediately to the next PSP in the priority list.
 pub fn is_psp_unavailable(reason: &DeclineReason) -> bool {
-    matches!(reason, DeclineReason::PspUnavailable)
+    classify(reason) == DeclineClass::PspUnavailable
 }
 
 #[cfg(test)]
@@ -56,6 +381,14 @@ mod tests {
             DeclineReason::CardExpired,
             DeclineReason::InvalidCard,
             DeclineReason::StolenCard,
+            DeclineReason::LostCard,
+            DeclineReason::PickUpCard,
+            DeclineReason::RestrictedCard,
+            DeclineReason::TransactionNotPermitted,
+            DeclineReason::ExceedsLimit,
+            DeclineReason::InvalidCvv,
+            DeclineReason::AccountUpdateRequired,
+            DeclineReason::DoNotRetry,
         ];
 
         for reason in &hard_reasons {
@@ -78,6 +411,9 @@ mod tests {
             DeclineReason::SuspectedFraud,
             DeclineReason::DoNotHonor,
             DeclineReason::ProcessorDeclined,
+            DeclineReason::TryAgainLater,
+            DeclineReason::SystemError,
+            DeclineReason::AuthenticationRequired,
         ];
 
         for reason in &soft_reasons {
@@ -101,6 +437,123 @@ mod tests {
         assert!(!is_soft_decline(&reason));
     }
 
+    #[test]
+    fn test_unrecognized_codes_follow_configured_class() {
+        assert!(is_hard_decline(&DeclineReason::Unrecognized(DeclineClass::Hard)));
+        assert!(is_soft_decline(&DeclineReason::Unrecognized(DeclineClass::Soft)));
+        assert!(is_psp_unavailable(&DeclineReason::Unrecognized(
+            DeclineClass::PspUnavailable
+        )));
+    }
+
+    #[test]
+    fn test_default_policy_follows_classification() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.action_for(&DeclineReason::CardExpired), RetryAction::RefreshCredential);
+        assert_eq!(policy.action_for(&DeclineReason::StolenCard), RetryAction::Stop);
+        assert_eq!(policy.action_for(&DeclineReason::ProcessorDeclined), RetryAction::RetryNextPsp);
+        assert_eq!(policy.action_for(&DeclineReason::DoNotHonor), RetryAction::Authenticate);
+        assert_eq!(policy.action_for(&DeclineReason::PspUnavailable), RetryAction::RetryNextPsp);
+        assert_eq!(policy.max_attempts, DEFAULT_MAX_ATTEMPTS);
+        assert!(policy.validate().is_ok());
+    }
+
+    #[test]
+    fn test_rules_override_classification() {
+        let policy: RetryPolicy = serde_json::from_str(
+            r#"{"rules": [
+                {"reason": "SuspectedFraud", "action": "Stop"},
+                {"reason": "IssuerUnavailable", "action": "RetrySamePsp"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.max_attempts, DEFAULT_MAX_ATTEMPTS);
+        assert_eq!(policy.action_for(&DeclineReason::SuspectedFraud), RetryAction::Stop);
+        assert_eq!(
+            policy.action_for(&DeclineReason::IssuerUnavailable),
+            RetryAction::RetrySamePsp
+        );
+        assert_eq!(policy.action_for(&DeclineReason::DoNotHonor), RetryAction::Authenticate);
+    }
+
+    #[test]
+    fn test_latency_budget_uses_expected_psp_latency() {
+        let catalog = crate::catalog::PspCatalog::builtin();
+        let cielo = catalog.psp("psp_br_2").unwrap(); // 150-250ms → 200ms expected
+        let policy = RetryPolicy { latency_budget_ms: Some(500), ..RetryPolicy::default() };
+
+        assert!(policy.fits_budget(0, cielo));
+        assert!(policy.fits_budget(300, cielo));
+        assert!(!policy.fits_budget(301, cielo));
+        assert!(RetryPolicy::default().fits_budget(u64::MAX, cielo));
+    }
+
+    #[test]
+    fn test_backoff_grows_to_its_cap_with_bounded_jitter() {
+        let exact = Backoff { jitter: 0.0, ..Backoff::default() };
+        let waits: Vec<u64> = (1..=5).map(|n| exact.delay_ms(n, "txn", "psp_br_1")).collect();
+        assert_eq!(waits, [100, 200, 400, 800, 1000]);
+
+        let jittered = Backoff::default();
+        for n in 1..=5 {
+            let wait = jittered.delay_ms(n, "txn", "psp_br_1");
+            assert!(wait >= waits[n as usize - 1] / 2 && wait <= waits[n as usize - 1]);
+            assert_eq!(wait, jittered.delay_ms(n, "txn", "psp_br_1"), "seeded, so reproducible");
+        }
+    }
+
+    #[test]
+    fn test_policy_validation() {
+        let zero = RetryPolicy { max_attempts: 0, ..RetryPolicy::default() };
+        assert_eq!(zero.validate(), Err(RetryPolicyError::ZeroMaxAttempts));
+
+        let no_time = RetryPolicy { latency_budget_ms: Some(0), ..RetryPolicy::default() };
+        assert_eq!(no_time.validate(), Err(RetryPolicyError::ZeroLatencyBudget));
+
+        let no_wait = RetryPolicy { attempt_timeout_ms: Some(0), ..RetryPolicy::default() };
+        assert_eq!(no_wait.validate(), Err(RetryPolicyError::ZeroAttemptTimeout));
+
+        let late_hedge = RetryPolicy {
+            attempt_timeout_ms: Some(300),
+            hedge_after_ms: Some(300),
+            ..RetryPolicy::default()
+        };
+        assert!(matches!(late_hedge.validate(), Err(RetryPolicyError::HedgeAfterTimeout { .. })));
+
+        let shrinking = RetryPolicy {
+            backoff: Backoff { multiplier: 0.5, ..Backoff::default() },
+            ..RetryPolicy::default()
+        };
+        assert!(matches!(shrinking.validate(), Err(RetryPolicyError::InvalidBackoff(_))));
+
+        let resends = RetryPolicy { max_same_psp_retries: MAX_SAME_PSP_RETRIES, ..RetryPolicy::default() };
+        assert_eq!(resends.validate(), Ok(()));
+        let endless = RetryPolicy { max_same_psp_retries: 1_000_000, ..RetryPolicy::default() };
+        assert_eq!(endless.validate(), Err(RetryPolicyError::TooManySamePspRetries(1_000_000)));
+
+        let long_wait = RetryPolicy {
+            backoff: Backoff { initial_ms: u64::MAX, max_ms: u64::MAX, ..Backoff::default() },
+            ..RetryPolicy::default()
+        };
+        assert!(matches!(long_wait.validate(), Err(RetryPolicyError::InvalidBackoff(_))));
+
+        let over_budget = RetryPolicy { latency_budget_ms: Some(800), ..RetryPolicy::default() };
+        assert_eq!(
+            over_budget.validate(),
+            Err(RetryPolicyError::BackoffExceedsBudget { max_ms: 1_000, latency_budget_ms: 800 })
+        );
+
+        let rule = RetryRule { reason: DeclineReason::DoNotHonor, action: RetryAction::Stop };
+        let duplicate = RetryPolicy {
+            rules: vec![rule.clone(), rule],
+            ..RetryPolicy::default()
+        };
+        assert_eq!(
+            duplicate.validate(),
+            Err(RetryPolicyError::DuplicateRule(DeclineReason::DoNotHonor))
+        );
+    }
+
     #[test]
     fn test_all_reasons_are_classified_into_exactly_one_category() {
         let all_reasons = vec![
@@ -113,6 +566,19 @@ mod tests {
             DeclineReason::DoNotHonor,
             DeclineReason::ProcessorDeclined,
             DeclineReason::PspUnavailable,
+            DeclineReason::LostCard,
+            DeclineReason::PickUpCard,
+            DeclineReason::RestrictedCard,
+            DeclineReason::TransactionNotPermitted,
+            DeclineReason::ExceedsLimit,
+            DeclineReason::InvalidCvv,
+            DeclineReason::AccountUpdateRequired,
+            DeclineReason::DoNotRetry,
+            DeclineReason::TryAgainLater,
+            DeclineReason::SystemError,
+            DeclineReason::AuthenticationRequired,
+            DeclineReason::Unrecognized(DeclineClass::Hard),
+            DeclineReason::Unrecognized(DeclineClass::Soft),
         ];
 
         for reason in &all_reasons {
@@ -2,93 +2,190 @@
 ///
 /// Determines the order in which PSPs are tried for a transaction,
 /// optimizing for different business objectives: approval rate, cost,
-/// or a balanced combination of both.
+/// a balanced combination of both, approval rates learned at runtime, or
+/// the expected value of each attempt. Costs are the fees each PSP would
+/// actually charge on the transaction's amount.
+/// PSPs whose circuit breaker is open are left out, half-open ones are
+/// tried only after the rest, and cross-border acquirers only after every
+/// local PSP.
+use crate::engine::breaker::BreakerState;
+use crate::engine::fees::CostModel;
+use crate::models::money::Money;
 use crate::models::psp::PspConfig;
-use crate::models::routing::RoutingStrategy;
+use crate::models::routing::{CandidateScore, RoutingStrategy};
+use crate::models::transaction::Transaction;
+use std::collections::HashMap;
 
 /// Order PSPs based on the chosen routing strategy.
 ///
 /// Returns a new `Vec<PspConfig>` sorted according to the strategy:
 /// - [`RoutingStrategy::OptimizeForApprovals`]: Highest success rate first.
-/// - [`RoutingStrategy::OptimizeForCost`]: Lowest total fee first.
+/// - [`RoutingStrategy::OptimizeForCost`]: Lowest fee on `transaction` first.
 /// - [`RoutingStrategy::Balanced`]: Weighted score combining success rate (70%) and cost (30%).
-pub fn select_psp_order(psps: &[PspConfig], strategy: &RoutingStrategy) -> Vec<PspConfig> {
-    let mut sorted = psps.to_vec();
+/// - [`RoutingStrategy::Adaptive`]: Highest `adaptive_scores` first (see
+///   [`AdaptiveRouter::scores`](crate::engine::bandit::AdaptiveRouter::scores));
+///   PSPs without a score fall back to their success rate.
+/// - [`RoutingStrategy::ExpectedValue`]: Highest expected value first (see
+///   [`CostModel::expected_value`]), with the approval probability taken
+///   from `adaptive_scores` when given (the engine passes the bandit's
+///   [`posterior_means`](crate::engine::bandit::AdaptiveRouter::posterior_means))
+///   and the success rate otherwise.
+///
+/// Fees are priced by `costs` on the transaction's amount, including each
+/// PSP's surcharge for its installment plan.
+///
+/// `circuits` holds the breaker state of each PSP by id (missing means
+/// closed): open PSPs are dropped and half-open ones are moved to the end,
+/// keeping the strategy's order within each group. Cross-border acquirers
+/// come after all of them, ordered the same way.
+pub fn select_psp_order(
+    psps: &[PspConfig],
+    strategy: &RoutingStrategy,
+    transaction: &Transaction,
+    costs: &CostModel,
+    circuits: &HashMap<String, BreakerState>,
+    adaptive_scores: &HashMap<String, f64>,
+) -> Vec<PspConfig> {
+    rank_psps(psps, strategy, transaction, costs, circuits, adaptive_scores)
+        .into_iter()
+        .map(|(psp, _)| psp)
+        .collect()
+}
 
-    match strategy {
-        RoutingStrategy::OptimizeForApprovals => {
-            sorted.sort_by(|a, b| {
-                b.base_success_rate
-                    .partial_cmp(&a.base_success_rate)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
-        }
-        RoutingStrategy::OptimizeForCost => {
-            sorted.sort_by(|a, b| {
-                let cost_a = total_fee(a);
-                let cost_b = total_fee(b);
-                cost_a
-                    .partial_cmp(&cost_b)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
+/// [`select_psp_order`], with the figures each PSP was ranked by.
+///
+/// Every score is computed whatever the strategy, so a trace can show
+/// what another strategy would have weighed. Open PSPs are not scored.
+pub fn rank_psps(
+    psps: &[PspConfig],
+    strategy: &RoutingStrategy,
+    transaction: &Transaction,
+    costs: &CostModel,
+    circuits: &HashMap<String, BreakerState>,
+    adaptive_scores: &HashMap<String, f64>,
+) -> Vec<(PspConfig, CandidateScore)> {
+    let circuit = |psp: &PspConfig| circuits.get(&psp.id).copied().unwrap_or_default();
+    let open: Vec<&PspConfig> = psps
+        .iter()
+        .filter(|psp| circuit(psp) != BreakerState::Open)
+        .collect();
+    let fees: Vec<Money> = open.iter().map(|psp| costs.fee(psp, transaction)).collect();
+    let max_fee = fees.iter().map(Money::to_major_f64).fold(0.0_f64, f64::max);
+
+    let mut ranked: Vec<(PspConfig, CandidateScore)> = open
+        .into_iter()
+        .zip(fees)
+        .map(|(psp, fee)| {
+            let learned_rate = adaptive_scores.get(&psp.id).copied();
+            let approval_rate = learned_rate.unwrap_or(psp.base_success_rate);
+            let score = CandidateScore {
+                psp_id: psp.id.clone(),
+                psp_name: psp.name.clone(),
+                rank: 0,
+                success_rate: psp.base_success_rate,
+                learned_rate,
+                balanced_score: balanced_score(psp, fee.to_major_f64(), max_fee),
+                expected_value: costs.expected_value(psp, transaction, approval_rate),
+                fee,
+                circuit: circuit(psp),
+            };
+            (psp.clone(), score)
+        })
+        .collect();
+
+    let descending = |key: fn(&CandidateScore) -> f64| {
+        move |a: &(PspConfig, CandidateScore), b: &(PspConfig, CandidateScore)| {
+            key(&b.1)
+                .partial_cmp(&key(&a.1))
+                .unwrap_or(std::cmp::Ordering::Equal)
         }
-        RoutingStrategy::Balanced => {
-            let max_fee = sorted.iter().map(total_fee).fold(0.0_f64, f64::max);
-
-            sorted.sort_by(|a, b| {
-                let score_a = balanced_score(a, max_fee);
-                let score_b = balanced_score(b, max_fee);
-                score_b
-                    .partial_cmp(&score_a)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
+    };
+    match strategy {
+        RoutingStrategy::OptimizeForApprovals => ranked.sort_by(descending(|s| s.success_rate)),
+        RoutingStrategy::OptimizeForCost => ranked.sort_by_key(|(_, s)| s.fee.minor_units),
+        RoutingStrategy::Adaptive => {
+            ranked.sort_by(descending(|s| s.learned_rate.unwrap_or(s.success_rate)))
         }
+        RoutingStrategy::Balanced => ranked.sort_by(descending(|s| s.balanced_score)),
+        RoutingStrategy::ExpectedValue => ranked.sort_by(descending(|s| s.expected_value)),
     }
 
-    sorted
-}
-
-/// Calculate the total effective fee for a PSP.
-///
-/// Combines the percentage-based fee with the fixed fee (converted from cents to
-/// a comparable unit) to produce a single cost metric for sorting.
-fn total_fee(psp: &PspConfig) -> f64 {
-    psp.fee_percentage + (psp.fee_fixed_cents as f64 / 100.0)
+    // Stable sort: healthy PSPs first, probes last, and cross-border
+    // acquirers after every local PSP
+    ranked.sort_by_key(|(psp, s)| (psp.is_cross_border(), s.circuit == BreakerState::HalfOpen));
+    for (i, (_, score)) in ranked.iter_mut().enumerate() {
+        score.rank = i + 1;
+    }
+    ranked
 }
 
 /// Calculate the balanced score for a PSP.
 ///
 /// Score = `success_rate * 0.7 + (1.0 - normalized_fee) * 0.3`
 ///
-/// The normalized fee is `total_fee / max_fee` across all PSPs in the set,
+/// The normalized fee is `fee / max_fee` across all PSPs in the set,
 /// ensuring the cost component falls in `[0.0, 1.0]`. A higher score is better.
-fn balanced_score(psp: &PspConfig, max_fee: f64) -> f64 {
-    let normalized_fee = if max_fee > 0.0 {
-        total_fee(psp) / max_fee
-    } else {
-        0.0
-    };
+fn balanced_score(psp: &PspConfig, fee: f64, max_fee: f64) -> f64 {
+    let normalized_fee = if max_fee > 0.0 { fee / max_fee } else { 0.0 };
     psp.base_success_rate * 0.7 + (1.0 - normalized_fee) * 0.3
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::transaction::Country;
+    use crate::models::money::Money;
+    use crate::models::psp::InstallmentFee;
+    use crate::models::transaction::{Country, Currency, PaymentMethod};
+    use crate::time::Timestamp;
 
-    fn make_psp(id: &str, success_rate: f64, fee_pct: f64, fee_fixed: u64) -> PspConfig {
+    fn make_psp(id: &str, success_rate: f64, fee_pct: f64, fee_fixed: i64) -> PspConfig {
         PspConfig {
             id: id.to_string(),
             name: id.to_string(),
-            country: Country::Brazil,
+            country: Country::new("Brazil"),
+            payment_methods: vec![PaymentMethod::Card],
             base_success_rate: success_rate,
             latency_min_ms: 100,
             latency_max_ms: 300,
             fee_percentage: fee_pct,
-            fee_fixed_cents: fee_fixed,
+            fee_fixed: Money::new(fee_fixed, Currency::USD),
+            decline_distribution: vec![],
+            response_codes: Default::default(),
+            installments: vec![],
+            min_amount: None,
+            max_amount: None,
+            card_schemes: vec![],
+            currencies: vec![],
+            cross_border: None,
+            three_ds: None,
         }
     }
 
+    /// A Brazilian card transaction of `minor_units` centavos.
+    fn make_txn(minor_units: i64, installments: u8) -> Transaction {
+        Transaction {
+            id: "txn_strategy".to_string(),
+            amount: Money::new(minor_units, Currency::new("BRL")),
+            country: Country::new("Brazil"),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some("411111".to_string()),
+            card_last4: Some("1111".to_string()),
+            bin_info: None,
+            installments: (installments > 1).then_some(installments),
+            customer_id: "cust_strategy".to_string(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
+        }
+    }
+
+    /// Order a BRL 150.00 transaction with every circuit closed and no
+    /// adaptive scores.
+    fn order(psps: &[PspConfig], strategy: &RoutingStrategy, installments: u8) -> Vec<PspConfig> {
+        let txn = make_txn(15_000, installments);
+        select_psp_order(psps, strategy, &txn, &CostModel::default(), &HashMap::new(), &HashMap::new())
+    }
+
     #[test]
     fn test_optimize_for_approvals_sorts_by_success_rate_descending() {
         let psps = vec![
@@ -97,7 +194,7 @@ mod tests {
             make_psp("mid", 0.75, 3.0, 25),
         ];
 
-        let ordered = select_psp_order(&psps, &RoutingStrategy::OptimizeForApprovals);
+        let ordered = order(&psps, &RoutingStrategy::OptimizeForApprovals, 1);
         assert_eq!(ordered[0].id, "high");
         assert_eq!(ordered[1].id, "mid");
         assert_eq!(ordered[2].id, "low");
@@ -105,13 +202,14 @@ mod tests {
 
     #[test]
     fn test_optimize_for_cost_sorts_by_total_fee_ascending() {
+        // Fees on BRL 150.00, fixed fees at 6.18 BRL/USD
         let psps = vec![
-            make_psp("expensive", 0.80, 3.5, 40), // 3.5 + 0.40 = 3.90
-            make_psp("cheap", 0.70, 2.0, 20),     // 2.0 + 0.20 = 2.20
-            make_psp("mid", 0.75, 2.8, 30),       // 2.8 + 0.30 = 3.10
+            make_psp("expensive", 0.80, 3.5, 40), // 5.25 + 2.47 = 7.72
+            make_psp("cheap", 0.70, 2.0, 20),     // 3.00 + 1.24 = 4.24
+            make_psp("mid", 0.75, 2.8, 30),       // 4.20 + 1.85 = 6.05
         ];
 
-        let ordered = select_psp_order(&psps, &RoutingStrategy::OptimizeForCost);
+        let ordered = order(&psps, &RoutingStrategy::OptimizeForCost, 1);
         assert_eq!(ordered[0].id, "cheap");
         assert_eq!(ordered[1].id, "mid");
         assert_eq!(ordered[2].id, "expensive");
@@ -123,17 +221,17 @@ mod tests {
         // PSP B: low success, lowest cost   → best cost bonus but weak success
         // PSP C: moderate both              → good success + decent cost bonus
         //
-        // Scores (fee_max = 3.90):
+        // Scores on BRL 150.00 (fee_max = 7.72):
         //   A: 0.90*0.7 + (1 - 1.000)*0.3 = 0.630
-        //   B: 0.60*0.7 + (1 - 0.564)*0.3 = 0.551
-        //   C: 0.78*0.7 + (1 - 0.705)*0.3 = 0.635
+        //   B: 0.60*0.7 + (1 - 0.549)*0.3 = 0.555
+        //   C: 0.78*0.7 + (1 - 0.687)*0.3 = 0.640
         let psps = vec![
-            make_psp("A", 0.90, 3.5, 40), // total fee 3.90
-            make_psp("B", 0.60, 2.0, 20), // total fee 2.20
-            make_psp("C", 0.78, 2.5, 25), // total fee 2.75
+            make_psp("A", 0.90, 3.5, 40), // fee 5.25 + 2.47 = 7.72
+            make_psp("B", 0.60, 2.0, 20), // fee 3.00 + 1.24 = 4.24
+            make_psp("C", 0.78, 2.5, 25), // fee 3.75 + 1.55 = 5.30
         ];
 
-        let ordered = select_psp_order(&psps, &RoutingStrategy::Balanced);
+        let ordered = order(&psps, &RoutingStrategy::Balanced, 1);
 
         // C edges out A because A's max fee zeroes out its cost bonus
         assert_eq!(ordered[0].id, "C");
@@ -141,17 +239,163 @@ mod tests {
         assert_eq!(ordered[2].id, "B");
     }
 
+    #[test]
+    fn test_rank_psps_reports_the_scores_behind_the_order() {
+        let psps = vec![
+            make_psp("A", 0.90, 3.5, 40),
+            make_psp("B", 0.60, 2.0, 20),
+            make_psp("C", 0.78, 2.5, 25),
+        ];
+        let circuits = HashMap::from([("B".to_string(), BreakerState::Open)]);
+        let ranked = rank_psps(
+            &psps,
+            &RoutingStrategy::Balanced,
+            &make_txn(15_000, 1),
+            &CostModel::default(),
+            &circuits,
+            &HashMap::new(),
+        );
+
+        // B's circuit is open, so it is neither ranked nor scored
+        let scores: Vec<(&str, usize)> = ranked.iter().map(|(_, s)| (s.psp_id.as_str(), s.rank)).collect();
+        assert_eq!(scores, [("C", 1), ("A", 2)]);
+        let (c, a) = (&ranked[0].1, &ranked[1].1);
+        assert_eq!(a.fee, Money::new(772, Currency::new("BRL")));
+        assert!((a.balanced_score - 0.63).abs() < 1e-9);
+        assert!(c.balanced_score > a.balanced_score);
+        assert!(c.success_rate < a.success_rate);
+        assert_eq!(c.learned_rate, None);
+    }
+
+    #[test]
+    fn test_cost_ordering_includes_installment_surcharge() {
+        let plan = |count, extra_fee_percentage| InstallmentFee { count, extra_fee_percentage };
+        let mut cheap_base = make_psp("cheap_base", 0.75, 2.0, 20);
+        cheap_base.installments = vec![plan(6, 3.0)]; // 6x: 5.0% = 7.50 + 1.24
+        let mut cheap_plans = make_psp("cheap_plans", 0.75, 3.0, 20);
+        cheap_plans.installments = vec![plan(6, 1.0)]; // 6x: 4.0% = 6.00 + 1.24
+        let psps = vec![cheap_base, cheap_plans];
+
+        let single = order(&psps, &RoutingStrategy::OptimizeForCost, 1);
+        assert_eq!(single[0].id, "cheap_base");
+
+        let six = order(&psps, &RoutingStrategy::OptimizeForCost, 6);
+        assert_eq!(six[0].id, "cheap_plans");
+    }
+
+    #[test]
+    fn test_open_circuits_are_skipped_and_half_open_demoted() {
+        let psps = vec![
+            make_psp("low", 0.65, 2.5, 30),
+            make_psp("high", 0.85, 3.5, 20),
+            make_psp("mid", 0.75, 3.0, 25),
+        ];
+        let circuits = HashMap::from([
+            ("high".to_string(), BreakerState::HalfOpen),
+            ("mid".to_string(), BreakerState::Open),
+        ]);
+
+        let ordered = select_psp_order(
+            &psps,
+            &RoutingStrategy::OptimizeForApprovals,
+            &make_txn(15_000, 1),
+            &CostModel::default(),
+            &circuits,
+            &HashMap::new(),
+        );
+        let ids: Vec<&str> = ordered.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["low", "high"]);
+    }
+
+    #[test]
+    fn test_adaptive_sorts_by_learned_scores() {
+        let psps = vec![
+            make_psp("low", 0.65, 2.5, 30),
+            make_psp("high", 0.85, 3.5, 20),
+            make_psp("mid", 0.75, 3.0, 25),
+        ];
+        let scores = HashMap::from([("low".to_string(), 0.95), ("high".to_string(), 0.40)]);
+
+        let ordered = select_psp_order(
+            &psps,
+            &RoutingStrategy::Adaptive,
+            &make_txn(15_000, 1),
+            &CostModel::default(),
+            &HashMap::new(),
+            &scores,
+        );
+        let ids: Vec<&str> = ordered.iter().map(|p| p.id.as_str()).collect();
+        // "mid" has no score and keeps its configured 0.75
+        assert_eq!(ids, ["low", "mid", "high"]);
+
+        let fallback = order(&psps, &RoutingStrategy::Adaptive, 1);
+        assert_eq!(fallback[0].id, "high");
+    }
+
+    #[test]
+    fn test_cost_ranking_depends_on_amount() {
+        let psps = vec![
+            make_psp("fixed_heavy", 0.75, 1.5, 50),
+            make_psp("percent_heavy", 0.75, 3.0, 5),
+        ];
+        let cheapest = |minor_units| {
+            let txn = make_txn(minor_units, 1);
+            let ordered = select_psp_order(
+                &psps,
+                &RoutingStrategy::OptimizeForCost,
+                &txn,
+                &CostModel::default(),
+                &HashMap::new(),
+                &HashMap::new(),
+            );
+            ordered[0].id.clone()
+        };
+
+        // BRL 10.00: 0.15 + 3.09 vs 0.30 + 0.31
+        assert_eq!(cheapest(1_000), "percent_heavy");
+        // BRL 5000.00: 75.00 + 3.09 vs 150.00 + 0.31
+        assert_eq!(cheapest(500_000), "fixed_heavy");
+    }
+
+    #[test]
+    fn test_expected_value_trades_approval_against_fees() {
+        let psps = vec![
+            make_psp("reliable", 0.90, 3.5, 40),
+            make_psp("cheap", 0.80, 2.0, 5),
+        ];
+        let best = |minor_units, scores: &HashMap<String, f64>| {
+            let txn = make_txn(minor_units, 1);
+            let ordered = select_psp_order(
+                &psps,
+                &RoutingStrategy::ExpectedValue,
+                &txn,
+                &CostModel::default(),
+                &HashMap::new(),
+                scores,
+            );
+            ordered[0].id.clone()
+        };
+
+        // On a large order the extra 10 points of approval outweigh the fee
+        assert_eq!(best(500_000, &HashMap::new()), "reliable");
+        // On BRL 10.00 the fixed fee eats most of the margin
+        assert_eq!(best(1_000, &HashMap::new()), "cheap");
+        // Learned approval rates replace the configured ones
+        let scores = HashMap::from([("cheap".to_string(), 0.99)]);
+        assert_eq!(best(500_000, &scores), "cheap");
+    }
+
     #[test]
     fn test_empty_psp_list_returns_empty() {
         let psps: Vec<PspConfig> = vec![];
-        let ordered = select_psp_order(&psps, &RoutingStrategy::OptimizeForApprovals);
+        let ordered = order(&psps, &RoutingStrategy::OptimizeForApprovals, 1);
         assert!(ordered.is_empty());
     }
 
     #[test]
     fn test_single_psp_returns_unchanged() {
         let psps = vec![make_psp("solo", 0.80, 3.0, 25)];
-        let ordered = select_psp_order(&psps, &RoutingStrategy::Balanced);
+        let ordered = order(&psps, &RoutingStrategy::Balanced, 1);
         assert_eq!(ordered.len(), 1);
         assert_eq!(ordered[0].id, "solo");
     }
@@ -163,7 +407,7 @@ mod tests {
             make_psp("high", 0.85, 3.5, 20),
         ];
 
-        let _ = select_psp_order(&psps, &RoutingStrategy::OptimizeForApprovals);
+        let _ = order(&psps, &RoutingStrategy::OptimizeForApprovals, 1);
 
         // Original order preserved
         assert_eq!(psps[0].id, "low");
@@ -0,0 +1,465 @@
+//! Foreign-exchange rates for normalizing amounts to USD.
+//!
+//! Rates are dated and quoted as local currency units per 1 USD
+//! (e.g., `BRL 6.18` means 1 USD buys 6.18 BRL). A table can be
+//! built in code, loaded from a JSON or CSV file, or taken from the
+//! built-in defaults compiled in from `config/fx_rates.csv`. The currencies
+//! a table must cover are the catalog's: see [`FxTable::check_currencies`].
+//!
+//! # File Formats
+//!
+//! JSON — an array of rate records:
+//!
+//! ```json
+//! [{ "currency": "BRL", "date": "2025-01-01", "units_per_usd": "6.18" }]
+//! ```
+//!
+//! CSV — a `currency,date,units_per_usd` header followed by one rate per line.
+//! Blank lines and lines starting with `#` are ignored.
+//!
+//! # Conversion
+//!
+//! Conversions between two local currencies use the cross rate through
+//! USD. They use integer arithmetic on minor units and round once, half
+//! away from zero, to the target currency's minor unit.
+
+use crate::catalog::PspCatalog;
+use crate::models::money::Money;
+use crate::models::transaction::Currency;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Environment variable pointing at a rates file that overrides the built-in one.
+pub const FX_RATES_PATH_ENV: &str = "FX_RATES_PATH";
+
+/// Fixed-point scale for stored rates (6 decimal places).
+const RATE_SCALE: i64 = 1_000_000;
+
+/// Built-in rates source.
+const BUILTIN_RATES: &str = include_str!("../../config/fx_rates.csv");
+
+/// A dated exchange rate for one currency against USD.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FxRate {
+    /// Currency the rate is quoted for.
+    pub currency: Currency,
+    /// Date the rate takes effect (`YYYY-MM-DD`).
+    pub date: String,
+    /// Local currency units per 1 USD, scaled by 1,000,000.
+    pub units_per_usd_micros: i64,
+}
+
+/// Errors produced while loading or applying FX rates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FxError {
+    /// The rates file could not be read.
+    Io(String),
+    /// The rates file could not be parsed.
+    Parse(String),
+    /// The file extension is neither `.json` nor `.csv`.
+    UnsupportedFormat(String),
+    /// A date is not in `YYYY-MM-DD` form.
+    InvalidDate(String),
+    /// A rate is zero, negative, or not a finite number.
+    InvalidRate { currency: Currency, date: String },
+    /// The same currency and date appear more than once.
+    DuplicateRate { currency: Currency, date: String },
+    /// No rate is available for the currency.
+    MissingCurrency(Currency),
+    /// The converted amount does not fit in `i64` minor units.
+    Overflow,
+}
+
+impl std::fmt::Display for FxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FxError::Io(msg) => write!(f, "failed to read FX rates: {msg}"),
+            FxError::Parse(msg) => write!(f, "failed to parse FX rates: {msg}"),
+            FxError::UnsupportedFormat(path) => {
+                write!(f, "unsupported FX rates format '{path}' (expected .json or .csv)")
+            }
+            FxError::InvalidDate(date) => write!(f, "invalid FX rate date '{date}'"),
+            FxError::InvalidRate { currency, date } => {
+                write!(f, "invalid FX rate for {currency} on {date}")
+            }
+            FxError::DuplicateRate { currency, date } => {
+                write!(f, "duplicate FX rate for {currency} on {date}")
+            }
+            FxError::MissingCurrency(currency) => write!(f, "no FX rate for {currency}"),
+            FxError::Overflow => write!(f, "converted amount overflow"),
+        }
+    }
+}
+
+impl std::error::Error for FxError {}
+
+/// A validated table of dated FX rates.
+///
+/// USD is implicitly `1.0`. Whether every currency a catalog checks out in
+/// has a rate is checked against that catalog, with
+/// [`check_currencies`](FxTable::check_currencies).
+#[derive(Debug, Clone)]
+pub struct FxTable {
+    /// Rates per currency, sorted by date ascending.
+    rates: HashMap<Currency, Vec<FxRate>>,
+}
+
+/// A rate record as it appears in a JSON file.
+#[derive(Deserialize)]
+struct RateRecord {
+    currency: Currency,
+    date: String,
+    units_per_usd: RateValue,
+}
+
+/// Rates may be written as JSON numbers or decimal strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RateValue {
+    Number(f64),
+    Text(String),
+}
+
+impl FxTable {
+    /// Builds a table from a list of rates, validating every entry.
+    pub fn new(rates: Vec<FxRate>) -> Result<Self, FxError> {
+        let mut by_currency: HashMap<Currency, Vec<FxRate>> = HashMap::new();
+
+        for rate in rates {
+            if !is_valid_date(&rate.date) {
+                return Err(FxError::InvalidDate(rate.date));
+            }
+            if rate.units_per_usd_micros <= 0 {
+                return Err(FxError::InvalidRate {
+                    currency: rate.currency,
+                    date: rate.date,
+                });
+            }
+            let entries = by_currency.entry(rate.currency.clone()).or_default();
+            if entries.iter().any(|r| r.date == rate.date) {
+                return Err(FxError::DuplicateRate {
+                    currency: rate.currency,
+                    date: rate.date,
+                });
+            }
+            entries.push(rate);
+        }
+
+        for entries in by_currency.values_mut() {
+            entries.sort_by(|a, b| a.date.cmp(&b.date));
+        }
+
+        Ok(FxTable { rates: by_currency })
+    }
+
+    /// Built-in reference rates compiled from `config/fx_rates.csv`, used
+    /// when no rates file is supplied.
+    pub fn builtin() -> Self {
+        FxTable::from_csv_str(BUILTIN_RATES).expect("built-in FX rates are valid")
+    }
+
+    /// Loads the rates named by [`FX_RATES_PATH_ENV`], or the built-in ones
+    /// when the variable is unset.
+    pub fn from_env() -> Result<Self, FxError> {
+        match std::env::var(FX_RATES_PATH_ENV) {
+            Ok(path) if !path.is_empty() => FxTable::load(path),
+            _ => Ok(FxTable::builtin()),
+        }
+    }
+
+    /// Loads a table from a `.json` or `.csv` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FxError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| FxError::Io(e.to_string()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => FxTable::from_json_str(&contents),
+            Some("csv") => FxTable::from_csv_str(&contents),
+            _ => Err(FxError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+
+    /// Parses a JSON array of rate records.
+    pub fn from_json_str(json: &str) -> Result<Self, FxError> {
+        let records: Vec<RateRecord> =
+            serde_json::from_str(json).map_err(|e| FxError::Parse(e.to_string()))?;
+        let rates = records
+            .into_iter()
+            .map(|r| {
+                let value = match r.units_per_usd {
+                    RateValue::Number(n) => n,
+                    RateValue::Text(s) => parse_rate_value(&s)?,
+                };
+                build_rate(r.currency, r.date, value)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        FxTable::new(rates)
+    }
+
+    /// Parses CSV with a `currency,date,units_per_usd` header.
+    pub fn from_csv_str(csv: &str) -> Result<Self, FxError> {
+        let mut lines = csv
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'));
+
+        let header = lines
+            .next()
+            .ok_or_else(|| FxError::Parse("empty CSV".into()))?;
+        let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+        if columns != ["currency", "date", "units_per_usd"] {
+            return Err(FxError::Parse(format!("unexpected CSV header '{header}'")));
+        }
+
+        let mut rates = Vec::new();
+        for line in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [code, date, value] = fields[..] else {
+                return Err(FxError::Parse(format!("expected 3 fields in '{line}'")));
+            };
+            if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+                return Err(FxError::Parse(format!("invalid currency code '{code}'")));
+            }
+            let currency = Currency::new(code);
+            rates.push(build_rate(currency, date.to_string(), parse_rate_value(value)?)?);
+        }
+        FxTable::new(rates)
+    }
+
+    /// Check that the table can convert every currency `catalog` declares a
+    /// country in, so fees and reports never meet a currency without a rate.
+    pub fn check_currencies(&self, catalog: &PspCatalog) -> Result<(), FxError> {
+        for country in catalog.countries() {
+            let currency = &country.currency;
+            if *currency != Currency::USD && !self.rates.contains_key(currency) {
+                return Err(FxError::MissingCurrency(currency.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the rate in effect for `currency` on `date`.
+    ///
+    /// `date` may be a full ISO 8601 timestamp; only the date part is used.
+    /// Picks the latest rate on or before the date, falling back to the
+    /// earliest known rate for dates before the table begins.
+    pub fn rate_on(&self, currency: &Currency, date: &str) -> Result<&FxRate, FxError> {
+        let entries = self
+            .rates
+            .get(currency)
+            .ok_or_else(|| FxError::MissingCurrency(currency.clone()))?;
+        let day = date.get(..10).unwrap_or(date);
+        entries
+            .iter()
+            .rev()
+            .find(|r| r.date.as_str() <= day)
+            .or_else(|| entries.first())
+            .ok_or_else(|| FxError::MissingCurrency(currency.clone()))
+    }
+
+    /// Converts an amount to USD at the rate in effect on `date`.
+    pub fn to_usd(&self, amount: &Money, date: &str) -> Result<Money, FxError> {
+        self.convert(amount, &Currency::USD, date)
+    }
+
+    /// Converts a USD amount into `currency` at the rate in effect on `date`.
+    pub fn from_usd(&self, usd: &Money, currency: &Currency, date: &str) -> Result<Money, FxError> {
+        self.convert(usd, currency, date)
+    }
+
+    /// Converts an amount into any currency at the cross rate through USD,
+    /// rounding once to the target currency's minor unit.
+    pub fn convert(&self, amount: &Money, to: &Currency, date: &str) -> Result<Money, FxError> {
+        if amount.currency == *to {
+            return Ok(amount.clone());
+        }
+        let from_rate = self.units_per_usd_micros(&amount.currency, date)?;
+        let to_rate = self.units_per_usd_micros(to, date)?;
+        let numerator = amount.minor_units as i128 * to.minor_per_major() as i128 * to_rate as i128;
+        let denominator = amount.currency.minor_per_major() as i128 * from_rate as i128;
+        to_money(div_round(numerator, denominator), to.clone())
+    }
+
+    /// Units of `currency` per 1 USD on `date`, scaled by 1,000,000.
+    fn units_per_usd_micros(&self, currency: &Currency, date: &str) -> Result<i64, FxError> {
+        if *currency == Currency::USD {
+            return Ok(RATE_SCALE);
+        }
+        Ok(self.rate_on(currency, date)?.units_per_usd_micros)
+    }
+}
+
+impl Default for FxTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Builds an [`FxRate`] from a decimal rate value.
+fn build_rate(currency: Currency, date: String, units_per_usd: f64) -> Result<FxRate, FxError> {
+    if !units_per_usd.is_finite() || units_per_usd <= 0.0 {
+        return Err(FxError::InvalidRate { currency, date });
+    }
+    Ok(FxRate {
+        currency,
+        date,
+        units_per_usd_micros: (units_per_usd * RATE_SCALE as f64).round() as i64,
+    })
+}
+
+/// Parses a textual rate value.
+fn parse_rate_value(s: &str) -> Result<f64, FxError> {
+    s.trim()
+        .parse()
+        .map_err(|_| FxError::Parse(format!("invalid rate '{s}'")))
+}
+
+/// Returns true if `date` looks like `YYYY-MM-DD`.
+fn is_valid_date(date: &str) -> bool {
+    let bytes = date.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+/// Integer division rounding half away from zero (`denominator > 0`).
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let half = denominator / 2;
+    if numerator >= 0 {
+        (numerator + half) / denominator
+    } else {
+        (numerator - half) / denominator
+    }
+}
+
+fn to_money(minor_units: i128, currency: Currency) -> Result<Money, FxError> {
+    i64::try_from(minor_units)
+        .map(|m| Money::new(m, currency))
+        .map_err(|_| FxError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_converts_to_usd() {
+        let fx = FxTable::builtin();
+        // 618.00 BRL at 6.18 = 100.00 USD
+        let brl = Money::new(61800, Currency::new("BRL"));
+        assert_eq!(fx.to_usd(&brl, "2025-01-15").unwrap(), Money::new(10000, Currency::USD));
+        // 440500 COP (zero-decimal) at 4405 = 100.00 USD
+        let cop = Money::new(440_500, Currency::new("COP"));
+        assert_eq!(fx.to_usd(&cop, "2025-01-15").unwrap(), Money::new(10000, Currency::USD));
+        // 99500 CLP (zero-decimal) at 995 = 100.00 USD
+        let clp = Money::new(99_500, Currency::new("CLP"));
+        assert_eq!(fx.to_usd(&clp, "2025-01-15").unwrap(), Money::new(10000, Currency::USD));
+    }
+
+    #[test]
+    fn test_from_usd_and_cross_conversion() {
+        let fx = FxTable::builtin();
+        let usd = Money::new(10000, Currency::USD);
+        assert_eq!(
+            fx.from_usd(&usd, &Currency::new("MXN"), "2025-01-15").unwrap(),
+            Money::new(206_200, Currency::new("MXN"))
+        );
+        let brl = Money::new(61800, Currency::new("BRL"));
+        assert_eq!(
+            fx.convert(&brl, &Currency::new("COP"), "2025-01-15").unwrap(),
+            Money::new(440_500, Currency::new("COP"))
+        );
+        // 0.01 BRL is less than half a US cent, but 3 MXN centavos at the
+        // cross rate: nothing is lost to an intermediate rounding
+        assert_eq!(
+            fx.convert(&Money::new(1, Currency::new("BRL")), &Currency::new("MXN"), "2025-01-15").unwrap(),
+            Money::new(3, Currency::new("MXN"))
+        );
+    }
+
+    #[test]
+    fn test_rate_on_picks_latest_rate_not_after_date() {
+        let csv = "currency,date,units_per_usd\n\
+                   BRL,2025-01-01,6.00\n\
+                   BRL,2025-02-01,5.00\n\
+                   MXN,2025-01-01,20\n\
+                   COP,2025-01-01,4000\n\
+                   CLP,2025-01-01,950\n\
+                   PEN,2025-01-01,3.7\n\
+                   ARS,2025-01-01,1000\n";
+        let fx = FxTable::from_csv_str(csv).unwrap();
+        assert_eq!(fx.rate_on(&Currency::new("BRL"), "2025-01-31T23:59:59Z").unwrap().units_per_usd_micros, 6_000_000);
+        assert_eq!(fx.rate_on(&Currency::new("BRL"), "2025-02-01").unwrap().units_per_usd_micros, 5_000_000);
+        // Before the table begins: earliest rate.
+        assert_eq!(fx.rate_on(&Currency::new("BRL"), "2024-06-01").unwrap().units_per_usd_micros, 6_000_000);
+    }
+
+    #[test]
+    fn test_json_accepts_numbers_and_strings() {
+        let json = r#"[
+            {"currency": "BRL", "date": "2025-01-01", "units_per_usd": "6.18"},
+            {"currency": "MXN", "date": "2025-01-01", "units_per_usd": 20.62},
+            {"currency": "COP", "date": "2025-01-01", "units_per_usd": 4405},
+            {"currency": "CLP", "date": "2025-01-01", "units_per_usd": 995},
+            {"currency": "PEN", "date": "2025-01-01", "units_per_usd": "3.76"},
+            {"currency": "ARS", "date": "2025-01-01", "units_per_usd": 1032}
+        ]"#;
+        let fx = FxTable::from_json_str(json).unwrap();
+        assert_eq!(fx.rate_on(&Currency::new("MXN"), "2025-01-01").unwrap().units_per_usd_micros, 20_620_000);
+    }
+
+    #[test]
+    fn test_currencies_are_required_by_the_catalog() {
+        let uruguay = r#"
+            [[currencies]]
+            code = "UYU"
+            exponent = 2
+
+            [[countries]]
+            country = "Uruguay"
+            iso_code = "UY"
+            currency = "UYU"
+            time_zone = "BuenosAires"
+        "#;
+        let source = include_str!("../../config/psp_catalog.toml").replacen(
+            "[[currencies]]",
+            &format!("{uruguay}\n[[currencies]]"),
+            1,
+        );
+        let catalog = PspCatalog::from_toml_str(&source).unwrap();
+        let fx = FxTable::builtin();
+        assert_eq!(
+            fx.check_currencies(&catalog),
+            Err(FxError::MissingCurrency(Currency::new("UYU")))
+        );
+
+        let rates = format!("{BUILTIN_RATES}UYU,2025-01-01,43.9\n");
+        let fx = FxTable::from_csv_str(&rates).unwrap();
+        assert_eq!(fx.check_currencies(&catalog), Ok(()));
+        let uyu = Money::new(439_000, Currency::new("UYU"));
+        assert_eq!(fx.to_usd(&uyu, "2025-01-15").unwrap(), Money::new(10000, Currency::USD));
+    }
+
+    #[test]
+    fn test_validation_rejects_bad_tables() {
+        let missing_cop = "currency,date,units_per_usd\nBRL,2025-01-01,6\nMXN,2025-01-01,20\n\
+                           CLP,2025-01-01,950\nPEN,2025-01-01,3.7\nARS,2025-01-01,1000\n";
+        let catalog = PspCatalog::builtin();
+        assert_eq!(
+            FxTable::from_csv_str(missing_cop).unwrap().check_currencies(&catalog),
+            Err(FxError::MissingCurrency(Currency::new("COP")))
+        );
+        assert_eq!(FxTable::builtin().check_currencies(&catalog), Ok(()));
+
+        let bad_date = "currency,date,units_per_usd\nBRL,01/01/2025,6\n";
+        assert!(matches!(FxTable::from_csv_str(bad_date), Err(FxError::InvalidDate(_))));
+
+        let negative = "currency,date,units_per_usd\nBRL,2025-01-01,-6\n";
+        assert!(matches!(FxTable::from_csv_str(negative), Err(FxError::InvalidRate { .. })));
+
+        let duplicate = "currency,date,units_per_usd\nBRL,2025-01-01,6\nBRL,2025-01-01,6.1\n";
+        assert!(matches!(FxTable::from_csv_str(duplicate), Err(FxError::DuplicateRate { .. })));
+    }
+}
@@ -0,0 +1,311 @@
+//! Key-value storage shared by the serverless functions.
+//!
+//! `/api/authorize` keeps the payments it authorizes and the routings it
+//! pauses on a challenge, and `/api/capture`, `/api/void`, `/api/refund`
+//! and `/api/authenticate` pick them up. Each endpoint is a separate
+//! function running in its own instances, so that state cannot live in
+//! process memory (see [`state`](crate::state)): it is kept in a
+//! [`KvStore`] every function reaches.
+//!
+//! Deployments use [`RestKv`], a Redis database behind the Upstash REST
+//! API (what Vercel KV provides), configured by [`KV_URL_ENV`] and
+//! [`KV_TOKEN_ENV`]. Tests and local runs use [`MemoryKv`].
+
+use crate::state;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Environment variable naming the REST endpoint of the key-value store.
+pub const KV_URL_ENV: &str = "KV_REST_API_URL";
+
+/// Environment variable holding the token for [`KV_URL_ENV`].
+pub const KV_TOKEN_ENV: &str = "KV_REST_API_TOKEN";
+
+/// Time a request to the key-value store may take before it fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors from the key-value store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvError {
+    /// A deployment has no key-value store configured.
+    NotConfigured,
+    /// The store could not be reached or refused the command.
+    Unavailable(String),
+    /// The store answered something other than what the command returns.
+    InvalidReply(String),
+    /// A stored value could not be read back.
+    CorruptValue { key: String, details: String },
+}
+
+impl std::fmt::Display for KvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KvError::NotConfigured => {
+                write!(f, "no key-value store is configured; set {KV_URL_ENV} and {KV_TOKEN_ENV}")
+            }
+            KvError::Unavailable(e) => write!(f, "key-value store unavailable: {e}"),
+            KvError::InvalidReply(reply) => write!(f, "unexpected key-value store reply: {reply}"),
+            KvError::CorruptValue { key, details } => write!(f, "stored value {key} is invalid: {details}"),
+        }
+    }
+}
+
+impl std::error::Error for KvError {}
+
+/// String values by key, each kept until deleted or until its time to
+/// live runs out.
+///
+/// Every command is atomic, so concurrent functions can claim a key with
+/// [`set_new`](KvStore::set_new) or consume one with
+/// [`take`](KvStore::take) without racing each other.
+pub trait KvStore: std::fmt::Debug + Send + Sync {
+    /// The value of `key`, if set.
+    fn get(&self, key: &str) -> Result<Option<String>, KvError>;
+
+    /// Set `key` to `value`, replacing any value, for `ttl` or for good.
+    fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), KvError>;
+
+    /// Set `key` to `value` unless it is already set. Returns false, and
+    /// leaves the value alone, if it was.
+    fn set_new(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<bool, KvError>;
+
+    /// Remove `key` and return its value, if it was set. Of concurrent
+    /// takes of one key, only one gets the value.
+    fn take(&self, key: &str) -> Result<Option<String>, KvError>;
+
+    /// Remove `key`, if set.
+    fn delete(&self, key: &str) -> Result<(), KvError>;
+}
+
+/// The store the API handlers share: [`RestKv`] when [`KV_URL_ENV`] and
+/// [`KV_TOKEN_ENV`] are set, or else this process's [`MemoryKv`].
+///
+/// Process memory is not shared between functions, so a Vercel preview or
+/// production deployment (`VERCEL_ENV`) without the variables is an error
+/// rather than falling back on it.
+pub fn from_env() -> Result<Arc<dyn KvStore>, KvError> {
+    let url = std::env::var(KV_URL_ENV).unwrap_or_default();
+    let token = std::env::var(KV_TOKEN_ENV).unwrap_or_default();
+    if !url.is_empty() && !token.is_empty() {
+        return Ok(Arc::new(RestKv::new(url, token)));
+    }
+    match std::env::var("VERCEL_ENV").as_deref() {
+        Ok("production" | "preview") => Err(KvError::NotConfigured),
+        _ => Ok(state::per_process(MemoryKv::default)),
+    }
+}
+
+/// A Redis database reached through the Upstash REST API.
+///
+/// Each command is one HTTPS request posting the command as a JSON array.
+pub struct RestKv {
+    url: String,
+    token: String,
+    agent: ureq::Agent,
+}
+
+impl std::fmt::Debug for RestKv {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The token is a credential, so it is left out
+        f.debug_struct("RestKv").field("url", &self.url).finish_non_exhaustive()
+    }
+}
+
+impl RestKv {
+    /// The database at REST endpoint `url`, authorized by `token`.
+    pub fn new(url: impl Into<String>, token: impl Into<String>) -> Self {
+        RestKv {
+            url: url.into(),
+            token: token.into(),
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+        }
+    }
+
+    /// Run one command and return its result.
+    fn command(&self, command: Value) -> Result<Value, KvError> {
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .send_json(command);
+        let body: Value = match response {
+            Ok(response) => response.into_json(),
+            // Refused commands come back with an error status and a body
+            // naming the error
+            Err(ureq::Error::Status(_, response)) => response.into_json(),
+            Err(e) => return Err(KvError::Unavailable(e.to_string())),
+        }
+        .map_err(|e| KvError::InvalidReply(e.to_string()))?;
+        reply(body)
+    }
+
+    /// `PX` and the time to live in milliseconds, for `SET`.
+    fn expiry(ttl: Option<Duration>) -> Vec<Value> {
+        // Redis rejects a zero expiry, so the shortest one is a millisecond
+        ttl.map(|ttl| vec![json!("PX"), json!(ttl.as_millis().max(1) as u64)])
+            .unwrap_or_default()
+    }
+}
+
+/// The result of a REST API reply, `{"result": ...}` or `{"error": ...}`.
+fn reply(mut body: Value) -> Result<Value, KvError> {
+    if let Some(error) = body.get("error") {
+        return Err(KvError::Unavailable(error.as_str().unwrap_or_default().to_string()));
+    }
+    match body.get_mut("result") {
+        Some(result) => Ok(result.take()),
+        None => Err(KvError::InvalidReply(body.to_string())),
+    }
+}
+
+/// A string or nil result.
+fn optional_string(result: Value) -> Result<Option<String>, KvError> {
+    match result {
+        Value::Null => Ok(None),
+        Value::String(value) => Ok(Some(value)),
+        other => Err(KvError::InvalidReply(other.to_string())),
+    }
+}
+
+impl KvStore for RestKv {
+    fn get(&self, key: &str) -> Result<Option<String>, KvError> {
+        optional_string(self.command(json!(["GET", key]))?)
+    }
+
+    fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), KvError> {
+        let mut command = vec![json!("SET"), json!(key), json!(value)];
+        command.extend(RestKv::expiry(ttl));
+        self.command(Value::Array(command)).map(|_| ())
+    }
+
+    fn set_new(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<bool, KvError> {
+        let mut command = vec![json!("SET"), json!(key), json!(value), json!("NX")];
+        command.extend(RestKv::expiry(ttl));
+        // "OK" when set, nil when the key was already set
+        Ok(optional_string(self.command(Value::Array(command))?)?.is_some())
+    }
+
+    fn take(&self, key: &str) -> Result<Option<String>, KvError> {
+        optional_string(self.command(json!(["GETDEL", key]))?)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), KvError> {
+        self.command(json!(["DEL", key])).map(|_| ())
+    }
+}
+
+/// Values kept in this process's memory.
+///
+/// Seen only by the process that keeps them, so fit for tests and local
+/// runs, where every endpoint is served by one process, but not for a
+/// deployment.
+#[derive(Debug, Default)]
+pub struct MemoryKv {
+    /// Each value with the instant it expires at, if it does.
+    values: Mutex<HashMap<String, (Option<Instant>, String)>>,
+}
+
+impl MemoryKv {
+    /// An empty store.
+    pub fn new() -> Self {
+        MemoryKv::default()
+    }
+
+    /// Number of values kept and not yet expired.
+    pub fn len(&self) -> usize {
+        let now = Instant::now();
+        let values = state::lock(&self.values);
+        values.values().filter(|(expires, _)| expires.is_none_or(|e| e > now)).count()
+    }
+
+    /// Returns true if no value is kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Set `key`, unless `only_new` and it is set. Expired values are
+    /// dropped first.
+    fn insert(&self, key: &str, value: &str, ttl: Option<Duration>, only_new: bool) -> bool {
+        let now = Instant::now();
+        let mut values = state::lock(&self.values);
+        values.retain(|_, (expires, _)| expires.is_none_or(|e| e > now));
+        if only_new && values.contains_key(key) {
+            return false;
+        }
+        let expires = ttl.map(|ttl| now + ttl);
+        values.insert(key.to_string(), (expires, value.to_string()));
+        true
+    }
+}
+
+impl KvStore for MemoryKv {
+    fn get(&self, key: &str) -> Result<Option<String>, KvError> {
+        let values = state::lock(&self.values);
+        let live = values.get(key).filter(|(expires, _)| expires.is_none_or(|e| e > Instant::now()));
+        Ok(live.map(|(_, value)| value.clone()))
+    }
+
+    fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), KvError> {
+        self.insert(key, value, ttl, false);
+        Ok(())
+    }
+
+    fn set_new(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<bool, KvError> {
+        Ok(self.insert(key, value, ttl, true))
+    }
+
+    fn take(&self, key: &str) -> Result<Option<String>, KvError> {
+        let removed = state::lock(&self.values).remove(key);
+        let live = removed.filter(|(expires, _)| expires.is_none_or(|e| e > Instant::now()));
+        Ok(live.map(|(_, value)| value))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), KvError> {
+        state::lock(&self.values).remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_values_are_claimed_and_taken_once() {
+        let kv = MemoryKv::new();
+        assert!(kv.set_new("a", "1", None).unwrap());
+        assert!(!kv.set_new("a", "2", None).unwrap());
+        assert_eq!(kv.get("a").unwrap().as_deref(), Some("1"));
+
+        kv.set("a", "3", None).unwrap();
+        assert_eq!(kv.take("a").unwrap().as_deref(), Some("3"));
+        assert_eq!(kv.take("a").unwrap(), None);
+        assert!(kv.set_new("a", "4", None).unwrap());
+        kv.delete("a").unwrap();
+        assert!(kv.is_empty());
+    }
+
+    #[test]
+    fn test_expired_memory_values_are_gone() {
+        let kv = MemoryKv::new();
+        kv.set("a", "1", Some(Duration::ZERO)).unwrap();
+        assert_eq!(kv.get("a").unwrap(), None);
+        assert!(kv.set_new("a", "2", Some(Duration::from_secs(60))).unwrap());
+        assert_eq!(kv.get("a").unwrap().as_deref(), Some("2"));
+        assert_eq!(kv.len(), 1);
+    }
+
+    #[test]
+    fn test_rest_replies_are_read() {
+        assert_eq!(reply(json!({"result": "OK"})).unwrap(), json!("OK"));
+        assert_eq!(optional_string(reply(json!({"result": null})).unwrap()).unwrap(), None);
+        assert_eq!(
+            reply(json!({"error": "WRONGPASS invalid password"})).unwrap_err(),
+            KvError::Unavailable("WRONGPASS invalid password".into())
+        );
+        assert!(matches!(reply(json!({})), Err(KvError::InvalidReply(_))));
+        assert!(matches!(optional_string(json!(1)), Err(KvError::InvalidReply(_))));
+    }
+}
@@ -7,10 +7,22 @@
 //! to keep handlers thin and logic reusable.
 
 pub mod models;
+pub mod card_bin;
+pub mod catalog;
+pub mod codes;
 pub mod simulator;
+pub mod connector;
+pub mod credentials;
 pub mod engine;
+pub mod kv;
+pub mod payments;
+pub mod sessions;
+pub(crate) mod state;
 pub mod data;
+pub mod fx;
 pub mod report;
+pub mod rules;
+pub mod time;
 
 /// Returns the crate version from Cargo.toml at compile time.
 pub fn version() -> &'static str {
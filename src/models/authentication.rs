@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use super::psp::PspConfig;
+use super::routing::{DecisionTrace, RoutingAttempt, RoutingStrategy};
+use super::transaction::Transaction;
+use crate::engine::retry::RetryPolicy;
+
+/// How the cardholder was authenticated with 3-D Secure.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ThreeDsFlow {
+    /// The issuer authenticated the cardholder from the device and
+    /// transaction data alone.
+    Frictionless,
+    /// The cardholder answered the issuer's challenge (a one-time code or a
+    /// banking app approval).
+    Challenge,
+}
+
+/// Outcome of a 3-D Secure step.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ThreeDsStatus {
+    /// The cardholder is authenticated; the authorization can be resent
+    /// with the authentication data.
+    Authenticated,
+    /// The issuer wants to challenge the cardholder before authenticating.
+    ChallengeRequired,
+    /// The issuer refused to authenticate the cardholder.
+    Failed,
+    /// The PSP's 3-D Secure server could not be reached.
+    Unavailable,
+}
+
+/// A PSP's answer to a 3-D Secure authentication or challenge.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThreeDsResponse {
+    /// PSP whose 3-D Secure server answered.
+    pub psp_id: String,
+    /// `Frictionless` for the initial authentication, `Challenge` for the
+    /// result of a challenge.
+    pub flow: ThreeDsFlow,
+    pub status: ThreeDsStatus,
+    /// Identifies the challenge to the PSP; set when `status` is
+    /// `ChallengeRequired`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Latency of this step in milliseconds.
+    pub latency_ms: u64,
+}
+
+impl ThreeDsResponse {
+    /// The answer of a PSP whose 3-D Secure server nobody could reach, or
+    /// that has none.
+    pub fn unavailable(psp: &PspConfig, flow: ThreeDsFlow) -> Self {
+        ThreeDsResponse {
+            psp_id: psp.id.clone(),
+            flow,
+            status: ThreeDsStatus::Unavailable,
+            session_id: None,
+            latency_ms: 0,
+        }
+    }
+}
+
+/// Proof that a transaction's cardholder was authenticated, sent with the
+/// authorization to the PSP that authenticated them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThreeDsAuthentication {
+    /// PSP that authenticated the cardholder.
+    pub psp_id: String,
+    pub flow: ThreeDsFlow,
+}
+
+/// A routing paused on a 3-D Secure challenge.
+///
+/// Returned in [`RoutingResult::challenge`](super::routing::RoutingResult::challenge)
+/// and resumed once the cardholder has answered the challenge; it carries
+/// everything needed to go on where routing stopped. It stays on the
+/// server (see [`SessionStore`](crate::sessions::SessionStore)): clients
+/// are given a [`PendingChallenge`] instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticationSession {
+    /// The PSP's identifier for the challenge.
+    pub session_id: String,
+    /// PSP that issued the challenge and is resent the authorization once
+    /// it is answered.
+    pub psp_id: String,
+    /// PSP name.
+    pub psp_name: String,
+    /// The transaction being routed.
+    pub transaction: Transaction,
+    /// The strategy routing was asked for; the PSPs left to try are ranked
+    /// by it again on resuming.
+    pub strategy: RoutingStrategy,
+    /// The retry policy routing resumes under.
+    pub policy: RetryPolicy,
+    /// Whether cross-border acquirers were opted in as a fallback; resume
+    /// with an engine set up the same way.
+    #[serde(default)]
+    pub cross_border_fallback: bool,
+    /// Attempts made before the challenge.
+    pub attempts: Vec<RoutingAttempt>,
+    /// Attempts counted against `max_attempts` so far.
+    pub total_attempts: usize,
+    /// Latency spent so far, in milliseconds.
+    pub total_latency_ms: u64,
+    /// 3-D Secure steps taken so far.
+    pub authentications: Vec<ThreeDsResponse>,
+    /// The decision trace so far, if routing was explained.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<DecisionTrace>,
+}
+
+/// What a client is told of a routing paused on a 3-D Secure challenge.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PendingChallenge {
+    /// Unguessable ID to post to `/api/authenticate` once the cardholder
+    /// has answered the challenge. It resumes routing once.
+    pub challenge_id: String,
+    /// PSP that issued the challenge.
+    pub psp_id: String,
+    /// PSP name.
+    pub psp_name: String,
+}
+
+/// Request body of `/api/authenticate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticationRequest {
+    /// The [`PendingChallenge::challenge_id`] of the routing to resume.
+    pub challenge_id: String,
+}
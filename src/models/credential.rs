@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a refreshed card credential came from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// A network token the card scheme keeps current as the card is
+    /// reissued.
+    NetworkToken,
+    /// The card scheme's account updater, which reports the card's new
+    /// details after it is reissued.
+    AccountUpdater,
+}
+
+/// Up-to-date credentials for a customer's card on file, sent in place of
+/// the card details the customer entered.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CardCredential {
+    pub source: CredentialSource,
+    /// The network token, or the account updater's reference for the
+    /// card's new details.
+    pub reference: String,
+    /// Expiry of the refreshed credential, as `MM/YY`.
+    pub expiry: String,
+}
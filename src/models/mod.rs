@@ -2,7 +2,11 @@
 //!
 //! These types are shared across all modules: simulator, engine, data, and report.
 
+pub mod money;
 pub mod transaction;
 pub mod psp;
 pub mod routing;
+pub mod payment;
 pub mod report;
+pub mod authentication;
+pub mod credential;
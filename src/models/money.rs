@@ -0,0 +1,403 @@
+use serde::{Deserialize, Serialize};
+
+use super::transaction::Currency;
+
+/// A monetary amount stored as an integer number of minor units.
+///
+/// Amounts never pass through `f64`: the number of minor units per major unit
+/// comes from [`Currency::exponent`], so `150.37 BRL` is stored as `15037` and
+/// `45000 COP` as `45000`. All arithmetic is checked and refuses to mix
+/// currencies.
+///
+/// # Serialization
+///
+/// Serializes as `{"minor_units": 15037, "currency": "BRL"}`. Deserialization
+/// accepts that form as well as a decimal string in major units:
+/// `{"amount": "150.37", "currency": "BRL"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "MoneyRepr")]
+pub struct Money {
+    /// Amount in the currency's minor units (e.g., centavos for BRL).
+    pub minor_units: i64,
+    /// Currency the amount is denominated in.
+    pub currency: Currency,
+}
+
+/// Errors produced by [`Money`] parsing and arithmetic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    /// The operands are denominated in different currencies.
+    CurrencyMismatch { left: Currency, right: Currency },
+    /// The result does not fit in an `i64` number of minor units.
+    Overflow,
+    /// The decimal string is not a valid amount.
+    InvalidAmount(String),
+    /// The decimal string has more fractional digits than the currency allows.
+    TooPrecise { amount: String, currency: Currency },
+}
+
+impl std::fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch { left, right } => {
+                write!(f, "currency mismatch: {left} vs {right}")
+            }
+            MoneyError::Overflow => write!(f, "amount overflow"),
+            MoneyError::InvalidAmount(s) => write!(f, "invalid amount '{s}'"),
+            MoneyError::TooPrecise { amount, currency } => write!(
+                f,
+                "amount '{amount}' has more than {} decimal places for {currency}",
+                currency.exponent()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+impl Money {
+    /// Creates an amount from a number of minor units.
+    pub fn new(minor_units: i64, currency: Currency) -> Self {
+        Money {
+            minor_units,
+            currency,
+        }
+    }
+
+    /// Creates a zero amount in the given currency.
+    pub fn zero(currency: Currency) -> Self {
+        Money::new(0, currency)
+    }
+
+    /// Parses a decimal string in major units (e.g., `"150.37"`).
+    ///
+    /// Trailing fractional zeros beyond the currency exponent are accepted
+    /// (`"45000.00"` is valid COP), any other extra precision is rejected
+    /// rather than rounded.
+    pub fn from_decimal_str(s: &str, currency: Currency) -> Result<Money, MoneyError> {
+        let invalid = || MoneyError::InvalidAmount(s.to_string());
+        let trimmed = s.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let (int_part, frac_part) = match digits.split_once('.') {
+            Some((i, f)) => (i, f),
+            None => (digits, ""),
+        };
+        if int_part.is_empty()
+            || !int_part.bytes().all(|b| b.is_ascii_digit())
+            || !frac_part.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let exponent = currency.exponent() as usize;
+        let significant = frac_part.trim_end_matches('0');
+        if significant.len() > exponent {
+            return Err(MoneyError::TooPrecise {
+                amount: s.to_string(),
+                currency,
+            });
+        }
+
+        let major: i64 = int_part.parse().map_err(|_| MoneyError::Overflow)?;
+        let frac: i64 = if significant.is_empty() {
+            0
+        } else {
+            format!("{significant:0<exponent$}")
+                .parse()
+                .map_err(|_| invalid())?
+        };
+        let minor_units = major
+            .checked_mul(currency.minor_per_major())
+            .and_then(|m| m.checked_add(frac))
+            .ok_or(MoneyError::Overflow)?;
+
+        Ok(Money::new(
+            if negative { -minor_units } else { minor_units },
+            currency,
+        ))
+    }
+
+    /// Formats the amount as a decimal string in major units (e.g., `"150.37"`).
+    pub fn to_decimal_string(&self) -> String {
+        let exponent = self.currency.exponent() as usize;
+        let scale = self.currency.minor_per_major();
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let abs = self.minor_units.unsigned_abs();
+        let major = abs / scale as u64;
+        if exponent == 0 {
+            return format!("{sign}{major}");
+        }
+        let frac = abs % scale as u64;
+        format!("{sign}{major}.{frac:0exponent$}")
+    }
+
+    /// Returns the amount in major units as `f64`.
+    ///
+    /// Only for ratios and display — never feed the result back into a `Money`.
+    pub fn to_major_f64(&self) -> f64 {
+        self.minor_units as f64 / self.currency.minor_per_major() as f64
+    }
+
+    /// Returns true if the amount is strictly greater than zero.
+    pub fn is_positive(&self) -> bool {
+        self.minor_units > 0
+    }
+
+    /// Adds two amounts of the same currency.
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        self.minor_units
+            .checked_add(other.minor_units)
+            .map(|m| Money::new(m, self.currency.clone()))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Subtracts `other` from `self`; both must share a currency.
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        self.minor_units
+            .checked_sub(other.minor_units)
+            .map(|m| Money::new(m, self.currency.clone()))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Multiplies the amount by an integer factor.
+    pub fn checked_mul(&self, factor: i64) -> Result<Money, MoneyError> {
+        self.minor_units
+            .checked_mul(factor)
+            .map(|m| Money::new(m, self.currency.clone()))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// Applies a rate expressed in basis points (1/100 of a percent).
+    ///
+    /// Rounds half away from zero to the nearest minor unit.
+    pub fn checked_apply_bps(&self, bps: i64) -> Result<Money, MoneyError> {
+        let product = (self.minor_units as i128) * (bps as i128);
+        let rounded = (product + product.signum() * 5_000) / 10_000;
+        i64::try_from(rounded)
+            .map(|m| Money::new(m, self.currency.clone()))
+            .map_err(|_| MoneyError::Overflow)
+    }
+
+    /// Sums an iterator of amounts, all of which must be in `currency`.
+    pub fn checked_sum<'a, I>(currency: Currency, amounts: I) -> Result<Money, MoneyError>
+    where
+        I: IntoIterator<Item = &'a Money>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), |acc, m| acc.checked_add(m))
+    }
+
+    fn ensure_same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch {
+                left: self.currency.clone(),
+                right: other.currency.clone(),
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.to_decimal_string(), self.currency)
+    }
+}
+
+/// Wire representations accepted when deserializing [`Money`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MoneyRepr {
+    Minor { minor_units: i64, currency: Currency },
+    Decimal { amount: String, currency: Currency },
+}
+
+impl TryFrom<MoneyRepr> for Money {
+    type Error = MoneyError;
+
+    fn try_from(repr: MoneyRepr) -> Result<Self, Self::Error> {
+        match repr {
+            MoneyRepr::Minor {
+                minor_units,
+                currency,
+            } => Ok(Money::new(minor_units, currency)),
+            MoneyRepr::Decimal { amount, currency } => Money::from_decimal_str(&amount, currency),
+        }
+    }
+}
+
+/// An amount supplied by an API client, before it is bound to a currency.
+///
+/// Clients send either a decimal string in major units (`"150.37"`) or an
+/// object with an integer number of minor units (`{"minor_units": 15037}`).
+/// A bare JSON number is rejected: it used to mean major units and would be
+/// ambiguous between the two.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged, try_from = "AmountRepr")]
+pub enum AmountInput {
+    /// Decimal string in major units.
+    Decimal(String),
+    /// Integer number of minor units.
+    MinorUnits { minor_units: i64 },
+}
+
+impl AmountInput {
+    /// Resolves the input into a [`Money`] in the given currency.
+    pub fn to_money(&self, currency: Currency) -> Result<Money, MoneyError> {
+        match self {
+            AmountInput::Decimal(s) => Money::from_decimal_str(s, currency),
+            AmountInput::MinorUnits { minor_units } => Ok(Money::new(*minor_units, currency)),
+        }
+    }
+}
+
+/// Wire representations accepted when deserializing [`AmountInput`],
+/// plus the bare number it refuses.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AmountRepr {
+    Decimal(String),
+    Minor { minor_units: i64 },
+    Bare(serde_json::Number),
+}
+
+impl TryFrom<AmountRepr> for AmountInput {
+    type Error = String;
+
+    fn try_from(repr: AmountRepr) -> Result<Self, Self::Error> {
+        match repr {
+            AmountRepr::Decimal(s) => Ok(AmountInput::Decimal(s)),
+            AmountRepr::Minor { minor_units } => Ok(AmountInput::MinorUnits { minor_units }),
+            AmountRepr::Bare(n) => Err(format!(
+                "bare number {n} is ambiguous: send a decimal string in major units (\"{n}\") \
+                 or {{\"minor_units\": {n}}}"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_decimal_strings_per_exponent() {
+        let brl = Money::from_decimal_str("150.37", Currency::new("BRL")).unwrap();
+        assert_eq!(brl.minor_units, 15037);
+
+        let mxn = Money::from_decimal_str("99.5", Currency::new("MXN")).unwrap();
+        assert_eq!(mxn.minor_units, 9950);
+
+        let cop = Money::from_decimal_str("45000", Currency::new("COP")).unwrap();
+        assert_eq!(cop.minor_units, 45000);
+
+        let cop_zeros = Money::from_decimal_str("45000.00", Currency::new("COP")).unwrap();
+        assert_eq!(cop_zeros.minor_units, 45000);
+
+        let negative = Money::from_decimal_str("-1.05", Currency::new("BRL")).unwrap();
+        assert_eq!(negative.minor_units, -105);
+    }
+
+    #[test]
+    fn test_rejects_invalid_or_too_precise_strings() {
+        assert!(matches!(
+            Money::from_decimal_str("150.375", Currency::new("BRL")),
+            Err(MoneyError::TooPrecise { .. })
+        ));
+        assert!(matches!(
+            Money::from_decimal_str("45000.5", Currency::new("COP")),
+            Err(MoneyError::TooPrecise { .. })
+        ));
+        for bad in ["", "abc", "1.2.3", ".50", "1e3"] {
+            assert!(
+                Money::from_decimal_str(bad, Currency::new("BRL")).is_err(),
+                "'{bad}' should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decimal_string_round_trip() {
+        for (s, currency) in [
+            ("150.37", Currency::new("BRL")),
+            ("0.05", Currency::new("MXN")),
+            ("45000", Currency::new("COP")),
+            ("-2.10", Currency::new("BRL")),
+        ] {
+            let money = Money::from_decimal_str(s, currency).unwrap();
+            assert_eq!(money.to_decimal_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let a = Money::new(1000, Currency::new("BRL"));
+        let b = Money::new(250, Currency::new("BRL"));
+        assert_eq!(a.checked_add(&b).unwrap().minor_units, 1250);
+        assert_eq!(a.checked_sub(&b).unwrap().minor_units, 750);
+        assert_eq!(b.checked_mul(3).unwrap().minor_units, 750);
+
+        let mxn = Money::new(100, Currency::new("MXN"));
+        assert!(matches!(
+            a.checked_add(&mxn),
+            Err(MoneyError::CurrencyMismatch { .. })
+        ));
+        assert_eq!(
+            Money::new(i64::MAX, Currency::new("BRL")).checked_add(&b),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_apply_bps_rounds_half_away_from_zero() {
+        // 2.9% of 150.37 = 4.36073 → 4.36
+        let amount = Money::new(15037, Currency::new("BRL"));
+        assert_eq!(amount.checked_apply_bps(290).unwrap().minor_units, 436);
+        // 2.5% of 0.50 = 0.0125 → 0.01
+        let small = Money::new(50, Currency::new("BRL"));
+        assert_eq!(small.checked_apply_bps(250).unwrap().minor_units, 1);
+        // 5% of 0.10 = 0.005 → 0.01
+        let half = Money::new(10, Currency::new("BRL"));
+        assert_eq!(half.checked_apply_bps(500).unwrap().minor_units, 1);
+    }
+
+    #[test]
+    fn test_serde_accepts_minor_units_and_decimal_strings() {
+        let minor: Money =
+            serde_json::from_str(r#"{"minor_units": 15037, "currency": "BRL"}"#).unwrap();
+        let decimal: Money =
+            serde_json::from_str(r#"{"amount": "150.37", "currency": "BRL"}"#).unwrap();
+        assert_eq!(minor, decimal);
+
+        let json = serde_json::to_string(&minor).unwrap();
+        assert_eq!(json, r#"{"minor_units":15037,"currency":"BRL"}"#);
+
+        assert!(serde_json::from_str::<Money>(r#"{"amount": "1.234", "currency": "BRL"}"#).is_err());
+    }
+
+    #[test]
+    fn test_amount_input_resolves_against_currency() {
+        let decimal: AmountInput = serde_json::from_str(r#""150.37""#).unwrap();
+        let minor: AmountInput = serde_json::from_str(r#"{"minor_units": 15037}"#).unwrap();
+        assert_eq!(
+            decimal.to_money(Currency::new("BRL")).unwrap(),
+            minor.to_money(Currency::new("BRL")).unwrap()
+        );
+        assert_eq!(serde_json::to_string(&minor).unwrap(), r#"{"minor_units":15037}"#);
+    }
+
+    #[test]
+    fn test_amount_input_rejects_bare_numbers() {
+        for bare in ["150", "150.5", "15037"] {
+            let err = serde_json::from_str::<AmountInput>(bare).unwrap_err();
+            assert!(err.to_string().contains("ambiguous"), "{bare}: {err}");
+        }
+    }
+}
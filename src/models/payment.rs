@@ -0,0 +1,522 @@
+use serde::{Deserialize, Serialize};
+use super::authentication::PendingChallenge;
+use super::money::{AmountInput, Money, MoneyError};
+use super::psp::DeclineReason;
+use super::routing::{AuthorizationStatus, RoutingAttempt, RoutingResult};
+use super::transaction::Transaction;
+use crate::kv::KvError;
+
+/// Where an authorized payment stands in its lifecycle.
+///
+/// ```text
+/// Authorized ──capture──▶ Captured ──refund──▶ PartiallyRefunded ──refund──▶ Refunded
+///     │                       └────────────────refund (full)─────────────────────▲
+///     └──void──▶ Voided
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PaymentState {
+    /// Funds are held on the card but not yet collected.
+    Authorized,
+    /// Funds were collected.
+    Captured,
+    /// Part of the captured amount was returned to the customer.
+    PartiallyRefunded,
+    /// The whole captured amount was returned to the customer.
+    Refunded,
+    /// The authorization was released without collecting funds.
+    Voided,
+}
+
+impl std::fmt::Display for PaymentState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            PaymentState::Authorized => "authorized",
+            PaymentState::Captured => "captured",
+            PaymentState::PartiallyRefunded => "partially refunded",
+            PaymentState::Refunded => "refunded",
+            PaymentState::Voided => "voided",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A follow-up operation on an authorized payment.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum OperationKind {
+    Capture,
+    Void,
+    Refund,
+}
+
+impl std::fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            OperationKind::Capture => "capture",
+            OperationKind::Void => "void",
+            OperationKind::Refund => "refund",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Errors that reject a lifecycle operation before it reaches the PSP.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LifecycleError {
+    /// The routing result did not authorize funds (declined or pending).
+    NotAuthorized {
+        transaction_id: String,
+        status: AuthorizationStatus,
+    },
+    /// The operation is not allowed from the payment's current state.
+    InvalidTransition {
+        state: PaymentState,
+        operation: OperationKind,
+    },
+    /// The amount is not in the payment's currency.
+    CurrencyMismatch { expected: String, found: String },
+    /// The amount is zero or negative.
+    NonPositiveAmount { operation: OperationKind },
+    /// The amount is larger than what is left to capture or refund.
+    AmountExceedsAvailable {
+        operation: OperationKind,
+        requested: Money,
+        available: Money,
+    },
+    /// The amount could not be resolved in the payment's currency.
+    InvalidAmount(MoneyError),
+    /// The PSP that approved the authorization is not in the catalog.
+    UnknownPsp(String),
+    /// No authorized payment is stored for the transaction ID.
+    UnknownPayment(String),
+    /// A payment is already stored for the transaction ID.
+    DuplicatePayment(String),
+    /// Another operation on the payment is in flight.
+    PaymentBusy(String),
+    /// The payment store could not be read or written.
+    Storage(KvError),
+}
+
+impl std::fmt::Display for LifecycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LifecycleError::NotAuthorized { transaction_id, status } => {
+                write!(f, "transaction {transaction_id} was not authorized (status {status:?})")
+            }
+            LifecycleError::InvalidTransition { state, operation } => {
+                write!(f, "cannot {operation} a payment that is {state}")
+            }
+            LifecycleError::CurrencyMismatch { expected, found } => {
+                write!(f, "amount is in {found} but the payment is in {expected}")
+            }
+            LifecycleError::NonPositiveAmount { operation } => {
+                write!(f, "{operation} amount must be greater than 0")
+            }
+            LifecycleError::AmountExceedsAvailable { operation, requested, available } => {
+                write!(f, "{operation} of {requested} exceeds the {available} available")
+            }
+            LifecycleError::InvalidAmount(e) => write!(f, "invalid amount: {e}"),
+            LifecycleError::UnknownPsp(id) => write!(f, "PSP '{id}' is not in the catalog"),
+            LifecycleError::UnknownPayment(id) => {
+                write!(f, "no authorized payment for transaction {id}")
+            }
+            LifecycleError::DuplicatePayment(id) => {
+                write!(f, "a payment is already stored for transaction {id}")
+            }
+            LifecycleError::PaymentBusy(id) => {
+                write!(f, "another operation on transaction {id} is in progress; retry once it completes")
+            }
+            LifecycleError::Storage(e) => write!(f, "payment store: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LifecycleError {}
+
+/// The PSP's answer to one follow-up operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentOperation {
+    /// Which operation was sent.
+    pub kind: OperationKind,
+    /// Amount the operation was for.
+    pub amount: Money,
+    /// Whether the PSP accepted the operation.
+    pub approved: bool,
+    /// Raw response code the PSP returned.
+    pub response_code: String,
+    /// Normalized reason the PSP rejected the operation (None if approved).
+    pub decline_reason: Option<DeclineReason>,
+    /// Latency of the PSP call in milliseconds.
+    pub latency_ms: u64,
+}
+
+/// An authorized payment and everything done to it since.
+///
+/// Follow-up operations always go to the PSP that approved the
+/// authorization (`psp_id`). Every call is recorded in `operations`,
+/// including the ones the PSP rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    /// Transaction ID of the original authorization.
+    pub transaction_id: String,
+    /// PSP that approved the authorization.
+    pub psp_id: String,
+    /// Name of that PSP.
+    pub psp_name: String,
+    /// Current lifecycle state.
+    pub state: PaymentState,
+    /// Amount authorized.
+    pub authorized_amount: Money,
+    /// Amount collected by capture.
+    pub captured_amount: Money,
+    /// Amount returned by refunds so far.
+    pub refunded_amount: Money,
+    /// Follow-up operations in the order they were sent.
+    #[serde(default)]
+    pub operations: Vec<PaymentOperation>,
+}
+
+impl Payment {
+    /// Start the lifecycle of a transaction the engine approved.
+    ///
+    /// Pending voucher and transfer payments settle on their own and
+    /// declined ones have nothing to capture, so both are rejected.
+    pub fn from_authorization(
+        transaction: &Transaction,
+        result: &RoutingResult,
+    ) -> Result<Payment, LifecycleError> {
+        let attempt = match result.status {
+            AuthorizationStatus::Approved => {
+                result.attempts.iter().rev().find(|a| a.approved && !a.cancelled)
+            }
+            _ => None,
+        };
+        let attempt = attempt.ok_or_else(|| LifecycleError::NotAuthorized {
+            transaction_id: result.transaction_id.clone(),
+            status: result.status.clone(),
+        })?;
+        Ok(Payment::authorized_by(transaction, attempt))
+    }
+
+    /// The authorized payment `attempt` created, whether or not routing
+    /// kept it (a cancelled hedge still has to be voided).
+    pub(crate) fn authorized_by(transaction: &Transaction, attempt: &RoutingAttempt) -> Payment {
+        let currency = transaction.amount.currency.clone();
+        Payment {
+            transaction_id: transaction.id.clone(),
+            psp_id: attempt.psp_id.clone(),
+            psp_name: attempt.psp_name.clone(),
+            state: PaymentState::Authorized,
+            authorized_amount: transaction.amount.clone(),
+            captured_amount: Money::zero(currency.clone()),
+            refunded_amount: Money::zero(currency),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Amount still available to the given operation from the current state.
+    pub fn available(&self, operation: OperationKind) -> Result<Money, LifecycleError> {
+        let invalid = LifecycleError::InvalidTransition {
+            state: self.state,
+            operation,
+        };
+        match (operation, self.state) {
+            (OperationKind::Capture | OperationKind::Void, PaymentState::Authorized) => {
+                Ok(self.authorized_amount.clone())
+            }
+            (OperationKind::Refund, PaymentState::Captured | PaymentState::PartiallyRefunded) => {
+                self.captured_amount
+                    .checked_sub(&self.refunded_amount)
+                    .map_err(|_| invalid)
+            }
+            _ => Err(invalid),
+        }
+    }
+
+    /// Check an operation against the state machine and resolve its amount.
+    ///
+    /// `amount` defaults to everything available. Captures may be for less
+    /// than the authorized amount; the remainder is released. Voids always
+    /// release the full authorization.
+    pub fn prepare(
+        &self,
+        operation: OperationKind,
+        amount: Option<Money>,
+    ) -> Result<Money, LifecycleError> {
+        let available = self.available(operation)?;
+        let requested = match (operation, amount) {
+            (OperationKind::Void, _) | (_, None) => return Ok(available),
+            (_, Some(amount)) => amount,
+        };
+        if requested.currency != available.currency {
+            return Err(LifecycleError::CurrencyMismatch {
+                expected: available.currency.to_string(),
+                found: requested.currency.to_string(),
+            });
+        }
+        if !requested.is_positive() {
+            return Err(LifecycleError::NonPositiveAmount { operation });
+        }
+        if requested.minor_units > available.minor_units {
+            return Err(LifecycleError::AmountExceedsAvailable {
+                operation,
+                requested,
+                available,
+            });
+        }
+        Ok(requested)
+    }
+
+    /// Record a PSP response, moving the state forward if it was approved.
+    ///
+    /// The operation must have come from [`prepare`](Payment::prepare) on
+    /// this payment's current state.
+    pub fn record(&mut self, operation: PaymentOperation) {
+        if operation.approved {
+            match operation.kind {
+                OperationKind::Capture => {
+                    self.captured_amount = operation.amount.clone();
+                    self.state = PaymentState::Captured;
+                }
+                OperationKind::Void => self.state = PaymentState::Voided,
+                OperationKind::Refund => {
+                    self.refunded_amount = self
+                        .refunded_amount
+                        .checked_add(&operation.amount)
+                        .expect("refund was prepared against this payment");
+                    self.state = if self.refunded_amount == self.captured_amount {
+                        PaymentState::Refunded
+                    } else {
+                        PaymentState::PartiallyRefunded
+                    };
+                }
+            }
+        }
+        self.operations.push(operation);
+    }
+}
+
+/// API response body for `/api/authorize`: the routing result plus, when
+/// funds were authorized, the payment the follow-up endpoints will act on,
+/// or the challenge to answer when routing paused on one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationResponse {
+    #[serde(flatten)]
+    pub result: RoutingResult,
+    /// Present only for approved authorizations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment: Option<Payment>,
+    /// Present only when the status is `ChallengeRequired`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<PendingChallenge>,
+}
+
+/// API request body for the capture, void and refund endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentOperationRequest {
+    /// Transaction ID of the authorization to act on.
+    pub transaction_id: String,
+    /// Amount in the payment's currency, as a decimal string or minor
+    /// units. Defaults to everything available; ignored for voids.
+    #[serde(default)]
+    pub amount: Option<AmountInput>,
+}
+
+/// API response body for the capture, void and refund endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentOperationResponse {
+    /// The payment after the operation.
+    pub payment: Payment,
+    /// The PSP's answer to this operation.
+    pub operation: PaymentOperation,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::routing::RetryType;
+    use crate::models::transaction::{Country, Currency, PaymentMethod};
+    use crate::time::Timestamp;
+
+    fn make_transaction() -> Transaction {
+        Transaction {
+            id: "txn_lifecycle".into(),
+            amount: Money::new(10000, Currency::new("BRL")),
+            country: Country::new("Brazil"),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some("411111".into()),
+            card_last4: Some("1234".into()),
+            bin_info: None,
+            installments: None,
+            customer_id: "cust_001".into(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
+        }
+    }
+
+    fn make_result(status: AuthorizationStatus) -> RoutingResult {
+        let attempt = |psp: &str, approved: bool, n: usize| RoutingAttempt {
+            psp_id: psp.into(),
+            psp_name: psp.to_uppercase(),
+            approved,
+            response_code: if approved { "00".into() } else { "05".into() },
+            decline_reason: (!approved).then_some(DeclineReason::DoNotHonor),
+            latency_ms: 200,
+            attempt_number: n,
+            retry_type: RetryType::Initial,
+            backoff_ms: 0,
+            hedged: false,
+            timed_out: false,
+            cancelled: false,
+            cross_border: false,
+            three_ds: None,
+            credential: None,
+        };
+        let approved = status != AuthorizationStatus::Declined;
+        RoutingResult {
+            transaction_id: "txn_lifecycle".into(),
+            approved: status == AuthorizationStatus::Approved,
+            status,
+            final_psp: approved.then(|| "PSP_BR_2".into()),
+            attempts: vec![attempt("psp_br_1", false, 1), attempt("psp_br_2", approved, 2)],
+            total_attempts: 2,
+            total_latency_ms: 400,
+            budget_exhausted: false,
+            matched_rule: None,
+            ineligible_psps: vec![],
+            trace: None,
+            authentications: Vec::new(),
+            challenge: None,
+        }
+    }
+
+    fn authorized() -> Payment {
+        Payment::from_authorization(&make_transaction(), &make_result(AuthorizationStatus::Approved))
+            .unwrap()
+    }
+
+    fn respond(payment: &Payment, kind: OperationKind, amount: Option<Money>, approved: bool) -> PaymentOperation {
+        PaymentOperation {
+            kind,
+            amount: payment.prepare(kind, amount).unwrap(),
+            approved,
+            response_code: if approved { "00".into() } else { "UNAVAILABLE".into() },
+            decline_reason: (!approved).then_some(DeclineReason::PspUnavailable),
+            latency_ms: 150,
+        }
+    }
+
+    fn brl(minor_units: i64) -> Money {
+        Money::new(minor_units, Currency::new("BRL"))
+    }
+
+    #[test]
+    fn test_payment_targets_approving_psp() {
+        let payment = authorized();
+        assert_eq!(payment.psp_id, "psp_br_2");
+        assert_eq!(payment.state, PaymentState::Authorized);
+        assert_eq!(payment.authorized_amount, brl(10000));
+        assert_eq!(payment.captured_amount, brl(0));
+    }
+
+    #[test]
+    fn test_only_approved_results_start_a_lifecycle() {
+        for status in [AuthorizationStatus::Declined, AuthorizationStatus::Pending] {
+            let err = Payment::from_authorization(&make_transaction(), &make_result(status.clone()))
+                .unwrap_err();
+            assert_eq!(
+                err,
+                LifecycleError::NotAuthorized {
+                    transaction_id: "txn_lifecycle".into(),
+                    status
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_capture_then_partial_and_full_refund() {
+        let mut payment = authorized();
+        let capture = respond(&payment, OperationKind::Capture, Some(brl(8000)), true);
+        payment.record(capture);
+        assert_eq!(payment.state, PaymentState::Captured);
+        assert_eq!(payment.captured_amount, brl(8000));
+
+        let refund = respond(&payment, OperationKind::Refund, Some(brl(3000)), true);
+        payment.record(refund);
+        assert_eq!(payment.state, PaymentState::PartiallyRefunded);
+        assert_eq!(payment.available(OperationKind::Refund).unwrap(), brl(5000));
+
+        let rest = respond(&payment, OperationKind::Refund, None, true);
+        assert_eq!(rest.amount, brl(5000));
+        payment.record(rest);
+        assert_eq!(payment.state, PaymentState::Refunded);
+        assert_eq!(payment.refunded_amount, brl(8000));
+        assert_eq!(payment.operations.len(), 3);
+    }
+
+    #[test]
+    fn test_void_releases_the_authorization() {
+        let mut payment = authorized();
+        let void = respond(&payment, OperationKind::Void, Some(brl(1)), true);
+        assert_eq!(void.amount, brl(10000));
+        payment.record(void);
+        assert_eq!(payment.state, PaymentState::Voided);
+
+        for kind in [OperationKind::Capture, OperationKind::Void, OperationKind::Refund] {
+            assert_eq!(
+                payment.prepare(kind, None).unwrap_err(),
+                LifecycleError::InvalidTransition {
+                    state: PaymentState::Voided,
+                    operation: kind
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_transitions_are_rejected() {
+        let mut payment = authorized();
+        assert!(matches!(
+            payment.prepare(OperationKind::Refund, None),
+            Err(LifecycleError::InvalidTransition { state: PaymentState::Authorized, .. })
+        ));
+
+        let capture = respond(&payment, OperationKind::Capture, None, true);
+        payment.record(capture);
+        for kind in [OperationKind::Capture, OperationKind::Void] {
+            assert!(matches!(
+                payment.prepare(kind, None),
+                Err(LifecycleError::InvalidTransition { state: PaymentState::Captured, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_amounts_are_validated() {
+        let payment = authorized();
+        assert!(matches!(
+            payment.prepare(OperationKind::Capture, Some(brl(10001))),
+            Err(LifecycleError::AmountExceedsAvailable { .. })
+        ));
+        assert_eq!(
+            payment.prepare(OperationKind::Capture, Some(brl(0))).unwrap_err(),
+            LifecycleError::NonPositiveAmount { operation: OperationKind::Capture }
+        );
+        assert!(matches!(
+            payment.prepare(OperationKind::Capture, Some(Money::new(100, Currency::new("MXN")))),
+            Err(LifecycleError::CurrencyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejected_operation_is_recorded_without_changing_state() {
+        let mut payment = authorized();
+        let capture = respond(&payment, OperationKind::Capture, None, false);
+        payment.record(capture);
+        assert_eq!(payment.state, PaymentState::Authorized);
+        assert_eq!(payment.captured_amount, brl(0));
+        assert_eq!(payment.operations.len(), 1);
+        assert!(payment.prepare(OperationKind::Capture, None).is_ok());
+    }
+}
@@ -1,9 +1,27 @@
 use serde::{Deserialize, Serialize};
+use super::money::Money;
+use super::transaction::{Currency, PaymentMethod, Transaction};
+use crate::card_bin::CardScheme;
+use crate::codes::ResponseCodeTable;
 
-/// Reasons why a PSP may decline a transaction.
+/// How the routing engine treats a decline.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum DeclineClass {
+    /// Permanent — do not retry.
+    #[default]
+    Hard,
+    /// Temporary — a different PSP may succeed.
+    Soft,
+    /// The PSP itself could not be reached — cascade immediately.
+    PspUnavailable,
+}
+
+/// Normalized reasons why a PSP may decline a transaction.
 ///
-/// Decline reasons are classified as either "hard" (permanent, do not retry)
-/// or "soft" (temporary, retry with a different PSP may succeed).
+/// PSPs return raw response codes (ISO 8583, Mastercard merchant advice
+/// codes, proprietary codes); [`crate::codes`] normalizes them into this
+/// taxonomy. Decline reasons are classified as either "hard" (permanent,
+/// do not retry) or "soft" (temporary, retry with a different PSP may succeed).
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum DeclineReason {
     // --- Hard declines: do NOT retry ---
@@ -15,6 +33,22 @@ pub enum DeclineReason {
     InvalidCard,
     /// Card has been reported stolen.
     StolenCard,
+    /// Card has been reported lost.
+    LostCard,
+    /// Issuer asked the merchant to retain the card.
+    PickUpCard,
+    /// Card is restricted from this kind of purchase.
+    RestrictedCard,
+    /// Transaction type is not permitted for this card or terminal.
+    TransactionNotPermitted,
+    /// Amount or frequency exceeds the cardholder's limits.
+    ExceedsLimit,
+    /// Security code (CVV/CVC) did not match.
+    InvalidCvv,
+    /// Card details have changed — fetch updated credentials before retrying.
+    AccountUpdateRequired,
+    /// Network advised the merchant not to try this card again.
+    DoNotRetry,
 
     // --- Soft declines: RETRY with next PSP ---
     /// Issuing bank is temporarily unavailable.
@@ -25,30 +59,96 @@ pub enum DeclineReason {
     DoNotHonor,
     /// PSP processor declined the transaction.
     ProcessorDeclined,
+    /// Issuer or network asked for the transaction to be retried later.
+    TryAgainLater,
+    /// Issuer or switch reported a system malfunction.
+    SystemError,
+    /// Issuer requires strong customer authentication (e.g. 3DS).
+    AuthenticationRequired,
     /// PSP is temporarily unavailable (for cascading).
     PspUnavailable,
+
+    /// A response code no normalization table recognised, carrying the
+    /// default classification configured for the PSP.
+    Unrecognized(DeclineClass),
 }
 
 impl DeclineReason {
+    /// How the routing engine should treat this decline.
+    pub fn class(&self) -> DeclineClass {
+        match self {
+            DeclineReason::InsufficientFunds
+            | DeclineReason::CardExpired
+            | DeclineReason::InvalidCard
+            | DeclineReason::StolenCard
+            | DeclineReason::LostCard
+            | DeclineReason::PickUpCard
+            | DeclineReason::RestrictedCard
+            | DeclineReason::TransactionNotPermitted
+            | DeclineReason::ExceedsLimit
+            | DeclineReason::InvalidCvv
+            | DeclineReason::AccountUpdateRequired
+            | DeclineReason::DoNotRetry => DeclineClass::Hard,
+            DeclineReason::IssuerUnavailable
+            | DeclineReason::SuspectedFraud
+            | DeclineReason::DoNotHonor
+            | DeclineReason::ProcessorDeclined
+            | DeclineReason::TryAgainLater
+            | DeclineReason::SystemError
+            | DeclineReason::AuthenticationRequired => DeclineClass::Soft,
+            DeclineReason::PspUnavailable => DeclineClass::PspUnavailable,
+            DeclineReason::Unrecognized(class) => *class,
+        }
+    }
+
     /// Returns true if this is a hard decline (permanent — do not retry).
     pub fn is_hard_decline(&self) -> bool {
-        matches!(
-            self,
-            DeclineReason::InsufficientFunds
-                | DeclineReason::CardExpired
-                | DeclineReason::InvalidCard
-                | DeclineReason::StolenCard
-        )
+        self.class() == DeclineClass::Hard
     }
 
     /// Returns true if this is a soft decline (temporary — retry may succeed).
     pub fn is_soft_decline(&self) -> bool {
-        !self.is_hard_decline()
+        self.class() == DeclineClass::Soft
     }
 
     /// Returns true if the PSP itself is unavailable (cascade immediately).
     pub fn is_psp_unavailable(&self) -> bool {
-        matches!(self, DeclineReason::PspUnavailable)
+        self.class() == DeclineClass::PspUnavailable
+    }
+
+    /// Returns true if the issuer doubts the cardholder rather than the
+    /// card or its funds, so authenticating them with 3-D Secure and
+    /// resending to the same PSP may succeed.
+    pub fn calls_for_authentication(&self) -> bool {
+        matches!(
+            self,
+            DeclineReason::SuspectedFraud
+                | DeclineReason::DoNotHonor
+                | DeclineReason::AuthenticationRequired
+        )
+    }
+
+    /// Returns true if the card on file is out of date rather than refused,
+    /// so resending with the card's refreshed credential (a network token
+    /// or the account updater's new details) may succeed.
+    pub fn calls_for_credential_refresh(&self) -> bool {
+        matches!(
+            self,
+            DeclineReason::CardExpired | DeclineReason::AccountUpdateRequired
+        )
+    }
+
+    /// Returns true if the decline comes from a passing outage (issuer,
+    /// switch or PSP) rather than a decision about the card, so resending
+    /// to the same PSP after a pause may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DeclineReason::IssuerUnavailable
+                | DeclineReason::TryAgainLater
+                | DeclineReason::SystemError
+                | DeclineReason::PspUnavailable
+        )
     }
 }
 
@@ -59,11 +159,23 @@ impl std::fmt::Display for DeclineReason {
             DeclineReason::CardExpired => write!(f, "card_expired"),
             DeclineReason::InvalidCard => write!(f, "invalid_card"),
             DeclineReason::StolenCard => write!(f, "stolen_card"),
+            DeclineReason::LostCard => write!(f, "lost_card"),
+            DeclineReason::PickUpCard => write!(f, "pick_up_card"),
+            DeclineReason::RestrictedCard => write!(f, "restricted_card"),
+            DeclineReason::TransactionNotPermitted => write!(f, "transaction_not_permitted"),
+            DeclineReason::ExceedsLimit => write!(f, "exceeds_limit"),
+            DeclineReason::InvalidCvv => write!(f, "invalid_cvv"),
+            DeclineReason::AccountUpdateRequired => write!(f, "account_update_required"),
+            DeclineReason::DoNotRetry => write!(f, "do_not_retry"),
             DeclineReason::IssuerUnavailable => write!(f, "issuer_unavailable"),
             DeclineReason::SuspectedFraud => write!(f, "suspected_fraud"),
             DeclineReason::DoNotHonor => write!(f, "do_not_honor"),
             DeclineReason::ProcessorDeclined => write!(f, "processor_declined"),
+            DeclineReason::TryAgainLater => write!(f, "try_again_later"),
+            DeclineReason::SystemError => write!(f, "system_error"),
+            DeclineReason::AuthenticationRequired => write!(f, "authentication_required"),
             DeclineReason::PspUnavailable => write!(f, "psp_unavailable"),
+            DeclineReason::Unrecognized(_) => write!(f, "unrecognized"),
         }
     }
 }
@@ -77,6 +189,9 @@ pub struct PspConfig {
     pub name: String,
     /// Country this PSP serves.
     pub country: super::transaction::Country,
+    /// Payment methods this PSP can process; defaults to cards only.
+    #[serde(default = "default_payment_methods")]
+    pub payment_methods: Vec<PaymentMethod>,
     /// Base approval rate (0.0–1.0).
     pub base_success_rate: f64,
     /// Minimum response latency in milliseconds.
@@ -85,8 +200,225 @@ pub struct PspConfig {
     pub latency_max_ms: u64,
     /// Processing fee as a percentage (e.g., 2.9 for 2.9%).
     pub fee_percentage: f64,
-    /// Fixed processing fee in USD cents.
-    pub fee_fixed_cents: u64,
+    /// Fixed processing fee per transaction, denominated in USD.
+    pub fee_fixed: Money,
+    /// Distribution of soft decline reasons this PSP returns.
+    pub decline_distribution: Vec<DeclineWeight>,
+    /// How this PSP's raw response codes normalize into [`DeclineReason`]s.
+    #[serde(default)]
+    pub response_codes: ResponseCodeTable,
+    /// Installment plans this PSP offers on card payments; empty means
+    /// single payments only.
+    #[serde(default)]
+    pub installments: Vec<InstallmentFee>,
+    /// Smallest amount accepted, in the country's currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_amount: Option<Money>,
+    /// Largest amount accepted, in the country's currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<Money>,
+    /// Card schemes accepted; empty means every scheme.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub card_schemes: Vec<CardScheme>,
+    /// Currencies accepted; empty means any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub currencies: Vec<Currency>,
+    /// Set for acquirers that also take transactions from other countries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cross_border: Option<CrossBorder>,
+    /// Set for PSPs that can authenticate cardholders with 3-D Secure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub three_ds: Option<ThreeDsConfig>,
+}
+
+/// How a PSP acquires transactions from countries other than its own.
+///
+/// A cross-border acquirer settles in its home currency, so on top of its
+/// regular fees it charges an FX markup and a cross-border fee, and
+/// issuers approve fewer of their cards when the acquirer is abroad.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossBorder {
+    /// Countries whose transactions it can take.
+    pub countries: Vec<super::transaction::Country>,
+    /// FX markup as a percentage of the amount (e.g., 1.5 for 1.5%).
+    pub fx_markup_percentage: f64,
+    /// Cross-border fee as a percentage of the amount.
+    pub fee_percentage: f64,
+    /// Approval rate lost on cards issued in the transaction's country
+    /// (0.0–1.0), which issuers see as foreign transactions.
+    pub domestic_card_penalty: f64,
+}
+
+/// How a PSP's 3-D Secure server and the issuers behind it respond.
+///
+/// Issuers authenticate most cardholders frictionlessly and challenge the
+/// rest; an authenticated authorization shifts fraud liability to the
+/// issuer, so it is approved more often than the same one unauthenticated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreeDsConfig {
+    /// Share of authentications that need no challenge (0.0–1.0).
+    pub frictionless_rate: f64,
+    /// Share of challenges the cardholder completes (0.0–1.0).
+    pub challenge_success_rate: f64,
+    /// Approval rate gained by an authenticated authorization (0.0–1.0).
+    pub approval_uplift: f64,
+}
+
+impl PspConfig {
+    /// Returns true if this PSP can process `method`.
+    pub fn supports(&self, method: &PaymentMethod) -> bool {
+        self.payment_methods.contains(method)
+    }
+
+    /// Returns true if this PSP can authenticate cardholders with 3-D
+    /// Secure.
+    pub fn supports_three_ds(&self) -> bool {
+        self.three_ds.is_some()
+    }
+
+    /// Returns true if this PSP acquires transactions from other countries.
+    pub fn is_cross_border(&self) -> bool {
+        self.cross_border.is_some()
+    }
+
+    /// Returns true if this PSP can take transactions from `country` as a
+    /// cross-border acquirer.
+    pub fn serves_cross_border(&self, country: &super::transaction::Country) -> bool {
+        self.cross_border
+            .as_ref()
+            .is_some_and(|cb| cb.countries.contains(country))
+    }
+
+    /// Check whether this PSP can take `transaction`, which is assumed to
+    /// be in its country or one it serves cross-border. Cards without BIN enrichment pass the scheme
+    /// check, since their scheme is unknown.
+    pub fn check_eligibility(&self, transaction: &Transaction) -> Result<(), IneligibilityReason> {
+        let amount = &transaction.amount;
+        if !self.supports(&transaction.payment_method) {
+            return Err(IneligibilityReason::PaymentMethodNotSupported(
+                transaction.payment_method.clone(),
+            ));
+        }
+        let installments = transaction.installment_count();
+        if !self.supports_installments(installments) {
+            return Err(IneligibilityReason::InstallmentsNotSupported(installments));
+        }
+        let limits_in_other_currency = [&self.min_amount, &self.max_amount]
+            .into_iter()
+            .flatten()
+            .any(|limit| limit.currency != amount.currency);
+        if (!self.currencies.is_empty() && !self.currencies.contains(&amount.currency))
+            || limits_in_other_currency
+        {
+            return Err(IneligibilityReason::CurrencyNotSupported(amount.currency.clone()));
+        }
+        if let Some(min) = self.min_amount.as_ref().filter(|min| amount.minor_units < min.minor_units) {
+            return Err(IneligibilityReason::BelowMinAmount(min.clone()));
+        }
+        if let Some(max) = self.max_amount.as_ref().filter(|max| amount.minor_units > max.minor_units) {
+            return Err(IneligibilityReason::AboveMaxAmount(max.clone()));
+        }
+        if let (PaymentMethod::Card, Some(info), false) = (
+            &transaction.payment_method,
+            &transaction.bin_info,
+            self.card_schemes.is_empty(),
+        ) {
+            if !self.card_schemes.contains(&info.scheme) {
+                return Err(IneligibilityReason::CardSchemeNotSupported(info.scheme.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Latency to plan for when budgeting a call: the midpoint of the
+    /// configured range.
+    pub fn expected_latency_ms(&self) -> u64 {
+        self.latency_min_ms + (self.latency_max_ms - self.latency_min_ms) / 2
+    }
+
+    /// Returns true if this PSP can split a payment into `count`
+    /// installments. Every PSP accepts a single payment.
+    pub fn supports_installments(&self, count: u8) -> bool {
+        count <= 1 || self.installments.iter().any(|plan| plan.count == count)
+    }
+
+    /// Percentage fee for a payment in `count` installments: the base
+    /// `fee_percentage` plus the plan's surcharge and, for a cross-border
+    /// acquirer, its FX markup and cross-border fee.
+    pub fn fee_percentage_for(&self, count: u8) -> f64 {
+        let extra = self
+            .installments
+            .iter()
+            .find(|plan| plan.count == count)
+            .map_or(0.0, |plan| plan.extra_fee_percentage);
+        let cross_border = self
+            .cross_border
+            .as_ref()
+            .map_or(0.0, |cb| cb.fx_markup_percentage + cb.fee_percentage);
+        self.fee_percentage + extra + cross_border
+    }
+}
+
+/// Why a PSP in the transaction's country cannot take it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum IneligibilityReason {
+    /// The PSP does not process this payment method.
+    PaymentMethodNotSupported(PaymentMethod),
+    /// The PSP offers no plan with this many installments.
+    InstallmentsNotSupported(u8),
+    /// The PSP does not accept this currency.
+    CurrencyNotSupported(Currency),
+    /// The amount is below the PSP's minimum.
+    BelowMinAmount(Money),
+    /// The amount is above the PSP's maximum.
+    AboveMaxAmount(Money),
+    /// The PSP does not accept this card scheme.
+    CardSchemeNotSupported(CardScheme),
+}
+
+impl std::fmt::Display for IneligibilityReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IneligibilityReason::PaymentMethodNotSupported(method) => {
+                write!(f, "does not process {method}")
+            }
+            IneligibilityReason::InstallmentsNotSupported(count) => {
+                write!(f, "has no {count}-installment plan")
+            }
+            IneligibilityReason::CurrencyNotSupported(currency) => {
+                write!(f, "does not accept {currency}")
+            }
+            IneligibilityReason::BelowMinAmount(min) => write!(f, "requires at least {min}"),
+            IneligibilityReason::AboveMaxAmount(max) => write!(f, "accepts at most {max}"),
+            IneligibilityReason::CardSchemeNotSupported(scheme) => {
+                write!(f, "does not accept {scheme} cards")
+            }
+        }
+    }
+}
+
+/// An installment plan a PSP offers and what it costs the merchant.
+///
+/// Installments are paid out to the merchant up front, so PSPs charge a
+/// surcharge that grows with the number of installments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallmentFee {
+    /// Number of installments (2 or more).
+    pub count: u8,
+    /// Percentage added to `fee_percentage` for this plan.
+    pub extra_fee_percentage: f64,
+}
+
+fn default_payment_methods() -> Vec<PaymentMethod> {
+    vec![PaymentMethod::Card]
+}
+
+/// Weighted decline reason for a PSP.
+/// The weight determines how likely this reason is relative to others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclineWeight {
+    pub reason: DeclineReason,
+    pub weight: f64,
 }
 
 /// Response from a PSP after attempting to process a transaction.
@@ -98,7 +430,13 @@ pub struct PspResponse {
     pub psp_name: String,
     /// Whether the transaction was approved.
     pub approved: bool,
-    /// Decline reason (None if approved).
+    /// Raw response code exactly as the PSP returned it (e.g. "00", "05").
+    pub response_code: String,
+    /// Raw response message returned alongside the code.
+    pub response_message: String,
+    /// Mastercard merchant advice code, when the PSP forwarded one.
+    pub merchant_advice_code: Option<String>,
+    /// Decline reason normalized from the raw code (None if approved).
     pub decline_reason: Option<DeclineReason>,
     /// Response latency in milliseconds.
     pub latency_ms: u64,
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use super::money::Money;
+use super::psp::DeclineReason;
+use crate::engine::breaker::CircuitBreaker;
 
 /// Complete performance report comparing no-retry vs smart-retry routing.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +19,66 @@ pub struct PerformanceReport {
     pub by_country: HashMap<String, CountryMetrics>,
     /// Performance breakdown by PSP.
     pub by_psp: HashMap<String, PspMetrics>,
+    /// Authorization rate breakdown by card scheme (from BIN enrichment).
+    pub by_card_scheme: HashMap<String, SegmentMetrics>,
+    /// Authorization rate breakdown by credit/debit/prepaid.
+    pub by_card_type: HashMap<String, SegmentMetrics>,
+    /// Circuit breaker of each PSP after the smart-retry run (empty when
+    /// the engine has no breakers).
+    #[serde(default)]
+    pub circuit_breakers: HashMap<String, CircuitBreaker>,
+    /// How each strategy's authorization rate evolves over the batch.
+    #[serde(default)]
+    pub convergence: ConvergenceReport,
+    /// How often each PSP approved right after a soft decline, by the
+    /// reason of that decline, in the smart-retry run.
+    #[serde(default)]
+    pub recovery: Vec<RecoveryMetrics>,
+}
+
+/// Attempts that followed a decline with `reason` and went to one PSP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryMetrics {
+    /// Reason of the decline that preceded the attempt.
+    pub reason: DeclineReason,
+    /// PSP tried next.
+    pub psp_id: String,
+    /// PSP name.
+    pub psp_name: String,
+    /// Attempts on the PSP after such a decline.
+    pub attempts: usize,
+    /// Attempts that were approved.
+    pub recoveries: usize,
+    /// `recoveries / attempts` as a percentage.
+    pub recovery_rate: f64,
+}
+
+/// Routing quality over time for every strategy, each starting cold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConvergenceReport {
+    /// Transactions per window, in time order.
+    pub window_size: usize,
+    /// One series per routing strategy.
+    pub strategies: Vec<StrategyConvergence>,
+}
+
+/// One strategy's results over the batch and per window.
+///
+/// With enough retries every strategy eventually reaches the same PSPs, so
+/// ranking quality shows in how often the first PSP tried approves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyConvergence {
+    /// The strategy replayed.
+    pub strategy: super::routing::RoutingStrategy,
+    /// Authorization rate over the whole batch (0.0–100.0).
+    pub authorization_rate: f64,
+    /// Average number of PSP attempts per transaction.
+    pub avg_attempts: f64,
+    /// Share of transactions approved by the first PSP tried (0.0–100.0).
+    pub first_attempt_rate: f64,
+    /// `first_attempt_rate` of each consecutive window of `window_size`
+    /// transactions (the last may be shorter).
+    pub window_first_attempt_rates: Vec<f64>,
 }
 
 /// Results for a single routing scenario (no-retry or smart-retry).
@@ -23,6 +86,8 @@ pub struct PerformanceReport {
 pub struct ScenarioResult {
     /// Number of approved transactions.
     pub approved: usize,
+    /// Number of voucher/transfer transactions accepted but not yet settled.
+    pub pending: usize,
     /// Number of declined transactions.
     pub declined: usize,
     /// Authorization rate as a percentage (0.0–100.0).
@@ -40,8 +105,25 @@ pub struct ImprovementMetrics {
     pub rate_lift_percentage: f64,
     /// Additional transactions approved by smart retry.
     pub additional_approvals: usize,
-    /// Estimated additional revenue in USD.
-    pub estimated_revenue_recovered_usd: f64,
+    /// Revenue of the transactions only smart retry approved, each
+    /// converted to USD at its date's rate.
+    pub estimated_revenue_recovered_usd: Money,
+    /// Average transaction amount across the batch, converted to USD.
+    pub avg_ticket_usd: Money,
+    /// Processing fees paid on smart-retry approvals, converted to USD.
+    pub total_fees_usd: Money,
+    /// Smart-retry approvals won by a cross-border acquirer after the local
+    /// PSPs declined (zero unless the engine falls back on them).
+    #[serde(default)]
+    pub cross_border_recoveries: usize,
+    /// Smart-retry approvals won by resending a declined transaction with
+    /// the cardholder's 3-D Secure authentication.
+    #[serde(default)]
+    pub three_ds_recoveries: usize,
+    /// Smart-retry approvals won by resending an expired or out-of-date
+    /// card with its network token or account-updater details.
+    #[serde(default)]
+    pub credential_recoveries: usize,
 }
 
 /// Authorization rate metrics for a specific country.
@@ -55,6 +137,27 @@ pub struct CountryMetrics {
     pub improvement: f64,
     /// Total transactions for this country.
     pub total_transactions: usize,
+    /// Volume approved with smart retry, in the country's currency.
+    pub approved_volume: Money,
+    /// Volume approved by smart retry that no-retry declined.
+    pub recovered_volume: Money,
+    /// Average transaction amount, in the country's currency.
+    pub avg_ticket: Money,
+    /// Processing fees paid on smart-retry approvals, in the country's currency.
+    pub fees_paid: Money,
+}
+
+/// Authorization rate metrics for a segment of card transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentMetrics {
+    /// Auth rate without retry.
+    pub no_retry_rate: f64,
+    /// Auth rate with smart retry.
+    pub smart_retry_rate: f64,
+    /// Improvement in percentage points.
+    pub improvement: f64,
+    /// Total transactions in this segment.
+    pub total_transactions: usize,
 }
 
 /// Performance metrics for a specific PSP.
@@ -79,4 +182,13 @@ pub struct ReportRequest {
     pub transaction_count: Option<usize>,
     /// Routing strategy to use for smart retry scenario.
     pub routing_strategy: Option<super::routing::RoutingStrategy>,
+    /// Retry policy for the smart retry scenario (default policy if omitted).
+    #[serde(default)]
+    pub retry_policy: Option<crate::engine::retry::RetryPolicy>,
+    /// Priors, decay and mode for the `Adaptive` strategy (defaults if omitted).
+    #[serde(default)]
+    pub bandit: Option<crate::engine::bandit::BanditConfig>,
+    /// Fall back on cross-border acquirers in the smart retry scenario.
+    #[serde(default)]
+    pub cross_border_fallback: bool,
 }
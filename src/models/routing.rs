@@ -1,5 +1,26 @@
 use serde::{Deserialize, Serialize};
-use super::psp::DeclineReason;
+use super::authentication::{AuthenticationSession, ThreeDsFlow, ThreeDsResponse};
+use super::credential::CredentialSource;
+use super::money::{AmountInput, Money};
+use super::psp::{DeclineClass, DeclineReason, IneligibilityReason};
+use super::transaction::PaymentMethod;
+use crate::engine::breaker::BreakerState;
+use crate::engine::retry::{RetryAction, RetryPolicy};
+
+/// Final outcome of routing a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum AuthorizationStatus {
+    /// Funds were authorized.
+    Approved,
+    /// A PSP accepted a voucher or bank transfer that settles later.
+    Pending,
+    /// No PSP accepted the payment.
+    #[default]
+    Declined,
+    /// Routing paused until the cardholder answers a 3-D Secure challenge;
+    /// post the result's `challenge` to /api/authenticate to resume it.
+    ChallengeRequired,
+}
 
 /// The result of routing a transaction through one or more PSPs.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,7 +29,9 @@ pub struct RoutingResult {
     pub transaction_id: String,
     /// Whether the transaction was ultimately approved.
     pub approved: bool,
-    /// The PSP that approved the transaction (None if all declined).
+    /// Final outcome; `Pending` for voucher and transfer payments a PSP accepted.
+    pub status: AuthorizationStatus,
+    /// The PSP that accepted the transaction (None if all declined).
     pub final_psp: Option<String>,
     /// All PSP attempts made during routing.
     pub attempts: Vec<RoutingAttempt>,
@@ -16,6 +39,148 @@ pub struct RoutingResult {
     pub total_attempts: usize,
     /// Total latency across all attempts in milliseconds.
     pub total_latency_ms: u64,
+    /// True when routing stopped short because the latency budget could not
+    /// fit another PSP.
+    #[serde(default)]
+    pub budget_exhausted: bool,
+    /// Name of the merchant routing rule that shaped the PSP order, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_rule: Option<String>,
+    /// PSPs in the transaction's country that could not take it, and why.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ineligible_psps: Vec<IneligiblePsp>,
+    /// How the engine ordered and retried PSPs, when asked to explain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<DecisionTrace>,
+    /// 3-D Secure steps taken, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authentications: Vec<ThreeDsResponse>,
+    /// The paused routing to resume once the cardholder has answered the
+    /// challenge, when `status` is `ChallengeRequired`. Never serialized:
+    /// it is kept server-side.
+    #[serde(skip)]
+    pub challenge: Option<AuthenticationSession>,
+}
+
+/// A PSP left out of routing because it cannot take the transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IneligiblePsp {
+    /// PSP identifier.
+    pub psp_id: String,
+    /// PSP name.
+    pub psp_name: String,
+    /// The first constraint the transaction failed.
+    pub reason: IneligibilityReason,
+}
+
+/// Why the engine routed a transaction the way it did.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DecisionTrace {
+    /// The strategy that ordered the PSPs: the request's, or the matched
+    /// rule's.
+    pub strategy: RoutingStrategy,
+    /// PSPs that were ranked, in the order they were to be tried.
+    pub candidates: Vec<CandidateScore>,
+    /// PSPs left out before ranking, and why.
+    pub excluded: Vec<ExcludedPsp>,
+    /// What the retry policy made of each declined attempt.
+    pub decisions: Vec<RetryDecision>,
+}
+
+/// The figures a PSP was ranked by. Which one decided the order depends
+/// on the strategy.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CandidateScore {
+    /// PSP identifier.
+    pub psp_id: String,
+    /// PSP name.
+    pub psp_name: String,
+    /// 1-indexed position in the initial order.
+    pub rank: usize,
+    /// Catalog approval rate.
+    pub success_rate: f64,
+    /// The bandit's approval estimate, when one ranked the PSPs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub learned_rate: Option<f64>,
+    /// Fee the PSP would charge on this transaction.
+    pub fee: Money,
+    /// Weighted approval rate and relative fee, as `Balanced` ranks.
+    pub balanced_score: f64,
+    /// Expected net revenue of the attempt, in major units, as
+    /// `ExpectedValue` ranks.
+    pub expected_value: f64,
+    /// Circuit breaker state; half-open PSPs are tried last.
+    pub circuit: BreakerState,
+}
+
+/// A PSP in the transaction's country that was never ranked.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExcludedPsp {
+    /// PSP identifier.
+    pub psp_id: String,
+    /// PSP name.
+    pub psp_name: String,
+    /// Why it was left out.
+    pub reason: ExclusionReason,
+}
+
+/// Why a PSP was left out of routing.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExclusionReason {
+    /// It cannot take the transaction.
+    Ineligible(IneligibilityReason),
+    /// The named merchant rule excludes it.
+    ExcludedByRule(String),
+    /// Its circuit breaker is open.
+    CircuitOpen,
+}
+
+/// The retry policy's verdict on one declined attempt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryDecision {
+    /// The attempt, as numbered in `attempts`.
+    pub attempt_number: usize,
+    /// PSP that declined.
+    pub psp_id: String,
+    /// Normalized decline reason.
+    pub decline_reason: DeclineReason,
+    /// Hard, soft or PSP unavailable.
+    pub class: DeclineClass,
+    /// The action the policy maps the reason to.
+    pub action: RetryAction,
+    /// Whether a merchant retry rule set `action` rather than the class.
+    pub from_rule: bool,
+    /// What the engine did next.
+    pub next: RetryStep,
+    /// PSPs left to try after this decline, in order (after any
+    /// re-ranking).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remaining: Vec<String>,
+}
+
+/// What the engine did after a declined attempt.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RetryStep {
+    /// Sent the transaction to the same PSP again.
+    RetriedSamePsp,
+    /// Moved on to the next PSP, if any was left.
+    MovedOn,
+    /// Stopped, as the policy says for this reason.
+    Stopped,
+    /// Stopped after `max_attempts` declines.
+    MaxAttemptsReached,
+    /// Stopped after `max_unavailable_cascades` unavailable PSPs.
+    MaxCascadesReached,
+    /// Authenticated the cardholder without a challenge and sent the
+    /// transaction to the same PSP again.
+    Authenticated,
+    /// Paused routing for the cardholder to answer a 3-D Secure challenge.
+    ChallengeRequired,
+    /// Stopped: the issuer refused to authenticate the cardholder.
+    AuthenticationFailed,
+    /// Sent the transaction to the same PSP again with the card's
+    /// refreshed credential.
+    CredentialRefreshed,
 }
 
 /// A single PSP attempt within a routing flow.
@@ -27,16 +192,65 @@ pub struct RoutingAttempt {
     pub psp_name: String,
     /// Whether this specific attempt was approved.
     pub approved: bool,
-    /// Decline reason for this attempt (None if approved).
+    /// Raw response code the PSP returned for this attempt.
+    pub response_code: String,
+    /// Normalized decline reason for this attempt (None if approved).
     pub decline_reason: Option<DeclineReason>,
     /// Latency of this specific attempt in milliseconds.
     pub latency_ms: u64,
     /// 1-indexed attempt number.
     pub attempt_number: usize,
+    /// Whether this was the first attempt, a move to another PSP or a
+    /// resend to the same one.
+    #[serde(default)]
+    pub retry_type: RetryType,
+    /// Time waited before a resend to the same PSP, in milliseconds;
+    /// included in the routing's total latency.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub backoff_ms: u64,
+    /// Sent in parallel because the PSP before it had not answered in time.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hedged: bool,
+    /// The PSP did not answer within the attempt timeout; recorded as
+    /// unavailable.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    /// Answered after a parallel attempt had already been approved, so its
+    /// outcome was discarded (and an approval voided).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    /// Sent to a cross-border acquirer after the local PSPs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cross_border: bool,
+    /// Sent with the 3-D Secure authentication of the cardholder, and how
+    /// they were authenticated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub three_ds: Option<ThreeDsFlow>,
+    /// Sent with the card's refreshed credential instead of the card
+    /// details entered, and where the credential came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<CredentialSource>,
+}
+
+/// How an attempt relates to the ones before it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum RetryType {
+    /// The first PSP tried.
+    #[default]
+    Initial,
+    /// Another PSP, after a decline, an outage or (when hedged) a slow
+    /// answer.
+    NextPsp,
+    /// The same PSP again, after a backoff.
+    SamePsp,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
 
 /// Routing strategy that determines PSP selection order.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub enum RoutingStrategy {
     /// Select PSPs with highest approval rates first.
     #[default]
@@ -45,23 +259,50 @@ pub enum RoutingStrategy {
     OptimizeForCost,
     /// Balance between approval rate and cost.
     Balanced,
+    /// Rank by approval rates learned from past attempts (see
+    /// [`crate::engine::bandit`]); falls back to `OptimizeForApprovals`
+    /// when the engine has no bandit.
+    Adaptive,
+    /// Highest expected value first: approval probability times the
+    /// amount net of the PSP's fee, minus the cost of a decline and of the
+    /// PSP's latency (see [`crate::engine::fees`]).
+    ExpectedValue,
 }
 
 /// API request body for the /api/authorize endpoint.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthorizationRequest {
-    /// Transaction amount in the local currency.
-    pub amount: f64,
-    /// Currency code (BRL, MXN, COP).
+    /// Transaction amount in the local currency: a decimal string in major
+    /// units (`"150.00"`) or an integer number of minor units (`15000`).
+    pub amount: AmountInput,
+    /// Currency code (BRL, MXN, COP, CLP, PEN, ARS).
     pub currency: String,
-    /// Country name (Brazil, Mexico, Colombia).
+    /// Country name (Brazil, Mexico, Colombia, Chile, Peru, Argentina).
     pub country: String,
-    /// First 6 digits of the card.
-    pub card_bin: String,
-    /// Last 4 digits of the card.
-    pub card_last4: String,
+    /// Payment method; defaults to `Card`.
+    #[serde(default)]
+    pub payment_method: PaymentMethod,
+    /// First 6 digits of the card (card payments only).
+    #[serde(default)]
+    pub card_bin: Option<String>,
+    /// Last 4 digits of the card (card payments only).
+    #[serde(default)]
+    pub card_last4: Option<String>,
+    /// Number of installments (card payments only); omit for a single payment.
+    #[serde(default)]
+    pub installments: Option<u8>,
     /// Customer identifier.
     pub customer_id: String,
     /// Optional routing strategy.
     pub routing_strategy: Option<RoutingStrategy>,
+    /// Optional retry policy; the default policy applies when omitted.
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Return a decision trace with the routing result.
+    #[serde(default)]
+    pub explain: bool,
+    /// Fall back on cross-border acquirers once the local PSPs have
+    /// declined.
+    #[serde(default)]
+    pub cross_border_fallback: bool,
 }
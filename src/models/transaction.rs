@@ -1,58 +1,185 @@
 use serde::{Deserialize, Serialize};
+use super::authentication::ThreeDsAuthentication;
+use super::credential::CardCredential;
+use super::money::Money;
+use crate::card_bin::BinInfo;
+use crate::catalog::locale;
+use crate::time::Timestamp;
+use std::borrow::Cow;
 
-/// Supported currencies in the FashionForward marketplace.
+/// A currency, by ISO 4217 code (e.g. `"BRL"`).
+///
+/// Currencies are data: the PSP catalog declares each one with its exponent
+/// (see [`CurrencyConfig`](crate::catalog::CurrencyConfig)), and requests
+/// name one through the catalog. `USD` is always known: it is not a
+/// checkout currency, but denominates PSP fixed fees and normalized
+/// reporting figures, and FX rates are quoted against it.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub enum Currency {
-    BRL,
-    MXN,
-    COP,
+#[serde(transparent)]
+pub struct Currency(Cow<'static, str>);
+
+impl Currency {
+    /// US dollars.
+    pub const USD: Currency = Currency(Cow::Borrowed("USD"));
+
+    /// The currency with ISO 4217 code `code`.
+    pub fn new(code: impl Into<String>) -> Self {
+        Currency(Cow::Owned(code.into()))
+    }
+
+    /// ISO 4217 code (e.g., `"BRL"`).
+    pub fn code(&self) -> &str {
+        &self.0
+    }
+
+    /// Number of decimal places used for amounts in this currency, as the
+    /// catalogs loaded so far declare it: 2 for a currency none declares.
+    pub fn exponent(&self) -> u32 {
+        locale::currency_exponent(self).unwrap_or(DEFAULT_EXPONENT)
+    }
+
+    /// Number of minor units in one major unit (`10^exponent`).
+    pub fn minor_per_major(&self) -> i64 {
+        10_i64.pow(self.exponent())
+    }
 }
 
 impl std::fmt::Display for Currency {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Currency::BRL => write!(f, "BRL"),
-            Currency::MXN => write!(f, "MXN"),
-            Currency::COP => write!(f, "COP"),
-        }
+        f.write_str(&self.0)
     }
 }
 
-/// Countries where FashionForward operates.
+/// Exponent of a currency no catalog declares, the ISO 4217 norm.
+const DEFAULT_EXPONENT: u32 = 2;
+
+/// A country, by the name the PSP catalog declares it under (e.g.
+/// `"Brazil"`).
+///
+/// Countries are data: the catalog declares each one with its ISO code,
+/// currency, time zone and payment methods (see
+/// [`CountryConfig`](crate::catalog::CountryConfig)), and requests name a
+/// market through it.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub enum Country {
-    Brazil,
-    Mexico,
-    Colombia,
+#[serde(transparent)]
+pub struct Country(String);
+
+impl Country {
+    /// The country named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Country(name.into())
+    }
+
+    /// Name the catalog declares the country under.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// ISO 3166-1 alpha-2 country code (e.g., `"BR"`), as the catalogs
+    /// loaded so far declare it.
+    pub fn iso_code(&self) -> Option<String> {
+        locale::iso_code(self)
+    }
 }
 
 impl std::fmt::Display for Country {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// How the customer pays.
+///
+/// The catalog lists the methods each country offers; cards are accepted
+/// everywhere and the alternative payment methods are local to one market.
+/// Voucher and bank-transfer methods settle after
+/// the customer completes payment offline, so a successful authorization
+/// leaves them pending rather than approved.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum PaymentMethod {
+    /// Credit or debit card.
+    #[default]
+    Card,
+    /// PIX instant bank transfer (Brazil).
+    Pix,
+    /// Boleto bancário cash voucher (Brazil).
+    Boleto,
+    /// OXXO cash voucher (Mexico).
+    Oxxo,
+    /// PSE bank transfer (Colombia).
+    Pse,
+}
+
+impl PaymentMethod {
+    /// Returns true if an accepted payment stays pending until the customer
+    /// pays the voucher or completes the transfer.
+    pub fn settles_asynchronously(&self) -> bool {
+        matches!(
+            self,
+            PaymentMethod::Boleto | PaymentMethod::Oxxo | PaymentMethod::Pse
+        )
+    }
+}
+
+impl std::fmt::Display for PaymentMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Country::Brazil => write!(f, "Brazil"),
-            Country::Mexico => write!(f, "Mexico"),
-            Country::Colombia => write!(f, "Colombia"),
+            PaymentMethod::Card => write!(f, "Card"),
+            PaymentMethod::Pix => write!(f, "PIX"),
+            PaymentMethod::Boleto => write!(f, "Boleto"),
+            PaymentMethod::Oxxo => write!(f, "OXXO"),
+            PaymentMethod::Pse => write!(f, "PSE"),
         }
     }
 }
 
+/// Largest number of installments any market offers.
+pub const MAX_INSTALLMENTS: u8 = 24;
+
 /// A payment transaction from a FashionForward customer.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     /// Unique transaction identifier.
     pub id: String,
-    /// Transaction amount in the local currency.
-    pub amount: f64,
-    /// Currency of the transaction.
-    pub currency: Currency,
+    /// Transaction amount, denominated in the local currency.
+    pub amount: Money,
     /// Country where the transaction originates.
     pub country: Country,
-    /// First 6 digits of the card (Bank Identification Number).
-    pub card_bin: String,
-    /// Last 4 digits of the card.
-    pub card_last4: String,
+    /// How the customer is paying.
+    #[serde(default)]
+    pub payment_method: PaymentMethod,
+    /// First 6 digits of the card (Bank Identification Number); card payments only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card_bin: Option<String>,
+    /// Last 4 digits of the card; card payments only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card_last4: Option<String>,
+    /// Scheme, card type and issuer looked up from the BIN before routing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin_info: Option<BinInfo>,
+    /// Number of installments the customer chose (parcelamento, meses sin
+    /// intereses); card payments only. `None` is a single payment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installments: Option<u8>,
     /// Unique customer identifier.
     pub customer_id: String,
-    /// ISO 8601 timestamp of the transaction.
-    pub timestamp: String,
+    /// When the transaction was made; serialized as an RFC 3339 string.
+    pub timestamp: Timestamp,
+    /// Set once the cardholder has been authenticated with 3-D Secure,
+    /// for the authorization sent to the authenticating PSP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub three_ds: Option<ThreeDsAuthentication>,
+    /// Set once the card's refreshed credential has been retrieved, for the
+    /// authorizations sent with it in place of the card details entered.
+    /// The routing engine sets it from its credential store and drops any
+    /// other.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<CardCredential>,
+}
+
+impl Transaction {
+    /// Number of installments, counting a single payment as 1.
+    pub fn installment_count(&self) -> u8 {
+        self.installments.unwrap_or(1).max(1)
+    }
 }
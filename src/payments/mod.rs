@@ -0,0 +1,376 @@
+//! Payment store and follow-up operations — capture, void and refund.
+//!
+//! Approved authorizations are kept in a [`PaymentStore`] keyed by
+//! transaction ID, and follow-up requests name the transaction rather than
+//! carry the payment: every transition starts from the stored state, so a
+//! client cannot move a payment along or change its amounts.
+//!
+//! The `/api/capture`, `/api/void` and `/api/refund` endpoints differ only
+//! in the [`OperationKind`] they send, so all three are served by
+//! [`follow_up`]: it checks the request, sends the operation to the PSP
+//! that approved the authorization and answers with the status code and
+//! JSON body to return.
+
+use crate::catalog::PspCatalog;
+use crate::connector::PspConnector;
+use crate::engine::RoutingEngine;
+use crate::kv::{self, KvError, KvStore, MemoryKv};
+use crate::models::money::AmountInput;
+use crate::models::payment::{
+    LifecycleError, OperationKind, Payment, PaymentOperationRequest, PaymentOperationResponse,
+};
+use crate::simulator::PspSimulator;
+use http::{Method, StatusCode};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time an operation holds its payment, longer than any PSP call takes.
+/// A hold left behind by a function that died mid-operation lapses after
+/// it.
+const OPERATION_HOLD: Duration = Duration::from_secs(30);
+
+/// Authorized payments and their follow-up history, keyed by transaction
+/// ID.
+///
+/// The payments live in the key-value store every function reaches (see
+/// [`kv`]), so one authorized by `/api/authorize` can be captured by
+/// `/api/capture` whichever instance serves it. They are kept for good:
+/// refunds can come long after the authorization. Operations on one
+/// payment are sent one at a time across all instances, so two captures
+/// of the same authorization cannot both pass the state check.
+#[derive(Debug, Clone)]
+pub struct PaymentStore {
+    kv: Arc<dyn KvStore>,
+}
+
+impl Default for PaymentStore {
+    /// A store of its own in memory.
+    fn default() -> Self {
+        PaymentStore::new(Arc::new(MemoryKv::new()))
+    }
+}
+
+impl PaymentStore {
+    /// A store keeping payments in `kv`.
+    pub fn new(kv: Arc<dyn KvStore>) -> Self {
+        PaymentStore { kv }
+    }
+
+    /// The store used by the API handlers, in the key-value store
+    /// [`kv::from_env`] configures.
+    pub fn from_env() -> Result<Self, KvError> {
+        kv::from_env().map(PaymentStore::new)
+    }
+
+    /// Keep a newly authorized payment. A payment already stored for the
+    /// transaction is left alone and the new one rejected.
+    pub fn insert(&self, payment: &Payment) -> Result<(), LifecycleError> {
+        let id = &payment.transaction_id;
+        let value = serde_json::to_string(payment).expect("payments serialize");
+        match self.kv.set_new(&payment_key(id), &value, None) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(LifecycleError::DuplicatePayment(id.clone())),
+            Err(e) => Err(LifecycleError::Storage(e)),
+        }
+    }
+
+    /// The stored payment for `transaction_id`, if any.
+    pub fn payment(&self, transaction_id: &str) -> Result<Option<Payment>, LifecycleError> {
+        let key = payment_key(transaction_id);
+        let Some(value) = self.kv.get(&key).map_err(LifecycleError::Storage)? else {
+            return Ok(None);
+        };
+        let payment = serde_json::from_str(&value).map_err(|e| {
+            LifecycleError::Storage(KvError::CorruptValue {
+                key,
+                details: e.to_string(),
+            })
+        })?;
+        Ok(Some(payment))
+    }
+
+    /// Send one operation on the stored payment to the PSP that approved
+    /// it and keep the outcome.
+    ///
+    /// `amount` is read in the payment's currency and defaults to
+    /// everything available (see [`Payment::prepare`]). While another
+    /// operation on the payment is in flight, this one is rejected with
+    /// [`LifecycleError::PaymentBusy`] without reaching the PSP.
+    pub fn execute<C: PspConnector>(
+        &self,
+        engine: &RoutingEngine<C>,
+        transaction_id: &str,
+        kind: OperationKind,
+        amount: Option<&AmountInput>,
+    ) -> Result<PaymentOperationResponse, LifecycleError> {
+        let hold = hold_key(transaction_id);
+        let held = self
+            .kv
+            .set_new(&hold, kind.to_string().as_str(), Some(OPERATION_HOLD))
+            .map_err(LifecycleError::Storage)?;
+        if !held {
+            return Err(LifecycleError::PaymentBusy(transaction_id.to_string()));
+        }
+        let response = self.execute_held(engine, transaction_id, kind, amount);
+        // A hold that cannot be released lapses on its own
+        let _ = self.kv.delete(&hold);
+        response
+    }
+
+    /// [`execute`](PaymentStore::execute), once the payment is held.
+    fn execute_held<C: PspConnector>(
+        &self,
+        engine: &RoutingEngine<C>,
+        transaction_id: &str,
+        kind: OperationKind,
+        amount: Option<&AmountInput>,
+    ) -> Result<PaymentOperationResponse, LifecycleError> {
+        let mut payment = self
+            .payment(transaction_id)?
+            .ok_or_else(|| LifecycleError::UnknownPayment(transaction_id.to_string()))?;
+        let currency = payment.authorized_amount.currency.clone();
+        let amount = amount
+            .map(|a| a.to_money(currency))
+            .transpose()
+            .map_err(LifecycleError::InvalidAmount)?;
+        let operation = engine.execute(&mut payment, kind, amount)?;
+        let value = serde_json::to_string(&payment).expect("payments serialize");
+        self.kv
+            .set(&payment_key(transaction_id), &value, None)
+            .map_err(LifecycleError::Storage)?;
+        Ok(PaymentOperationResponse { payment, operation })
+    }
+}
+
+/// Key the payment for `transaction_id` is stored under.
+fn payment_key(transaction_id: &str) -> String {
+    format!("payment:{transaction_id}")
+}
+
+/// Key held while an operation on the payment for `transaction_id` is in
+/// flight.
+fn hold_key(transaction_id: &str) -> String {
+    format!("payment-hold:{transaction_id}")
+}
+
+/// Run one follow-up operation on a payment in `store` from an API
+/// request's method and body.
+///
+/// Returns the status code and JSON body of the response: 200 with a
+/// [`PaymentOperationResponse`] once the PSP answered (approved or not),
+/// 404 for a transaction with no stored payment, 405 for methods other
+/// than POST, 409 when the payment's state does not allow the operation
+/// or another operation on it is in flight, 400 for any other invalid
+/// request, 500 when the PSP catalog cannot be loaded and 503 when the
+/// payment store cannot be reached.
+pub fn follow_up(
+    store: &PaymentStore,
+    method: &Method,
+    body: &[u8],
+    kind: OperationKind,
+) -> (StatusCode, Value) {
+    if *method != Method::POST {
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            json!({
+                "error": "Method not allowed",
+                "details": format!("Use POST to {kind} a payment")
+            }),
+        );
+    }
+
+    let request: PaymentOperationRequest = match serde_json::from_slice(body) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                json!({
+                    "error": "Invalid request body",
+                    "details": format!("Failed to parse JSON: {e}")
+                }),
+            );
+        }
+    };
+
+    let catalog = match PspCatalog::from_env() {
+        Ok(c) => c,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({
+                    "error": "PSP catalog unavailable",
+                    "details": e.to_string()
+                }),
+            );
+        }
+    };
+
+    let engine = RoutingEngine::new(PspSimulator::new(), catalog);
+    match store.execute(&engine, &request.transaction_id, kind, request.amount.as_ref()) {
+        Ok(response) => (StatusCode::OK, json!(response)),
+        Err(e) => {
+            let status = match e {
+                LifecycleError::UnknownPayment(_) => StatusCode::NOT_FOUND,
+                LifecycleError::InvalidTransition { .. } | LifecycleError::PaymentBusy(_) => {
+                    StatusCode::CONFLICT
+                }
+                LifecycleError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            (
+                status,
+                json!({
+                    "error": format!("Cannot {kind} payment"),
+                    "details": e.to_string()
+                }),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::money::Money;
+    use crate::models::payment::PaymentState;
+    use crate::models::psp::{PspConfig, PspResponse};
+    use crate::models::transaction::{Currency, Transaction};
+
+    /// Approves every operation.
+    struct Approving;
+
+    impl PspConnector for Approving {
+        fn authorize(&self, _transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+            crate::simulator::respond(psp, 100, None)
+        }
+
+        fn execute(&self, _payment: &Payment, psp: &PspConfig, _kind: OperationKind) -> PspResponse {
+            crate::simulator::respond(psp, 100, None)
+        }
+    }
+
+    fn brl(minor_units: i64) -> Money {
+        Money::new(minor_units, Currency::new("BRL"))
+    }
+
+    fn payment() -> Payment {
+        Payment {
+            transaction_id: "txn_stored".into(),
+            psp_id: "psp_br_1".into(),
+            psp_name: "PagSeguro".into(),
+            state: PaymentState::Authorized,
+            authorized_amount: brl(10000),
+            captured_amount: brl(0),
+            refunded_amount: brl(0),
+            operations: Vec::new(),
+        }
+    }
+
+    fn stored_payment() -> PaymentStore {
+        let store = PaymentStore::default();
+        store.insert(&payment()).unwrap();
+        store
+    }
+
+    #[test]
+    fn test_operations_move_the_stored_payment() {
+        let store = stored_payment();
+        let engine = RoutingEngine::new(Approving, PspCatalog::builtin());
+        let amount = AmountInput::Decimal("80.00".into());
+
+        let capture = store
+            .execute(&engine, "txn_stored", OperationKind::Capture, Some(&amount))
+            .unwrap();
+        assert!(capture.operation.approved);
+        assert_eq!(capture.payment.state, PaymentState::Captured);
+
+        // The next operation starts from the stored capture
+        let refund = store.execute(&engine, "txn_stored", OperationKind::Refund, None).unwrap();
+        assert_eq!(refund.operation.amount, brl(8000));
+        let payment = store.payment("txn_stored").unwrap().unwrap();
+        assert_eq!(payment.state, PaymentState::Refunded);
+        assert_eq!(payment.operations.len(), 2);
+
+        assert_eq!(
+            store.execute(&engine, "txn_stored", OperationKind::Capture, None).unwrap_err(),
+            LifecycleError::InvalidTransition {
+                state: PaymentState::Refunded,
+                operation: OperationKind::Capture
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_transactions_and_bad_amounts_are_rejected() {
+        let store = stored_payment();
+        let engine = RoutingEngine::new(Approving, PspCatalog::builtin());
+
+        assert_eq!(
+            store.execute(&engine, "txn_other", OperationKind::Void, None).unwrap_err(),
+            LifecycleError::UnknownPayment("txn_other".into())
+        );
+        let err = store
+            .execute(&engine, "txn_stored", OperationKind::Capture, Some(&AmountInput::Decimal("1.2.3".into())))
+            .unwrap_err();
+        assert!(matches!(err, LifecycleError::InvalidAmount(_)));
+        assert_eq!(store.payment("txn_stored").unwrap().unwrap().state, PaymentState::Authorized);
+    }
+
+    #[test]
+    fn test_payments_are_shared_through_the_key_value_store() {
+        let kv: Arc<dyn KvStore> = Arc::new(MemoryKv::new());
+        let authorizing = PaymentStore::new(kv.clone());
+        authorizing.insert(&payment()).unwrap();
+
+        // A second authorization of the transaction does not replace the first
+        let mut other = payment();
+        other.authorized_amount = brl(500);
+        assert_eq!(
+            authorizing.insert(&other).unwrap_err(),
+            LifecycleError::DuplicatePayment("txn_stored".into())
+        );
+
+        // Another function's store over the same key-value store sees it
+        let capturing = PaymentStore::new(kv.clone());
+        let engine = RoutingEngine::new(Approving, PspCatalog::builtin());
+        let capture = capturing
+            .execute(&engine, "txn_stored", OperationKind::Capture, None)
+            .unwrap();
+        assert_eq!(capture.operation.amount, brl(10000));
+        assert_eq!(authorizing.payment("txn_stored").unwrap().unwrap().state, PaymentState::Captured);
+
+        // An operation is turned away while another holds the payment
+        kv.set_new(&hold_key("txn_stored"), "refund", Some(OPERATION_HOLD)).unwrap();
+        assert_eq!(
+            capturing.execute(&engine, "txn_stored", OperationKind::Refund, None).unwrap_err(),
+            LifecycleError::PaymentBusy("txn_stored".into())
+        );
+        kv.delete(&hold_key("txn_stored")).unwrap();
+        assert!(capturing.execute(&engine, "txn_stored", OperationKind::Refund, None).is_ok());
+    }
+
+    #[test]
+    fn test_follow_up_answers_with_api_status_codes() {
+        let store = stored_payment();
+        let post = |body: &str, kind| follow_up(&store, &Method::POST, body.as_bytes(), kind).0;
+
+        assert_eq!(
+            follow_up(&store, &Method::GET, b"", OperationKind::Void).0,
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+        assert_eq!(post("{}", OperationKind::Void), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            post(r#"{"transaction_id": "txn_other"}"#, OperationKind::Void),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            post(r#"{"transaction_id": "txn_stored"}"#, OperationKind::Refund),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            post(r#"{"transaction_id": "txn_stored", "amount": "500.00"}"#, OperationKind::Capture),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
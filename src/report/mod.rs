@@ -2,27 +2,47 @@
 ///
 /// Compares no-retry vs smart-retry routing scenarios and
 /// quantifies the business impact of intelligent routing.
+/// Monetary figures are reported in each country's local currency and,
+/// for batch totals, converted to USD through an [`FxTable`].
+use crate::card_bin::BinInfo;
+use crate::connector::PspConnector;
+use crate::engine::bandit::AdaptiveRouter;
+use crate::engine::fees::transaction_fee;
+use crate::engine::retry::{is_psp_unavailable, RetryPolicy};
 use crate::engine::RoutingEngine;
-use crate::models::psp::DeclineReason;
+use crate::fx::FxTable;
+use crate::models::money::Money;
+use crate::models::psp::{DeclineReason, PspConfig};
 use crate::models::report::{
-    CountryMetrics, ImprovementMetrics, PerformanceReport, PspMetrics, ScenarioResult,
+    ConvergenceReport, CountryMetrics, ImprovementMetrics, PerformanceReport, PspMetrics,
+    RecoveryMetrics, ScenarioResult, SegmentMetrics, StrategyConvergence,
 };
-use crate::models::routing::{RoutingResult, RoutingStrategy};
-use crate::models::transaction::Transaction;
-use std::collections::HashMap;
+use crate::models::routing::{AuthorizationStatus, RoutingResult, RoutingStrategy};
+use crate::models::transaction::{Currency, PaymentMethod, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Transactions per window in the strategy convergence series.
+pub const CONVERGENCE_WINDOW: usize = 30;
 
 /// Generate a complete performance report comparing no-retry vs smart-retry.
 ///
 /// Runs every transaction through both scenarios (single-PSP and full routing),
 /// then computes aggregate metrics, country breakdowns, PSP breakdowns, and
-/// the business impact of switching to smart retry.
-pub fn generate_report(
+/// the business impact of switching to smart retry. Smart retry follows
+/// `policy`. Every strategy is also replayed from a cold start to show how
+/// the adaptive one converges against the static ones, through the same
+/// connector as `engine`. Amounts are converted to USD with `fx` at the rate in effect
+/// on each transaction's date.
+pub fn generate_report<C: PspConnector + Clone>(
     transactions: &[Transaction],
-    engine: &RoutingEngine,
+    engine: &RoutingEngine<C>,
     strategy: &RoutingStrategy,
+    policy: &RetryPolicy,
+    fx: &FxTable,
 ) -> PerformanceReport {
     let no_retry_results = run_no_retry(transactions, engine);
-    let smart_retry_results = run_smart_retry(transactions, engine, strategy);
+    let smart_retry_results = run_smart_retry(transactions, engine, strategy, policy);
 
     let no_retry_metrics = calculate_metrics(&no_retry_results);
     let smart_retry_metrics = calculate_metrics(&smart_retry_results);
@@ -32,24 +52,80 @@ pub fn generate_report(
         .saturating_sub(no_retry_metrics.approved);
     let rate_lift = smart_retry_metrics.authorization_rate - no_retry_metrics.authorization_rate;
 
-    let avg_transaction_value = if transactions.is_empty() {
-        0.0
-    } else {
-        transactions.iter().map(|t| t.amount).sum::<f64>() / transactions.len() as f64
-    };
+    let psps: HashMap<String, PspConfig> = engine
+        .catalog()
+        .psps()
+        .iter()
+        .map(|p| (p.id.clone(), p.clone()))
+        .collect();
+
+    let amounts_usd: Vec<Money> = transactions.iter().map(|t| to_usd(fx, t, &t.amount)).collect();
+    let avg_ticket_usd = average(
+        &Money::checked_sum(Currency::USD, &amounts_usd).expect("USD total overflowed"),
+        amounts_usd.len(),
+    );
+
+    let smart_by_id: HashMap<&str, &RoutingResult> = smart_retry_results
+        .iter()
+        .map(|r| (r.transaction_id.as_str(), r))
+        .collect();
+    let no_retry_approved: HashSet<&str> = no_retry_results
+        .iter()
+        .filter(|r| r.approved)
+        .map(|r| r.transaction_id.as_str())
+        .collect();
+    // Transactions only smart retry approved, each at its own USD value
+    let recovered_usd: Vec<Money> = transactions
+        .iter()
+        .filter(|t| {
+            smart_by_id.get(t.id.as_str()).is_some_and(|r| r.approved)
+                && !no_retry_approved.contains(t.id.as_str())
+        })
+        .map(|t| to_usd(fx, t, &t.amount))
+        .collect();
+    let fees_usd: Vec<Money> = transactions
+        .iter()
+        .filter_map(|t| {
+            let result = smart_by_id.get(t.id.as_str())?;
+            Some(to_usd(fx, t, &approval_fee(t, result, &psps, fx)))
+        })
+        .collect();
 
     let improvement = ImprovementMetrics {
         rate_lift_percentage: round2(rate_lift),
         additional_approvals,
-        estimated_revenue_recovered_usd: round2(
-            additional_approvals as f64 * avg_transaction_value,
-        ),
+        estimated_revenue_recovered_usd: Money::checked_sum(Currency::USD, &recovered_usd)
+            .expect("recovered revenue overflowed"),
+        avg_ticket_usd,
+        total_fees_usd: Money::checked_sum(Currency::USD, &fees_usd).expect("USD fees overflowed"),
+        cross_border_recoveries: count_cross_border_recoveries(&smart_retry_results),
+        three_ds_recoveries: count_three_ds_recoveries(&smart_retry_results),
+        credential_recoveries: count_credential_recoveries(&smart_retry_results),
     };
 
-    let by_country = build_country_breakdown(transactions, &no_retry_results, &smart_retry_results);
+    let by_country = build_country_breakdown(
+        transactions,
+        &no_retry_results,
+        &smart_retry_results,
+        &psps,
+        fx,
+    );
 
     let by_psp = build_psp_breakdown(&smart_retry_results);
 
+    let by_card_scheme = build_segment_breakdown(
+        transactions,
+        &no_retry_results,
+        &smart_retry_results,
+        |t| card_segment(t, |info| info.scheme.to_string()),
+    );
+    let by_card_type = build_segment_breakdown(
+        transactions,
+        &no_retry_results,
+        &smart_retry_results,
+        |t| card_segment(t, |info| info.card_type.to_string()),
+    );
+
     PerformanceReport {
         total_transactions: transactions.len(),
         no_retry: no_retry_metrics,
@@ -57,11 +133,22 @@ pub fn generate_report(
         improvement,
         by_country,
         by_psp,
+        by_card_scheme,
+        by_card_type,
+        circuit_breakers: engine
+            .breakers()
+            .map(|breakers| breakers.snapshot())
+            .unwrap_or_default(),
+        convergence: build_convergence(transactions, engine, policy),
+        recovery: build_recovery_breakdown(&smart_retry_results),
     }
 }
 
 /// Run all transactions in no-retry mode (single PSP, fail on any decline).
-fn run_no_retry(transactions: &[Transaction], engine: &RoutingEngine) -> Vec<RoutingResult> {
+fn run_no_retry<C: PspConnector>(
+    transactions: &[Transaction],
+    engine: &RoutingEngine<C>,
+) -> Vec<RoutingResult> {
     transactions
         .iter()
         .map(|txn| engine.route_no_retry(txn))
@@ -69,15 +156,138 @@ fn run_no_retry(transactions: &[Transaction], engine: &RoutingEngine) -> Vec<Rou
 }
 
 /// Run all transactions with smart retry (full routing engine).
-fn run_smart_retry(
+///
+/// Transactions are routed in time order so the engine's circuit breakers
+/// open and recover as they would have live; results come back in input
+/// order. Cardholders answer 3-D Secure challenges as the connector
+/// simulates them.
+fn run_smart_retry<C: PspConnector>(
     transactions: &[Transaction],
-    engine: &RoutingEngine,
+    engine: &RoutingEngine<C>,
     strategy: &RoutingStrategy,
+    policy: &RetryPolicy,
 ) -> Vec<RoutingResult> {
-    transactions
+    let mut results: Vec<Option<RoutingResult>> = vec![None; transactions.len()];
+    for i in time_order(transactions) {
+        results[i] = Some(route_to_completion(engine, &transactions[i], strategy, policy));
+    }
+    results.into_iter().flatten().collect()
+}
+
+/// Route `transaction` and, while routing is paused on a 3-D Secure
+/// challenge, complete it.
+fn route_to_completion<C: PspConnector>(
+    engine: &RoutingEngine<C>,
+    transaction: &Transaction,
+    strategy: &RoutingStrategy,
+    policy: &RetryPolicy,
+) -> RoutingResult {
+    let mut result = engine.route(transaction, strategy, policy);
+    while let Some(session) = result.challenge.take() {
+        result = engine
+            .complete_challenge(&session)
+            .expect("the session was issued by this engine");
+    }
+    result
+}
+
+/// Indices of `transactions` sorted by timestamp (stable for ties).
+fn time_order(transactions: &[Transaction]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..transactions.len()).collect();
+    order.sort_by_key(|&i| transactions[i].timestamp);
+    order
+}
+
+/// Replay the batch in time order under every strategy, each on a fresh
+/// engine without circuit breakers or merchant rules, so only the ranking
+/// differs. The adaptive run starts from an empty bandit configured like
+/// the engine's.
+fn build_convergence<C: PspConnector + Clone>(
+    transactions: &[Transaction],
+    engine: &RoutingEngine<C>,
+    policy: &RetryPolicy,
+) -> ConvergenceReport {
+    let bandit_config = engine.bandit().map(|b| *b.config()).unwrap_or_default();
+    let order = time_order(transactions);
+
+    let strategies = [
+        RoutingStrategy::OptimizeForApprovals,
+        RoutingStrategy::OptimizeForCost,
+        RoutingStrategy::Balanced,
+        RoutingStrategy::Adaptive,
+        RoutingStrategy::ExpectedValue,
+    ]
+    .into_iter()
+    .map(|strategy| {
+        let mut cold = RoutingEngine::new(engine.connector().clone(), engine.catalog().clone())
+            .with_costs(engine.costs().clone());
+        if let RoutingStrategy::Adaptive = strategy {
+            cold = cold.with_bandit(Arc::new(AdaptiveRouter::new(bandit_config)));
+        }
+        let results: Vec<RoutingResult> = order
+            .iter()
+            .map(|&i| route_to_completion(&cold, &transactions[i], &strategy, policy))
+            .collect();
+        let metrics = calculate_metrics(&results);
+        StrategyConvergence {
+            authorization_rate: metrics.authorization_rate,
+            avg_attempts: metrics.avg_attempts,
+            first_attempt_rate: first_attempt_rate(&results),
+            window_first_attempt_rates: results
+                .chunks(CONVERGENCE_WINDOW)
+                .map(first_attempt_rate)
+                .collect(),
+            strategy,
+        }
+    })
+    .collect();
+
+    ConvergenceReport {
+        window_size: CONVERGENCE_WINDOW,
+        strategies,
+    }
+}
+
+/// Percentage of results approved by the first PSP tried.
+fn first_attempt_rate(results: &[RoutingResult]) -> f64 {
+    if results.is_empty() {
+        return 0.0;
+    }
+    let first_approved = results
         .iter()
-        .map(|txn| engine.route(txn, strategy))
-        .collect()
+        .filter(|r| r.attempts.first().is_some_and(|a| a.approved))
+        .count();
+    round2(first_approved as f64 / results.len() as f64 * 100.0)
+}
+
+/// Transactions a cross-border acquirer accepted. It is only tried once
+/// every local PSP has declined, so each one is a recovery.
+fn count_cross_border_recoveries(results: &[RoutingResult]) -> usize {
+    results
+        .iter()
+        .filter(|r| r.status != AuthorizationStatus::Declined)
+        .filter(|r| r.attempts.iter().any(|a| a.approved && !a.cancelled && a.cross_border))
+        .count()
+}
+
+/// Transactions accepted on an attempt sent with the cardholder's 3-D
+/// Secure authentication, which only follows a decline.
+fn count_three_ds_recoveries(results: &[RoutingResult]) -> usize {
+    results
+        .iter()
+        .filter(|r| r.status != AuthorizationStatus::Declined)
+        .filter(|r| r.attempts.iter().any(|a| a.approved && !a.cancelled && a.three_ds.is_some()))
+        .count()
+}
+
+/// Transactions accepted on an attempt sent with the card's refreshed
+/// credential, which is only fetched after a decline.
+fn count_credential_recoveries(results: &[RoutingResult]) -> usize {
+    results
+        .iter()
+        .filter(|r| r.status != AuthorizationStatus::Declined)
+        .filter(|r| r.attempts.iter().any(|a| a.approved && !a.cancelled && a.credential.is_some()))
+        .count()
 }
 
 /// Calculate aggregate metrics from a set of routing results.
@@ -85,6 +295,7 @@ fn calculate_metrics(results: &[RoutingResult]) -> ScenarioResult {
     if results.is_empty() {
         return ScenarioResult {
             approved: 0,
+            pending: 0,
             declined: 0,
             authorization_rate: 0.0,
             avg_attempts: 0.0,
@@ -94,13 +305,18 @@ fn calculate_metrics(results: &[RoutingResult]) -> ScenarioResult {
 
     let total = results.len();
     let approved = results.iter().filter(|r| r.approved).count();
-    let declined = total - approved;
+    let pending = results
+        .iter()
+        .filter(|r| r.status == AuthorizationStatus::Pending)
+        .count();
+    let declined = total - approved - pending;
 
     let total_attempts: usize = results.iter().map(|r| r.total_attempts).sum();
     let total_latency: u64 = results.iter().map(|r| r.total_latency_ms).sum();
 
     ScenarioResult {
         approved,
+        pending,
         declined,
         authorization_rate: round2(approved as f64 / total as f64 * 100.0),
         avg_attempts: round2(total_attempts as f64 / total as f64),
@@ -108,11 +324,13 @@ fn calculate_metrics(results: &[RoutingResult]) -> ScenarioResult {
     }
 }
 
-/// Build per-country authorization rate breakdown.
+/// Build per-country authorization rate and local-currency volume breakdown.
 fn build_country_breakdown(
     transactions: &[Transaction],
     no_retry_results: &[RoutingResult],
     smart_retry_results: &[RoutingResult],
+    psps: &HashMap<String, PspConfig>,
+    fx: &FxTable,
 ) -> HashMap<String, CountryMetrics> {
     let mut country_map: HashMap<String, CountryMetrics> = HashMap::new();
 
@@ -141,19 +359,38 @@ fn build_country_breakdown(
             continue;
         }
 
-        let no_retry_approved = txns
+        let approved_without_retry = |t: &Transaction| {
+            no_retry_by_id
+                .get(t.id.as_str())
+                .is_some_and(|r| r.approved)
+        };
+        let smart_approved_txns: Vec<&Transaction> = txns
             .iter()
-            .filter(|t| {
-                no_retry_by_id
-                    .get(t.id.as_str())
-                    .is_some_and(|r| r.approved)
-            })
-            .count();
+            .copied()
+            .filter(|t| smart_by_id.get(t.id.as_str()).is_some_and(|r| r.approved))
+            .collect();
+        let recovered_txns: Vec<&Transaction> = smart_approved_txns
+            .iter()
+            .copied()
+            .filter(|t| !approved_without_retry(t))
+            .collect();
+
+        let no_retry_approved = txns.iter().filter(|t| approved_without_retry(t)).count();
+        let smart_approved = smart_approved_txns.len();
 
-        let smart_approved = txns
+        // Every transaction in a country shares its currency.
+        let currency = &txns[0].amount.currency;
+        let approved_volume = sum_volume(currency, &smart_approved_txns);
+        let recovered_volume = sum_volume(currency, &recovered_txns);
+        let avg_ticket = average(&sum_volume(currency, txns), total);
+        let fees: Vec<Money> = smart_approved_txns
             .iter()
-            .filter(|t| smart_by_id.get(t.id.as_str()).is_some_and(|r| r.approved))
-            .count();
+            .filter_map(|t| {
+                let result = smart_by_id.get(t.id.as_str())?;
+                Some(approval_fee(t, result, psps, fx))
+            })
+            .collect();
+        let fees_paid = Money::checked_sum(currency.clone(), &fees).expect("country fees overflowed");
 
         let no_retry_rate = round2(no_retry_approved as f64 / total as f64 * 100.0);
         let smart_retry_rate = round2(smart_approved as f64 / total as f64 * 100.0);
@@ -165,6 +402,10 @@ fn build_country_breakdown(
                 smart_retry_rate,
                 improvement: round2(smart_retry_rate - no_retry_rate),
                 total_transactions: total,
+                approved_volume,
+                recovered_volume,
+                avg_ticket,
+                fees_paid,
             },
         );
     }
@@ -172,6 +413,119 @@ fn build_country_breakdown(
     country_map
 }
 
+/// Segment key for a card transaction, or `"Unknown"` when its BIN is not
+/// in the BIN table. Non-card transactions are not segmented.
+fn card_segment(txn: &Transaction, key: impl Fn(&BinInfo) -> String) -> Option<String> {
+    if txn.payment_method != PaymentMethod::Card {
+        return None;
+    }
+    Some(txn.bin_info.as_ref().map(key).unwrap_or_else(|| "Unknown".to_string()))
+}
+
+/// Build no-retry vs smart-retry authorization rates per segment.
+fn build_segment_breakdown(
+    transactions: &[Transaction],
+    no_retry_results: &[RoutingResult],
+    smart_retry_results: &[RoutingResult],
+    segment: impl Fn(&Transaction) -> Option<String>,
+) -> HashMap<String, SegmentMetrics> {
+    let approved_ids = |results: &[RoutingResult]| -> HashMap<String, bool> {
+        results
+            .iter()
+            .map(|r| (r.transaction_id.clone(), r.approved))
+            .collect()
+    };
+    let no_retry = approved_ids(no_retry_results);
+    let smart = approved_ids(smart_retry_results);
+
+    // (total, no-retry approvals, smart-retry approvals) per segment.
+    let mut counts: HashMap<String, (usize, usize, usize)> = HashMap::new();
+    for txn in transactions {
+        let Some(key) = segment(txn) else { continue };
+        let entry = counts.entry(key).or_default();
+        entry.0 += 1;
+        if no_retry.get(&txn.id).copied().unwrap_or(false) {
+            entry.1 += 1;
+        }
+        if smart.get(&txn.id).copied().unwrap_or(false) {
+            entry.2 += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(key, (total, no_retry_approved, smart_approved))| {
+            let no_retry_rate = round2(no_retry_approved as f64 / total as f64 * 100.0);
+            let smart_retry_rate = round2(smart_approved as f64 / total as f64 * 100.0);
+            (
+                key,
+                SegmentMetrics {
+                    no_retry_rate,
+                    smart_retry_rate,
+                    improvement: round2(smart_retry_rate - no_retry_rate),
+                    total_transactions: total,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Sum transaction amounts exactly in minor units.
+///
+/// Amounts in a different currency are skipped rather than mixed in.
+fn sum_volume(currency: &Currency, txns: &[&Transaction]) -> Money {
+    Money::checked_sum(
+        currency.clone(),
+        txns.iter()
+            .map(|t| &t.amount)
+            .filter(|m| &m.currency == currency),
+    )
+    .expect("country volume overflowed i64 minor units")
+}
+
+/// Processing fee charged for an approved transaction, in its local currency.
+///
+/// The approving PSP's fee on the transaction (see [`transaction_fee`]).
+/// Declined transactions and unknown PSPs cost nothing.
+fn approval_fee(
+    txn: &Transaction,
+    result: &RoutingResult,
+    psps: &HashMap<String, PspConfig>,
+    fx: &FxTable,
+) -> Money {
+    let psp = result
+        .attempts
+        .iter()
+        .find(|a| a.approved)
+        .and_then(|a| psps.get(&a.psp_id));
+    let Some(psp) = psp else {
+        return Money::zero(txn.amount.currency.clone());
+    };
+
+    transaction_fee(psp, txn, fx)
+}
+
+/// Convert a transaction-related amount to USD at the transaction date.
+fn to_usd(fx: &FxTable, txn: &Transaction, amount: &Money) -> Money {
+    fx.to_usd(amount, &txn.timestamp.date_string())
+        .expect("FX table covers every checkout currency")
+}
+
+/// Average of `count` amounts totalling `total`, rounded to the minor unit.
+fn average(total: &Money, count: usize) -> Money {
+    if count == 0 {
+        return Money::zero(total.currency.clone());
+    }
+    let count = count as i64;
+    let half = count / 2;
+    let rounded = if total.minor_units >= 0 {
+        (total.minor_units + half) / count
+    } else {
+        (total.minor_units - half) / count
+    };
+    Money::new(rounded, total.currency.clone())
+}
+
 /// Build per-PSP performance breakdown from smart-retry results.
 fn build_psp_breakdown(results: &[RoutingResult]) -> HashMap<String, PspMetrics> {
     let mut psp_map: HashMap<String, (usize, usize, usize, u64)> = HashMap::new();
@@ -225,6 +579,48 @@ fn build_psp_breakdown(results: &[RoutingResult]) -> HashMap<String, PspMetrics>
         .collect()
 }
 
+/// Build the recovery rate of each (decline reason, next PSP) pair from
+/// smart-retry results: every attempt that directly follows a decline,
+/// skipping PSP-unavailable cascades, counts toward the reason of that
+/// decline. Sorted by reason, then PSP.
+fn build_recovery_breakdown(results: &[RoutingResult]) -> Vec<RecoveryMetrics> {
+    let mut pairs: HashMap<(DeclineReason, String), (String, usize, usize)> = HashMap::new();
+
+    for result in results {
+        let mut previous: Option<&DeclineReason> = None;
+        for attempt in &result.attempts {
+            let reason = attempt.decline_reason.as_ref();
+            if reason.is_some_and(is_psp_unavailable) {
+                continue;
+            }
+            if let Some(previous) = previous {
+                let entry = pairs
+                    .entry((previous.clone(), attempt.psp_id.clone()))
+                    .or_insert_with(|| (attempt.psp_name.clone(), 0, 0));
+                entry.1 += 1;
+                if attempt.approved {
+                    entry.2 += 1;
+                }
+            }
+            previous = reason;
+        }
+    }
+
+    let mut recovery: Vec<RecoveryMetrics> = pairs
+        .into_iter()
+        .map(|((reason, psp_id), (psp_name, attempts, recoveries))| RecoveryMetrics {
+            reason,
+            psp_id,
+            psp_name,
+            attempts,
+            recoveries,
+            recovery_rate: round2(recoveries as f64 / attempts as f64 * 100.0),
+        })
+        .collect();
+    recovery.sort_by(|a, b| (a.reason.to_string(), &a.psp_id).cmp(&(b.reason.to_string(), &b.psp_id)));
+    recovery
+}
+
 /// Round a floating-point value to 2 decimal places.
 fn round2(val: f64) -> f64 {
     (val * 100.0).round() / 100.0
@@ -233,24 +629,36 @@ fn round2(val: f64) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::routing::{RoutingAttempt, RoutingResult};
-    use crate::models::transaction::{Country, Currency, Transaction};
-
-    fn make_transaction(id: &str, country: Country, amount: f64) -> Transaction {
-        let (currency, bin) = match country {
-            Country::Brazil => (Currency::BRL, "411111"),
-            Country::Mexico => (Currency::MXN, "424242"),
-            Country::Colombia => (Currency::COP, "431940"),
+    use crate::time::Timestamp;
+    use crate::catalog::PspCatalog;
+    use crate::models::payment::{OperationKind, Payment};
+    use crate::models::psp::PspResponse;
+    use crate::models::routing::{RetryType, RoutingAttempt, RoutingResult};
+    use crate::models::transaction::{Country, Transaction};
+
+    fn make_transaction(id: &str, country: Country, amount: &str) -> Transaction {
+        let (currency, bin) = match country.name() {
+            "Brazil" => (Currency::new("BRL"), "411111"),
+            "Mexico" => (Currency::new("MXN"), "424242"),
+            "Colombia" => (Currency::new("COP"), "431940"),
+            "Chile" => (Currency::new("CLP"), "455638"),
+            "Peru" => (Currency::new("PEN"), "421355"),
+            "Argentina" => (Currency::new("ARS"), "450799"),
+            other => unreachable!("{other} is not a market"),
         };
         Transaction {
             id: id.to_string(),
-            amount,
-            currency,
+            amount: Money::from_decimal_str(amount, currency).unwrap(),
             country,
-            card_bin: bin.to_string(),
-            card_last4: "1234".to_string(),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some(bin.to_string()),
+            card_last4: Some("1234".to_string()),
+            bin_info: None,
+            installments: None,
             customer_id: "cust_001".to_string(),
-            timestamp: "2025-01-15T10:00:00Z".to_string(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
         }
     }
 
@@ -260,6 +668,7 @@ mod tests {
                 psp_id: format!("psp_{}", i),
                 psp_name: format!("PSP_{}", i),
                 approved: i == attempts && approved,
+                response_code: if i == attempts && approved { "00" } else { "91" }.to_string(),
                 decline_reason: if i == attempts && approved {
                     None
                 } else {
@@ -267,12 +676,25 @@ mod tests {
                 },
                 latency_ms: latency / attempts as u64,
                 attempt_number: i,
+                retry_type: if i == 1 { RetryType::Initial } else { RetryType::NextPsp },
+                backoff_ms: 0,
+                hedged: false,
+                timed_out: false,
+                cancelled: false,
+                cross_border: false,
+                three_ds: None,
+                credential: None,
             })
             .collect();
 
         RoutingResult {
             transaction_id: txn_id.to_string(),
             approved,
+            status: if approved {
+                AuthorizationStatus::Approved
+            } else {
+                AuthorizationStatus::Declined
+            },
             final_psp: if approved {
                 Some(format!("PSP_{}", attempts))
             } else {
@@ -281,6 +703,12 @@ mod tests {
             attempts: attempt_list,
             total_attempts: attempts,
             total_latency_ms: latency,
+            budget_exhausted: false,
+            matched_rule: None,
+            ineligible_psps: vec![],
+            trace: None,
+            authentications: Vec::new(),
+            challenge: None,
         }
     }
 
@@ -308,6 +736,19 @@ mod tests {
         assert_eq!(metrics.avg_latency_ms, 262.5);
     }
 
+    #[test]
+    fn test_calculate_metrics_counts_pending_separately() {
+        let mut pending = make_result("txn_2", false, 1, 300);
+        pending.status = AuthorizationStatus::Pending;
+        let results = vec![make_result("txn_1", true, 1, 200), pending];
+
+        let metrics = calculate_metrics(&results);
+        assert_eq!(metrics.approved, 1);
+        assert_eq!(metrics.pending, 1);
+        assert_eq!(metrics.declined, 0);
+        assert_eq!(metrics.authorization_rate, 50.0);
+    }
+
     #[test]
     fn test_round2() {
         assert_eq!(round2(78.123456), 78.12);
@@ -318,9 +759,9 @@ mod tests {
     #[test]
     fn test_build_country_breakdown() {
         let transactions = vec![
-            make_transaction("txn_1", Country::Brazil, 100.0),
-            make_transaction("txn_2", Country::Brazil, 200.0),
-            make_transaction("txn_3", Country::Mexico, 150.0),
+            make_transaction("txn_1", Country::new("Brazil"), "100.10"),
+            make_transaction("txn_2", Country::new("Brazil"), "200.20"),
+            make_transaction("txn_3", Country::new("Mexico"), "150.00"),
         ];
 
         let no_retry = vec![
@@ -334,17 +775,225 @@ mod tests {
             make_result("txn_3", true, 1, 250),
         ];
 
-        let breakdown = build_country_breakdown(&transactions, &no_retry, &smart);
+        let breakdown = build_country_breakdown(
+            &transactions,
+            &no_retry,
+            &smart,
+            &HashMap::new(),
+            &FxTable::builtin(),
+        );
 
         let brazil = breakdown.get("Brazil").unwrap();
         assert_eq!(brazil.no_retry_rate, 50.0);
         assert_eq!(brazil.smart_retry_rate, 100.0);
         assert_eq!(brazil.improvement, 50.0);
         assert_eq!(brazil.total_transactions, 2);
+        assert_eq!(brazil.approved_volume, Money::new(30030, Currency::new("BRL")));
+        assert_eq!(brazil.recovered_volume, Money::new(20020, Currency::new("BRL")));
+        assert_eq!(brazil.avg_ticket, Money::new(15015, Currency::new("BRL")));
 
         let mexico = breakdown.get("Mexico").unwrap();
         assert_eq!(mexico.no_retry_rate, 100.0);
         assert_eq!(mexico.smart_retry_rate, 100.0);
+        assert_eq!(mexico.recovered_volume, Money::zero(Currency::new("MXN")));
+    }
+
+    #[test]
+    fn test_build_country_breakdown_covers_new_markets() {
+        let transactions = vec![
+            make_transaction("txn_1", Country::new("Chile"), "95000"),
+            make_transaction("txn_2", Country::new("Peru"), "376.00"),
+            make_transaction("txn_3", Country::new("Argentina"), "103200.00"),
+        ];
+        let results = vec![
+            make_result("txn_1", true, 1, 200),
+            make_result("txn_2", false, 1, 300),
+            make_result("txn_3", true, 2, 400),
+        ];
+
+        let breakdown = build_country_breakdown(
+            &transactions,
+            &results,
+            &results,
+            &HashMap::new(),
+            &FxTable::builtin(),
+        );
+
+        assert_eq!(breakdown.len(), 3);
+        assert_eq!(breakdown["Chile"].approved_volume, Money::new(95000, Currency::new("CLP")));
+        assert_eq!(breakdown["Peru"].smart_retry_rate, 0.0);
+        assert_eq!(breakdown["Peru"].avg_ticket.currency, Currency::new("PEN"));
+        assert_eq!(
+            breakdown["Argentina"].approved_volume,
+            Money::new(10_320_000, Currency::new("ARS"))
+        );
+    }
+
+    #[test]
+    fn test_segment_breakdown_by_card_scheme() {
+        let table = crate::card_bin::BinTable::builtin();
+        let mut visa = make_transaction("txn_1", Country::new("Brazil"), "100.00");
+        table.enrich(&mut visa);
+        let mut unknown = make_transaction("txn_2", Country::new("Brazil"), "100.00");
+        unknown.card_bin = Some("999999".to_string());
+        table.enrich(&mut unknown);
+        let mut pix = make_transaction("txn_3", Country::new("Brazil"), "100.00");
+        pix.payment_method = PaymentMethod::Pix;
+
+        let no_retry = vec![
+            make_result("txn_1", false, 1, 200),
+            make_result("txn_2", true, 1, 200),
+            make_result("txn_3", true, 1, 200),
+        ];
+        let smart = vec![
+            make_result("txn_1", true, 2, 400),
+            make_result("txn_2", true, 1, 200),
+            make_result("txn_3", true, 1, 200),
+        ];
+
+        let breakdown = build_segment_breakdown(
+            &[visa, unknown, pix],
+            &no_retry,
+            &smart,
+            |t| card_segment(t, |info| info.scheme.to_string()),
+        );
+
+        assert_eq!(breakdown.len(), 2, "PIX is not a card segment");
+        assert_eq!(breakdown["Visa"].no_retry_rate, 0.0);
+        assert_eq!(breakdown["Visa"].smart_retry_rate, 100.0);
+        assert_eq!(breakdown["Unknown"].total_transactions, 1);
+    }
+
+    #[test]
+    fn test_approval_fee_uses_amount_and_converted_fixed_fee() {
+        let psps: HashMap<String, PspConfig> = PspCatalog::builtin()
+            .psps()
+            .iter()
+            .map(|p| (p.id.clone(), p.clone()))
+            .collect();
+        let fx = FxTable::builtin();
+        let txn = make_transaction("txn_1", Country::new("Brazil"), "100.00");
+        let mut result = make_result("txn_1", true, 1, 200);
+        result.attempts[0].psp_id = "psp_br_1".to_string();
+
+        // PagSeguro: 2.9% of 100.00 BRL = 2.90, plus 0.30 USD * 6.18 = 1.85 (rounded)
+        let fee = approval_fee(&txn, &result, &psps, &fx);
+        assert_eq!(fee, Money::new(475, Currency::new("BRL")));
+
+        let declined = make_result("txn_1", false, 1, 200);
+        assert_eq!(approval_fee(&txn, &declined, &psps, &fx), Money::zero(Currency::new("BRL")));
+    }
+
+    /// Declines every authorization at one PSP and approves the rest.
+    #[derive(Clone)]
+    struct DeclinesAt(&'static str);
+
+    impl PspConnector for DeclinesAt {
+        fn authorize(&self, _transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+            let outcome = (psp.id == self.0).then_some(DeclineReason::IssuerUnavailable);
+            crate::simulator::respond(psp, 100, outcome)
+        }
+
+        fn execute(&self, _payment: &Payment, psp: &PspConfig, _kind: OperationKind) -> PspResponse {
+            crate::simulator::respond(psp, 100, None)
+        }
+    }
+
+    #[test]
+    fn test_generate_report_normalizes_to_usd() {
+        let transactions = vec![
+            make_transaction("txn_1", Country::new("Brazil"), "618.00"),
+            make_transaction("txn_2", Country::new("Colombia"), "881000"),
+        ];
+        // Only the Brazilian payment needs a retry
+        let engine = RoutingEngine::new(DeclinesAt("psp_br_1"), PspCatalog::builtin());
+        let report = generate_report(
+            &transactions,
+            &engine,
+            &RoutingStrategy::OptimizeForApprovals,
+            &RetryPolicy::default(),
+            &FxTable::builtin(),
+        );
+        // 100.00 and 200.00 USD at the built-in rates
+        assert_eq!(report.improvement.avg_ticket_usd, Money::new(15000, Currency::USD));
+        assert_eq!(report.improvement.additional_approvals, 1);
+        // The recovered payment's own value, not the average ticket
+        assert_eq!(report.improvement.estimated_revenue_recovered_usd, Money::new(10000, Currency::USD));
+        assert_eq!(report.by_country["Brazil"].recovered_volume, Money::new(61800, Currency::new("BRL")));
+        assert_eq!(report.by_country["Colombia"].avg_ticket.currency, Currency::new("COP"));
+    }
+
+    #[test]
+    fn test_convergence_replays_every_strategy() {
+        let transactions = crate::data::generate_test_data(70);
+        let engine = RoutingEngine::new(crate::simulator::PspSimulator::new(), PspCatalog::builtin());
+
+        let convergence = build_convergence(&transactions, &engine, &RetryPolicy::default());
+        assert_eq!(convergence.window_size, CONVERGENCE_WINDOW);
+        assert_eq!(convergence.strategies.len(), 5);
+        for series in &convergence.strategies {
+            // 70 transactions: two full windows and a short one
+            assert_eq!(series.window_first_attempt_rates.len(), 3, "{:?}", series.strategy);
+            assert!(series.first_attempt_rate <= series.authorization_rate);
+        }
+        assert!(matches!(convergence.strategies[3].strategy, RoutingStrategy::Adaptive));
+    }
+
+    #[test]
+    fn test_build_recovery_breakdown() {
+        let mut cascaded = make_result("txn_3", true, 2, 400);
+        cascaded.attempts[0].decline_reason = Some(DeclineReason::PspUnavailable);
+        let results = vec![
+            make_result("txn_1", true, 3, 600),
+            make_result("txn_2", false, 2, 400),
+            cascaded,
+        ];
+
+        let recovery = build_recovery_breakdown(&results);
+        let pairs: Vec<(&str, usize, usize, f64)> = recovery
+            .iter()
+            .map(|r| (r.psp_id.as_str(), r.attempts, r.recoveries, r.recovery_rate))
+            .collect();
+        // Every decline is IssuerUnavailable; the cascade has no predecessor
+        assert_eq!(pairs, [("psp_2", 2, 0, 0.0), ("psp_3", 1, 1, 100.0)]);
+        assert!(recovery.iter().all(|r| r.reason == DeclineReason::IssuerUnavailable));
+    }
+
+    #[test]
+    fn test_counts_cross_border_recoveries_separately() {
+        let mut recovered = make_result("txn_1", true, 4, 800);
+        recovered.attempts[3].cross_border = true;
+        let mut declined = make_result("txn_2", false, 4, 800);
+        declined.attempts[3].cross_border = true;
+        let results = vec![recovered, declined, make_result("txn_3", true, 2, 400)];
+
+        assert_eq!(count_cross_border_recoveries(&results), 1);
+        assert_eq!(calculate_metrics(&results).approved, 2);
+    }
+
+    #[test]
+    fn test_counts_three_ds_recoveries() {
+        let mut recovered = make_result("txn_1", true, 2, 400);
+        recovered.attempts[1].three_ds = Some(crate::models::authentication::ThreeDsFlow::Challenge);
+        let mut declined = make_result("txn_2", false, 2, 400);
+        declined.attempts[1].three_ds = Some(crate::models::authentication::ThreeDsFlow::Frictionless);
+        let results = vec![recovered, declined, make_result("txn_3", true, 2, 400)];
+
+        assert_eq!(count_three_ds_recoveries(&results), 1);
+    }
+
+    #[test]
+    fn test_counts_credential_recoveries_separately() {
+        use crate::models::credential::CredentialSource;
+
+        let mut recovered = make_result("txn_1", true, 2, 400);
+        recovered.attempts[1].credential = Some(CredentialSource::AccountUpdater);
+        let mut declined = make_result("txn_2", false, 2, 400);
+        declined.attempts[1].credential = Some(CredentialSource::NetworkToken);
+        let results = vec![recovered, declined, make_result("txn_3", true, 2, 400)];
+
+        assert_eq!(count_credential_recoveries(&results), 1);
+        assert_eq!(count_three_ds_recoveries(&results), 0);
     }
 
     #[test]
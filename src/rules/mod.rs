@@ -0,0 +1,631 @@
+//! Merchant routing rules — declarative overrides of the routing strategy.
+//!
+//! A rule pairs conditions on a [`Transaction`] with an action on the PSPs
+//! it may be routed to, e.g. "amount at least 2000 BRL and an Amex card →
+//! Cielo first" or "customer in the allow-list → Stone only". Rules are
+//! loaded from a TOML or JSON file and evaluated in file order before the
+//! strategy orders PSPs; the first rule whose conditions all hold applies,
+//! and its name is reported on the [`RoutingResult`](crate::models::routing::RoutingResult).
+//!
+//! ```toml
+//! [[rules]]
+//! name = "large-amex-to-cielo"
+//! when = { card_schemes = ["Amex"], min_amount = { amount = "2000.00", currency = "BRL" } }
+//! then = { psp_order = ["psp_br_2"] }
+//! ```
+//!
+//! # Conditions (`when`, all optional, all must hold)
+//!
+//! - `countries`, `currencies`, `card_schemes`, `customer_ids`: the
+//!   transaction's value is in the list.
+//! - `bin_prefixes`: the card BIN starts with one of the prefixes.
+//! - `min_amount` / `max_amount`: inclusive bounds; transactions in another
+//!   currency do not match.
+//! - `time_window`: `start` / `end` as `"HH:MM"` in the transaction
+//!   country's local time; a window may wrap past midnight. Omit it to
+//!   match at any time.
+//!
+//! # Actions (`then`)
+//!
+//! - `strategy`: order with this strategy instead of the requested one.
+//! - `psp_order`: try these PSPs first, in this order; with `exclusive`,
+//!   try only them.
+//! - `exclude`: never try these PSPs.
+//!
+//! # Validation
+//!
+//! - Rule names are non-empty and unique.
+//! - Every rule has an action, and `exclusive` comes with a `psp_order`.
+//! - No PSP is both preferred and excluded, or listed twice.
+//! - BIN prefixes are 1 to [`BIN_LENGTH`] digits.
+//! - Amount bounds share a currency and `min_amount <= max_amount`.
+//! - A time window's `start` and `end` differ.
+//! - [`RuleSet::check_catalog`] rejects PSP ids, countries and currencies
+//!   missing from a catalog.
+
+use crate::card_bin::{CardScheme, BIN_LENGTH};
+use crate::catalog::PspCatalog;
+use crate::models::money::Money;
+use crate::models::psp::PspConfig;
+use crate::models::routing::RoutingStrategy;
+use crate::models::transaction::{Country, Currency, Transaction};
+use crate::time::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Environment variable pointing at a rules file; no rules apply when unset.
+pub const RULES_PATH_ENV: &str = "ROUTING_RULES_PATH";
+
+/// Errors produced while loading or validating routing rules.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleError {
+    /// The rules file could not be read.
+    Io(String),
+    /// The rules file could not be parsed.
+    Parse(String),
+    /// The file extension is neither `.toml` nor `.json`.
+    UnsupportedFormat(String),
+    /// A rule has an empty name.
+    EmptyName,
+    /// Two rules share the same name.
+    DuplicateName(String),
+    /// A rule's conditions or action are malformed.
+    InvalidRule { rule: String, details: String },
+    /// A rule names a PSP the catalog does not have.
+    UnknownPsp { rule: String, psp_id: String },
+    /// A rule names a country the catalog does not declare.
+    UnknownCountry { rule: String, country: Country },
+    /// A rule names a currency the catalog does not declare.
+    UnknownCurrency { rule: String, currency: Currency },
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::Io(msg) => write!(f, "failed to read routing rules: {msg}"),
+            RuleError::Parse(msg) => write!(f, "failed to parse routing rules: {msg}"),
+            RuleError::UnsupportedFormat(path) => {
+                write!(f, "unsupported routing rules format '{path}' (expected .toml or .json)")
+            }
+            RuleError::EmptyName => write!(f, "routing rule names must not be empty"),
+            RuleError::DuplicateName(name) => write!(f, "duplicate routing rule '{name}'"),
+            RuleError::InvalidRule { rule, details } => {
+                write!(f, "routing rule '{rule}' is invalid: {details}")
+            }
+            RuleError::UnknownPsp { rule, psp_id } => {
+                write!(f, "routing rule '{rule}' names unknown PSP '{psp_id}'")
+            }
+            RuleError::UnknownCountry { rule, country } => {
+                write!(f, "routing rule '{rule}' names unknown country '{country}'")
+            }
+            RuleError::UnknownCurrency { rule, currency } => {
+                write!(f, "routing rule '{rule}' names unknown currency '{currency}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// A time of day, written `"HH:MM"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay {
+    minutes: u32,
+}
+
+impl TimeOfDay {
+    /// The time `hour:minute`, if both are in range.
+    pub fn new(hour: u32, minute: u32) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(TimeOfDay {
+            minutes: hour * 60 + minute,
+        })
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid time of day '{s}' (expected HH:MM)");
+        let (hour, minute) = s.split_once(':').ok_or_else(invalid)?;
+        if hour.len() != 2 || minute.len() != 2 {
+            return Err(invalid());
+        }
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+        TimeOfDay::new(hour, minute).ok_or_else(invalid)
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(t: TimeOfDay) -> String {
+        format!("{:02}:{:02}", t.minutes / 60, t.minutes % 60)
+    }
+}
+
+/// Local hours a rule applies in; `start` is inclusive, `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+impl TimeWindow {
+    fn contains(&self, time: TimeOfDay) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            // Wraps past midnight, e.g. 22:00–06:00
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// What a transaction must look like for a rule to apply.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleConditions {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub countries: Vec<Country>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub currencies: Vec<Currency>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub card_schemes: Vec<CardScheme>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bin_prefixes: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub customer_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_amount: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_window: Option<TimeWindow>,
+}
+
+impl RuleConditions {
+    /// Returns true if every condition holds for the transaction.
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        (self.countries.is_empty() || self.countries.contains(&transaction.country))
+            && (self.currencies.is_empty()
+                || self.currencies.contains(&transaction.amount.currency))
+            && (self.card_schemes.is_empty()
+                || transaction
+                    .bin_info
+                    .as_ref()
+                    .is_some_and(|info| self.card_schemes.contains(&info.scheme)))
+            && (self.bin_prefixes.is_empty()
+                || transaction
+                    .card_bin
+                    .as_deref()
+                    .is_some_and(|bin| self.bin_prefixes.iter().any(|p| bin.starts_with(p.as_str()))))
+            && (self.customer_ids.is_empty() || self.customer_ids.contains(&transaction.customer_id))
+            && self.min_amount.as_ref().is_none_or(|min| {
+                min.currency == transaction.amount.currency
+                    && transaction.amount.minor_units >= min.minor_units
+            })
+            && self.max_amount.as_ref().is_none_or(|max| {
+                max.currency == transaction.amount.currency
+                    && transaction.amount.minor_units <= max.minor_units
+            })
+            && self.time_window.is_none_or(|window| {
+                let local = transaction
+                    .timestamp
+                    .to_zone(&TimeZone::for_country(&transaction.country));
+                TimeOfDay::new(local.hour, local.minute).is_some_and(|t| window.contains(t))
+            })
+    }
+}
+
+/// What a matching rule does to the PSPs a transaction may be routed to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleAction {
+    /// Strategy to order the remaining PSPs with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<RoutingStrategy>,
+    /// PSPs to try first, in this order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub psp_order: Vec<String>,
+    /// Try only the PSPs in `psp_order`.
+    pub exclusive: bool,
+    /// PSPs never to try.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+impl RuleAction {
+    /// Drop the PSPs this action rules out.
+    pub fn filter(&self, psps: &mut Vec<PspConfig>) {
+        psps.retain(|psp| {
+            !self.exclude.contains(&psp.id) && (!self.exclusive || self.psp_order.contains(&psp.id))
+        });
+    }
+
+    /// Move the PSPs in `psp_order` to the front, in that order, keeping
+    /// the order of the rest.
+    pub fn reorder(&self, psps: &mut [PspConfig]) {
+        psps.sort_by_key(|psp| {
+            self.psp_order
+                .iter()
+                .position(|id| *id == psp.id)
+                .unwrap_or(self.psp_order.len())
+        });
+    }
+}
+
+/// A named routing rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// Name reported on results routed by this rule.
+    pub name: String,
+    /// Conditions; a rule without any applies to every transaction.
+    #[serde(default)]
+    pub when: RuleConditions,
+    /// What to do with matching transactions.
+    pub then: RuleAction,
+}
+
+impl RoutingRule {
+    fn validate(&self) -> Result<(), RuleError> {
+        let invalid = |details: &str| RuleError::InvalidRule {
+            rule: self.name.clone(),
+            details: details.to_string(),
+        };
+        let when = &self.when;
+        let then = &self.then;
+
+        for prefix in &when.bin_prefixes {
+            if prefix.is_empty()
+                || prefix.len() > BIN_LENGTH
+                || !prefix.bytes().all(|b| b.is_ascii_digit())
+            {
+                return Err(invalid(&format!(
+                    "BIN prefix '{prefix}' must be 1 to {BIN_LENGTH} digits"
+                )));
+            }
+        }
+        if let (Some(min), Some(max)) = (&when.min_amount, &when.max_amount) {
+            if min.currency != max.currency {
+                return Err(invalid("min_amount and max_amount use different currencies"));
+            }
+            if min.minor_units > max.minor_units {
+                return Err(invalid("min_amount is greater than max_amount"));
+            }
+        }
+        if when.time_window.is_some_and(|w| w.start == w.end) {
+            // Empty, or the whole day; neither is worth a window
+            return Err(invalid("time_window start and end are equal"));
+        }
+
+        if then.strategy.is_none() && then.psp_order.is_empty() && then.exclude.is_empty() {
+            return Err(invalid("the action sets no strategy, psp_order or exclude"));
+        }
+        if then.exclusive && then.psp_order.is_empty() {
+            return Err(invalid("exclusive requires a psp_order"));
+        }
+        let mut seen = HashSet::new();
+        for id in then.psp_order.iter().chain(&then.exclude) {
+            if !seen.insert(id.as_str()) {
+                return Err(invalid(&format!("PSP '{id}' is listed more than once")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An ordered, validated list of routing rules.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RulesFile")]
+pub struct RuleSet {
+    rules: Vec<RoutingRule>,
+}
+
+/// On-disk shape of a rules file.
+#[derive(Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<RoutingRule>,
+}
+
+impl TryFrom<RulesFile> for RuleSet {
+    type Error = RuleError;
+
+    fn try_from(file: RulesFile) -> Result<Self, Self::Error> {
+        RuleSet::new(file.rules)
+    }
+}
+
+impl RuleSet {
+    /// Builds a rule set, validating every rule.
+    pub fn new(rules: Vec<RoutingRule>) -> Result<Self, RuleError> {
+        let mut names = HashSet::new();
+        for rule in &rules {
+            if rule.name.trim().is_empty() {
+                return Err(RuleError::EmptyName);
+            }
+            if !names.insert(rule.name.as_str()) {
+                return Err(RuleError::DuplicateName(rule.name.clone()));
+            }
+            rule.validate()?;
+        }
+        Ok(RuleSet { rules })
+    }
+
+    /// Loads the rules named by [`RULES_PATH_ENV`], or no rules when the
+    /// variable is unset.
+    pub fn from_env() -> Result<Self, RuleError> {
+        match std::env::var(RULES_PATH_ENV) {
+            Ok(path) if !path.is_empty() => RuleSet::load(path),
+            _ => Ok(RuleSet::default()),
+        }
+    }
+
+    /// Loads rules from a `.toml` or `.json` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| RuleError::Io(e.to_string()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => RuleSet::from_toml_str(&contents),
+            Some("json") => RuleSet::from_json_str(&contents),
+            _ => Err(RuleError::UnsupportedFormat(path.display().to_string())),
+        }
+    }
+
+    /// Parses and validates TOML rules.
+    pub fn from_toml_str(source: &str) -> Result<Self, RuleError> {
+        let file: RulesFile = toml::from_str(source).map_err(|e| RuleError::Parse(e.to_string()))?;
+        RuleSet::try_from(file)
+    }
+
+    /// Parses and validates JSON rules.
+    pub fn from_json_str(source: &str) -> Result<Self, RuleError> {
+        let file: RulesFile =
+            serde_json::from_str(source).map_err(|e| RuleError::Parse(e.to_string()))?;
+        RuleSet::try_from(file)
+    }
+
+    /// The rules, in evaluation order.
+    pub fn rules(&self) -> &[RoutingRule] {
+        &self.rules
+    }
+
+    /// Check that every PSP, country and currency a rule names exists in
+    /// `catalog`, so a misspelt one is caught rather than never matching.
+    pub fn check_catalog(&self, catalog: &PspCatalog) -> Result<(), RuleError> {
+        for rule in &self.rules {
+            for id in rule.then.psp_order.iter().chain(&rule.then.exclude) {
+                if catalog.psp(id).is_none() {
+                    return Err(RuleError::UnknownPsp {
+                        rule: rule.name.clone(),
+                        psp_id: id.clone(),
+                    });
+                }
+            }
+            let when = &rule.when;
+            for country in &when.countries {
+                if !catalog.countries().iter().any(|c| c.country == *country) {
+                    return Err(RuleError::UnknownCountry {
+                        rule: rule.name.clone(),
+                        country: country.clone(),
+                    });
+                }
+            }
+            let bounds = when.min_amount.iter().chain(&when.max_amount).map(|m| &m.currency);
+            for currency in when.currencies.iter().chain(bounds) {
+                if !catalog.currencies().iter().any(|c| c.code == *currency) {
+                    return Err(RuleError::UnknownCurrency {
+                        rule: rule.name.clone(),
+                        currency: currency.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The first rule whose conditions hold for the transaction.
+    pub fn evaluate(&self, transaction: &Transaction) -> Option<&RoutingRule> {
+        self.rules.iter().find(|rule| rule.when.matches(transaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card_bin::{BinInfo, CardType};
+    use crate::models::transaction::PaymentMethod;
+    use crate::time::Timestamp;
+
+    const EXAMPLE_RULES: &str = include_str!("../../config/routing_rules.example.toml");
+
+    fn make_transaction(amount: &str, scheme: CardScheme) -> Transaction {
+        Transaction {
+            id: "txn_rules".to_string(),
+            amount: Money::from_decimal_str(amount, Currency::new("BRL")).unwrap(),
+            country: Country::new("Brazil"),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some("378282".to_string()),
+            card_last4: Some("0005".to_string()),
+            bin_info: Some(BinInfo {
+                scheme,
+                card_type: CardType::Credit,
+                issuer: "Test Bank".to_string(),
+                issuing_country: "BR".to_string(),
+            }),
+            installments: None,
+            customer_id: "cust_001".to_string(),
+            // 13:00 in São Paulo
+            timestamp: Timestamp::parse("2025-01-15T16:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
+        }
+    }
+
+    fn rule(name: &str, when: RuleConditions, then: RuleAction) -> RoutingRule {
+        RoutingRule {
+            name: name.to_string(),
+            when,
+            then,
+        }
+    }
+
+    fn prefer(ids: &[&str]) -> RuleAction {
+        RuleAction {
+            psp_order: ids.iter().map(|s| s.to_string()).collect(),
+            ..RuleAction::default()
+        }
+    }
+
+    #[test]
+    fn test_example_rules_parse_and_name_catalog_psps() {
+        let rules = RuleSet::from_toml_str(EXAMPLE_RULES).unwrap();
+        assert!(!rules.rules().is_empty());
+        rules.check_catalog(&PspCatalog::builtin()).unwrap();
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let large_amex = RuleConditions {
+            card_schemes: vec![CardScheme::Amex],
+            min_amount: Some(Money::from_decimal_str("2000.00", Currency::new("BRL")).unwrap()),
+            ..RuleConditions::default()
+        };
+        let brazil = RuleConditions {
+            countries: vec![Country::new("Brazil")],
+            ..RuleConditions::default()
+        };
+        let rules = RuleSet::new(vec![
+            rule("large-amex", large_amex, prefer(&["psp_br_1"])),
+            rule("brazil", brazil, prefer(&["psp_br_2"])),
+        ])
+        .unwrap();
+
+        let big = make_transaction("2500.00", CardScheme::Amex);
+        assert_eq!(rules.evaluate(&big).unwrap().name, "large-amex");
+        let small = make_transaction("1999.99", CardScheme::Amex);
+        assert_eq!(rules.evaluate(&small).unwrap().name, "brazil");
+        let mut mexico = make_transaction("2500.00", CardScheme::Visa);
+        mexico.country = Country::new("Mexico");
+        assert!(rules.evaluate(&mexico).is_none());
+    }
+
+    #[test]
+    fn test_amount_bounds_ignore_other_currencies() {
+        let conditions = RuleConditions {
+            max_amount: Some(Money::from_decimal_str("100.00", Currency::new("MXN")).unwrap()),
+            ..RuleConditions::default()
+        };
+        assert!(!conditions.matches(&make_transaction("50.00", CardScheme::Visa)));
+    }
+
+    #[test]
+    fn test_bin_prefix_customer_and_time_window() {
+        let txn = make_transaction("100.00", CardScheme::Amex);
+        let window = |start: &str, end: &str| {
+            Some(TimeWindow {
+                start: TimeOfDay::try_from(start.to_string()).unwrap(),
+                end: TimeOfDay::try_from(end.to_string()).unwrap(),
+            })
+        };
+        let conditions = RuleConditions {
+            bin_prefixes: vec!["37".to_string()],
+            customer_ids: vec!["cust_001".to_string()],
+            time_window: window("09:00", "18:00"),
+            ..RuleConditions::default()
+        };
+        assert!(conditions.matches(&txn));
+
+        let overnight = RuleConditions {
+            time_window: window("22:00", "06:00"),
+            ..RuleConditions::default()
+        };
+        assert!(!overnight.matches(&txn));
+        let mut late = txn.clone();
+        late.timestamp = Timestamp::parse("2025-01-16T02:30:00Z").unwrap(); // 23:30 local
+        assert!(overnight.matches(&late));
+
+        let other_customer = RuleConditions {
+            customer_ids: vec!["cust_002".to_string()],
+            ..RuleConditions::default()
+        };
+        assert!(!other_customer.matches(&txn));
+    }
+
+    #[test]
+    fn test_action_filters_and_reorders() {
+        let catalog = PspCatalog::builtin();
+        let ids = |psps: &[PspConfig]| psps.iter().map(|p| p.id.clone()).collect::<Vec<_>>();
+
+        let mut psps = catalog.psps_for_country(&Country::new("Brazil"));
+        let action = RuleAction {
+            exclude: vec!["psp_br_2".to_string()],
+            ..prefer(&["psp_br_3"])
+        };
+        action.filter(&mut psps);
+        action.reorder(&mut psps);
+        assert_eq!(ids(&psps), ["psp_br_3", "psp_br_1"]);
+
+        let mut only = catalog.psps_for_country(&Country::new("Brazil"));
+        let exclusive = RuleAction {
+            exclusive: true,
+            ..prefer(&["psp_br_2"])
+        };
+        exclusive.filter(&mut only);
+        assert_eq!(ids(&only), ["psp_br_2"]);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        let no_action = rule("noop", RuleConditions::default(), RuleAction::default());
+        assert!(matches!(RuleSet::new(vec![no_action]), Err(RuleError::InvalidRule { .. })));
+
+        let dup = || rule("dup", RuleConditions::default(), prefer(&["psp_br_1"]));
+        assert_eq!(
+            RuleSet::new(vec![dup(), dup()]),
+            Err(RuleError::DuplicateName("dup".to_string()))
+        );
+
+        let bad_prefix = RuleConditions {
+            bin_prefixes: vec!["4111111".to_string()],
+            ..RuleConditions::default()
+        };
+        assert!(RuleSet::new(vec![rule("bin", bad_prefix, prefer(&["psp_br_1"]))]).is_err());
+
+        let unknown = RuleSet::new(vec![rule("ghost", RuleConditions::default(), prefer(&["psp_xx"]))]).unwrap();
+        assert!(matches!(
+            unknown.check_catalog(&PspCatalog::builtin()),
+            Err(RuleError::UnknownPsp { .. })
+        ));
+
+        let brasil = RuleConditions {
+            countries: vec![Country::new("Brasil")],
+            ..RuleConditions::default()
+        };
+        let misspelt = RuleSet::new(vec![rule("brasil", brasil, prefer(&["psp_br_1"]))]).unwrap();
+        assert_eq!(
+            misspelt.check_catalog(&PspCatalog::builtin()),
+            Err(RuleError::UnknownCountry {
+                rule: "brasil".into(),
+                country: Country::new("Brasil")
+            })
+        );
+        let reais = RuleConditions {
+            min_amount: Some(Money::new(100, Currency::new("BRR"))),
+            ..RuleConditions::default()
+        };
+        let misspelt = RuleSet::new(vec![rule("reais", reais, prefer(&["psp_br_1"]))]).unwrap();
+        assert!(matches!(
+            misspelt.check_catalog(&PspCatalog::builtin()),
+            Err(RuleError::UnknownCurrency { .. })
+        ));
+
+        let bad_time = r#"{"rules": [{"name": "late", "when": {"time_window": {"start": "25:00", "end": "06:00"}}, "then": {"exclude": ["psp_br_1"]}}]}"#;
+        assert!(matches!(RuleSet::from_json_str(bad_time), Err(RuleError::Parse(_))));
+
+        let empty_window = r#"{"rules": [{"name": "never", "when": {"time_window": {"start": "09:00", "end": "09:00"}}, "then": {"exclude": ["psp_br_1"]}}]}"#;
+        assert!(matches!(RuleSet::from_json_str(empty_window), Err(RuleError::InvalidRule { .. })));
+    }
+}
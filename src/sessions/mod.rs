@@ -0,0 +1,287 @@
+//! Session store — routings paused on a 3-D Secure challenge.
+//!
+//! A paused routing carries everything needed to resume it: the
+//! transaction, the retry policy and the attempts so far. A client trusted
+//! to post it back could change the amount, the attempts or the PSPs left
+//! to try, so the [`SessionStore`] keeps it on the server and the client is
+//! given a [`PendingChallenge`] naming it by an unguessable ID. Each ID
+//! resumes its routing once, and abandoned challenges expire.
+//!
+//! [`complete`] resumes a routing for `/api/authenticate`. It takes the
+//! session out of the store, so that two requests completing the same
+//! challenge cannot both resume it, and puts it back if the routing cannot
+//! resume, so a request that failed can be retried.
+
+use crate::connector::AsyncPspConnector;
+use crate::engine::{RoutingEngine, RoutingError};
+use crate::kv::{self, KvError, KvStore, MemoryKv};
+use crate::models::authentication::{AuthenticationSession, PendingChallenge};
+use crate::models::routing::RoutingResult;
+use crate::models::transaction::Transaction;
+use crate::time::Timestamp;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default time a cardholder has to answer a challenge: 15 minutes.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Routings paused on a challenge, keyed by challenge ID.
+///
+/// `/api/authorize` pauses a routing and `/api/authenticate` resumes it,
+/// in separate functions, so the sessions live in the key-value store
+/// every function reaches (see [`kv`]). Each is kept until it is taken to
+/// resume its routing, or until the cardholder's time to answer the
+/// challenge (the store's [`ttl`](SessionStore::ttl)) runs out.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    ttl: Duration,
+    kv: Arc<dyn KvStore>,
+}
+
+impl Default for SessionStore {
+    /// A store of its own in memory.
+    fn default() -> Self {
+        SessionStore::new(Arc::new(MemoryKv::new()))
+    }
+}
+
+/// A paused routing as kept in the store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeptSession {
+    /// The ID the session is kept under.
+    #[serde(skip)]
+    pub challenge_id: String,
+    /// When the session stops being resumable.
+    pub expires_at: Timestamp,
+    /// The paused routing.
+    pub session: AuthenticationSession,
+}
+
+impl SessionStore {
+    /// A store keeping sessions in `kv` for [`DEFAULT_SESSION_TTL`].
+    pub fn new(kv: Arc<dyn KvStore>) -> Self {
+        SessionStore {
+            ttl: DEFAULT_SESSION_TTL,
+            kv,
+        }
+    }
+
+    /// The store used by the API handlers, in the key-value store
+    /// [`kv::from_env`] configures.
+    pub fn from_env() -> Result<Self, KvError> {
+        kv::from_env().map(SessionStore::new)
+    }
+
+    /// Keep sessions for `ttl` instead.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Time a kept session stays resumable.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Keep `session` and return what the client is told of it, under a
+    /// fresh random challenge ID.
+    pub fn insert(&self, session: AuthenticationSession) -> Result<PendingChallenge, KvError> {
+        let pending = PendingChallenge {
+            challenge_id: String::new(),
+            psp_id: session.psp_id.clone(),
+            psp_name: session.psp_name.clone(),
+        };
+        let kept = KeptSession {
+            challenge_id: String::new(),
+            expires_at: Timestamp::now().checked_add(self.ttl).unwrap_or_else(Timestamp::now),
+            session,
+        };
+        let value = serde_json::to_string(&kept).expect("sessions serialize");
+        loop {
+            let challenge_id = format!("chl_{:032x}", rand::thread_rng().gen::<u128>());
+            if self.kv.set_new(&session_key(&challenge_id), &value, Some(self.ttl))? {
+                return Ok(PendingChallenge { challenge_id, ..pending });
+            }
+        }
+    }
+
+    /// Remove and return the session `challenge_id` names, unless it has
+    /// expired. Of concurrent takes of one ID, only one gets the session.
+    pub fn take(&self, challenge_id: &str) -> Result<Option<KeptSession>, KvError> {
+        let key = session_key(challenge_id);
+        let Some(value) = self.kv.take(&key)? else {
+            return Ok(None);
+        };
+        let kept: KeptSession = serde_json::from_str(&value).map_err(|e| KvError::CorruptValue {
+            key,
+            details: e.to_string(),
+        })?;
+        Ok(Some(KeptSession {
+            challenge_id: challenge_id.to_string(),
+            ..kept
+        }))
+    }
+
+    /// Keep a taken session again under its challenge ID, for the time it
+    /// had left. A session that has expired since is dropped.
+    pub fn put_back(&self, kept: &KeptSession) -> Result<(), KvError> {
+        let left = kept.expires_at.duration_since(&Timestamp::now()).unwrap_or_default();
+        if left.is_zero() {
+            return Ok(());
+        }
+        let value = serde_json::to_string(kept).expect("sessions serialize");
+        self.kv.set(&session_key(&kept.challenge_id), &value, Some(left))
+    }
+}
+
+/// Key the session for `challenge_id` is kept under.
+fn session_key(challenge_id: &str) -> String {
+    format!("session:{challenge_id}")
+}
+
+/// Why a challenge could not be completed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResumeError {
+    /// No session is kept under the challenge ID: it is unknown, has
+    /// expired or was already completed.
+    UnknownChallenge(String),
+    /// The session store could not be reached.
+    Storage(KvError),
+    /// The routing could not resume. The session is kept, so the challenge
+    /// can be completed again.
+    Routing(RoutingError),
+}
+
+impl std::fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResumeError::UnknownChallenge(id) => write!(
+                f,
+                "no routing is paused on challenge {id}; it may have expired or been completed"
+            ),
+            ResumeError::Storage(e) => write!(f, "session store: {e}"),
+            ResumeError::Routing(e) => {
+                write!(f, "cannot resume the routing: {e}; the challenge is kept and can be completed again")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResumeError {}
+
+/// Resume the routing paused on `challenge_id` with `engine`, once the
+/// cardholder has answered the challenge, and return its transaction and
+/// result.
+///
+/// The session leaves `store` for good only once its routing has resumed:
+/// if `engine` cannot resume it (say its PSP has left the catalog), it is
+/// put back and [`ResumeError::Routing`] returned.
+pub async fn complete<C: AsyncPspConnector + 'static>(
+    store: &SessionStore,
+    engine: &RoutingEngine<C>,
+    challenge_id: &str,
+) -> Result<(Transaction, RoutingResult), ResumeError> {
+    let kept = store
+        .take(challenge_id)
+        .map_err(ResumeError::Storage)?
+        .ok_or_else(|| ResumeError::UnknownChallenge(challenge_id.to_string()))?;
+    match engine.complete_challenge_async(&kept.session).await {
+        Ok(result) => Ok((kept.session.transaction, result)),
+        Err(e) => {
+            store.put_back(&kept).map_err(ResumeError::Storage)?;
+            Err(ResumeError::Routing(e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::PspCatalog;
+    use crate::data::generate_test_data;
+    use crate::engine::retry::RetryPolicy;
+    use crate::models::routing::RoutingStrategy;
+    use crate::simulator::PspSimulator;
+
+    fn session() -> AuthenticationSession {
+        AuthenticationSession {
+            session_id: "sess_1".into(),
+            psp_id: "psp_br_1".into(),
+            psp_name: "PagSeguro".into(),
+            transaction: generate_test_data(1).remove(0),
+            strategy: RoutingStrategy::default(),
+            policy: RetryPolicy::default(),
+            cross_border_fallback: false,
+            attempts: Vec::new(),
+            total_attempts: 0,
+            total_latency_ms: 0,
+            authentications: Vec::new(),
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_sessions_are_taken_once_by_an_unguessable_id() {
+        let kv = Arc::new(MemoryKv::new());
+        let store = SessionStore::new(kv.clone());
+        let first = store.insert(session()).unwrap();
+        let second = store.insert(session()).unwrap();
+        assert_ne!(first.challenge_id, second.challenge_id);
+        assert_eq!(first.challenge_id.len(), "chl_".len() + 32);
+        assert_eq!(first.psp_id, "psp_br_1");
+        assert_eq!(kv.len(), 2);
+
+        let kept = store.take(&first.challenge_id).unwrap().unwrap();
+        assert_eq!(kept.challenge_id, first.challenge_id);
+        assert_eq!(kept.session.session_id, "sess_1");
+        assert!(store.take(&first.challenge_id).unwrap().is_none());
+        assert!(store.take("chl_0").unwrap().is_none());
+        assert_eq!(kv.len(), 1);
+
+        store.put_back(&kept).unwrap();
+        assert!(store.take(&first.challenge_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_expired_sessions_cannot_be_resumed() {
+        let kv = Arc::new(MemoryKv::new());
+        let store = SessionStore::new(kv.clone()).with_ttl(Duration::ZERO);
+        let pending = store.insert(session()).unwrap();
+        assert!(store.take(&pending.challenge_id).unwrap().is_none());
+        assert!(kv.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_completions_keep_the_session() {
+        let store = SessionStore::default();
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let gone = AuthenticationSession {
+            psp_id: "psp_gone".into(),
+            ..session()
+        };
+        let pending = store.insert(gone).unwrap();
+
+        let err = complete(&store, &engine, &pending.challenge_id).await.unwrap_err();
+        assert_eq!(err, ResumeError::Routing(RoutingError::UnknownPsp("psp_gone".into())));
+        // Still there to complete once the catalog has the PSP again
+        let kept = store.take(&pending.challenge_id).unwrap().unwrap();
+        assert_eq!(kept.session.psp_id, "psp_gone");
+    }
+
+    #[tokio::test]
+    async fn test_completed_challenges_cannot_be_resumed_again() {
+        let store = SessionStore::default();
+        let engine = RoutingEngine::new(PspSimulator::new(), PspCatalog::builtin());
+        let pending = store.insert(session()).unwrap();
+
+        let (transaction, result) = complete(&store, &engine, &pending.challenge_id).await.unwrap();
+        assert_eq!(transaction.id, session().transaction.id);
+        assert!(result.challenge.is_none());
+        assert_eq!(
+            complete(&store, &engine, &pending.challenge_id).await.unwrap_err(),
+            ResumeError::UnknownChallenge(pending.challenge_id)
+        );
+    }
+}
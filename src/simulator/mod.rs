@@ -13,14 +13,30 @@
 //!   Different PSPs get different seeds, so PSP#1 may decline while PSP#2 approves.
 //! - **Approvals (~70-75%)**: Transaction is approved.
 //!
+//! Every outcome is returned as the raw response code the PSP would send
+//! and normalized through the PSP's [`crate::codes::ResponseCodeTable`].
+//!
+//! PSPs with 3-D Secure authenticate cardholders frictionlessly or with a
+//! challenge at their configured rates, and approve an authenticated
+//! authorization more often than the same card unauthenticated.
+//!
+//! A card that hard-declines as expired is approved or soft-declined like
+//! any other once it is sent with its refreshed credential (see
+//! [`crate::credentials`]).
+//!
 //! This design ensures that smart retry logic produces measurable improvement
 //! over single-PSP routing.
-
-pub mod config;
-
+//!
+//! The simulator is the default [`PspConnector`] the engine routes through.
+
+use crate::card_bin::CardType;
+use crate::codes;
+use crate::connector::PspConnector;
+use crate::models::authentication::{ThreeDsFlow, ThreeDsResponse, ThreeDsStatus};
+use crate::models::money::Money;
+use crate::models::payment::{OperationKind, Payment};
 use crate::models::psp::{DeclineReason, PspConfig, PspResponse};
-use crate::models::transaction::Transaction;
-use config::get_decline_distribution;
+use crate::models::transaction::{PaymentMethod, Transaction};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use std::collections::hash_map::DefaultHasher;
@@ -33,10 +49,27 @@ const HARD_DECLINE_RATE: f64 = 0.06;
 /// Used for the real-time cascading stretch goal.
 const PSP_UNAVAILABLE_RATE: f64 = 0.08;
 
+/// Chance that a transient decline (an issuer, switch or PSP outage) has
+/// cleared by each resend to the same PSP.
+const TRANSIENT_CLEAR_RATE: f64 = 0.5;
+
+/// Approval-rate penalty for debit cards, which issuers decline more
+/// often on card-not-present purchases.
+const DEBIT_SUCCESS_PENALTY: f64 = 0.03;
+
+/// Approval-rate penalty for prepaid cards, which often run out of balance
+/// or are blocked for online use.
+const PREPAID_SUCCESS_PENALTY: f64 = 0.10;
+
+/// Approval-rate penalty per installment beyond the first: issuers weigh
+/// the whole amount against the card's limit and decline long plans more.
+const INSTALLMENT_SUCCESS_PENALTY: f64 = 0.008;
+
 /// Simulates PSP behavior for transaction processing.
 ///
 /// The simulator is stateless — all randomness is derived from
 /// deterministic seeds, making results reproducible across runs.
+#[derive(Debug, Clone, Copy)]
 pub struct PspSimulator;
 
 impl PspSimulator {
@@ -51,65 +84,155 @@ impl PspSimulator {
     /// to produce reproducible but realistic outcomes.
     ///
     /// # Decision Flow
-    /// 1. Check if the card is a "hard decline card" (PSP-independent)
+    /// 1. Check if the card is a "hard decline card" (PSP-independent;
+    ///    cards only — alternative payment methods have no card to fail).
+    ///    An expired card sent with its refreshed credential goes on to
+    ///    the next steps like any other.
     /// 2. Check if this PSP is temporarily unavailable (cascading)
-    /// 3. Roll against PSP's success rate (PSP-dependent seed)
+    /// 3. Roll against PSP's success rate, adjusted for the card type,
+    ///    installment count and 3-D Secure authentication (PSP-dependent
+    ///    seed, so authenticating can only turn a decline into an approval)
     /// 4. If declined, select a soft decline reason from the PSP's distribution
     pub fn process(&self, transaction: &Transaction, psp: &PspConfig) -> PspResponse {
         let latency_ms = self.simulate_latency(transaction, psp);
 
+        let (card_bin, card_last4) = instrument(transaction);
+
         // Step 1: Check for hard decline (card-level, PSP-independent)
-        if self.is_hard_decline_card(&transaction.card_bin, &transaction.card_last4) {
-            let reason = self.select_hard_decline_reason(&transaction.card_bin, &transaction.card_last4);
-            return PspResponse {
-                psp_id: psp.id.clone(),
-                psp_name: psp.name.clone(),
-                approved: false,
-                decline_reason: Some(reason),
-                latency_ms,
-            };
+        if transaction.payment_method == PaymentMethod::Card
+            && self.is_hard_decline_card(card_bin, card_last4)
+        {
+            let reason = self.select_hard_decline_reason(card_bin, card_last4);
+            // The refreshed credential carries the reissued card's details;
+            // the engine only sends one from its credential store
+            if !(reason.calls_for_credential_refresh() && transaction.credential.is_some()) {
+                return respond(psp, latency_ms, Some(reason));
+            }
         }
 
         // Step 2: Check for PSP unavailability (stretch: cascading)
         if self.is_psp_unavailable(transaction, psp) {
-            return PspResponse {
-                psp_id: psp.id.clone(),
-                psp_name: psp.name.clone(),
-                approved: false,
-                decline_reason: Some(DeclineReason::PspUnavailable),
-                latency_ms,
-            };
+            return respond(psp, latency_ms, Some(DeclineReason::PspUnavailable));
         }
 
         // Step 3: Roll against PSP's success rate (PSP-dependent)
         let seed = self.make_psp_seed(
-            &transaction.card_bin,
-            &transaction.card_last4,
+            card_bin,
+            card_last4,
             &psp.id,
-            transaction.amount,
+            &transaction.amount,
         );
         let mut rng = StdRng::seed_from_u64(seed);
         let roll: f64 = rng.gen();
 
-        if roll < psp.base_success_rate {
+        if roll < success_rate(transaction, psp) {
             // Approved
-            PspResponse {
-                psp_id: psp.id.clone(),
-                psp_name: psp.name.clone(),
-                approved: true,
-                decline_reason: None,
-                latency_ms,
-            }
+            respond(psp, latency_ms, None)
         } else {
             // Step 4: Soft decline — pick reason from PSP's distribution
-            let reason = self.select_soft_decline_reason(&mut rng, &psp.id);
-            PspResponse {
-                psp_id: psp.id.clone(),
-                psp_name: psp.name.clone(),
-                approved: false,
-                decline_reason: Some(reason),
-                latency_ms,
-            }
+            let reason = self.select_soft_decline_reason(&mut rng, psp);
+            respond(psp, latency_ms, Some(reason))
+        }
+    }
+
+    /// Simulate resending a transaction to the same PSP after `retry`
+    /// earlier attempts on it.
+    ///
+    /// Decisions about the card stand: an approval, a hard decline or a
+    /// soft decline such as `DoNotHonor` comes back unchanged. A transient
+    /// decline (see [`DeclineReason::is_transient`]) clears with
+    /// probability [`TRANSIENT_CLEAR_RATE`] on each resend, and once it has
+    /// cleared the PSP approves. Each resend rolls a fresh latency.
+    pub fn process_resend(&self, transaction: &Transaction, psp: &PspConfig, retry: u32) -> PspResponse {
+        let first = self.process(transaction, psp);
+        if retry == 0 {
+            return first;
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.make_resend_seed(transaction, psp));
+        let mut latency_ms = first.latency_ms;
+        let mut cleared = false;
+        for _ in 0..retry {
+            latency_ms = rng.gen_range(psp.latency_min_ms..=psp.latency_max_ms);
+            cleared = cleared || rng.gen::<f64>() < TRANSIENT_CLEAR_RATE;
+        }
+        let outcome = match first.decline_reason {
+            Some(reason) if reason.is_transient() && cleared => None,
+            reason => reason,
+        };
+        respond(psp, latency_ms, outcome)
+    }
+
+    /// Simulate the approving PSP handling a capture, void or refund.
+    ///
+    /// Follow-up operations carry no issuer risk: the PSP accepts them
+    /// unless it is temporarily unavailable. The seed includes how many
+    /// operations were already sent, so a retried call gets a fresh roll.
+    pub fn process_operation(
+        &self,
+        payment: &Payment,
+        psp: &PspConfig,
+        kind: OperationKind,
+    ) -> PspResponse {
+        let seed = self.make_operation_seed(payment, psp, kind);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let latency_ms = rng.gen_range(psp.latency_min_ms..=psp.latency_max_ms);
+
+        if rng.gen::<f64>() < PSP_UNAVAILABLE_RATE {
+            respond(psp, latency_ms, Some(DeclineReason::PspUnavailable))
+        } else {
+            respond(psp, latency_ms, None)
+        }
+    }
+
+    /// Simulate a PSP's 3-D Secure server authenticating the cardholder.
+    ///
+    /// The issuer authenticates a share `frictionless_rate` of cards
+    /// without friction and challenges the rest, seeded on the card and PSP
+    /// so a card always meets the same flow there. A PSP without 3-D Secure
+    /// answers as unavailable.
+    pub fn process_authentication(&self, transaction: &Transaction, psp: &PspConfig) -> ThreeDsResponse {
+        let Some(three_ds) = &psp.three_ds else {
+            return ThreeDsResponse::unavailable(psp, ThreeDsFlow::Frictionless);
+        };
+        let mut rng = StdRng::seed_from_u64(self.make_three_ds_seed(transaction, psp, "3ds"));
+        let latency_ms = rng.gen_range(psp.latency_min_ms..=psp.latency_max_ms);
+        let (status, session_id) = if rng.gen::<f64>() < three_ds.frictionless_rate {
+            (ThreeDsStatus::Authenticated, None)
+        } else {
+            (ThreeDsStatus::ChallengeRequired, Some(self.challenge_session_id(transaction, psp)))
+        };
+        ThreeDsResponse {
+            psp_id: psp.id.clone(),
+            flow: ThreeDsFlow::Frictionless,
+            status,
+            session_id,
+            latency_ms,
+        }
+    }
+
+    /// Simulate the result of the challenge `session_id`: the cardholder
+    /// completes it with probability `challenge_success_rate`, seeded on
+    /// the card and PSP. A session this PSP did not issue for the
+    /// transaction fails.
+    pub fn process_challenge(&self, transaction: &Transaction, psp: &PspConfig, session_id: &str) -> ThreeDsResponse {
+        let Some(three_ds) = &psp.three_ds else {
+            return ThreeDsResponse::unavailable(psp, ThreeDsFlow::Challenge);
+        };
+        let mut rng = StdRng::seed_from_u64(self.make_three_ds_seed(transaction, psp, "challenge"));
+        let latency_ms = rng.gen_range(psp.latency_min_ms..=psp.latency_max_ms);
+        let completed = rng.gen::<f64>() < three_ds.challenge_success_rate;
+        let status = if completed && session_id == self.challenge_session_id(transaction, psp) {
+            ThreeDsStatus::Authenticated
+        } else {
+            ThreeDsStatus::Failed
+        };
+        ThreeDsResponse {
+            psp_id: psp.id.clone(),
+            flow: ThreeDsFlow::Challenge,
+            status,
+            session_id: Some(session_id.to_string()),
+            latency_ms,
         }
     }
 
@@ -130,14 +253,20 @@ impl PspSimulator {
         let mut rng = StdRng::seed_from_u64(seed);
         let roll: f64 = rng.gen();
 
-        if roll < 0.45 {
+        if roll < 0.40 {
             DeclineReason::InsufficientFunds
-        } else if roll < 0.75 {
+        } else if roll < 0.65 {
             DeclineReason::CardExpired
-        } else if roll < 0.90 {
+        } else if roll < 0.77 {
             DeclineReason::InvalidCard
-        } else {
+        } else if roll < 0.83 {
             DeclineReason::StolenCard
+        } else if roll < 0.88 {
+            DeclineReason::LostCard
+        } else if roll < 0.94 {
+            DeclineReason::DoNotRetry
+        } else {
+            DeclineReason::InvalidCvv
         }
     }
 
@@ -154,12 +283,12 @@ impl PspSimulator {
     }
 
     /// Selects a soft decline reason based on the PSP's decline distribution.
-    fn select_soft_decline_reason(&self, rng: &mut StdRng, psp_id: &str) -> DeclineReason {
-        let distribution = get_decline_distribution(psp_id);
+    fn select_soft_decline_reason(&self, rng: &mut StdRng, psp: &PspConfig) -> DeclineReason {
+        let distribution = &psp.decline_distribution;
         let roll: f64 = rng.gen();
 
         let mut cumulative = 0.0;
-        for dw in &distribution {
+        for dw in distribution {
             cumulative += dw.weight;
             if roll < cumulative {
                 return dw.reason.clone();
@@ -191,50 +320,199 @@ impl PspSimulator {
         hasher.finish()
     }
 
+    /// Creates a deterministic seed for the resends of a transaction to one PSP.
+    fn make_resend_seed(&self, transaction: &Transaction, psp: &PspConfig) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        transaction.id.hash(&mut hasher);
+        psp.id.hash(&mut hasher);
+        "resend".hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Creates a deterministic seed for a 3-D Secure `step` of the card at
+    /// one PSP.
+    fn make_three_ds_seed(&self, transaction: &Transaction, psp: &PspConfig, step: &str) -> u64 {
+        let (card_bin, card_last4) = instrument(transaction);
+        let mut hasher = DefaultHasher::new();
+        card_bin.hash(&mut hasher);
+        card_last4.hash(&mut hasher);
+        psp.id.hash(&mut hasher);
+        step.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The id of the challenge a PSP issues for a transaction.
+    fn challenge_session_id(&self, transaction: &Transaction, psp: &PspConfig) -> String {
+        let mut hasher = DefaultHasher::new();
+        transaction.id.hash(&mut hasher);
+        psp.id.hash(&mut hasher);
+        "challenge_session".hash(&mut hasher);
+        format!("3ds_{:016x}", hasher.finish())
+    }
+
+    /// Creates a deterministic seed for one follow-up operation on a payment.
+    fn make_operation_seed(&self, payment: &Payment, psp: &PspConfig, kind: OperationKind) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        payment.transaction_id.hash(&mut hasher);
+        psp.id.hash(&mut hasher);
+        kind.hash(&mut hasher);
+        payment.operations.len().hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Creates a deterministic seed from card + PSP + amount (PSP-dependent).
     ///
     /// This is the key to making retry valuable: the same card may produce
     /// different outcomes with different PSPs because the PSP ID changes the seed.
-    fn make_psp_seed(&self, card_bin: &str, card_last4: &str, psp_id: &str, amount: f64) -> u64 {
+    /// The amount is hashed as exact minor units so equal amounts always seed
+    /// identically.
+    fn make_psp_seed(&self, card_bin: &str, card_last4: &str, psp_id: &str, amount: &Money) -> u64 {
         let mut hasher = DefaultHasher::new();
         card_bin.hash(&mut hasher);
         card_last4.hash(&mut hasher);
         psp_id.hash(&mut hasher);
-        ((amount * 100.0) as u64).hash(&mut hasher);
+        amount.minor_units.hash(&mut hasher);
         hasher.finish()
     }
 }
 
+/// The PSP's approval rate adjusted for the card type from BIN enrichment,
+/// the number of installments, at a cross-border acquirer whether the card
+/// was issued in the transaction's country (assumed without BIN
+/// enrichment) and whether this PSP authenticated the cardholder.
+fn success_rate(transaction: &Transaction, psp: &PspConfig) -> f64 {
+    let card_penalty = match transaction.bin_info.as_ref().map(|info| &info.card_type) {
+        Some(CardType::Debit) => DEBIT_SUCCESS_PENALTY,
+        Some(CardType::Prepaid) => PREPAID_SUCCESS_PENALTY,
+        Some(CardType::Credit) | None => 0.0,
+    };
+    let extra_installments = f64::from(transaction.installment_count() - 1);
+    let installment_penalty = extra_installments * INSTALLMENT_SUCCESS_PENALTY;
+    let domestic_card = transaction
+        .bin_info
+        .as_ref()
+        .is_none_or(|info| info.is_domestic(&transaction.country));
+    let cross_border_penalty = match &psp.cross_border {
+        Some(cross_border) if domestic_card && psp.country != transaction.country => {
+            cross_border.domestic_card_penalty
+        }
+        _ => 0.0,
+    };
+    let uplift = match (&transaction.three_ds, &psp.three_ds) {
+        (Some(authentication), Some(three_ds)) if authentication.psp_id == psp.id => three_ds.approval_uplift,
+        _ => 0.0,
+    };
+    (psp.base_success_rate - card_penalty - installment_penalty - cross_border_penalty + uplift).clamp(0.0, 1.0)
+}
+
+/// The payment instrument a transaction is seeded on: the card's BIN and
+/// last four digits, or the customer and method for alternative payments.
+fn instrument(transaction: &Transaction) -> (&str, &str) {
+    match (&transaction.card_bin, &transaction.card_last4) {
+        (Some(bin), Some(last4)) => (bin, last4),
+        _ => (&transaction.customer_id, payment_method_key(&transaction.payment_method)),
+    }
+}
+
+fn payment_method_key(method: &PaymentMethod) -> &'static str {
+    match method {
+        PaymentMethod::Card => "card",
+        PaymentMethod::Pix => "pix",
+        PaymentMethod::Boleto => "boleto",
+        PaymentMethod::Oxxo => "oxxo",
+        PaymentMethod::Pse => "pse",
+    }
+}
+
+/// Build the PSP's response for an outcome.
+///
+/// The outcome is rendered as the raw code this PSP would send and then
+/// normalized back through the PSP's response code table, exactly as a
+/// live response would be.
+pub(crate) fn respond(psp: &PspConfig, latency_ms: u64, outcome: Option<DeclineReason>) -> PspResponse {
+    let raw = match &outcome {
+        Some(reason) => psp.response_codes.encode(reason),
+        None => codes::approved(),
+    };
+    let decline_reason = psp
+        .response_codes
+        .normalize(&raw.code, raw.merchant_advice_code.as_deref());
+    PspResponse {
+        psp_id: psp.id.clone(),
+        psp_name: psp.name.clone(),
+        approved: outcome.is_none(),
+        response_code: raw.code,
+        response_message: raw.message,
+        merchant_advice_code: raw.merchant_advice_code,
+        decline_reason,
+        latency_ms,
+    }
+}
+
 impl Default for PspSimulator {
     fn default() -> Self {
         Self::new()
     }
 }
 
+impl PspConnector for PspSimulator {
+    fn authorize(&self, transaction: &Transaction, psp: &PspConfig) -> PspResponse {
+        self.process(transaction, psp)
+    }
+
+    fn resend(&self, transaction: &Transaction, psp: &PspConfig, retry: u32) -> PspResponse {
+        self.process_resend(transaction, psp, retry)
+    }
+
+    fn execute(&self, payment: &Payment, psp: &PspConfig, kind: OperationKind) -> PspResponse {
+        self.process_operation(payment, psp, kind)
+    }
+
+    fn authenticate(&self, transaction: &Transaction, psp: &PspConfig) -> ThreeDsResponse {
+        self.process_authentication(transaction, psp)
+    }
+
+    fn complete_challenge(
+        &self,
+        transaction: &Transaction,
+        psp: &PspConfig,
+        session_id: &str,
+    ) -> ThreeDsResponse {
+        self.process_challenge(transaction, psp, session_id)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time::Timestamp;
     use crate::models::transaction::{Country, Currency, Transaction};
-    use crate::simulator::config::get_psps_for_country;
+    use crate::models::authentication::ThreeDsAuthentication;
+    use crate::models::credential::{CardCredential, CredentialSource};
+    use crate::catalog::PspCatalog;
 
-    fn make_test_transaction(bin: &str, last4: &str, amount: f64) -> Transaction {
+    fn make_test_transaction(bin: &str, last4: &str, amount_minor: i64) -> Transaction {
         Transaction {
             id: format!("test_{}_{}", bin, last4),
-            amount,
-            currency: Currency::BRL,
-            country: Country::Brazil,
-            card_bin: bin.to_string(),
-            card_last4: last4.to_string(),
+            amount: Money::new(amount_minor, Currency::new("BRL")),
+            country: Country::new("Brazil"),
+            payment_method: PaymentMethod::Card,
+            card_bin: Some(bin.to_string()),
+            card_last4: Some(last4.to_string()),
+            bin_info: None,
+            installments: None,
             customer_id: "test_cust".to_string(),
-            timestamp: "2025-01-15T10:00:00Z".to_string(),
+            timestamp: Timestamp::parse("2025-01-15T10:00:00Z").unwrap(),
+            three_ds: None,
+            credential: None,
         }
     }
 
     #[test]
     fn test_simulator_is_deterministic() {
         let sim = PspSimulator::new();
-        let tx = make_test_transaction("411111", "1234", 100.0);
-        let psps = get_psps_for_country(&Country::Brazil);
+        let tx = make_test_transaction("411111", "1234", 10000);
+        let psps = PspCatalog::builtin().psps_for_country(&Country::new("Brazil"));
         let psp = &psps[0];
 
         let r1 = sim.process(&tx, psp);
@@ -247,17 +525,17 @@ mod tests {
     #[test]
     fn test_different_psps_can_produce_different_results() {
         let sim = PspSimulator::new();
-        let psps = get_psps_for_country(&Country::Brazil);
+        let psps = PspCatalog::builtin().psps_for_country(&Country::new("Brazil"));
 
         // Try many cards — at least some should differ between PSPs
         let mut found_difference = false;
         for i in 0..100 {
-            let tx = make_test_transaction("411111", &format!("{:04}", i), 150.0);
+            let tx = make_test_transaction("411111", &format!("{:04}", i), 15000);
             let r1 = sim.process(&tx, &psps[0]);
             let r2 = sim.process(&tx, &psps[1]);
 
             // Skip hard declines (same across PSPs)
-            if r1.decline_reason.as_ref().map_or(false, |r| r.is_hard_decline()) {
+            if r1.decline_reason.as_ref().is_some_and(|r| r.is_hard_decline()) {
                 continue;
             }
 
@@ -272,10 +550,10 @@ mod tests {
     #[test]
     fn test_hard_declines_are_psp_independent() {
         let sim = PspSimulator::new();
-        let psps = get_psps_for_country(&Country::Brazil);
+        let psps = PspCatalog::builtin().psps_for_country(&Country::new("Brazil"));
 
         for i in 0..200 {
-            let tx = make_test_transaction("411111", &format!("{:04}", i), 100.0);
+            let tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
             let r1 = sim.process(&tx, &psps[0]);
 
             if let Some(ref reason) = r1.decline_reason {
@@ -285,7 +563,7 @@ mod tests {
                         let r = sim.process(&tx, psp);
                         assert!(!r.approved, "Hard decline card should fail on all PSPs");
                         assert!(
-                            r.decline_reason.as_ref().map_or(false, |r| r.is_hard_decline()
+                            r.decline_reason.as_ref().is_some_and(|r| r.is_hard_decline()
                                 || r.is_psp_unavailable()),
                             "Hard decline card should return hard decline reason"
                         );
@@ -295,13 +573,230 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resends_clear_transient_declines_only() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let pagseguro = catalog.psp("psp_br_1").unwrap();
+
+        let (mut transient, mut cleared) = (0, 0);
+        for i in 0..300 {
+            let tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
+            let first = sim.process(&tx, pagseguro);
+            assert_eq!(sim.process_resend(&tx, pagseguro, 0).approved, first.approved);
+            let resent = sim.process_resend(&tx, pagseguro, 3);
+            match &first.decline_reason {
+                Some(reason) if reason.is_transient() => {
+                    transient += 1;
+                    if resent.approved {
+                        cleared += 1;
+                        // Once cleared, later resends keep approving
+                        assert!(sim.process_resend(&tx, pagseguro, 4).approved);
+                    }
+                }
+                _ => {
+                    assert_eq!(resent.approved, first.approved);
+                    assert_eq!(resent.decline_reason, first.decline_reason);
+                }
+            }
+        }
+        // Three resends at 50% each clear about 7 in 8
+        assert!(transient > 10);
+        assert!(cleared * 10 > transient * 7, "{cleared} of {transient} cleared");
+    }
+
+    #[test]
+    fn test_responses_carry_raw_codes() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let conekta = catalog.psp("psp_mx_1").unwrap();
+
+        for i in 0..100 {
+            let tx = make_test_transaction("424242", &format!("{:04}", i), 10000);
+            let r = sim.process(&tx, conekta);
+            let renormalized = conekta
+                .response_codes
+                .normalize(&r.response_code, r.merchant_advice_code.as_deref());
+            assert_eq!(r.decline_reason, renormalized);
+            assert_eq!(r.approved, r.response_code == "00");
+            if r.decline_reason == Some(DeclineReason::ProcessorDeclined) {
+                assert_eq!(r.response_code, "processing_error");
+            }
+        }
+    }
+
+    #[test]
+    fn test_alternative_payment_methods_never_hard_decline() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let pagseguro = catalog.psp("psp_br_1").unwrap();
+
+        for i in 0..200 {
+            let mut tx = make_test_transaction("411111", "0000", 10000);
+            tx.id = format!("pix_{i}");
+            tx.customer_id = format!("cust_{i}");
+            tx.payment_method = PaymentMethod::Pix;
+            tx.card_bin = None;
+            tx.card_last4 = None;
+
+            let r = sim.process(&tx, pagseguro);
+            assert!(!r.decline_reason.is_some_and(|reason| reason.is_hard_decline()));
+        }
+    }
+
+    #[test]
+    fn test_prepaid_cards_approve_less_often() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let psp = catalog.psp("psp_br_2").unwrap();
+        let table = crate::card_bin::BinTable::builtin();
+
+        let approvals = |bin: &str| {
+            (0..1000)
+                .filter(|i| {
+                    let mut tx = make_test_transaction(bin, &format!("{:04}", i), 10000);
+                    // Seed on the same card digits so only the card type differs.
+                    tx.bin_info = table.lookup(bin).cloned();
+                    tx.card_bin = Some("411111".to_string());
+                    sim.process(&tx, psp).approved
+                })
+                .count()
+        };
+        assert!(approvals("506250") < approvals("411111"));
+    }
+
+    #[test]
+    fn test_long_installment_plans_approve_less_often() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let psp = catalog.psp("psp_br_2").unwrap();
+
+        let approvals = |installments: Option<u8>| {
+            (0..1000)
+                .filter(|i| {
+                    let mut tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
+                    tx.installments = installments;
+                    sim.process(&tx, psp).approved
+                })
+                .count()
+        };
+        assert!(approvals(Some(12)) < approvals(None));
+    }
+
+    #[test]
+    fn test_cross_border_acquirer_approves_fewer_domestic_cards() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let psp = catalog.psp("psp_us_1").unwrap();
+        let info = crate::card_bin::BinTable::builtin().lookup("411111").cloned().unwrap();
+
+        let approvals = |issuing_country: &str| {
+            (0..1000)
+                .filter(|i| {
+                    let mut tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
+                    tx.bin_info = Some(crate::card_bin::BinInfo {
+                        issuing_country: issuing_country.to_string(),
+                        ..info.clone()
+                    });
+                    sim.process(&tx, psp).approved
+                })
+                .count()
+        };
+        assert!(approvals("BR") < approvals("US"));
+    }
+
+    #[test]
+    fn test_three_ds_authentication_and_uplift() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let psp = catalog.psp("psp_br_2").unwrap();
+        let (mut frictionless, mut challenged, mut recovered) = (0, 0, 0);
+        for i in 0..1000 {
+            let mut tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
+            let declined = !sim.process(&tx, psp).approved;
+
+            let response = sim.process_authentication(&tx, psp);
+            let flow = match response.status {
+                ThreeDsStatus::Authenticated => {
+                    frictionless += 1;
+                    ThreeDsFlow::Frictionless
+                }
+                ThreeDsStatus::ChallengeRequired => {
+                    challenged += 1;
+                    let session_id = response.session_id.unwrap();
+                    assert_eq!(
+                        sim.process_challenge(&tx, psp, "3ds_forged").status,
+                        ThreeDsStatus::Failed
+                    );
+                    match sim.process_challenge(&tx, psp, &session_id).status {
+                        ThreeDsStatus::Authenticated => ThreeDsFlow::Challenge,
+                        _ => continue,
+                    }
+                }
+                status => panic!("unexpected {status:?}"),
+            };
+            let unauthenticated = sim.process(&tx, psp).approved;
+            tx.three_ds = Some(ThreeDsAuthentication { psp_id: psp.id.clone(), flow });
+            let authenticated = sim.process(&tx, psp).approved;
+            // Authentication never costs an approval
+            assert!(authenticated || !unauthenticated);
+            if declined && authenticated {
+                recovered += 1;
+            }
+        }
+        assert!((650..850).contains(&frictionless), "frictionless: {frictionless}");
+        assert_eq!(frictionless + challenged, 1000);
+        assert!(recovered > 0);
+
+        // Only the authenticating PSP gives the uplift
+        let stone = catalog.psp("psp_br_3").unwrap();
+        let uplifted = (0..1000).any(|i| {
+            let mut tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
+            let before = sim.process(&tx, stone).approved;
+            tx.three_ds = Some(ThreeDsAuthentication { psp_id: psp.id.clone(), flow: ThreeDsFlow::Frictionless });
+            before != sim.process(&tx, stone).approved
+        });
+        assert!(!uplifted);
+        let without = catalog.psp("psp_co_1").unwrap();
+        let tx = make_test_transaction("411111", "0001", 10000);
+        assert_eq!(sim.process_authentication(&tx, without).status, ThreeDsStatus::Unavailable);
+    }
+
+    #[test]
+    fn test_refreshed_credential_rescues_only_out_of_date_cards() {
+        let sim = PspSimulator::new();
+        let catalog = PspCatalog::builtin();
+        let psp = catalog.psp("psp_br_1").unwrap();
+        let credential = CardCredential {
+            source: CredentialSource::AccountUpdater,
+            reference: "au_test".to_string(),
+            expiry: "08/29".to_string(),
+        };
+        let mut rescued = 0;
+        for i in 0..500 {
+            let mut tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
+            let Some(reason) = sim.process(&tx, psp).decline_reason.filter(DeclineReason::is_hard_decline) else {
+                continue;
+            };
+            tx.credential = Some(credential.clone());
+            let refreshed = sim.process(&tx, psp);
+            if reason.calls_for_credential_refresh() {
+                assert!(!refreshed.decline_reason.as_ref().is_some_and(DeclineReason::is_hard_decline));
+                rescued += usize::from(refreshed.approved);
+            } else {
+                assert_eq!(refreshed.decline_reason, Some(reason));
+            }
+        }
+        assert!(rescued > 0);
+    }
+
     #[test]
     fn test_latency_within_range() {
         let sim = PspSimulator::new();
-        let psps = get_psps_for_country(&Country::Brazil);
+        let psps = PspCatalog::builtin().psps_for_country(&Country::new("Brazil"));
 
         for i in 0..50 {
-            let tx = make_test_transaction("411111", &format!("{:04}", i), 100.0);
+            let tx = make_test_transaction("411111", &format!("{:04}", i), 10000);
             for psp in &psps {
                 let r = sim.process(&tx, psp);
                 assert!(r.latency_ms >= psp.latency_min_ms && r.latency_ms <= psp.latency_max_ms,
@@ -314,13 +809,13 @@ mod tests {
     #[test]
     fn test_approval_rate_distribution() {
         let sim = PspSimulator::new();
-        let psps = get_psps_for_country(&Country::Brazil);
+        let psps = PspCatalog::builtin().psps_for_country(&Country::new("Brazil"));
         let psp = &psps[0]; // PagSeguro, 78% rate
 
         let mut approved = 0;
         let total = 1000;
         for i in 0..total {
-            let tx = make_test_transaction("411111", &format!("{:04}", i), 100.0 + i as f64);
+            let tx = make_test_transaction("411111", &format!("{:04}", i), 10000 + i * 100);
             let r = sim.process(&tx, psp);
             if r.approved {
                 approved += 1;
@@ -0,0 +1,67 @@
+//! Process-wide state — the in-memory stores one instance keeps for the
+//! requests it serves.
+//!
+//! Each serverless function runs in its own processes, and a process
+//! lives as long as its instance stays warm. What is kept here is shared
+//! by the requests one instance serves and by nothing else: it starts
+//! empty on a cold start, is lost when the instance is recycled, and is
+//! never seen by another function or another instance of the same one.
+//! State that must outlive that, or reach another function, belongs in
+//! the shared key-value store instead (see [`kv`](crate::kv)).
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+/// The process's one value of type `T`, built by `init` on first use.
+/// `init` runs under a process-wide lock, so it must not call this.
+pub(crate) fn per_process<T: Any + Send + Sync>(init: impl FnOnce() -> T) -> Arc<T> {
+    type Instances = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+    static INSTANCES: OnceLock<Mutex<Instances>> = OnceLock::new();
+    let mut instances = lock(INSTANCES.get_or_init(Mutex::default));
+    let instance = instances
+        .entry(TypeId::of::<T>())
+        .or_insert_with(|| Arc::new(init()))
+        .clone();
+    instance.downcast().expect("instances are keyed by their type")
+}
+
+/// Lock `mutex`, recovering it if a panic elsewhere poisoned it.
+///
+/// Every store kept here changes in single steps under its lock, so a
+/// holder that panicked leaves it consistent enough to keep serving.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_instance_per_type() {
+        #[derive(Default)]
+        struct Counter(Mutex<u32>);
+        struct Other(u32);
+
+        *lock(&per_process(Counter::default).0) += 1;
+        *lock(&per_process(Counter::default).0) += 1;
+        assert_eq!(*lock(&per_process(Counter::default).0), 2);
+        assert_eq!(per_process(|| Other(7)).0, 7);
+        assert_eq!(per_process(|| Other(8)).0, 7);
+    }
+
+    #[test]
+    fn test_poisoned_locks_are_recovered() {
+        let mutex = Arc::new(Mutex::new(1));
+        let held = mutex.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = held.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+        assert!(mutex.is_poisoned());
+        *lock(&mutex) += 1;
+        assert_eq!(*lock(&mutex), 2);
+    }
+}
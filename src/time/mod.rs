@@ -0,0 +1,515 @@
+//! Timestamps and time zones.
+//!
+//! [`Timestamp`] is an instant stored as whole seconds since the Unix epoch
+//! (UTC). It parses and formats RFC 3339 / ISO 8601 strings such as
+//! `2025-01-15T10:00:00Z` or `2025-01-15T07:00:00-03:00`, supports
+//! arithmetic with [`std::time::Duration`], and converts to local wall-clock
+//! time in the markets' time zones.
+//!
+//! Calendar conversions use Howard Hinnant's `days_from_civil` /
+//! `civil_from_days` algorithms, so no date crate is needed.
+//!
+//! # Time Zones
+//!
+//! | Zone | Offset |
+//! |---|---|
+//! | São Paulo, Buenos Aires | UTC−3 |
+//! | Bogotá, Lima | UTC−5 |
+//! | Mexico City | UTC−6 |
+//! | Santiago | UTC−4, UTC−3 in southern summer |
+//! | New York | UTC−5, UTC−4 in northern summer |
+//!
+//! Brazil and Mexico abolished daylight saving time in 2019 and 2022; of
+//! the markets only Santiago still observes it (first Sunday of September
+//! to first Sunday of April). New York, home of the cross-border
+//! acquirers, observes it from the second Sunday of March to the first
+//! Sunday of November.
+
+use crate::catalog::locale;
+use crate::models::transaction::Country;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_MINUTE: i64 = 60;
+const SECONDS_PER_HOUR: i64 = 3_600;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Errors produced while parsing or shifting timestamps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeError {
+    /// The string is not an RFC 3339 timestamp.
+    InvalidFormat(String),
+    /// A date or time field is out of range (e.g., month 13).
+    OutOfRange(String),
+    /// The result does not fit in the representable range.
+    Overflow,
+}
+
+impl std::fmt::Display for TimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeError::InvalidFormat(s) => write!(f, "invalid timestamp '{s}' (expected RFC 3339)"),
+            TimeError::OutOfRange(s) => write!(f, "timestamp field out of range in '{s}'"),
+            TimeError::Overflow => write!(f, "timestamp overflow"),
+        }
+    }
+}
+
+impl std::error::Error for TimeError {}
+
+/// Time zones a catalog country can report in (see
+/// [`CountryConfig::time_zone`](crate::catalog::CountryConfig::time_zone)).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum TimeZone {
+    Utc,
+    SaoPaulo,
+    MexicoCity,
+    Bogota,
+    Santiago,
+    Lima,
+    BuenosAires,
+    NewYork,
+}
+
+impl TimeZone {
+    /// The zone a country's transactions are reported in, as the catalogs
+    /// loaded so far declare it: UTC for a country none declares.
+    pub fn for_country(country: &Country) -> TimeZone {
+        locale::time_zone(country).unwrap_or(TimeZone::Utc)
+    }
+
+    /// IANA zone name (e.g., `"America/Sao_Paulo"`).
+    pub fn iana_name(&self) -> &'static str {
+        match self {
+            TimeZone::Utc => "UTC",
+            TimeZone::SaoPaulo => "America/Sao_Paulo",
+            TimeZone::MexicoCity => "America/Mexico_City",
+            TimeZone::Bogota => "America/Bogota",
+            TimeZone::Santiago => "America/Santiago",
+            TimeZone::Lima => "America/Lima",
+            TimeZone::BuenosAires => "America/Argentina/Buenos_Aires",
+            TimeZone::NewYork => "America/New_York",
+        }
+    }
+
+    /// UTC offset in effect at `at`, in seconds (negative west of Greenwich).
+    pub fn offset_seconds_at(&self, at: &Timestamp) -> i64 {
+        let hours = match self {
+            TimeZone::Utc => 0,
+            TimeZone::SaoPaulo | TimeZone::BuenosAires => -3,
+            TimeZone::Bogota | TimeZone::Lima => -5,
+            TimeZone::MexicoCity => -6,
+            TimeZone::Santiago if santiago_observes_dst(at) => -3,
+            TimeZone::Santiago => -4,
+            TimeZone::NewYork if new_york_observes_dst(at) => -4,
+            TimeZone::NewYork => -5,
+        };
+        hours * SECONDS_PER_HOUR
+    }
+}
+
+impl std::fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.iana_name())
+    }
+}
+
+/// An instant in time, with one-second precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Timestamp {
+    unix_seconds: i64,
+}
+
+/// Calendar fields of a [`Timestamp`] in some time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// UTC offset these fields are expressed in, in seconds.
+    pub offset_seconds: i64,
+}
+
+impl Timestamp {
+    /// The Unix epoch, 1970-01-01T00:00:00Z.
+    pub const UNIX_EPOCH: Timestamp = Timestamp { unix_seconds: 0 };
+
+    /// An instant `unix_seconds` after the Unix epoch.
+    pub fn from_unix_seconds(unix_seconds: i64) -> Self {
+        Timestamp { unix_seconds }
+    }
+
+    /// The current time from the system clock, or the epoch if the clock
+    /// is set before 1970.
+    pub fn now() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        Timestamp::from_unix_seconds(secs)
+    }
+
+    /// Builds a UTC timestamp from calendar fields, validating each one.
+    pub fn from_utc(
+        year: i64,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Result<Self, TimeError> {
+        let describe = || format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}");
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(TimeError::OutOfRange(describe()));
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(TimeError::OutOfRange(describe()));
+        }
+        let days = days_from_civil(year, month, day);
+        let seconds = days
+            .checked_mul(SECONDS_PER_DAY)
+            .and_then(|s| s.checked_add(hour as i64 * SECONDS_PER_HOUR))
+            .and_then(|s| s.checked_add(minute as i64 * SECONDS_PER_MINUTE + second as i64))
+            .ok_or(TimeError::Overflow)?;
+        Ok(Timestamp::from_unix_seconds(seconds))
+    }
+
+    /// Parses an RFC 3339 timestamp: `YYYY-MM-DDTHH:MM:SS`, an optional
+    /// fractional second (truncated), and `Z` or a `±HH:MM` offset.
+    pub fn parse(s: &str) -> Result<Self, TimeError> {
+        let invalid = || TimeError::InvalidFormat(s.to_string());
+        let b = s.as_bytes();
+        if !s.is_ascii() || b.len() < 20 || b[4] != b'-' || b[7] != b'-' || b[10] != b'T' || b[13] != b':' || b[16] != b':' {
+            return Err(invalid());
+        }
+        let field = |range: std::ops::Range<usize>| -> Result<u32, TimeError> {
+            let text = &s[range];
+            if !text.bytes().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            text.parse().map_err(|_| invalid())
+        };
+        let year = field(0..4)? as i64;
+        let (month, day) = (field(5..7)?, field(8..10)?);
+        let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+
+        let mut rest = &s[19..];
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return Err(invalid());
+            }
+            rest = &fraction[digits..];
+        }
+
+        let offset_seconds = match rest {
+            "Z" => 0,
+            _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+                let sign = match rest.as_bytes()[0] {
+                    b'+' => 1,
+                    b'-' => -1,
+                    _ => return Err(invalid()),
+                };
+                let hours: i64 = rest[1..3].parse().map_err(|_| invalid())?;
+                let minutes: i64 = rest[4..6].parse().map_err(|_| invalid())?;
+                if hours > 23 || minutes > 59 {
+                    return Err(TimeError::OutOfRange(s.to_string()));
+                }
+                sign * (hours * SECONDS_PER_HOUR + minutes * SECONDS_PER_MINUTE)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let local = Timestamp::from_utc(year, month, day, hour, minute, second)
+            .map_err(|_| TimeError::OutOfRange(s.to_string()))?;
+        local
+            .unix_seconds
+            .checked_sub(offset_seconds)
+            .map(Timestamp::from_unix_seconds)
+            .ok_or(TimeError::Overflow)
+    }
+
+    /// Seconds since the Unix epoch.
+    pub fn unix_seconds(&self) -> i64 {
+        self.unix_seconds
+    }
+
+    /// This instant shifted forward by `duration`.
+    pub fn checked_add(&self, duration: Duration) -> Option<Timestamp> {
+        let secs = i64::try_from(duration.as_secs()).ok()?;
+        self.unix_seconds.checked_add(secs).map(Timestamp::from_unix_seconds)
+    }
+
+    /// This instant shifted back by `duration`.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Timestamp> {
+        let secs = i64::try_from(duration.as_secs()).ok()?;
+        self.unix_seconds.checked_sub(secs).map(Timestamp::from_unix_seconds)
+    }
+
+    /// Time elapsed from `earlier` to `self`, or `None` if `earlier` is later.
+    pub fn duration_since(&self, earlier: &Timestamp) -> Option<Duration> {
+        let secs = self.unix_seconds.checked_sub(earlier.unix_seconds)?;
+        u64::try_from(secs).ok().map(Duration::from_secs)
+    }
+
+    /// The start of the UTC hour containing this instant.
+    pub fn truncate_to_hour(&self) -> Timestamp {
+        Timestamp::from_unix_seconds(self.unix_seconds - self.unix_seconds.rem_euclid(SECONDS_PER_HOUR))
+    }
+
+    /// Calendar fields in UTC.
+    pub fn to_utc(&self) -> LocalDateTime {
+        self.local_at_offset(0)
+    }
+
+    /// Calendar fields in `zone`.
+    pub fn to_zone(&self, zone: &TimeZone) -> LocalDateTime {
+        self.local_at_offset(zone.offset_seconds_at(self))
+    }
+
+    /// The UTC calendar date as `YYYY-MM-DD`.
+    pub fn date_string(&self) -> String {
+        let utc = self.to_utc();
+        format!("{:04}-{:02}-{:02}", utc.year, utc.month, utc.day)
+    }
+
+    fn local_at_offset(&self, offset_seconds: i64) -> LocalDateTime {
+        let local = self.unix_seconds + offset_seconds;
+        let days = local.div_euclid(SECONDS_PER_DAY);
+        let time_of_day = local.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        LocalDateTime {
+            year,
+            month,
+            day,
+            hour: (time_of_day / SECONDS_PER_HOUR) as u32,
+            minute: (time_of_day % SECONDS_PER_HOUR / SECONDS_PER_MINUTE) as u32,
+            second: (time_of_day % SECONDS_PER_MINUTE) as u32,
+            offset_seconds,
+        }
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    /// Formats as RFC 3339 in UTC, e.g. `2025-01-15T10:00:00Z`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_utc())
+    }
+}
+
+impl std::str::FromStr for Timestamp {
+    type Err = TimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Timestamp::parse(s)
+    }
+}
+
+impl TryFrom<String> for Timestamp {
+    type Error = TimeError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Timestamp::parse(&s)
+    }
+}
+
+impl From<Timestamp> for String {
+    fn from(ts: Timestamp) -> String {
+        ts.to_string()
+    }
+}
+
+impl std::fmt::Display for LocalDateTime {
+    /// Formats as RFC 3339 with the local offset (`Z` for UTC).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        if self.offset_seconds == 0 {
+            return write!(f, "Z");
+        }
+        let sign = if self.offset_seconds < 0 { '-' } else { '+' };
+        let abs = self.offset_seconds.abs();
+        write!(
+            f,
+            "{sign}{:02}:{:02}",
+            abs / SECONDS_PER_HOUR,
+            abs % SECONDS_PER_HOUR / SECONDS_PER_MINUTE
+        )
+    }
+}
+
+/// Santiago is on summer time from the first Sunday of September (04:00 UTC)
+/// to the first Sunday of April (03:00 UTC).
+fn santiago_observes_dst(at: &Timestamp) -> bool {
+    let year = at.to_utc().year;
+    let starts = first_sunday(year, 9) * SECONDS_PER_DAY + 4 * SECONDS_PER_HOUR;
+    let ends = first_sunday(year, 4) * SECONDS_PER_DAY + 3 * SECONDS_PER_HOUR;
+    let t = at.unix_seconds;
+    t < ends || t >= starts
+}
+
+/// New York is on summer time from the second Sunday of March (07:00 UTC)
+/// to the first Sunday of November (06:00 UTC).
+fn new_york_observes_dst(at: &Timestamp) -> bool {
+    let year = at.to_utc().year;
+    let starts = (first_sunday(year, 3) + 7) * SECONDS_PER_DAY + 7 * SECONDS_PER_HOUR;
+    let ends = first_sunday(year, 11) * SECONDS_PER_DAY + 6 * SECONDS_PER_HOUR;
+    let t = at.unix_seconds;
+    t >= starts && t < ends
+}
+
+/// Days since the epoch of the first Sunday of `month` in `year`.
+fn first_sunday(year: i64, month: u32) -> i64 {
+    let first = days_from_civil(year, month, 1);
+    // 1970-01-01 was a Thursday; weekday 0 = Sunday.
+    let weekday = (first + 4).rem_euclid(7);
+    first + (7 - weekday) % 7
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let mp = if m > 2 { m - 3 } else { m + 9 };
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian (year, month, day) for days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_round_trip() {
+        let ts = Timestamp::parse("2025-01-15T10:00:00Z").unwrap();
+        assert_eq!(ts.unix_seconds(), 1_736_935_200);
+        assert_eq!(ts.to_string(), "2025-01-15T10:00:00Z");
+        assert_eq!(ts.date_string(), "2025-01-15");
+    }
+
+    #[test]
+    fn test_parse_offsets_and_fractions() {
+        let utc = Timestamp::parse("2025-01-15T10:00:00Z").unwrap();
+        assert_eq!(Timestamp::parse("2025-01-15T07:00:00-03:00").unwrap(), utc);
+        assert_eq!(Timestamp::parse("2025-01-15T10:00:00.250Z").unwrap(), utc);
+        assert_eq!(Timestamp::parse("2025-01-15T15:30:00+05:30").unwrap(), utc);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        for bad in [
+            "2025-01-15",
+            "2025-01-15 10:00:00Z",
+            "2025-01-15T10:00:00",
+            "2025-13-01T00:00:00Z",
+            "2025-02-29T00:00:00Z",
+            "2025-01-15T24:00:00Z",
+            "2025-01-15T10:00:00+0300",
+            "2025-01-15T10:00:00.Z",
+        ] {
+            assert!(Timestamp::parse(bad).is_err(), "{bad} should not parse");
+        }
+        assert!(Timestamp::parse("2024-02-29T00:00:00Z").is_ok());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let ts = Timestamp::parse("2025-01-31T23:30:00Z").unwrap();
+        let later = ts.checked_add(Duration::from_secs(3_600)).unwrap();
+        assert_eq!(later.to_string(), "2025-02-01T00:30:00Z");
+        assert_eq!(later.duration_since(&ts), Some(Duration::from_secs(3_600)));
+        assert_eq!(ts.duration_since(&later), None);
+        assert_eq!(later.checked_sub(Duration::from_secs(3_600)), Some(ts));
+        assert_eq!(later.truncate_to_hour().to_string(), "2025-02-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_market_time_zones() {
+        let ts = Timestamp::parse("2025-01-15T10:00:00Z").unwrap();
+        assert_eq!(ts.to_zone(&TimeZone::SaoPaulo).to_string(), "2025-01-15T07:00:00-03:00");
+        assert_eq!(ts.to_zone(&TimeZone::MexicoCity).to_string(), "2025-01-15T04:00:00-06:00");
+        assert_eq!(ts.to_zone(&TimeZone::Bogota).to_string(), "2025-01-15T05:00:00-05:00");
+        assert_eq!(TimeZone::for_country(&Country::new("Colombia")), TimeZone::Bogota);
+    }
+
+    #[test]
+    fn test_local_date_can_differ_from_utc_date() {
+        let ts = Timestamp::parse("2025-01-15T02:00:00Z").unwrap();
+        let local = ts.to_zone(&TimeZone::MexicoCity);
+        assert_eq!((local.month, local.day, local.hour), (1, 14, 20));
+    }
+
+    #[test]
+    fn test_santiago_daylight_saving() {
+        // January is southern summer: UTC-3. July is winter: UTC-4.
+        let summer = Timestamp::parse("2025-01-15T12:00:00Z").unwrap();
+        let winter = Timestamp::parse("2025-07-15T12:00:00Z").unwrap();
+        assert_eq!(TimeZone::Santiago.offset_seconds_at(&summer), -3 * 3_600);
+        assert_eq!(TimeZone::Santiago.offset_seconds_at(&winter), -4 * 3_600);
+    }
+
+    #[test]
+    fn test_new_york_daylight_saving() {
+        // 2025: EDT from March 9 07:00 UTC to November 2 06:00 UTC.
+        let offset = |s: &str| TimeZone::NewYork.offset_seconds_at(&Timestamp::parse(s).unwrap());
+        assert_eq!(offset("2025-03-09T06:59:59Z"), -5 * 3_600);
+        assert_eq!(offset("2025-03-09T07:00:00Z"), -4 * 3_600);
+        assert_eq!(offset("2025-11-02T05:59:59Z"), -4 * 3_600);
+        assert_eq!(offset("2025-11-02T06:00:00Z"), -5 * 3_600);
+    }
+
+    #[test]
+    fn test_serde_uses_rfc3339_strings() {
+        let ts = Timestamp::parse("2025-01-15T10:00:00Z").unwrap();
+        assert_eq!(serde_json::to_string(&ts).unwrap(), "\"2025-01-15T10:00:00Z\"");
+        let back: Timestamp = serde_json::from_str("\"2025-01-15T07:00:00-03:00\"").unwrap();
+        assert_eq!(back, ts);
+        assert!(serde_json::from_str::<Timestamp>("\"yesterday\"").is_err());
+    }
+
+    #[test]
+    fn test_civil_conversions_round_trip() {
+        for days in [-719_468, -1, 0, 1, 10_957, 19_737, 2_932_896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+    }
+}